@@ -0,0 +1,83 @@
+use std::hint::black_box;
+
+use chip8_emulator_rs::{builtin, Machine, Opcode, Quirks};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+// Instructions in a frame at the default speed of 1000 per second
+const INSTRUCTIONS_PER_FRAME: u32 = 16;
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(0x10000));
+    group.bench_function("every instruction", |b| {
+        b.iter(|| {
+            for instruction in 0..=u16::MAX {
+                let _ = black_box(Opcode::decode(black_box(instruction)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn alu(c: &mut Criterion) {
+    // ADD V0, V1; SUB V2, V0; SHR V3, V0; SHL V4, V2; XOR V5, V4; ADD V1, 3; JP 0x200
+    let rom = [
+        0x80, 0x14, 0x82, 0x05, 0x83, 0x06, 0x84, 0x2E, 0x85, 0x43, 0x71, 0x03, 0x12, 0x00,
+    ];
+    let mut machine = Machine::new(&rom, Quirks::default(), 1).unwrap();
+    let mut group = c.benchmark_group("alu");
+    group.throughput(Throughput::Elements(7));
+    group.bench_function("8XYN loop", |b| {
+        b.iter(|| {
+            for _ in 0..7 {
+                black_box(machine.step().unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn draw(c: &mut Criterion) {
+    // LD F, V2; DRW V0, V1, 5; ADD V0, 5; ADD V1, 3; ADD V2, 1; JP 0x200, a digit anywhere on the screen every time
+    let rom = [0xF2, 0x29, 0xD0, 0x15, 0x70, 0x05, 0x71, 0x03, 0x72, 0x01, 0x12, 0x00];
+    let mut group = c.benchmark_group("draw");
+    group.throughput(Throughput::Elements(6));
+    for (name, quirks) in [
+        ("clip", Quirks::default()),
+        (
+            "wrap",
+            Quirks {
+                wrap_sprites: true,
+                ..Quirks::default()
+            },
+        ),
+    ] {
+        let mut machine = Machine::new(&rom, quirks, 1).unwrap();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for _ in 0..6 {
+                    black_box(machine.step().unwrap());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn frame(c: &mut Criterion) {
+    let catch = builtin::find("catch").unwrap();
+    let mut machine = Machine::new(catch.data, Quirks::default(), 1).unwrap();
+    // past the title screen into the game
+    for _ in 0..120 {
+        machine.run_frame(INSTRUCTIONS_PER_FRAME).unwrap();
+    }
+    let mut group = c.benchmark_group("frame");
+    group.throughput(Throughput::Elements(INSTRUCTIONS_PER_FRAME as u64));
+    group.bench_function("builtin:catch", |b| {
+        b.iter(|| black_box(machine.run_frame(INSTRUCTIONS_PER_FRAME).unwrap()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode, alu, draw, frame);
+criterion_main!(benches);
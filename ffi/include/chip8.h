@@ -0,0 +1,116 @@
+#ifndef CHIP8_H
+#define CHIP8_H
+
+/* Generated by cbindgen from ffi/src/lib.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define CHIP8_DISPLAY_WIDTH 64
+
+#define CHIP8_DISPLAY_HEIGHT 32
+
+/**
+ * The desktop emulator's speed, for chip8_run_frame.
+ */
+#define CHIP8_DEFAULT_CYCLES_PER_FRAME 16
+
+/**
+ * Returned by every function that can fail.
+ */
+typedef enum Chip8Status {
+  CHIP8_STATUS_OK = 0,
+  CHIP8_STATUS_NULL_POINTER,
+  CHIP8_STATUS_ROM_TOO_LARGE,
+  CHIP8_STATUS_INVALID_OPCODE,
+  CHIP8_STATUS_STACK_OVERFLOW,
+  CHIP8_STATUS_STACK_UNDERFLOW,
+  CHIP8_STATUS_PC_OUT_OF_BOUNDS,
+  CHIP8_STATUS_ERROR,
+} Chip8Status;
+
+/**
+ * An emulator instance, only ever handled through a pointer.
+ */
+typedef struct Chip8 Chip8;
+
+/**
+ * What happened during a call to chip8_step or chip8_run_frame.
+ */
+typedef struct Chip8StepResult {
+  bool display_changed;
+  bool sound_started;
+  bool sound_stopped;
+  bool waiting_for_key;
+  bool halted;
+} Chip8StepResult;
+
+/**
+ * Creates an emulator with an empty memory, load a program with chip8_load_rom.
+ * The seed drives CXNN, the same seed gives the same run. Free it with chip8_free.
+ */
+struct Chip8 *chip8_new(uint64_t seed);
+
+/**
+ * # Safety
+ * `chip8` must come from chip8_new and must not be used afterwards. NULL is ignored.
+ */
+void chip8_free(struct Chip8 *chip8);
+
+/**
+ * Resets the emulator and loads `len` bytes at 0x200.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new and `rom` must point to `len` readable bytes.
+ */
+enum Chip8Status chip8_load_rom(struct Chip8 *chip8, const uint8_t *rom, size_t len);
+
+/**
+ * Executes one instruction. Timers are not touched, call chip8_tick_timers 60 times per second.
+ * `result` may be NULL.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new, `result` must be NULL or writable.
+ */
+enum Chip8Status chip8_step(struct Chip8 *chip8, struct Chip8StepResult *result);
+
+/**
+ * Executes the instructions of one 60 Hz frame, stopping early when the program halts, and ticks the
+ * timers. Call it 60 times per second, e.g. with CHIP8_DEFAULT_CYCLES_PER_FRAME. `result` may be NULL.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new, `result` must be NULL or writable.
+ */
+enum Chip8Status chip8_run_frame(struct Chip8 *chip8,
+                                 uint32_t instructions,
+                                 struct Chip8StepResult *result);
+
+/**
+ * Decrements the delay and sound timers, returns true when the sound stopped with this tick.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new.
+ */
+bool chip8_tick_timers(struct Chip8 *chip8);
+
+/**
+ * Returns CHIP8_DISPLAY_WIDTH * CHIP8_DISPLAY_HEIGHT bytes, row by row, 0 is off and 1 is on.
+ * The pointer stays valid until chip8_load_rom or chip8_free.
+ *
+ * # Safety
+ * `chip8` must come from chip8_new.
+ */
+const uint8_t *chip8_framebuffer(const struct Chip8 *chip8);
+
+/**
+ * Presses or releases one of the 16 keys (0x0 - 0xF).
+ *
+ * # Safety
+ * `chip8` must come from chip8_new.
+ */
+void chip8_key_event(struct Chip8 *chip8, uint8_t key, bool pressed);
+
+#endif  /* CHIP8_H */
@@ -0,0 +1,267 @@
+use std::ptr;
+use std::slice;
+
+use chip8_emulator_rs::machine::{DEFAULT_IPS, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use chip8_emulator_rs::{Chip8Error, Machine, Quirks, StepResult};
+
+pub const CHIP8_DISPLAY_WIDTH: usize = 64;
+pub const CHIP8_DISPLAY_HEIGHT: usize = 32;
+/// The desktop emulator's speed, for chip8_run_frame.
+pub const CHIP8_DEFAULT_CYCLES_PER_FRAME: u32 = 16;
+
+const _: () = assert!(CHIP8_DISPLAY_WIDTH == DISPLAY_WIDTH && CHIP8_DISPLAY_HEIGHT == DISPLAY_HEIGHT);
+const _: () = assert!(CHIP8_DEFAULT_CYCLES_PER_FRAME == DEFAULT_IPS / 60);
+
+/// Returned by every function that can fail.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Status {
+    Ok = 0,
+    NullPointer,
+    RomTooLarge,
+    InvalidOpcode,
+    StackOverflow,
+    StackUnderflow,
+    PcOutOfBounds,
+    Error,
+}
+
+/// What happened during a call to chip8_step or chip8_run_frame.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Chip8StepResult {
+    pub display_changed: bool,
+    pub sound_started: bool,
+    pub sound_stopped: bool,
+    pub waiting_for_key: bool,
+    pub halted: bool,
+}
+
+impl From<StepResult> for Chip8StepResult {
+    fn from(result: StepResult) -> Self {
+        Chip8StepResult {
+            display_changed: result.display_changed,
+            sound_started: result.sound_started,
+            sound_stopped: result.sound_stopped,
+            waiting_for_key: result.waiting_for_key,
+            halted: result.halted,
+        }
+    }
+}
+
+/// An emulator instance, only ever handled through a pointer.
+pub struct Chip8 {
+    machine: Machine,
+    seed: u64,
+}
+
+impl From<Chip8Error> for Chip8Status {
+    fn from(err: Chip8Error) -> Self {
+        match err {
+            Chip8Error::RomTooLarge(_) => Chip8Status::RomTooLarge,
+            Chip8Error::InvalidOpcode(_) => Chip8Status::InvalidOpcode,
+            Chip8Error::StackOverflow => Chip8Status::StackOverflow,
+            Chip8Error::StackUnderflow => Chip8Status::StackUnderflow,
+            Chip8Error::PCOutOfBounds(_) => Chip8Status::PcOutOfBounds,
+            _ => Chip8Status::Error,
+        }
+    }
+}
+
+/// Creates an emulator with an empty memory, load a program with chip8_load_rom.
+/// The seed drives CXNN, the same seed gives the same run. Free it with chip8_free.
+#[no_mangle]
+pub extern "C" fn chip8_new(seed: u64) -> *mut Chip8 {
+    match Machine::new(&[], Quirks::default(), seed) {
+        Ok(machine) => Box::into_raw(Box::new(Chip8 { machine, seed })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `chip8` must come from chip8_new and must not be used afterwards. NULL is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free(chip8: *mut Chip8) {
+    if !chip8.is_null() {
+        drop(Box::from_raw(chip8));
+    }
+}
+
+/// Resets the emulator and loads `len` bytes at 0x200.
+///
+/// # Safety
+/// `chip8` must come from chip8_new and `rom` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(chip8: *mut Chip8, rom: *const u8, len: usize) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    if rom.is_null() && len > 0 {
+        return Chip8Status::NullPointer;
+    }
+    let rom = if len == 0 { &[] } else { slice::from_raw_parts(rom, len) };
+    match Machine::new(rom, Quirks::default(), chip8.seed) {
+        Ok(machine) => {
+            chip8.machine = machine;
+            Chip8Status::Ok
+        }
+        Err(err) => err.into(),
+    }
+}
+
+/// Executes one instruction. Timers are not touched, call chip8_tick_timers 60 times per second.
+/// `result` may be NULL.
+///
+/// # Safety
+/// `chip8` must come from chip8_new, `result` must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_step(chip8: *mut Chip8, result: *mut Chip8StepResult) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    match chip8.machine.step() {
+        Ok(step) => {
+            if let Some(result) = result.as_mut() {
+                *result = step.into();
+            }
+            Chip8Status::Ok
+        }
+        Err(err) => err.into(),
+    }
+}
+
+/// Executes the instructions of one 60 Hz frame, stopping early when the program halts, and ticks the
+/// timers. Call it 60 times per second, e.g. with CHIP8_DEFAULT_CYCLES_PER_FRAME. `result` may be NULL.
+///
+/// # Safety
+/// `chip8` must come from chip8_new, `result` must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_run_frame(
+    chip8: *mut Chip8,
+    instructions: u32,
+    result: *mut Chip8StepResult,
+) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    match chip8.machine.run_frame(instructions) {
+        Ok(frame) => {
+            if let Some(result) = result.as_mut() {
+                *result = frame.into();
+            }
+            Chip8Status::Ok
+        }
+        Err(err) => err.into(),
+    }
+}
+
+/// Decrements the delay and sound timers, returns true when the sound stopped with this tick.
+///
+/// # Safety
+/// `chip8` must come from chip8_new.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_tick_timers(chip8: *mut Chip8) -> bool {
+    chip8.as_mut().is_some_and(|chip8| chip8.machine.tick_timers())
+}
+
+/// Returns CHIP8_DISPLAY_WIDTH * CHIP8_DISPLAY_HEIGHT bytes, row by row, 0 is off and 1 is on.
+/// The pointer stays valid until chip8_load_rom or chip8_free.
+///
+/// # Safety
+/// `chip8` must come from chip8_new.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(chip8: *const Chip8) -> *const u8 {
+    match chip8.as_ref() {
+        Some(chip8) => chip8.machine.pixels().as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Presses or releases one of the 16 keys (0x0 - 0xF).
+///
+/// # Safety
+/// `chip8` must come from chip8_new.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_key_event(chip8: *mut Chip8, key: u8, pressed: bool) {
+    if let Some(chip8) = chip8.as_mut() {
+        if pressed {
+            chip8.machine.keypad_mut().press(key);
+        } else {
+            chip8.machine.keypad_mut().release(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_c_api_runs_a_rom() {
+        // LD V0, K; LD F, V0; DRW V1, V1, 5; JP 0x206
+        let rom = [0xF0, 0x0A, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x06];
+        unsafe {
+            let chip8 = chip8_new(1);
+            assert_eq!(chip8_load_rom(chip8, rom.as_ptr(), rom.len()), Chip8Status::Ok);
+
+            let mut result = Chip8StepResult::default();
+            assert_eq!(chip8_step(chip8, &mut result), Chip8Status::Ok);
+            assert!(result.waiting_for_key);
+
+            chip8_key_event(chip8, 0x1, true);
+            chip8_step(chip8, ptr::null_mut());
+            chip8_key_event(chip8, 0x1, false);
+            for _ in 0..4 {
+                chip8_step(chip8, &mut result);
+            }
+            assert!(result.halted);
+
+            let pixels = slice::from_raw_parts(chip8_framebuffer(chip8), CHIP8_DISPLAY_WIDTH * CHIP8_DISPLAY_HEIGHT);
+            assert_eq!(&pixels[..4], &[0, 0, 1, 0]);
+            assert_eq!(chip8_step(ptr::null_mut(), &mut result), Chip8Status::NullPointer);
+            chip8_free(chip8);
+        }
+    }
+
+    #[test]
+    fn test_run_frame_steps_and_ticks_the_timers() {
+        // LD V0, 2; LD ST, V0; JP 0x204
+        let rom = [0x60, 0x02, 0xF0, 0x18, 0x12, 0x04];
+        unsafe {
+            let chip8 = chip8_new(1);
+            assert_eq!(chip8_load_rom(chip8, rom.as_ptr(), rom.len()), Chip8Status::Ok);
+
+            let mut result = Chip8StepResult::default();
+            assert_eq!(
+                chip8_run_frame(chip8, CHIP8_DEFAULT_CYCLES_PER_FRAME, &mut result),
+                Chip8Status::Ok
+            );
+            assert!(result.sound_started && result.halted && !result.sound_stopped);
+            assert_eq!(
+                chip8_run_frame(chip8, CHIP8_DEFAULT_CYCLES_PER_FRAME, &mut result),
+                Chip8Status::Ok
+            );
+            assert!(result.sound_stopped);
+            assert_eq!(
+                chip8_run_frame(ptr::null_mut(), 1, ptr::null_mut()),
+                Chip8Status::NullPointer
+            );
+            chip8_free(chip8);
+        }
+    }
+
+    #[test]
+    fn test_header_matches_the_exported_functions() {
+        let crate_dir = env!("CARGO_MANIFEST_DIR");
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).unwrap();
+        let mut header = Vec::new();
+        cbindgen::generate_with_config(crate_dir, config)
+            .unwrap()
+            .write(&mut header);
+        let committed = std::fs::read(format!("{}/include/chip8.h", crate_dir)).unwrap();
+        assert!(
+            header == committed,
+            "include/chip8.h is out of date, run cbindgen --config ffi/cbindgen.toml --output ffi/include/chip8.h ffi"
+        );
+    }
+}
@@ -0,0 +1,62 @@
+#![no_main]
+
+use chip8_emulator_rs::debugger::{BreakCondition, Breakpoint};
+use chip8_emulator_rs::emulator::CycleLimitPolicy;
+use chip8_emulator_rs::{Chip8, Machine, Quirks};
+use libfuzzer_sys::fuzz_target;
+
+// Instructions every ROM gets in each interpreter, enough to run into code it wrote itself
+const STEPS: u32 = 10_000;
+const INSTRUCTIONS_PER_FRAME: u32 = 16;
+
+// The first byte picks the quirks, the rest is the ROM. Errors are fine, panics are not.
+fuzz_target!(|data: &[u8]| {
+    let Some((&bits, rom)) = data.split_first() else {
+        return;
+    };
+    let quirks = Quirks {
+        vf_reset: bits & 0x01 != 0,
+        memory_increment: bits & 0x02 != 0,
+        shift_vy: bits & 0x04 != 0,
+        jump_vx: bits & 0x08 != 0,
+        wrap_sprites: bits & 0x10 != 0,
+        key_wait_on_press: bits & 0x20 != 0,
+    };
+
+    if let Ok(mut machine) = Machine::new(rom, quirks, 1) {
+        for _ in 0..STEPS / INSTRUCTIONS_PER_FRAME {
+            if machine.run_frame(INSTRUCTIONS_PER_FRAME).is_err() {
+                break;
+            }
+        }
+    }
+
+    if let Ok(mut chip8) = Chip8::builder().rom_bytes(rom).quirks(quirks).seed(1).build() {
+        for step in 0..STEPS {
+            if chip8.step().is_err() {
+                break;
+            }
+            if step % INSTRUCTIONS_PER_FRAME == 0 {
+                chip8.tick_timers();
+            }
+        }
+    }
+
+    // The run loop checks the breakpoints before every step. They never pause it, as nothing would resume it.
+    if let Ok(mut chip8) = Chip8::builder().rom_bytes(rom).quirks(quirks).seed(1).build() {
+        let conditions = [
+            BreakCondition::Address(0x200),
+            BreakCondition::Opcode("DXYN"),
+            BreakCondition::Write(0x000, 0xFFF),
+        ];
+        for condition in conditions {
+            chip8.add_breakpoint(Breakpoint {
+                ignore: u32::MAX,
+                ..Breakpoint::new(condition)
+            });
+        }
+        chip8.set_throttle(false);
+        chip8.set_max_cycles(Some(STEPS as u64), CycleLimitPolicy::Exit);
+        let _ = chip8.run();
+    }
+});
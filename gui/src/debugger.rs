@@ -0,0 +1,144 @@
+use eframe::egui::{self, RichText};
+
+use chip8_emulator_rs::{Chip8Snapshot, Opcode};
+
+const DISASSEMBLY_LINES: usize = 16;
+
+// Where the debugger panel sits next to the display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dock {
+    Hidden,
+    Left,
+    Right,
+    Bottom,
+}
+
+// What the buttons in the panel asked for, carried out by the app after drawing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    None,
+    TogglePause,
+    Step,
+    Frame,
+}
+
+pub struct Debugger {
+    pub dock: Dock,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Debugger { dock: Dock::Right }
+    }
+}
+
+impl Debugger {
+    pub fn show(&self, ctx: &egui::Context, state: &Chip8Snapshot, paused: bool) -> Action {
+        let mut action = Action::None;
+        let mut panel = |ui: &mut egui::Ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| action = contents(ui, state, paused));
+        };
+        match self.dock {
+            Dock::Hidden => {}
+            Dock::Left => {
+                egui::SidePanel::left("debugger")
+                    .resizable(true)
+                    .default_width(260.0)
+                    .show(ctx, |ui| panel(ui));
+            }
+            Dock::Right => {
+                egui::SidePanel::right("debugger")
+                    .resizable(true)
+                    .default_width(260.0)
+                    .show(ctx, |ui| panel(ui));
+            }
+            Dock::Bottom => {
+                egui::TopBottomPanel::bottom("debugger")
+                    .resizable(true)
+                    .default_height(220.0)
+                    .show(ctx, |ui| panel(ui));
+            }
+        }
+        action
+    }
+}
+
+fn contents(ui: &mut egui::Ui, state: &Chip8Snapshot, paused: bool) -> Action {
+    let mut action = Action::None;
+    ui.horizontal(|ui| {
+        if ui.button(if paused { "Run" } else { "Pause" }).clicked() {
+            action = Action::TogglePause;
+        }
+        if ui.button("Step").clicked() {
+            action = Action::Step;
+        }
+        if ui.button("Frame").clicked() {
+            action = Action::Frame;
+        }
+    });
+    ui.separator();
+
+    egui::Grid::new("registers").num_columns(4).show(ui, |ui| {
+        for (n, value) in state.registers.iter().enumerate() {
+            ui.label(RichText::new(format!("V{n:X} {value:02X}")).monospace());
+            if n % 4 == 3 {
+                ui.end_row();
+            }
+        }
+    });
+    ui.label(
+        RichText::new(format!(
+            "PC {:04X}  I {:04X}  DT {:02X}  ST {:02X}",
+            state.pc, state.i, state.delay_timer, state.sound_timer
+        ))
+        .monospace(),
+    );
+    let stack: Vec<String> = state.stack.iter().map(|addr| format!("{addr:04X}")).collect();
+    ui.label(RichText::new(format!("Stack [{}]", stack.join(" "))).monospace());
+    ui.separator();
+
+    for (addr, text) in disassemble(&state.memory, state.pc, DISASSEMBLY_LINES) {
+        let line = RichText::new(format!("{addr:04X}  {text}")).monospace();
+        if addr == state.pc {
+            ui.label(line.strong().color(ui.visuals().warn_fg_color));
+        } else {
+            ui.label(line);
+        }
+    }
+    action
+}
+
+// The instructions starting at pc, words that are not valid opcodes are shown as data.
+fn disassemble(memory: &[u8], pc: u16, count: usize) -> Vec<(u16, String)> {
+    (0..count)
+        .map(|n| pc as usize + 2 * n)
+        .take_while(|&addr| addr + 1 < memory.len())
+        .map(|addr| {
+            let word = (memory[addr] as u16) << 8 | memory[addr + 1] as u16;
+            let text = match Opcode::decode(word) {
+                Ok(opcode) => opcode.to_string(),
+                Err(_) => format!("DW {word:#06X}"),
+            };
+            (addr as u16, text)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble_stops_at_end_of_memory() {
+        let memory = [0x00, 0xE0, 0x12, 0x00, 0xFF, 0xFF, 0x00];
+        let lines = disassemble(&memory, 0, 8);
+        assert_eq!(
+            lines,
+            vec![
+                (0, "CLS".to_string()),
+                (2, "JP 0x200".to_string()),
+                (4, "DW 0xFFFF".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use eframe::egui::{self, Key, RichText, Sense};
+
+// The COSMAC VIP's keypad as it was printed on the case
+const ROWS: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+const BUTTON_SIZE: f32 = 36.0;
+
+// A 4x4 keypad under the display whose buttons press the CHIP-8 keys while the mouse holds them down.
+#[derive(Default)]
+pub struct KeypadPanel {
+    pub visible: bool,
+}
+
+impl KeypadPanel {
+    // The keys held down with the mouse, indexed by CHIP-8 key. pressed lights up the keys the game sees, keys
+    // are the keyboard keys bound to them.
+    pub fn show(&self, ctx: &egui::Context, pressed: &[bool; 16], keys: &[(Key, u8)]) -> [bool; 16] {
+        let mut held = [false; 16];
+        if !self.visible {
+            return held;
+        }
+        egui::TopBottomPanel::bottom("keypad").show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                egui::Grid::new("keypad-grid").spacing([4.0, 4.0]).show(ui, |ui| {
+                    for row in ROWS {
+                        for key in row {
+                            let button = egui::Button::new(RichText::new(format!("{key:X}")).monospace().size(18.0))
+                                .min_size(egui::vec2(BUTTON_SIZE, BUTTON_SIZE))
+                                .selected(pressed[key as usize])
+                                .sense(Sense::click_and_drag());
+                            let response = ui.add(button);
+                            held[key as usize] = response.is_pointer_button_down_on();
+                            if let Some((code, _)) = keys.iter().find(|&&(_, bound)| bound == key) {
+                                response.on_hover_text(format!("Keyboard: {}", code.name()));
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+        held
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keypad_has_every_key_once() {
+        let mut keys: Vec<u8> = ROWS.into_iter().flatten().collect();
+        keys.sort();
+        assert_eq!(keys, (0..16).collect::<Vec<u8>>());
+    }
+}
@@ -0,0 +1,511 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{self, Color32, ColorImage, Key, TextureHandle, TextureOptions};
+
+use chip8_emulator_rs::config::{Config, KeyMap, Palette, Rgb};
+use chip8_emulator_rs::database::Database;
+use chip8_emulator_rs::machine::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use chip8_emulator_rs::recent::RecentRoms;
+use chip8_emulator_rs::theme::THEMES;
+use chip8_emulator_rs::{roms, Chip8, Chip8Error, Chip8Snapshot, Quirks};
+
+mod debugger;
+mod keypad;
+
+use debugger::{Debugger, Dock};
+use keypad::KeypadPanel;
+
+const FRAME_SECS: f64 = 1.0 / 60.0;
+// After a long hitch the emulator skips ahead instead of trying to catch up
+const MAX_FRAMES_PER_UPDATE: u32 = 4;
+
+struct App {
+    path: String,
+    rom_name: String,
+    // the speed comes from the config file, like the emulator's --ips
+    ips: u32,
+    quirks: Quirks,
+    palette: Palette,
+    // the keyboard keys of the config's key map, with the CHIP-8 key each one presses
+    keys: Vec<(Key, u8)>,
+    chip8: Option<Chip8>,
+    saved: Option<Chip8Snapshot>,
+    error: Option<String>,
+    paused: bool,
+    lag: f64,
+    debugger: Debugger,
+    keypad: KeypadPanel,
+    screen: Option<TextureHandle>,
+    recent: RecentRoms,
+    database: Database,
+    // the ROMs of the folder being browsed, shown in a window while it is open
+    rom_list: Option<Vec<PathBuf>>,
+}
+
+fn main() -> eframe::Result {
+    let mut app = App::new();
+    match std::env::args().nth(1) {
+        Some(arg) if arg == "--last" => {
+            if let Some(path) = app.recent.last().map(String::from) {
+                app.open(Path::new(&path));
+            }
+        }
+        Some(path) if Path::new(&path).is_dir() => app.browse(Path::new(&path)),
+        Some(path) => app.open(Path::new(&path)),
+        None => {}
+    }
+
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("chip8-emulator-rs")
+            .with_inner_size([960.0, 600.0])
+            .with_drag_and_drop(true),
+        ..Default::default()
+    };
+    eframe::run_native("chip8-emulator-rs", options, Box::new(|_| Ok(Box::new(app))))
+}
+
+impl App {
+    fn new() -> Self {
+        let (config, error) = match Config::default_path().map(|path| Config::load(&path)) {
+            Some(Err(err)) => (
+                Config::default(),
+                Some(format!("could not read the config file: {err}")),
+            ),
+            Some(Ok(config)) => (config, None),
+            None => (Config::default(), None),
+        };
+        let (keys, unknown) = bind_keys(&config.key_map());
+        let error = error
+            .or_else(|| (!unknown.is_empty()).then(|| format!("unknown keys in the key map: {}", unknown.join(", "))));
+        App {
+            path: String::new(),
+            rom_name: String::new(),
+            ips: config.ips,
+            quirks: config.quirks,
+            palette: config.palette,
+            keys,
+            chip8: None,
+            saved: None,
+            error,
+            paused: false,
+            lag: 0.0,
+            debugger: Debugger::default(),
+            keypad: KeypadPanel::default(),
+            screen: None,
+            recent: RecentRoms::load(),
+            database: Database::load(),
+            rom_list: None,
+        }
+    }
+
+    fn open(&mut self, path: &Path) {
+        let location = path.to_string_lossy();
+        // the quirks stay as they are for ROMs the database does not know
+        let built = Chip8::builder()
+            .rom_file(&location)
+            .quirks(self.quirks)
+            .ips(self.ips)
+            .database(self.database.clone())
+            .build();
+        match built {
+            Ok(chip8) => {
+                self.quirks = chip8.quirks();
+                self.chip8 = Some(chip8);
+                self.path = location.into_owned();
+                self.rom_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                self.saved = None;
+                self.error = None;
+                self.lag = 0.0;
+                self.recent.add(&self.path);
+                if let Err(err) = self.recent.save() {
+                    eprintln!("Could not save the recent ROMs: {err}");
+                }
+            }
+            Err(err) => self.error = Some(format!("could not open {}: {err}", path.display())),
+        }
+    }
+
+    fn browse(&mut self, dir: &Path) {
+        match roms::list(dir) {
+            Ok(roms) => self.rom_list = Some(roms),
+            Err(err) => self.error = Some(format!("could not list {}: {err}", dir.display())),
+        }
+    }
+
+    // Starts the ROM over with the quirks picked in the menu, the ones of the database only count on open.
+    fn reset(&mut self) {
+        let Some(chip8) = &mut self.chip8 else {
+            return;
+        };
+        match chip8.open_rom(&self.path) {
+            Ok(()) => {
+                chip8.set_quirks(self.quirks);
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+        self.lag = 0.0;
+    }
+
+    fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        if let Some(chip8) = &mut self.chip8 {
+            chip8.set_quirks(quirks);
+        }
+    }
+
+    // held are the keys of the on-screen keypad under the mouse.
+    fn read_keys(&mut self, ctx: &egui::Context, held: [bool; 16]) {
+        let Some(chip8) = &mut self.chip8 else {
+            return;
+        };
+        // Typing into a text field must not press keys in the game
+        let focused = !ctx.wants_keyboard_input();
+        let keypad = chip8.keypad_mut();
+        for (key, held) in held.into_iter().enumerate() {
+            let typed = focused
+                && self
+                    .keys
+                    .iter()
+                    .any(|&(code, bound)| bound == key as u8 && ctx.input(|input| input.key_down(code)));
+            if held || typed {
+                keypad.press(key as u8);
+            } else {
+                keypad.release(key as u8);
+            }
+        }
+    }
+
+    fn run_frames(&mut self, ctx: &egui::Context) {
+        let dt = ctx.input(|input| input.stable_dt) as f64;
+        let Some(chip8) = &mut self.chip8 else {
+            return;
+        };
+        if self.paused || self.error.is_some() {
+            return;
+        }
+        self.lag = (self.lag + dt).min(FRAME_SECS * MAX_FRAMES_PER_UPDATE as f64);
+
+        while self.lag >= FRAME_SECS {
+            self.lag -= FRAME_SECS;
+            if let Err(err) = run_frame(chip8) {
+                self.error = Some(err.to_string());
+                self.paused = true;
+                return;
+            }
+        }
+    }
+
+    fn apply(&mut self, action: debugger::Action) {
+        let Some(chip8) = &mut self.chip8 else {
+            return;
+        };
+        let result = match action {
+            debugger::Action::None => Ok(()),
+            debugger::Action::TogglePause => {
+                self.paused = !self.paused;
+                Ok(())
+            }
+            debugger::Action::Step => {
+                self.paused = true;
+                chip8.step().map(|_| ())
+            }
+            debugger::Action::Frame => {
+                self.paused = true;
+                run_frame(chip8)
+            }
+        };
+        if let Err(err) = result {
+            self.error = Some(err.to_string());
+        }
+    }
+
+    fn menu_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            egui::MenuBar::new().ui(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("Open ROM…").clicked() {
+                        let file = rfd::FileDialog::new()
+                            .add_filter("CHIP-8 ROM", &roms::EXTENSIONS)
+                            .add_filter("All files", &["*"])
+                            .pick_file();
+                        if let Some(path) = file {
+                            self.open(&path);
+                        }
+                    }
+                    if ui.button("Open Folder…").clicked() {
+                        if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                            self.browse(&dir);
+                        }
+                    }
+                    ui.add_enabled_ui(!self.recent.roms().is_empty(), |ui| {
+                        ui.menu_button("Open Recent", |ui| {
+                            let mut chosen = None;
+                            for rom in self.recent.roms() {
+                                let name = Path::new(rom).file_name().unwrap_or_default().to_string_lossy();
+                                if ui.button(name).on_hover_text(rom).clicked() {
+                                    chosen = Some(rom.clone());
+                                }
+                            }
+                            if let Some(rom) = chosen {
+                                self.open(Path::new(&rom));
+                            }
+                        });
+                    });
+                    ui.separator();
+                    if ui
+                        .add_enabled(self.chip8.is_some(), egui::Button::new("Save State"))
+                        .clicked()
+                    {
+                        self.saved = self.chip8.as_ref().map(Chip8::snapshot);
+                    }
+                    if ui
+                        .add_enabled(self.saved.is_some(), egui::Button::new("Load State"))
+                        .clicked()
+                    {
+                        if let (Some(chip8), Some(saved)) = (&mut self.chip8, &self.saved) {
+                            self.error = chip8.restore(saved).err().map(|err| err.to_string());
+                            self.quirks = saved.quirks;
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("Quit").clicked() {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                });
+
+                ui.menu_button("Emulation", |ui| {
+                    let label = if self.paused { "Resume" } else { "Pause" };
+                    if ui.button(label).clicked() {
+                        self.paused = !self.paused;
+                    }
+                    if ui.button("Reset").clicked() {
+                        self.reset();
+                    }
+                });
+
+                ui.menu_button("Quirks", |ui| {
+                    let mut quirks = self.quirks;
+                    ui.checkbox(&mut quirks.vf_reset, "VF reset (8XY1, 8XY2, 8XY3)");
+                    ui.checkbox(&mut quirks.memory_increment, "Memory increments I (FX55, FX65)");
+                    ui.checkbox(&mut quirks.shift_vy, "Shift VY (8XY6, 8XYE)");
+                    ui.checkbox(&mut quirks.jump_vx, "Jump with VX (BXNN)");
+                    ui.checkbox(&mut quirks.wrap_sprites, "Wrap sprites");
+                    ui.checkbox(&mut quirks.key_wait_on_press, "Key wait ends on press (FX0A)");
+                    ui.separator();
+                    if ui.button("Modern defaults").clicked() {
+                        quirks = Quirks::default();
+                    }
+                    if ui.button("COSMAC VIP").clicked() {
+                        quirks = Quirks::cosmac_vip();
+                    }
+                    if quirks != self.quirks {
+                        self.set_quirks(quirks);
+                    }
+                });
+
+                ui.menu_button("Palette", |ui| {
+                    for theme in &THEMES {
+                        ui.radio_value(&mut self.palette, theme.palette(), theme.name)
+                            .on_hover_text(theme.description);
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        edit_color(ui, &mut self.palette.foreground);
+                        ui.label("Foreground");
+                    });
+                    ui.horizontal(|ui| {
+                        edit_color(ui, &mut self.palette.background);
+                        ui.label("Background");
+                    });
+                });
+
+                ui.menu_button("View", |ui| {
+                    ui.label("Debugger");
+                    ui.radio_value(&mut self.debugger.dock, Dock::Hidden, "Hidden");
+                    ui.radio_value(&mut self.debugger.dock, Dock::Left, "Left");
+                    ui.radio_value(&mut self.debugger.dock, Dock::Right, "Right");
+                    ui.radio_value(&mut self.debugger.dock, Dock::Bottom, "Bottom");
+                    ui.separator();
+                    ui.checkbox(&mut self.keypad.visible, "On-screen keypad");
+                });
+            });
+        });
+    }
+
+    // A list of the ROMs in the browsed folder, clicking one starts it.
+    fn rom_list(&mut self, ctx: &egui::Context) {
+        let Some(roms) = &self.rom_list else {
+            return;
+        };
+        let mut open = true;
+        let mut chosen = None;
+        egui::Window::new("ROMs").open(&mut open).show(ctx, |ui| {
+            if roms.is_empty() {
+                ui.label("No ROMs in this folder");
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for rom in roms {
+                    let name = rom.file_name().unwrap_or_default().to_string_lossy();
+                    let current = name == self.rom_name.as_str();
+                    if ui.selectable_label(current, name).clicked() {
+                        chosen = Some(rom.clone());
+                    }
+                }
+            });
+        });
+        if let Some(rom) = chosen {
+            self.open(&rom);
+        }
+        if !open {
+            self.rom_list = None;
+        }
+    }
+
+    fn status_bar(&self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if let Some(chip8) = &self.chip8 {
+                    ui.label(&self.rom_name);
+                    if let Some(info) = &chip8.loaded_rom().recognized {
+                        ui.label(format!("({}, {})", info.title, info.platform));
+                    }
+                    if self.paused {
+                        ui.label("(paused)");
+                    }
+                } else {
+                    ui.label("No ROM loaded, use File > Open ROM or drop a file on the window");
+                }
+                if let Some(err) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+            });
+        });
+    }
+
+    fn screen(&mut self, ctx: &egui::Context) {
+        let image = match &self.chip8 {
+            Some(chip8) => screen_image(chip8.pixels(), self.palette),
+            None => ColorImage::filled([DISPLAY_WIDTH, DISPLAY_HEIGHT], color(self.palette.background)),
+        };
+        let texture = match &mut self.screen {
+            Some(texture) => {
+                texture.set(image, TextureOptions::NEAREST);
+                texture
+            }
+            None => self
+                .screen
+                .insert(ctx.load_texture("screen", image, TextureOptions::NEAREST)),
+        };
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // Integer scales keep every CHIP-8 pixel the same size
+            let available = ui.available_size();
+            let scale = (available.x / DISPLAY_WIDTH as f32)
+                .min(available.y / DISPLAY_HEIGHT as f32)
+                .floor()
+                .max(1.0);
+            let size = egui::vec2(DISPLAY_WIDTH as f32, DISPLAY_HEIGHT as f32) * scale;
+            ui.centered_and_justified(|ui| ui.add(egui::Image::new((texture.id(), size)).fit_to_exact_size(size)));
+        });
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let dropped = ctx.input(|input| input.raw.dropped_files.first().and_then(|file| file.path.clone()));
+        if let Some(path) = dropped {
+            self.open(&path);
+        }
+
+        self.menu_bar(ctx);
+        self.status_bar(ctx);
+        self.rom_list(ctx);
+        let mut pressed = [false; 16];
+        if let Some(chip8) = &mut self.chip8 {
+            let keypad = chip8.keypad_mut();
+            for (key, pressed) in pressed.iter_mut().enumerate() {
+                *pressed = keypad.is_pressed(key as u8);
+            }
+        }
+        let held = self.keypad.show(ctx, &pressed, &self.keys);
+
+        self.read_keys(ctx, held);
+        self.run_frames(ctx);
+        if let Some(chip8) = &self.chip8 {
+            let action = self.debugger.show(ctx, &chip8.snapshot(), self.paused);
+            self.apply(action);
+        }
+        self.screen(ctx);
+
+        // The emulator runs on every repaint
+        if !self.paused {
+            ctx.request_repaint();
+        }
+    }
+}
+
+// A 60 Hz frame of the config's instructions per second, with the timers and cheats of the frame.
+fn run_frame(chip8: &mut Chip8) -> Result<(), Chip8Error> {
+    chip8.frames().next().map_or(Ok(()), |frame| frame.map(|_| ()))
+}
+
+// The egui keys for the names in the key map, and the names egui has no key for. egui tells the keys by their
+// labels, so the names are used as they are instead of by their QWERTY positions.
+fn bind_keys(map: &KeyMap) -> (Vec<(Key, u8)>, Vec<String>) {
+    let mut keys = Vec::new();
+    let mut unknown = Vec::new();
+    for (key, name) in map.iter() {
+        // egui does not tell the keypad's digits from the others
+        match Key::from_name(name.strip_prefix("Keypad ").unwrap_or(name)) {
+            Some(code) => keys.push((code, key)),
+            None => unknown.push(name.to_string()),
+        }
+    }
+    (keys, unknown)
+}
+
+fn color(rgb: Rgb) -> Color32 {
+    Color32::from_rgb(rgb.0, rgb.1, rgb.2)
+}
+
+fn edit_color(ui: &mut egui::Ui, rgb: &mut Rgb) {
+    let mut srgb = [rgb.0, rgb.1, rgb.2];
+    ui.color_edit_button_srgb(&mut srgb);
+    *rgb = Rgb(srgb[0], srgb[1], srgb[2]);
+}
+
+fn screen_image(pixels: &[u8], palette: Palette) -> ColorImage {
+    let (foreground, background) = (color(palette.foreground), color(palette.background));
+    let colors = pixels
+        .iter()
+        .map(|&pixel| if pixel != 0 { foreground } else { background })
+        .collect();
+    ColorImage::new([DISPLAY_WIDTH, DISPLAY_HEIGHT], colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_image_uses_palette() {
+        let mut pixels = [0; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+        pixels[1] = 1;
+        let palette = THEMES[1].palette();
+        let image = screen_image(&pixels, palette);
+        assert_eq!(image.pixels[0], Color32::from_rgb(0x0F, 0x38, 0x0F));
+        assert_eq!(image.pixels[1], Color32::from_rgb(0x9B, 0xBC, 0x0F));
+    }
+
+    #[test]
+    fn test_keys_come_from_the_key_map() {
+        let config: Config = "[keys]\n0 = \"Space\"\n1 = \"Keypad 7\"\n2 = \"Nope\"".parse().unwrap();
+        let (keys, unknown) = bind_keys(&config.key_map());
+        assert!(keys.contains(&(Key::Space, 0x0)));
+        assert!(keys.contains(&(Key::Num7, 0x1)));
+        assert!(keys.contains(&(Key::Q, 0x4)));
+        assert_eq!(unknown, vec!["Nope".to_string()]);
+    }
+}
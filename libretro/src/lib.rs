@@ -0,0 +1,390 @@
+// The entry points are specified by libretro.h, RetroArch only calls them with valid pointers.
+#![allow(clippy::missing_safety_doc)]
+
+mod libretro;
+
+use std::ffi::{c_uint, c_void};
+use std::slice;
+use std::sync::Mutex;
+
+use chip8_emulator_rs::machine::{DEFAULT_IPS, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use chip8_emulator_rs::{Machine, Quirks};
+
+use libretro::*;
+
+const FPS: f64 = 60.0;
+const SAMPLE_RATE: f64 = 44100.0;
+const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE / FPS) as usize;
+const TONE_FREQ: f64 = 440.0;
+const VOLUME: i16 = i16::MAX / 10;
+// The desktop emulator's speed
+const CYCLES_PER_FRAME: u32 = DEFAULT_IPS / FPS as u32;
+const SEED: u64 = 0x5EED;
+
+const FOREGROUND: u32 = 0x00FF_FFFF;
+const BACKGROUND: u32 = 0x0000_0000;
+
+// Same layout as the desktop frontend: 1234 / QWER / ASDF / ZXCV
+const KEYBOARD: [(c_uint, u8); 16] = [
+    (b'1' as c_uint, 0x1),
+    (b'2' as c_uint, 0x2),
+    (b'3' as c_uint, 0x3),
+    (b'4' as c_uint, 0xC),
+    (b'q' as c_uint, 0x4),
+    (b'w' as c_uint, 0x5),
+    (b'e' as c_uint, 0x6),
+    (b'r' as c_uint, 0xD),
+    (b'a' as c_uint, 0x7),
+    (b's' as c_uint, 0x8),
+    (b'd' as c_uint, 0x9),
+    (b'f' as c_uint, 0xE),
+    (b'z' as c_uint, 0xA),
+    (b'x' as c_uint, 0x0),
+    (b'c' as c_uint, 0xB),
+    (b'v' as c_uint, 0xF),
+];
+
+// Most games move with 2/4/6/8 and act with 5, the remaining keys go to the other buttons.
+const JOYPAD: [(c_uint, u8); 16] = [
+    (RETRO_DEVICE_ID_JOYPAD_UP, 0x2),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, 0x8),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, 0x4),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, 0x6),
+    (RETRO_DEVICE_ID_JOYPAD_A, 0x5),
+    (RETRO_DEVICE_ID_JOYPAD_B, 0x0),
+    (RETRO_DEVICE_ID_JOYPAD_Y, 0x1),
+    (RETRO_DEVICE_ID_JOYPAD_X, 0x3),
+    (RETRO_DEVICE_ID_JOYPAD_L, 0x7),
+    (RETRO_DEVICE_ID_JOYPAD_R, 0x9),
+    (RETRO_DEVICE_ID_JOYPAD_L2, 0xA),
+    (RETRO_DEVICE_ID_JOYPAD_R2, 0xB),
+    (RETRO_DEVICE_ID_JOYPAD_L3, 0xC),
+    (RETRO_DEVICE_ID_JOYPAD_R3, 0xD),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, 0xE),
+    (RETRO_DEVICE_ID_JOYPAD_START, 0xF),
+];
+
+#[derive(Default)]
+struct Core {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+    rom: Vec<u8>,
+    machine: Option<Machine>,
+    frame: Vec<u32>,
+    audio: Vec<i16>,
+    phase: f64,
+    crashed: bool,
+}
+
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+fn with_core<T>(f: impl FnOnce(&mut Core) -> T) -> T {
+    let mut core = CORE.lock().unwrap_or_else(|err| err.into_inner());
+    f(core.get_or_insert_with(Core::default))
+}
+
+impl Core {
+    fn load(&mut self) -> bool {
+        match Machine::new(&self.rom, Quirks::default(), SEED) {
+            Ok(machine) => {
+                self.machine = Some(machine);
+                self.crashed = false;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn run_frame(&mut self) {
+        if let Some(poll) = self.input_poll {
+            unsafe { poll() };
+        }
+        let pressed = self.pressed_keys();
+        let Some(machine) = &mut self.machine else {
+            return;
+        };
+
+        for (key, &down) in pressed.iter().enumerate() {
+            if down {
+                machine.keypad_mut().press(key as u8);
+            } else {
+                machine.keypad_mut().release(key as u8);
+            }
+        }
+
+        // RetroArch keeps calling retro_run after a crash, the last frame stays on screen
+        if !self.crashed {
+            self.crashed = machine.run_frame(CYCLES_PER_FRAME).is_err();
+        }
+
+        self.frame.clear();
+        self.frame.extend(
+            machine
+                .pixels()
+                .iter()
+                .map(|&pixel| if pixel != 0 { FOREGROUND } else { BACKGROUND }),
+        );
+        if let Some(video_refresh) = self.video_refresh {
+            unsafe {
+                video_refresh(
+                    self.frame.as_ptr() as *const c_void,
+                    DISPLAY_WIDTH as c_uint,
+                    DISPLAY_HEIGHT as c_uint,
+                    DISPLAY_WIDTH * size_of::<u32>(),
+                )
+            };
+        }
+
+        let beeping = machine.beeping() && !self.crashed;
+        self.audio.clear();
+        for _ in 0..SAMPLES_PER_FRAME {
+            let sample = match (beeping, self.phase < 0.5) {
+                (false, _) => 0,
+                (true, true) => VOLUME,
+                (true, false) => -VOLUME,
+            };
+            self.phase = (self.phase + TONE_FREQ / SAMPLE_RATE) % 1.0;
+            self.audio.extend([sample, sample]);
+        }
+        if let Some(audio_sample_batch) = self.audio_sample_batch {
+            unsafe { audio_sample_batch(self.audio.as_ptr(), SAMPLES_PER_FRAME) };
+        }
+    }
+
+    fn pressed_keys(&self) -> [bool; 16] {
+        let mut pressed = [false; 16];
+        let Some(input_state) = self.input_state else {
+            return pressed;
+        };
+        for &(id, key) in &KEYBOARD {
+            pressed[key as usize] |= unsafe { input_state(0, RETRO_DEVICE_KEYBOARD, 0, id) } != 0;
+        }
+        for &(id, key) in &JOYPAD {
+            pressed[key as usize] |= unsafe { input_state(0, RETRO_DEVICE_JOYPAD, 0, id) } != 0;
+        }
+        pressed
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    if let Some(info) = info.as_mut() {
+        *info = SystemInfo {
+            library_name: c"CHIP-8".as_ptr(),
+            library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast(),
+            valid_extensions: c"ch8|c8".as_ptr(),
+            need_fullpath: false,
+            block_extract: false,
+        };
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    if let Some(info) = info.as_mut() {
+        *info = SystemAvInfo {
+            geometry: GameGeometry {
+                base_width: DISPLAY_WIDTH as c_uint,
+                base_height: DISPLAY_HEIGHT as c_uint,
+                max_width: DISPLAY_WIDTH as c_uint,
+                max_height: DISPLAY_HEIGHT as c_uint,
+                aspect_ratio: DISPLAY_WIDTH as f32 / DISPLAY_HEIGHT as f32,
+            },
+            timing: SystemTiming {
+                fps: FPS,
+                sample_rate: SAMPLE_RATE,
+            },
+        };
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(environment: EnvironmentFn) {
+    with_core(|core| core.environment = Some(environment));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(video_refresh: VideoRefreshFn) {
+    with_core(|core| core.video_refresh = Some(video_refresh));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(audio_sample_batch: AudioSampleBatchFn) {
+    with_core(|core| core.audio_sample_batch = Some(audio_sample_batch));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(input_poll: InputPollFn) {
+    with_core(|core| core.input_poll = Some(input_poll));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(input_state: InputStateFn) {
+    with_core(|core| core.input_state = Some(input_state));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    with_core(|core| core.load());
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    with_core(|core| core.run_frame());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    let Some(game) = game.as_ref() else {
+        return false;
+    };
+    if game.data.is_null() {
+        return false;
+    }
+    let rom = slice::from_raw_parts(game.data as *const u8, game.size).to_vec();
+
+    with_core(|core| {
+        if let Some(environment) = core.environment {
+            let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+            if !environment(
+                RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+                &mut format as *mut c_uint as *mut c_void,
+            ) {
+                return false;
+            }
+        }
+        core.rom = rom;
+        core.load()
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(_type: c_uint, _info: *const GameInfo, _num: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    with_core(|core| {
+        core.machine = None;
+        core.rom.clear();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+// Save states are not supported yet.
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize(_data: *mut c_void, _size: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unserialize(_data: *const c_void, _size: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const std::ffi::c_char) {}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static LIT_PIXELS: AtomicUsize = AtomicUsize::new(0);
+    static AUDIO_FRAMES: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn environment(_cmd: c_uint, _data: *mut c_void) -> bool {
+        true
+    }
+
+    unsafe extern "C" fn video_refresh(data: *const c_void, width: c_uint, height: c_uint, _pitch: usize) {
+        let pixels = slice::from_raw_parts(data as *const u32, (width * height) as usize);
+        let lit = pixels.iter().filter(|&&pixel| pixel == FOREGROUND).count();
+        LIT_PIXELS.store(lit, Ordering::SeqCst);
+    }
+
+    unsafe extern "C" fn audio_sample_batch(_data: *const i16, frames: usize) -> usize {
+        AUDIO_FRAMES.fetch_add(frames, Ordering::SeqCst);
+        frames
+    }
+
+    unsafe extern "C" fn input_poll() {}
+
+    unsafe extern "C" fn input_state(_port: c_uint, _device: c_uint, _index: c_uint, _id: c_uint) -> i16 {
+        0
+    }
+
+    #[test]
+    fn test_core_runs_frames() {
+        // LD V0, 8; LD F, V0; DRW V1, V1, 5; JP 0x206
+        let rom: [u8; 8] = [0x60, 0x08, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x06];
+        let game = GameInfo {
+            path: std::ptr::null(),
+            data: rom.as_ptr() as *const c_void,
+            size: rom.len(),
+            meta: std::ptr::null(),
+        };
+
+        retro_init();
+        retro_set_environment(environment);
+        retro_set_video_refresh(video_refresh);
+        retro_set_audio_sample_batch(audio_sample_batch);
+        retro_set_input_poll(input_poll);
+        retro_set_input_state(input_state);
+        assert!(unsafe { retro_load_game(&game) });
+
+        retro_run();
+        retro_run();
+        // the font's 8 has 16 pixels set
+        assert_eq!(LIT_PIXELS.load(Ordering::SeqCst), 16);
+        assert_eq!(AUDIO_FRAMES.load(Ordering::SeqCst), 2 * SAMPLES_PER_FRAME);
+
+        retro_unload_game();
+        retro_deinit();
+    }
+}
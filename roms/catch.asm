@@ -0,0 +1,116 @@
+; Shown by --builtin catch: move the paddle with 7 and 9 to catch the falling balls. Three
+; missed balls end the game, any key starts a new one. Public domain, written for chip8-emulator-rs in the
+; mnemonics of its disassembler.
+;
+; V8 paddle x, V9 paddle y, VA ball x, VB ball y, VC score, VD balls left, V0-V4 scratch
+
+start:
+  CLS
+  LD VC, 0
+  LD VD, 3
+  LD V8, 28
+  LD V9, 30
+  CALL draw_score
+  LD I, paddle
+  DRW V8, V9, 1
+  CALL new_ball
+
+loop:
+  LD V0, 2
+  LD DT, V0
+wait:
+  LD V0, DT
+  SE V0, 0
+  JP wait
+
+  ; the paddle moves two pixels per frame and stops at the edges
+  LD I, paddle
+  DRW V8, V9, 1
+  LD V0, 7
+  SKNP V0
+  ADD V8, 0xFE
+  LD V0, 9
+  SKNP V0
+  ADD V8, 2
+  LD V0, 0x80
+  AND V0, V8
+  SE V0, 0
+  LD V8, 0
+  LD V0, 58
+  SUB V0, V8
+  SE VF, 1
+  LD V8, 58
+  DRW V8, V9, 1
+
+  ; the ball falls a pixel per frame and is caught when it runs into the paddle
+  LD I, ball
+  DRW VA, VB, 2
+  ADD VB, 1
+  DRW VA, VB, 2
+  SE VF, 1
+  JP not_caught
+  DRW VA, VB, 2
+  LD I, paddle
+  DRW V8, V9, 1
+  CALL draw_score
+  ADD VC, 1
+  CALL draw_score
+  LD V0, 3
+  LD ST, V0
+  CALL new_ball
+  JP loop
+
+not_caught:
+  SE VB, 30
+  JP loop
+  DRW VA, VB, 2
+  ADD VD, 0xFF
+  SE VD, 0
+  JP next_ball
+  LD V0, K
+  JP start
+next_ball:
+  LD V0, 12
+  LD ST, V0
+  CALL new_ball
+  JP loop
+
+; A ball at a random column below the score
+new_ball:
+  RND VA, 0x3F
+  LD V0, 62
+  SUB V0, VA
+  SE VF, 1
+  LD VA, 62
+  LD VB, 6
+  LD I, ball
+  DRW VA, VB, 2
+  RET
+
+; Draws the score in decimal at the top left, drawing it again erases it
+draw_score:
+  LD I, digits
+  LD B, VC
+  LD V2, [I]
+  LD V3, 1
+  LD V4, 0
+  LD I, digits
+  LD F, V0
+  DRW V3, V4, 5
+  ADD V3, 5
+  LD F, V1
+  DRW V3, V4, 5
+  ADD V3, 5
+  LD F, V2
+  DRW V3, V4, 5
+  RET
+
+digits:
+  DB 0, 0, 0
+
+paddle:
+  DB 0b11111100
+
+ball:
+  DB 0b11000000
+  DB 0b11000000
@@ -0,0 +1,92 @@
+; Shown by --builtin splash: CHIP-8 in large letters, then the program halts.
+; Public domain, written for chip8-emulator-rs in the mnemonics of its disassembler.
+
+start:
+  CLS
+  LD V0, 6        ; x of the first letter
+  LD V1, 11       ; y of all letters
+  LD I, letter_c
+  DRW V0, V1, 9
+  ADD V0, 9
+  LD I, letter_h
+  DRW V0, V1, 9
+  ADD V0, 9
+  LD I, letter_i
+  DRW V0, V1, 9
+  ADD V0, 9
+  LD I, letter_p
+  DRW V0, V1, 9
+  ADD V0, 9
+  LD I, dash
+  DRW V0, V1, 9
+  ADD V0, 9
+  LD I, eight
+  DRW V0, V1, 9
+halt:
+  JP halt
+
+letter_c:
+  DB 0b01111100
+  DB 0b11000110
+  DB 0b11000000
+  DB 0b11000000
+  DB 0b11000000
+  DB 0b11000000
+  DB 0b11000000
+  DB 0b11000110
+  DB 0b01111100
+
+letter_h:
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b11111110
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b11000110
+
+letter_i:
+  DB 0b11111100
+  DB 0b00110000
+  DB 0b00110000
+  DB 0b00110000
+  DB 0b00110000
+  DB 0b00110000
+  DB 0b00110000
+  DB 0b00110000
+  DB 0b11111100
+
+letter_p:
+  DB 0b11111100
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b11111100
+  DB 0b11000000
+  DB 0b11000000
+  DB 0b11000000
+  DB 0b11000000
+
+dash:
+  DB 0b00000000
+  DB 0b00000000
+  DB 0b00000000
+  DB 0b00000000
+  DB 0b01111100
+  DB 0b00000000
+  DB 0b00000000
+  DB 0b00000000
+  DB 0b00000000
+
+eight:
+  DB 0b01111100
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b01111100
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b01111100
@@ -0,0 +1,185 @@
+; Shown by --builtin test: checks the arithmetic, skips, subroutines, BCD, memory and font instructions.
+; OK means all of them passed, otherwise E and the hex number of the first failed check is shown.
+; The checks hold with and without any of the quirks. Public domain, written for chip8-emulator-rs.
+
+start:
+  CLS
+  ; 1: 7XNN wraps around and leaves VF alone
+  LD VE, 1
+  LD VF, 0x55
+  LD V0, 0xFF
+  ADD V0, 2
+  SE V0, 1
+  JP fail
+  SE VF, 0x55
+  JP fail
+  ; 2: 8XY4 sets the carry
+  LD VE, 2
+  LD V0, 0xFF
+  LD V1, 2
+  ADD V0, V1
+  SE V0, 1
+  JP fail
+  SE VF, 1
+  JP fail
+  ; 3: 8XY5 clears VF on a borrow
+  LD VE, 3
+  LD V0, 1
+  LD V1, 2
+  SUB V0, V1
+  SE V0, 0xFF
+  JP fail
+  SE VF, 0
+  JP fail
+  ; 4: 8XY7 subtracts the other way around
+  LD VE, 4
+  LD V0, 2
+  LD V1, 5
+  SUBN V0, V1
+  SE V0, 3
+  JP fail
+  SE VF, 1
+  JP fail
+  ; 5: 8XY1, 8XY2 and 8XY3
+  LD VE, 5
+  LD V0, 0x0F
+  LD V1, 0x3C
+  LD V2, V0
+  OR V2, V1
+  SE V2, 0x3F
+  JP fail
+  LD V2, V0
+  AND V2, V1
+  SE V2, 0x0C
+  JP fail
+  XOR V0, V1
+  SE V0, 0x33
+  JP fail
+  ; 6: 8XY6 and 8XYE shift out into VF, VY equals VX so shift-vy makes no difference
+  LD VE, 6
+  LD V0, 0x81
+  LD V1, 0x81
+  SHR V0, V1
+  SE V0, 0x40
+  JP fail
+  SE VF, 1
+  JP fail
+  LD V0, 0x81
+  LD V1, 0x81
+  SHL V0, V1
+  SE V0, 0x02
+  JP fail
+  SE VF, 1
+  JP fail
+  ; 7: 5XY0, 9XY0 and 4XNN
+  LD VE, 7
+  LD V0, 3
+  LD V1, 3
+  SE V0, V1
+  JP fail
+  SNE V0, V1
+  JP skips_done
+  JP fail
+skips_done:
+  SNE V0, 4
+  JP fail
+  ; 8: 2NNN and 00EE
+  LD VE, 8
+  LD V0, 0
+  CALL set_v0
+  SE V0, 1
+  JP fail
+  ; 9: FX33 and FX65
+  LD VE, 9
+  LD V0, 254
+  LD I, scratch
+  LD B, V0
+  LD V2, [I]
+  SE V0, 2
+  JP fail
+  SE V1, 5
+  JP fail
+  SE V2, 4
+  JP fail
+  ; A: FX55 and FX1E
+  LD VE, 0xA
+  LD V0, 0xA5
+  LD V1, 0x5A
+  LD I, scratch
+  LD [I], V1
+  LD I, scratch
+  LD V0, 1
+  ADD I, V0
+  LD V0, [I]
+  SE V0, 0x5A
+  JP fail
+  ; B: FX15 and FX07
+  LD VE, 0xB
+  LD V0, 10
+  LD DT, V0
+  LD V1, DT
+  SNE V1, 0
+  JP fail
+  ; C: BNNN, V2 is set too for the jump-vx quirk, which reads VX of BXNN
+  LD VE, 0xC
+  LD V0, 2
+  LD V2, 2
+  JP V0, jump_table
+jump_table:
+  JP fail
+  JP jumped
+jumped:
+  ; D: FX29 points at the font
+  LD VE, 0xD
+  LD V0, 0xA
+  LD F, V0
+  LD V1, [I]
+  SE V0, 0xF0
+  JP fail
+  SE V1, 0x90
+  JP fail
+
+passed:
+  LD V0, 24
+  LD V1, 13
+  LD I, letter_o
+  DRW V0, V1, 6
+  ADD V0, 9
+  LD I, letter_k
+  DRW V0, V1, 6
+  JP halt
+
+fail:
+  LD V0, 0xE
+  LD V1, 13
+  LD V2, 26
+  LD F, V0
+  DRW V2, V1, 5
+  ADD V2, 8
+  LD F, VE
+  DRW V2, V1, 5
+halt:
+  JP halt
+
+set_v0:
+  LD V0, 1
+  RET
+
+scratch:
+  DB 0, 0, 0
+
+letter_o:
+  DB 0b01111100
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b11000110
+  DB 0b01111100
+
+letter_k:
+  DB 0b11000110
+  DB 0b11001100
+  DB 0b11111000
+  DB 0b11001100
+  DB 0b11000110
+  DB 0b11000110
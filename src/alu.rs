@@ -0,0 +1,70 @@
+// The arithmetic of the 8XYN instructions as pure functions. Each returns the new value of VX and the value of
+// VF, which is written after VX, so with X = F the flag wins.
+
+// 8XY4: VF is 1 when the sum does not fit in a byte.
+pub fn add(x: u8, y: u8) -> (u8, u8) {
+    let (value, carry) = x.overflowing_add(y);
+    (value, carry as u8)
+}
+
+// 8XY5 is sub(VX, VY) and 8XY7 is sub(VY, VX): VF is 1 when nothing had to be borrowed, so 0 means the result
+// went below zero.
+pub fn sub(x: u8, y: u8) -> (u8, u8) {
+    let (value, borrow) = x.overflowing_sub(y);
+    (value, !borrow as u8)
+}
+
+// 8XY6: VF is the bit shifted out.
+pub fn shift_right(value: u8) -> (u8, u8) {
+    (value >> 1, value & 0x1)
+}
+
+// 8XYE: VF is the bit shifted out.
+pub fn shift_left(value: u8) -> (u8, u8) {
+    (value << 1, value >> 7)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_add_carries_what_does_not_fit(x: u8, y: u8) {
+            let (value, carry) = add(x, y);
+            prop_assert_eq!(value as u16 + carry as u16 * 0x100, x as u16 + y as u16);
+            prop_assert!(carry <= 1);
+        }
+
+        #[test]
+        fn test_add_commutes(x: u8, y: u8) {
+            prop_assert_eq!(add(x, y), add(y, x));
+        }
+
+        #[test]
+        fn test_sub_flags_no_borrow(x: u8, y: u8) {
+            let (value, no_borrow) = sub(x, y);
+            prop_assert_eq!(no_borrow, (x >= y) as u8);
+            prop_assert_eq!(value as i16 - (1 - no_borrow as i16) * 0x100, x as i16 - y as i16);
+        }
+
+        #[test]
+        fn test_sub_undoes_add(x: u8, y: u8) {
+            let (sum, carry) = add(x, y);
+            let (difference, no_borrow) = sub(sum, y);
+            prop_assert_eq!(difference, x);
+            // the subtraction borrows exactly when the addition carried
+            prop_assert_eq!(no_borrow, 1 - carry);
+        }
+
+        #[test]
+        fn test_shifts_keep_every_bit(value: u8) {
+            let (right, low_bit) = shift_right(value);
+            prop_assert_eq!(right << 1 | low_bit, value);
+            let (left, high_bit) = shift_left(value);
+            prop_assert_eq!(high_bit << 7 | left >> 1, value);
+            prop_assert!(low_bit <= 1 && high_bit <= 1);
+        }
+    }
+}
@@ -0,0 +1,478 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::machine::{MEMORY_SIZE, PROGRAM_START, STACK_SIZE};
+use crate::opcode::Opcode;
+
+// Tracking the value of I multiplies the paths to follow, past this many it is given up on
+const MAX_STATES: usize = 1 << 16;
+
+// The CHIP-8 extensions that added instructions of their own. This emulator runs neither of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Extension {
+    SuperChip,
+    XoChip,
+}
+
+impl Extension {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Extension::SuperChip => "SUPER-CHIP",
+            Extension::XoChip => "XO-CHIP",
+        }
+    }
+}
+
+// An instruction of one of the extensions found in a ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtensionUse {
+    pub addr: u16,
+    pub instruction: u16,
+    pub extension: Extension,
+    pub description: &'static str,
+}
+
+// The instruction at addr of a ROM loaded at the program start, None past its end.
+pub fn instruction_at(rom: &[u8], addr: u16) -> Option<u16> {
+    let offset = (addr as usize).checked_sub(PROGRAM_START)?;
+    match rom.get(offset..offset + 2) {
+        Some(&[high, low]) => Some((high as u16) << 8 | low as u16),
+        _ => None,
+    }
+}
+
+// Which extension instruction is the one, if any. 00CN and DXY0 also mean something on a plain CHIP-8
+// (a machine code call and a sprite without rows) but no program uses them that way.
+pub fn extension(instruction: u16) -> Option<(Extension, &'static str)> {
+    let x = (instruction >> 8) & 0xF;
+    let known = match (instruction >> 12, instruction & 0xFF) {
+        (0x0, 0xFB) if x == 0 => (Extension::SuperChip, "scroll right"),
+        (0x0, 0xFC) if x == 0 => (Extension::SuperChip, "scroll left"),
+        (0x0, 0xFD) if x == 0 => (Extension::SuperChip, "exit"),
+        (0x0, 0xFE) if x == 0 => (Extension::SuperChip, "low resolution"),
+        (0x0, 0xFF) if x == 0 => (Extension::SuperChip, "high resolution"),
+        (0x0, nn) if x == 0 && nn & 0xF0 == 0xC0 => (Extension::SuperChip, "scroll down"),
+        (0x0, nn) if x == 0 && nn & 0xF0 == 0xD0 => (Extension::XoChip, "scroll up"),
+        (0xD, nn) if nn & 0xF == 0 => (Extension::SuperChip, "16x16 sprite"),
+        (0xF, 0x30) => (Extension::SuperChip, "large font digit"),
+        (0xF, 0x75) => (Extension::SuperChip, "save flags"),
+        (0xF, 0x85) => (Extension::SuperChip, "load flags"),
+        (0x5, nn) if nn & 0xF == 0x2 => (Extension::XoChip, "save register range"),
+        (0x5, nn) if nn & 0xF == 0x3 => (Extension::XoChip, "load register range"),
+        (0xF, 0x00) if x == 0 => (Extension::XoChip, "load 16-bit I"),
+        (0xF, 0x01) => (Extension::XoChip, "select plane"),
+        (0xF, 0x02) if x == 0 => (Extension::XoChip, "load audio pattern"),
+        (0xF, 0x3A) => (Extension::XoChip, "set pitch"),
+        _ => return None,
+    };
+    Some(known)
+}
+
+// The extension instructions among the ones the analysis found to be code.
+pub fn extension_uses(rom: &[u8], analysis: &Analysis) -> Vec<ExtensionUse> {
+    analysis
+        .instructions
+        .iter()
+        .filter_map(|&addr| {
+            let instruction = instruction_at(rom, addr)?;
+            let (extension, description) = extension(instruction)?;
+            Some(ExtensionUse {
+                addr,
+                instruction,
+                extension,
+                description,
+            })
+        })
+        .collect()
+}
+
+// The instructions a program starts with, up to and including the first one that does not simply go on to
+// the next: a jump, call, return, skip, one of an extension or one that does not decode. Ends early at the
+// end of the ROM.
+pub fn entry_block(rom: &[u8]) -> Vec<(u16, u16)> {
+    let mut block = Vec::new();
+    let mut addr = PROGRAM_START as u16;
+    while let Some(instruction) = instruction_at(rom, addr) {
+        block.push((addr, instruction));
+        if !falls_through(instruction) {
+            break;
+        }
+        addr += 2;
+    }
+    block
+}
+
+// Something about the program that will go wrong or only works on some interpreters.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Problem {
+    pub addr: u16,
+    pub message: String,
+}
+
+// What following every path through the program from its start found. The bytes of the ROM that are not
+// part of one of the instructions are data, or code only reached through a computed jump.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    pub instructions: BTreeSet<u16>,
+    pub problems: Vec<Problem>,
+    // instructions that do something else depending on the quirks
+    pub quirk_dependent: Vec<Problem>,
+}
+
+impl Analysis {
+    pub fn is_code(&self, addr: u16) -> bool {
+        self.instructions.contains(&addr) || addr > 0 && self.instructions.contains(&(addr - 1))
+    }
+}
+
+// Where a path is at: the address, how many calls deep, I when it is known, and the FX55 or FX65 that moved
+// I with the memory-increment quirk unless I was set again since.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
+    addr: u16,
+    depth: usize,
+    i: Option<u16>,
+    moved_i: Option<u16>,
+}
+
+// Follows the jumps, calls and both ways of every skip from the program start. A call is assumed to return
+// to the instruction after it, and computed jumps (BNNN) are not followed.
+pub fn analyze(rom: &[u8]) -> Analysis {
+    let end = (PROGRAM_START + rom.len()) as u16;
+    let mut instructions = BTreeSet::new();
+    let mut problems = BTreeSet::new();
+    let mut problem = |addr: u16, message: String| {
+        problems.insert(Problem { addr, message });
+    };
+    let mut quirk_dependent = BTreeSet::new();
+    let mut depends = |addr: u16, message: String| {
+        quirk_dependent.insert(Problem { addr, message });
+    };
+    let start = State {
+        addr: PROGRAM_START as u16,
+        depth: 0,
+        i: None,
+        moved_i: None,
+    };
+    let mut visited = HashSet::new();
+    let mut pending = vec![start];
+    while let Some(state) = pending.pop() {
+        let state = if visited.len() > MAX_STATES {
+            State { i: None, ..state }
+        } else {
+            state
+        };
+        if !visited.insert(state) {
+            continue;
+        }
+        let addr = state.addr;
+        let Some(instruction) = instruction_at(rom, addr) else {
+            problem(addr, "the program runs past the end of the ROM".to_string());
+            continue;
+        };
+        instructions.insert(addr);
+        let next = State {
+            addr: addr + 2,
+            ..state
+        };
+        if let Some((extension, description)) = extension(instruction) {
+            problem(
+                addr,
+                format!("{} instruction ({}), not supported", extension.name(), description),
+            );
+            // followed anyway so the rest of an extension program counts as code
+            match instruction {
+                0x00FD => {}
+                0xF000 => pending.push(State {
+                    addr: addr + 4,
+                    ..state
+                }),
+                _ => pending.push(next),
+            }
+            continue;
+        }
+        let Ok(opcode) = Opcode::decode(instruction) else {
+            problem(addr, format!("unknown instruction {:04X}", instruction));
+            continue;
+        };
+        match (opcode, state.moved_i) {
+            (
+                Opcode::Draw(_, _, _)
+                | Opcode::AddI(_)
+                | Opcode::StoreBCD(_)
+                | Opcode::StoreRegs(_)
+                | Opcode::LoadRegs(_),
+                Some(moved_at),
+            ) => depends(
+                addr,
+                format!(
+                    "uses I after {:#05X}, which only moves it past the registers with the memory-increment quirk, \
+                     set I again to work with both",
+                    moved_at
+                ),
+            ),
+            (Opcode::ShiftRight(x, y) | Opcode::ShiftLeft(x, y), _) if x != y && !copies(rom, addr, x, y) => depends(
+                addr,
+                format!(
+                    "shifts V{:X} into V{:X} with the shift-vy quirk and V{:X} itself without, copy V{:X} to V{:X} \
+                     first to work with both",
+                    y, x, x, y, x
+                ),
+            ),
+            (Opcode::JumpV0(base), _) if base >> 8 != 0 => depends(
+                addr,
+                format!(
+                    "jumps to {:#05X} + V{:X} with the jump-vx quirk and + V0 without",
+                    base,
+                    base >> 8
+                ),
+            ),
+            _ => {}
+        }
+        match opcode {
+            Opcode::Jump(target) if target == addr => {}
+            Opcode::Jump(target) => {
+                if check_target(target, end, &mut problem, addr) {
+                    pending.push(State { addr: target, ..state });
+                }
+            }
+            Opcode::Call(target) => {
+                if state.depth == STACK_SIZE {
+                    problem(
+                        addr,
+                        format!("the call overflows the stack of {} return addresses", STACK_SIZE),
+                    );
+                } else if check_target(target, end, &mut problem, addr) {
+                    pending.push(State {
+                        addr: target,
+                        depth: state.depth + 1,
+                        ..state
+                    });
+                }
+                // what the subroutine did to I is not known here
+                pending.push(State {
+                    i: None,
+                    moved_i: None,
+                    ..next
+                });
+            }
+            Opcode::Return if state.depth == 0 => problem(addr, "return with an empty stack".to_string()),
+            Opcode::Return => {}
+            Opcode::JumpV0(base) => {
+                if base >= end {
+                    problem(
+                        addr,
+                        format!("computed jump from {:#05X}, past the end of the ROM", base),
+                    );
+                }
+            }
+            Opcode::MachineCall(target) => {
+                problem(
+                    addr,
+                    format!("machine code call to {:#05X}, which cannot run here", target),
+                );
+                pending.push(next);
+            }
+            Opcode::SkipEqualVal(_, _)
+            | Opcode::SkipNotEqualVal(_, _)
+            | Opcode::SkipEqual(_, _)
+            | Opcode::SkipNotEqual(_, _)
+            | Opcode::SkipKey(_)
+            | Opcode::SkipNotKey(_) => {
+                pending.push(next);
+                pending.push(State {
+                    addr: addr + 4,
+                    ..state
+                });
+            }
+            Opcode::SetI(value) => pending.push(State {
+                i: Some(value),
+                moved_i: None,
+                ..next
+            }),
+            Opcode::SetSprite(_) => pending.push(State {
+                i: None,
+                moved_i: None,
+                ..next
+            }),
+            Opcode::AddI(_) => pending.push(State { i: None, ..next }),
+            Opcode::StoreRegs(_) | Opcode::LoadRegs(_) => pending.push(State {
+                i: None,
+                moved_i: Some(addr),
+                ..next
+            }),
+            Opcode::Draw(_, _, rows) => {
+                if let Some(i) = state.i {
+                    let last = i as usize + rows as usize;
+                    if last > MEMORY_SIZE {
+                        problem(
+                            addr,
+                            format!("the {} byte sprite at {:#05X} ends past the memory", rows, i),
+                        );
+                    } else if i >= PROGRAM_START as u16 && last > end as usize {
+                        problem(
+                            addr,
+                            format!("the {} byte sprite at {:#05X} ends past the ROM", rows, i),
+                        );
+                    }
+                }
+                pending.push(next);
+            }
+            _ => pending.push(next),
+        }
+    }
+    Analysis {
+        instructions,
+        problems: problems.into_iter().collect(),
+        quirk_dependent: quirk_dependent.into_iter().collect(),
+    }
+}
+
+// True when a jump or call to target can be followed.
+fn check_target(target: u16, end: u16, problem: &mut impl FnMut(u16, String), addr: u16) -> bool {
+    if target < PROGRAM_START as u16 {
+        problem(addr, format!("jumps to {:#05X}, below the program start", target));
+        return false;
+    }
+    if target >= end {
+        problem(addr, format!("jumps to {:#05X}, past the end of the ROM", target));
+        return false;
+    }
+    if !target.is_multiple_of(2) {
+        problem(addr, format!("jumps to the odd address {:#05X}", target));
+    }
+    true
+}
+
+// Whether the instruction before addr is 8XY0, after which shifting VX or VY gives the same.
+fn copies(rom: &[u8], addr: u16, x: u8, y: u8) -> bool {
+    addr.checked_sub(2)
+        .and_then(|before| instruction_at(rom, before))
+        .is_some_and(|before| before == 0x8000 | (x as u16) << 8 | (y as u16) << 4)
+}
+
+fn falls_through(instruction: u16) -> bool {
+    match Opcode::decode(instruction) {
+        Ok(
+            Opcode::Jump(_)
+            | Opcode::JumpV0(_)
+            | Opcode::Call(_)
+            | Opcode::Return
+            | Opcode::SkipEqualVal(_, _)
+            | Opcode::SkipNotEqualVal(_, _)
+            | Opcode::SkipEqual(_, _)
+            | Opcode::SkipNotEqual(_, _)
+            | Opcode::SkipKey(_)
+            | Opcode::SkipNotKey(_),
+        ) => false,
+        Ok(_) => extension(instruction).is_none(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extension_instructions_are_found() {
+        assert_eq!(extension(0x00FF), Some((Extension::SuperChip, "high resolution")));
+        assert_eq!(extension(0xD120), Some((Extension::SuperChip, "16x16 sprite")));
+        assert_eq!(extension(0xF201), Some((Extension::XoChip, "select plane")));
+        assert_eq!(extension(0x00E0), None);
+        assert_eq!(extension(0x01FF), None);
+        assert_eq!(extension(0xD125), None);
+
+        // hires, LD V0, F000 with its address, JP to itself, data that looks like hires
+        let rom = [0x00, 0xFF, 0x60, 0x01, 0xF0, 0x00, 0x12, 0x34, 0x12, 0x08, 0x00, 0xFF];
+        let uses = extension_uses(&rom, &analyze(&rom));
+        let found: Vec<_> = uses.iter().map(|found| (found.addr, found.extension)).collect();
+        assert_eq!(found, [(0x200, Extension::SuperChip), (0x204, Extension::XoChip)]);
+    }
+
+    fn problems(rom: &[u8]) -> Vec<(u16, String)> {
+        analyze(rom)
+            .problems
+            .into_iter()
+            .map(|problem| (problem.addr, problem.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_analyze_accepts_a_sound_program() {
+        // CALL 0x204, JP 0x202 (halt), LD I 0x20A, DRW 2 rows, RET, sprite
+        let rom = [0x22, 0x04, 0x12, 0x02, 0xA2, 0x0A, 0xD0, 0x02, 0x00, 0xEE, 0xFF, 0xFF];
+        assert_eq!(problems(&rom), []);
+    }
+
+    #[test]
+    fn test_analyze_finds_problems_on_every_path() {
+        // SE V0, 0 then JP 0x207 or JP 0x300, neither of which leads anywhere good
+        let rom = [0x30, 0x00, 0x12, 0x07, 0x13, 0x00, 0x00, 0xE0];
+        assert_eq!(
+            problems(&rom),
+            [
+                (0x202, "jumps to the odd address 0x207".to_string()),
+                (0x204, "jumps to 0x300, past the end of the ROM".to_string()),
+                (0x207, "the program runs past the end of the ROM".to_string()),
+            ]
+        );
+
+        let rom = [0xA2, 0x04, 0xD0, 0x05, 0x00, 0xEE];
+        let found = problems(&rom);
+        assert!(found.contains(&(0x202, "the 5 byte sprite at 0x204 ends past the ROM".to_string())));
+        assert!(found.contains(&(0x204, "return with an empty stack".to_string())));
+
+        // a subroutine calling itself
+        assert!(problems(&[0x22, 0x00])[0].1.contains("overflows the stack"));
+        assert_eq!(
+            problems(&[0x00, 0xFF])[0].1,
+            "SUPER-CHIP instruction (high resolution), not supported"
+        );
+        assert_eq!(problems(&[0x60, 0x00])[0].1, "the program runs past the end of the ROM");
+        assert_eq!(problems(&[0xFF, 0xFF])[0].1, "unknown instruction FFFF");
+    }
+
+    #[test]
+    fn test_analyze_separates_code_from_data() {
+        // LD I 0x208, SNE V0 0, JP 0x20A, halt at 0x206, sprite, then code after the sprite
+        let rom = [0xA2, 0x08, 0x40, 0x00, 0x12, 0x0A, 0x12, 0x06, 0xF0, 0x90, 0x12, 0x0A];
+        let analysis = analyze(&rom);
+        assert_eq!(
+            analysis.instructions.iter().copied().collect::<Vec<_>>(),
+            [0x200, 0x202, 0x204, 0x206, 0x20A]
+        );
+        assert!(analysis.is_code(0x207) && analysis.is_code(0x20B));
+        assert!(!analysis.is_code(0x208) && !analysis.is_code(0x209));
+    }
+
+    #[test]
+    fn test_analyze_finds_quirk_dependent_instructions() {
+        let rom = [
+            0x81, 0x26, // 0x200 SHR V1 with VY = V2
+            0x81, 0x20, // 0x202 LD V1, V2
+            0x81, 0x26, // 0x204 the same shift after a copy
+            0x88, 0x8E, // 0x206 SHL V8 shifting itself
+            0xA3, 0x00, // 0x208 LD I
+            0xF1, 0x55, // 0x20A LD [I], V1
+            0xF1, 0x65, // 0x20C LD V1, [I] relies on I staying put
+            0xA3, 0x00, // 0x20E LD I
+            0xF1, 0x65, // 0x210 fine after setting I again
+            0xB2, 0x16, // 0x212 JP V0, 0x216 with X = 2
+            0x12, 0x14, // 0x214
+        ];
+        let found: Vec<u16> = analyze(&rom)
+            .quirk_dependent
+            .iter()
+            .map(|problem| problem.addr)
+            .collect();
+        assert_eq!(found, [0x200, 0x20C, 0x212]);
+    }
+
+    #[test]
+    fn test_entry_block_ends_at_the_first_branch() {
+        let rom = [0x00, 0xE0, 0x60, 0x05, 0x12, 0x08, 0x61, 0x01];
+        assert_eq!(entry_block(&rom), [(0x200, 0x00E0), (0x202, 0x6005), (0x204, 0x1208)]);
+        assert_eq!(entry_block(&[0x60, 0x05, 0x70]), [(0x200, 0x6005)]);
+        assert_eq!(entry_block(&[0xFF, 0xFF, 0x00, 0xE0]), [(0x200, 0xFFFF)]);
+    }
+}
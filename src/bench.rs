@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::database::Database;
+use crate::emulator::DEFAULT_IPS;
+use crate::error::Chip8Error;
+use crate::machine::Machine;
+use crate::opcode::Opcode;
+use crate::quirks::Quirks;
+use crate::roms;
+
+pub const DEFAULT_SECONDS: f64 = 5.0;
+// The timers tick every this many instructions, as in a headless run
+const INSTRUCTIONS_PER_FRAME: u32 = DEFAULT_IPS / 60;
+// One instruction in this many is timed on its own for the opcode classes, timing all of them would slow the
+// run down to the speed of the clock
+const SAMPLE_EVERY: u64 = 64;
+const SEED: u64 = 0;
+
+pub struct Bench {
+    pub instructions: u64,
+    pub frames: u64,
+    pub elapsed: Duration,
+    // the frame the ROM halted or started waiting for a key in, from there on it only runs that instruction
+    pub stuck_at: Option<u64>,
+    // the timed instructions and the time they took by pattern, like DXYN
+    pub classes: BTreeMap<&'static str, (u64, Duration)>,
+}
+
+impl Bench {
+    pub fn instructions_per_second(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn frames_per_second(&self) -> f64 {
+        self.frames as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+pub fn run(location: &str, seconds: f64, quirks: Option<Quirks>) -> Result<(), Chip8Error> {
+    let rom = roms::read(location)?;
+    let quirks = quirks
+        .or_else(|| Database::load().lookup(&rom).map(|info| info.quirks))
+        .unwrap_or_default();
+    println!(
+        "Running {} flat out for {} seconds with quirks: {}",
+        location, seconds, quirks
+    );
+    let bench = measure(&rom, quirks, Duration::from_secs_f64(seconds))?;
+    print!("{}", report(&bench));
+    Ok(())
+}
+
+// Runs the ROM as fast as it goes until the duration is over, a ROM that halts or waits for a key keeps
+// running the instruction it is stuck on.
+pub fn measure(rom: &[u8], quirks: Quirks, duration: Duration) -> Result<Bench, Chip8Error> {
+    let mut machine = Machine::new(rom, quirks, SEED)?;
+    let overhead = clock_overhead();
+    let mut bench = Bench {
+        instructions: 0,
+        frames: 0,
+        elapsed: Duration::ZERO,
+        stuck_at: None,
+        classes: BTreeMap::new(),
+    };
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        for _ in 0..INSTRUCTIONS_PER_FRAME {
+            bench.instructions += 1;
+            if !bench.instructions.is_multiple_of(SAMPLE_EVERY) {
+                let result = machine.step()?;
+                if (result.halted || result.waiting_for_key) && bench.stuck_at.is_none() {
+                    bench.stuck_at = Some(bench.frames);
+                }
+                continue;
+            }
+            let pc = machine.pc() as usize;
+            let instruction = match machine.memory().get(pc..pc + 2) {
+                Some(&[high, low]) => u16::from_be_bytes([high, low]),
+                _ => 0,
+            };
+            let before = Instant::now();
+            machine.step()?;
+            let took = before.elapsed().saturating_sub(overhead);
+            // step already failed on anything that does not decode
+            let pattern = Opcode::decode(instruction)
+                .map(|opcode| opcode.pattern())
+                .unwrap_or("????");
+            let class = bench.classes.entry(pattern).or_default();
+            class.0 += 1;
+            class.1 += took;
+        }
+        machine.tick_timers();
+        bench.frames += 1;
+    }
+    bench.elapsed = start.elapsed();
+    Ok(bench)
+}
+
+// What reading the clock twice costs, taken off every timed instruction.
+fn clock_overhead() -> Duration {
+    const ROUNDS: u32 = 1000;
+    (0..ROUNDS).map(|_| Instant::now().elapsed()).min().unwrap_or_default()
+}
+
+pub fn report(bench: &Bench) -> String {
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "{} instructions and {} frames in {:.2} s",
+        bench.instructions,
+        bench.frames,
+        bench.elapsed.as_secs_f64()
+    );
+    let _ = writeln!(
+        report,
+        "{:.0} instructions/s, {:.0} frames/s ({:.0}x the speed of {} instructions per second)",
+        bench.instructions_per_second(),
+        bench.frames_per_second(),
+        bench.instructions_per_second() / DEFAULT_IPS as f64,
+        DEFAULT_IPS
+    );
+    if let Some(frame) = bench.stuck_at {
+        let _ = writeln!(
+            report,
+            "The ROM halted or waited for a key in frame {}, the numbers after that are of that one instruction",
+            frame
+        );
+    }
+
+    let total: Duration = bench.classes.values().map(|&(_, time)| time).sum();
+    let mut classes: Vec<_> = bench.classes.iter().collect();
+    classes.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then(a.0.cmp(b.0)));
+    let _ = writeln!(
+        report,
+        "Time per opcode class, from one instruction in {}:",
+        SAMPLE_EVERY
+    );
+    let _ = writeln!(report, "  class     count   ns each  share");
+    for (pattern, &(count, time)) in classes {
+        let share = match total.as_nanos() {
+            0 => 0.0,
+            total => time.as_nanos() as f64 * 100.0 / total as f64,
+        };
+        let _ = writeln!(
+            report,
+            "  {}  {:>10}  {:>8.1}  {:>4.1}%",
+            pattern,
+            count,
+            time.as_nanos() as f64 / count as f64,
+            share
+        );
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_counts_instructions_and_classes() {
+        // LD V0, 1; ADD V0, V0; JP 0x200
+        let rom = [0x60, 0x01, 0x80, 0x04, 0x12, 0x00];
+        let bench = measure(&rom, Quirks::default(), Duration::from_millis(20)).unwrap();
+
+        assert!(bench.frames > 0);
+        assert_eq!(bench.instructions, bench.frames * INSTRUCTIONS_PER_FRAME as u64);
+        let sampled: u64 = bench.classes.values().map(|&(count, _)| count).sum();
+        assert_eq!(sampled, bench.instructions / SAMPLE_EVERY);
+        assert!(bench
+            .classes
+            .keys()
+            .all(|pattern| ["6XNN", "8XY4", "1NNN"].contains(pattern)));
+        assert!(report(&bench).contains("frames/s"));
+    }
+}
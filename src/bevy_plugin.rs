@@ -0,0 +1,210 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::error::Chip8Error;
+use crate::machine::{Machine, DEFAULT_IPS, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::quirks::Quirks;
+
+const FRAME_SECS: f64 = 1.0 / 60.0;
+// After a long hitch the emulator skips ahead instead of trying to catch up
+const MAX_FRAMES_PER_UPDATE: u32 = 4;
+
+const FOREGROUND: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+const BACKGROUND: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+
+// Same layout as the SDL frontend: 1234 / QWER / ASDF / ZXCV
+const KEYS: [(KeyCode, u8); 16] = [
+    (KeyCode::Digit1, 0x1),
+    (KeyCode::Digit2, 0x2),
+    (KeyCode::Digit3, 0x3),
+    (KeyCode::Digit4, 0xC),
+    (KeyCode::KeyQ, 0x4),
+    (KeyCode::KeyW, 0x5),
+    (KeyCode::KeyE, 0x6),
+    (KeyCode::KeyR, 0xD),
+    (KeyCode::KeyA, 0x7),
+    (KeyCode::KeyS, 0x8),
+    (KeyCode::KeyD, 0x9),
+    (KeyCode::KeyF, 0xE),
+    (KeyCode::KeyZ, 0xA),
+    (KeyCode::KeyX, 0x0),
+    (KeyCode::KeyC, 0xB),
+    (KeyCode::KeyV, 0xF),
+];
+
+// Runs a ROM inside a Bevy app. The display is rendered to the image in Chip8Screen, which can be
+// put on a sprite, a UI node or the material of a mesh.
+pub struct Chip8Plugin {
+    rom: Vec<u8>,
+    quirks: Quirks,
+    ips: u32,
+    seed: u64,
+}
+
+impl Chip8Plugin {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Chip8Plugin {
+            rom,
+            quirks: Quirks::default(),
+            ips: DEFAULT_IPS,
+            seed: 0,
+        }
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    pub fn ips(mut self, ips: u32) -> Self {
+        self.ips = ips;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+// The running emulator. Stops when the ROM crashes or does not fit in memory, the error is kept for the app
+// to show.
+#[derive(Resource)]
+pub struct Chip8Machine {
+    pub machine: Machine,
+    pub error: Option<Chip8Error>,
+    cycles_per_frame: u32,
+    lag: f64,
+    display_changed: bool,
+}
+
+#[derive(Resource)]
+pub struct Chip8Screen(pub Handle<Image>);
+
+impl Plugin for Chip8Plugin {
+    fn build(&self, app: &mut App) {
+        // a ROM that does not load leaves the empty machine stopped, like a crash would
+        let (machine, error) = match Machine::new(&self.rom, self.quirks, self.seed) {
+            Ok(machine) => (machine, None),
+            Err(err) => (Machine::new(&[], self.quirks, self.seed).unwrap(), Some(err)),
+        };
+        app.insert_resource(Chip8Machine {
+            machine,
+            error,
+            cycles_per_frame: (self.ips / 60).max(1),
+            lag: 0.0,
+            display_changed: true,
+        })
+        .add_systems(PreStartup, create_screen)
+        .add_systems(Update, (read_keys, run_frames, update_screen).chain());
+    }
+}
+
+fn create_screen(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: DISPLAY_WIDTH as u32,
+            height: DISPLAY_HEIGHT as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &BACKGROUND,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    commands.insert_resource(Chip8Screen(images.add(image)));
+}
+
+fn read_keys(keyboard: Res<ButtonInput<KeyCode>>, mut chip8: ResMut<Chip8Machine>) {
+    let keypad = chip8.machine.keypad_mut();
+    for (code, key) in KEYS {
+        if keyboard.pressed(code) {
+            keypad.press(key);
+        } else {
+            keypad.release(key);
+        }
+    }
+}
+
+fn run_frames(time: Res<Time>, mut chip8: ResMut<Chip8Machine>) {
+    if chip8.error.is_some() {
+        return;
+    }
+    chip8.lag = (chip8.lag + time.delta_secs_f64()).min(FRAME_SECS * MAX_FRAMES_PER_UPDATE as f64);
+
+    while chip8.lag >= FRAME_SECS {
+        chip8.lag -= FRAME_SECS;
+        let cycles = chip8.cycles_per_frame;
+        match chip8.machine.run_frame(cycles) {
+            Ok(frame) => chip8.display_changed |= frame.display_changed,
+            Err(err) => {
+                chip8.error = Some(err);
+                return;
+            }
+        }
+    }
+}
+
+fn update_screen(mut chip8: ResMut<Chip8Machine>, screen: Res<Chip8Screen>, mut images: ResMut<Assets<Image>>) {
+    if !chip8.display_changed {
+        return;
+    }
+    let Some(data) = images.get_mut(&screen.0).and_then(|image| image.data.as_mut()) else {
+        return;
+    };
+    for (rgba, &pixel) in data.chunks_exact_mut(4).zip(chip8.machine.pixels()) {
+        rgba.copy_from_slice(if pixel != 0 { &FOREGROUND } else { &BACKGROUND });
+    }
+    chip8.display_changed = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    #[test]
+    fn test_plugin_renders_display_to_image() {
+        // LD V0, 8; LD F, V0; DRW V1, V1, 5; JP 0x206
+        let rom = vec![0x60, 0x08, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x06];
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            ImagePlugin::default(),
+            Chip8Plugin::new(rom),
+        ))
+        .init_resource::<ButtonInput<KeyCode>>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(20)));
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let handle = app.world().resource::<Chip8Screen>().0.clone();
+        let images = app.world().resource::<Assets<Image>>();
+        let data = images.get(&handle).unwrap().data.as_ref().unwrap();
+        let lit = data.chunks_exact(4).filter(|rgba| *rgba == FOREGROUND).count();
+        assert_eq!(lit, 16);
+        assert!(app.world().resource::<Chip8Machine>().error.is_none());
+    }
+
+    #[test]
+    fn test_plugin_keeps_the_error_of_a_rom_that_does_not_fit() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            ImagePlugin::default(),
+            Chip8Plugin::new(vec![0; 4096]),
+        ))
+        .init_resource::<ButtonInput<KeyCode>>();
+        app.update();
+
+        let chip8 = app.world().resource::<Chip8Machine>();
+        assert!(matches!(chip8.error, Some(Chip8Error::RomTooLarge(4096))));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::database::Database;
+use crate::emulator::{Chip8, DEFAULT_IPS};
+use crate::error::Chip8Error;
+use crate::quirks::Quirks;
+use crate::roms;
+
+enum Rom {
+    Empty,
+    File(String),
+    Bytes(Vec<u8>),
+}
+
+pub struct Chip8Builder {
+    rom: Rom,
+    debug: bool,
+    quirks: Quirks,
+    ips: u32,
+    seed: Option<u64>,
+    database: Option<Database>,
+    patch: Option<String>,
+}
+
+impl Default for Chip8Builder {
+    fn default() -> Self {
+        Chip8Builder {
+            rom: Rom::Empty,
+            debug: false,
+            quirks: Quirks::default(),
+            ips: DEFAULT_IPS,
+            seed: None,
+            database: None,
+            patch: None,
+        }
+    }
+}
+
+impl Chip8Builder {
+    // A file, or an URL with the http feature.
+    pub fn rom_file(mut self, path: &str) -> Self {
+        self.rom = Rom::File(path.to_string());
+        self
+    }
+
+    pub fn rom_bytes(mut self, data: &[u8]) -> Self {
+        self.rom = Rom::Bytes(data.to_vec());
+        self
+    }
+
+    // Starts paused with the debugger console, profiler and register timeline enabled.
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    // Instructions per second when throttled, and how many instructions make up a frame otherwise.
+    pub fn ips(mut self, ips: u32) -> Self {
+        self.ips = ips;
+        self
+    }
+
+    // Seeds the random number generator used by CXNN so runs can be reproduced.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    // ROMs found in the database run with the quirks it has for them instead of the ones from quirks(), also
+    // when another ROM is opened later.
+    pub fn database(mut self, database: Database) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    // An IPS patch to apply to the ROM, instead of the .ips file next to a ROM file that is applied otherwise.
+    pub fn patch(mut self, path: &str) -> Self {
+        self.patch = Some(path.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<Chip8, Chip8Error> {
+        let (data, path) = match self.rom {
+            Rom::Empty => (Vec::new(), None),
+            Rom::File(path) => (roms::read(&path)?, Some(path)),
+            Rom::Bytes(data) => (data, None),
+        };
+        let (patched, patch) = roms::patch(path.as_deref(), self.patch.as_deref(), &data)?;
+        let mut chip8 = Chip8::from_config(&patched, path, patch, self.debug, self.quirks, self.ips, self.seed)?;
+        // the database knows the ROMs as they were released
+        if let Some(database) = self.database {
+            chip8.set_database(database);
+            chip8.detect_rom(&data);
+        }
+        Ok(chip8)
+    }
+}
@@ -0,0 +1,72 @@
+// ROMs that come with the emulator so it can be tried without finding one first. The assembly they were
+// made from is next to them in roms/.
+pub struct Builtin {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub data: &'static [u8],
+}
+
+pub const BUILTINS: [Builtin; 3] = [
+    Builtin {
+        name: "splash",
+        description: "CHIP-8 in large letters",
+        data: include_bytes!("../roms/splash.ch8"),
+    },
+    Builtin {
+        name: "test",
+        description: "checks the basic instructions, shows OK when they all work",
+        data: include_bytes!("../roms/test.ch8"),
+    },
+    Builtin {
+        name: "catch",
+        description: "catch the falling balls with the paddle, 7 and 9 move it",
+        data: include_bytes!("../roms/catch.ch8"),
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::Machine;
+    use crate::quirks::Quirks;
+
+    #[test]
+    fn test_self_test_passes_with_any_quirks() {
+        let all = Quirks {
+            vf_reset: true,
+            memory_increment: true,
+            shift_vy: true,
+            jump_vx: true,
+            wrap_sprites: true,
+            key_wait_on_press: true,
+        };
+        for quirks in [Quirks::default(), Quirks::cosmac_vip(), all] {
+            let mut machine = Machine::new(find("test").unwrap().data, quirks, 0).unwrap();
+            let halted = (1..100_000).any(|step| {
+                if step % 16 == 0 {
+                    machine.tick_timers();
+                }
+                machine.step().unwrap().halted
+            });
+            assert!(halted);
+            // V0 holds the x of the K of OK, a failed check leaves 0xE in it for the E
+            assert_eq!(machine.registers()[0], 33, "{:?}", quirks);
+        }
+    }
+
+    #[test]
+    fn test_builtins_start_without_errors() {
+        for builtin in &BUILTINS {
+            let mut machine = Machine::new(builtin.data, Quirks::default(), 0).unwrap();
+            for _ in 0..1000 {
+                if machine.step().unwrap().waiting_for_key {
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Chip8Error;
+use crate::machine::MEMORY_SIZE;
+use crate::parse::{parse_u16, parse_u8};
+use crate::paths;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub addr: u16,
+    pub value: u8,
+    // written every frame instead of once
+    pub freeze: bool,
+    pub description: String,
+    pub enabled: bool,
+    applied: bool,
+}
+
+impl Cheat {
+    pub fn new(addr: u16, value: u8, freeze: bool, description: &str) -> Self {
+        Cheat {
+            addr,
+            value,
+            freeze,
+            description: description.to_string(),
+            enabled: true,
+            applied: false,
+        }
+    }
+}
+
+impl Cheat {
+    // A line of a cheat file, e.g. "0x3F2 0x09 freeze on Infinite lives".
+    fn parse(line: &str) -> Result<Self, String> {
+        let mut fields = line.split_whitespace();
+        let mut field = || {
+            fields
+                .next()
+                .ok_or("expected an address, a value, freeze or once, on or off and a description")
+        };
+        let addr = parse_u16(field()?)?;
+        if addr as usize >= MEMORY_SIZE {
+            return Err(format!("{:#X} is outside the memory", addr));
+        }
+        let value = parse_u8(field()?)?;
+        let freeze = match field()? {
+            "freeze" => true,
+            "once" => false,
+            other => return Err(format!("expected freeze or once instead of {}", other)),
+        };
+        let enabled = match field()? {
+            "on" => true,
+            "off" => false,
+            other => return Err(format!("expected on or off instead of {}", other)),
+        };
+        Ok(Cheat {
+            enabled,
+            ..Cheat::new(addr, value, freeze, &fields.collect::<Vec<&str>>().join(" "))
+        })
+    }
+
+    fn line(&self) -> String {
+        format!(
+            "{:#06X}  {:#04X}  {:<6}  {:<3}  {}",
+            self.addr,
+            self.value,
+            if self.freeze { "freeze" } else { "once" },
+            if self.enabled { "on" } else { "off" },
+            self.description
+        )
+        .trim_end()
+        .to_string()
+    }
+}
+
+impl std::fmt::Display for Cheat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}] {:#06X} = {:#04X} {:<6}  {}",
+            if self.enabled { "x" } else { " " },
+            self.addr,
+            self.value,
+            if self.freeze { "freeze" } else { "once" },
+            self.description
+        )
+    }
+}
+
+// Memory writes made for the player at the end of every frame, like keeping the lives of a game from running out.
+#[derive(Debug, Default)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    // One cheat per line, see Cheat::parse. Everything after a '#' is a comment.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let cheats = text
+            .lines()
+            .enumerate()
+            .map(|(number, line)| (number, line.split('#').next().unwrap_or("").trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(number, line)| Cheat::parse(line).map_err(|err| format!("line {}: {}", number + 1, err)))
+            .collect::<Result<_, _>>()?;
+        Ok(Cheats { cheats })
+    }
+
+    pub fn add(&mut self, cheat: Cheat) -> usize {
+        self.cheats.push(cheat);
+        self.cheats.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Cheat> {
+        (index < self.cheats.len()).then(|| self.cheats.remove(index))
+    }
+
+    // A cheat that is enabled again is written again, also when it is only written once.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> Option<&Cheat> {
+        let cheat = self.cheats.get_mut(index)?;
+        cheat.enabled = enabled;
+        cheat.applied = false;
+        Some(cheat)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Cheat> {
+        self.cheats.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.cheats.clear();
+    }
+
+    // The writes due this frame, as (address, value).
+    pub fn due(&mut self) -> Vec<(u16, u8)> {
+        self.cheats
+            .iter_mut()
+            .filter(|cheat| cheat.enabled && (cheat.freeze || !cheat.applied))
+            .map(|cheat| {
+                cheat.applied = true;
+                (cheat.addr, cheat.value)
+            })
+            .collect()
+    }
+}
+
+fn cheat_file(rom: &str) -> Option<PathBuf> {
+    let name = Path::new(rom).file_name()?;
+    let mut file = paths::cheat_dir()?.join(name);
+    file.set_extension("cheats");
+    Some(file)
+}
+
+// The cheats kept for a ROM with the file they came from, None when it has no cheat file or the file cannot be
+// read.
+pub fn load(rom: &str) -> Result<Option<(PathBuf, Cheats)>, Chip8Error> {
+    let Some(file) = cheat_file(rom) else {
+        return Ok(None);
+    };
+    let Ok(content) = fs::read_to_string(&file) else {
+        return Ok(None);
+    };
+    match Cheats::parse(&content) {
+        Ok(cheats) => Ok(Some((file, cheats))),
+        Err(err) => Err(Chip8Error::InvalidCheats(format!("{}: {}", file.display(), err))),
+    }
+}
+
+pub fn save(rom: &str, cheats: &Cheats) -> Result<(), Chip8Error> {
+    let Some(file) = cheat_file(rom) else {
+        return Ok(());
+    };
+
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut text = String::from("# address  value  freeze/once  on/off  description\n");
+    for cheat in &cheats.cheats {
+        text.push_str(&cheat.line());
+        text.push('\n');
+    }
+    fs::write(file, text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_due_writes_frozen_cheats_every_frame() {
+        let mut cheats = Cheats::default();
+        cheats.add(Cheat::new(0x300, 9, true, "lives"));
+        cheats.add(Cheat::new(0x301, 1, false, "level"));
+        assert_eq!(cheats.due(), [(0x300, 9), (0x301, 1)]);
+        assert_eq!(cheats.due(), [(0x300, 9)]);
+
+        cheats.set_enabled(0, false);
+        assert_eq!(cheats.due(), []);
+        cheats.set_enabled(1, true);
+        assert_eq!(cheats.due(), [(0x301, 1)]);
+        assert_eq!(cheats.remove(0).unwrap().description, "lives");
+        assert!(cheats.remove(1).is_none());
+    }
+
+    #[test]
+    fn test_parse_reads_cheat_files() {
+        let mut cheats =
+            Cheats::parse("# lives and level\n0x3F2 0x09 freeze on Infinite lives\n0x3F0 5 once off  # start later\n")
+                .unwrap();
+        assert_eq!(cheats.get(0).unwrap().description, "Infinite lives");
+        assert_eq!(cheats.get(1).unwrap().line(), "0x03F0  0x05  once    off");
+        assert_eq!(
+            Cheats::parse(&cheats.get(0).unwrap().line()).unwrap().cheats[0],
+            cheats.cheats[0]
+        );
+        assert_eq!(cheats.due(), [(0x3F2, 9)]);
+
+        assert!(Cheats::parse("0x3F2 0x09 freeze").is_err());
+        assert!(Cheats::parse("0x3F2 0x09 always on").is_err());
+        assert!(Cheats::parse("0x3F2 0x100 once on").is_err());
+        assert!(Cheats::parse("0x1000 0x01 once on").is_err());
+    }
+}
@@ -0,0 +1,33 @@
+use crate::analysis::{self, instruction_at, Problem};
+use crate::error::Chip8Error;
+use crate::roms;
+
+// Prints what is wrong with the ROM at location and what behaves differently depending on the quirks, false
+// when anything is wrong.
+pub fn run(location: &str) -> Result<bool, Chip8Error> {
+    let rom = roms::read(location)?;
+    let analysis = analysis::analyze(&rom);
+    let problems = analysis.problems;
+    print_problems(&rom, &problems);
+    // these work, only not on every interpreter, so they are not counted as problems
+    if !analysis.quirk_dependent.is_empty() {
+        println!("Depends on the quirks:");
+        print_problems(&rom, &analysis.quirk_dependent);
+    }
+    match problems.len() {
+        0 => println!("{}: no problems found", location),
+        1 => println!("{}: 1 problem found", location),
+        count => println!("{}: {} problems found", location, count),
+    }
+    Ok(problems.is_empty())
+}
+
+fn print_problems(rom: &[u8], problems: &[Problem]) {
+    for problem in problems {
+        let instruction = match instruction_at(rom, problem.addr) {
+            Some(instruction) => format!("{:04X}", instruction),
+            None => "----".to_string(),
+        };
+        println!("{:#05X}  {}  {}", problem.addr, instruction, problem.message);
+    }
+}
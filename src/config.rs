@@ -0,0 +1,742 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Deserialize;
+
+use crate::emulator::DEFAULT_IPS;
+use crate::error::Chip8Error;
+use crate::frontend::{
+    CHEAT, CHEAT_COUNT, FAST_FORWARD, NEXT_THEME, PAUSE, PICK_ROM, QUIT, RESET, SHOULD_STEP, STEP_MANY, STEP_MODE,
+};
+use crate::keypad::KEY_COUNT;
+use crate::paths;
+use crate::quirks::Quirks;
+
+// How often the watched config file is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+// Defaults read from config.toml at startup, command line flags take precedence over them. Every key is
+// optional, a missing file is the same as an empty one.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Size of a CHIP-8 pixel in window pixels
+    pub scale: u32,
+    pub ips: u32,
+    pub quirks: Quirks,
+    pub palette: Palette,
+    pub audio: AudioConfig,
+    pub layout: Layout,
+    // The keys bound to something other than the layout's default, by CHIP-8 key
+    #[serde(deserialize_with = "key_overrides")]
+    pub keys: BTreeMap<u8, String>,
+    pub hotkeys: Hotkeys,
+    pub gamepad: GamepadConfig,
+    pub touch: TouchConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Palette {
+    pub foreground: Rgb,
+    pub background: Rgb,
+    // The XO-CHIP colors of pixels lit on the second plane only and on both planes, the foreground is the one
+    // of the first plane. Kept for XO-CHIP, CHIP-8 draws with the first two.
+    pub plane2: Rgb,
+    pub both_planes: Rgb,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    pub enabled: bool,
+    // Amplitude of the square wave, between 0 and 1
+    pub volume: f32,
+    // Pitch of the tone in Hz
+    pub tone: f32,
+}
+
+// The keyboard key for each of the CHIP-8 keys 0 to F, named the way SDL names scancodes: "X", "1", "Space",
+// "Return", "Up", "Keypad 4". Letters and punctuation are the labels printed on the keys of the layout.
+// Written as a [keys] table with the CHIP-8 keys as names, keys that are left out keep the layout's default
+// 1234 / QWER / ASDF / ZXCV block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    pub layout: Layout,
+    pub keys: [String; KEY_COUNT],
+    pub hotkeys: Hotkeys,
+}
+
+// The keys for the emulator's own controls, named like the keypad keys. A keypad binding on the same key
+// wins, so a hotkey that a game needs has to move elsewhere.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hotkeys {
+    pub pause: String,
+    pub step_mode: String,
+    // Runs one instruction while in step mode
+    pub step: String,
+    pub step_many: String,
+    pub reset: String,
+    pub quit: String,
+    // Toggles running as fast as possible
+    pub fast_forward: String,
+    // Asks for another ROM to run instead of the current one
+    pub open_rom: String,
+    // Switches to the next color theme
+    pub theme: String,
+    // Switch the cheats of the ROM on and off, the first key cheat 0 and so on
+    pub cheats: Vec<String>,
+}
+
+// Used by the SDL frontend. Buttons go by their names in SDL's gamepad mappings: a, b, x, y for the face
+// buttons where they are on an Xbox controller, back, guide, start, leftstick, rightstick, leftshoulder,
+// rightshoulder, dpup, dpdown, dpleft, dpright. An empty name leaves a control unbound.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GamepadConfig {
+    pub enabled: bool,
+    // The button for each bound CHIP-8 key, a [gamepad.keys] table replaces all of the defaults
+    #[serde(deserialize_with = "key_overrides")]
+    pub keys: BTreeMap<u8, String>,
+    pub pause: String,
+    pub reset: String,
+    pub fast_forward: String,
+    // Pauses the game when the gamepad last played on is unplugged
+    pub pause_on_disconnect: bool,
+    // Shakes the gamepad last played on while the sound timer runs
+    pub rumble: bool,
+}
+
+// The keyboard in front of the user. Frontends that see key labels bind the names as they are, the ones
+// that see key positions (SDL scancodes, winit key codes) look up where the label sits on this layout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    #[default]
+    Qwerty,
+    Azerty,
+    Qwertz,
+    Dvorak,
+}
+
+// Used by the SDL frontend on touch screens.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TouchConfig {
+    pub enabled: bool,
+    pub layout: TouchLayout,
+}
+
+// The regions of the window that press a CHIP-8 key while touched: rows from top to bottom, each split evenly
+// between its keys. Written as a list of strings of hex digits, "." is a region without a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TouchLayout(pub Vec<Vec<Option<u8>>>);
+
+// Notices when the config file was written and reads it again. The overrides are applied to every
+// reloaded config, so settings given on the command line keep winning over the file.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+    overrides: Box<dyn Fn(&mut Config)>,
+}
+
+// A color written as "#RRGGBB" in the config file and on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            scale: 8,
+            ips: DEFAULT_IPS,
+            quirks: Quirks::default(),
+            palette: Palette::default(),
+            audio: AudioConfig::default(),
+            layout: Layout::default(),
+            keys: BTreeMap::new(),
+            hotkeys: Hotkeys::default(),
+            gamepad: GamepadConfig::default(),
+            touch: TouchConfig::default(),
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            foreground: Rgb(0xFF, 0xFF, 0xFF),
+            background: Rgb(0x00, 0x00, 0x00),
+            plane2: Rgb(0xAA, 0xAA, 0xAA),
+            both_planes: Rgb(0x55, 0x55, 0x55),
+        }
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            enabled: true,
+            volume: 0.1,
+            tone: 440.0,
+        }
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Layout::default().key_map()
+    }
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        Hotkeys {
+            pause: "P".to_string(),
+            step_mode: "M".to_string(),
+            step: "N".to_string(),
+            step_many: "B".to_string(),
+            reset: "0".to_string(),
+            quit: "Escape".to_string(),
+            fast_forward: "Tab".to_string(),
+            open_rom: "O".to_string(),
+            theme: "T".to_string(),
+            cheats: (1..=CHEAT_COUNT).map(|n| format!("F{}", n)).collect(),
+        }
+    }
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        // The D-pad on W / A / S / D and two face buttons on E and Q, what most games use
+        let keys = [
+            (0x5, "dpup"),
+            (0x8, "dpdown"),
+            (0x7, "dpleft"),
+            (0x9, "dpright"),
+            (0x6, "a"),
+            (0x4, "b"),
+        ];
+        GamepadConfig {
+            enabled: true,
+            keys: keys
+                .into_iter()
+                .map(|(key, button)| (key, button.to_string()))
+                .collect(),
+            pause: "start".to_string(),
+            reset: "back".to_string(),
+            fast_forward: "rightshoulder".to_string(),
+            pause_on_disconnect: true,
+            rumble: false,
+        }
+    }
+}
+
+impl Default for TouchConfig {
+    fn default() -> Self {
+        TouchConfig {
+            enabled: true,
+            layout: "123C 456D 789E A0BF"
+                .parse()
+                .expect("the default touch layout is valid"),
+        }
+    }
+}
+
+impl TouchLayout {
+    // The key at x, y given as fractions of the window's width and height.
+    pub fn key_at(&self, x: f32, y: f32) -> Option<u8> {
+        let index = |fraction: f32, count: usize| ((fraction.clamp(0.0, 1.0) * count as f32) as usize).min(count - 1);
+        if self.0.is_empty() {
+            return None;
+        }
+        let row = &self.0[index(y, self.0.len())];
+        if row.is_empty() {
+            return None;
+        }
+        row[index(x, row.len())]
+    }
+}
+
+// The rows separated by whitespace, "..5.. 7...9 ..8.." puts 5, 7, 9 and 8 around the middle.
+impl FromStr for TouchLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_whitespace()
+            .map(|row| {
+                row.chars()
+                    .map(|c| match c {
+                        '.' => Ok(None),
+                        c => c
+                            .to_digit(16)
+                            .map(|key| Some(key as u8))
+                            .ok_or(format!("invalid touch layout (expected hex digits or .): {}", row)),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()
+            .map(TouchLayout)
+    }
+}
+
+impl<'de> Deserialize<'de> for TouchLayout {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .join(" ")
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl GamepadConfig {
+    // The control flag from frontend for every bound control, with the name of its button.
+    pub fn controls(&self) -> impl Iterator<Item = (u32, &str)> {
+        [
+            (PAUSE, &self.pause),
+            (RESET, &self.reset),
+            (FAST_FORWARD, &self.fast_forward),
+        ]
+        .into_iter()
+        .filter(|(_, name)| !name.is_empty())
+        .map(|(flag, name)| (flag, name.as_str()))
+    }
+}
+
+impl Hotkeys {
+    // The control flag from frontend for every hotkey, with the name of its key.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &str)> {
+        [
+            (PAUSE, &self.pause),
+            (STEP_MODE, &self.step_mode),
+            (SHOULD_STEP, &self.step),
+            (STEP_MANY, &self.step_many),
+            (RESET, &self.reset),
+            (QUIT, &self.quit),
+            (FAST_FORWARD, &self.fast_forward),
+            (PICK_ROM, &self.open_rom),
+            (NEXT_THEME, &self.theme),
+        ]
+        .into_iter()
+        // the controls above win over a cheat on the same key
+        .chain(
+            self.cheats
+                .iter()
+                .enumerate()
+                .map(|(index, name)| (CHEAT << index, name)),
+        )
+        .map(|(flag, name)| (flag, name.as_str()))
+    }
+
+    fn names_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        [
+            &mut self.pause,
+            &mut self.step_mode,
+            &mut self.step,
+            &mut self.step_many,
+            &mut self.reset,
+            &mut self.quit,
+            &mut self.fast_forward,
+            &mut self.open_rom,
+            &mut self.theme,
+        ]
+        .into_iter()
+        .chain(self.cheats.iter_mut())
+    }
+}
+
+impl KeyMap {
+    // The CHIP-8 keys with the name of the keyboard key each one is bound to.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &str)> {
+        self.keys
+            .iter()
+            .enumerate()
+            .map(|(key, name)| (key as u8, name.as_str()))
+    }
+
+    // The same keys named after the QWERTY keys in their place, for frontends that read key positions.
+    pub fn to_qwerty(&self) -> KeyMap {
+        let mut hotkeys = self.hotkeys.clone();
+        for name in hotkeys.names_mut() {
+            *name = self.layout.to_qwerty(name);
+        }
+        KeyMap {
+            layout: Layout::Qwerty,
+            keys: self.keys.clone().map(|name| self.layout.to_qwerty(&name)),
+            hotkeys,
+        }
+    }
+
+    // Writes the key map as the [keys] table and its layout to the config file at path, everything else in
+    // the file stays as it was, comments included.
+    pub fn save(&self, path: &Path) -> Result<(), Chip8Error> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let mut document: toml_edit::DocumentMut = content
+            .parse()
+            .map_err(|err: toml_edit::TomlError| Chip8Error::InvalidConfig(err.to_string()))?;
+        let mut table = toml_edit::Table::new();
+        for (key, name) in self.iter() {
+            table.insert(&format!("{:X}", key), toml_edit::value(name));
+        }
+        document.insert("layout", toml_edit::value(self.layout.name()));
+        document.insert("keys", toml_edit::Item::Table(table));
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, document.to_string())?;
+        Ok(())
+    }
+}
+
+// The printable keys of every layout row by row, each at the position of the QWERTY key with the same index
+const QWERTY_ROWS: [&str; 4] = ["1234567890-=", "QWERTYUIOP[]", "ASDFGHJKL;'", "ZXCVBNM,./"];
+const AZERTY_ROWS: [&str; 4] = ["1234567890)=", "AZERTYUIOP^$", "QSDFGHJKLMÙ", "WXCVBN,;:!"];
+const QWERTZ_ROWS: [&str; 4] = ["1234567890ß´", "QWERTZUIOPÜ+", "ASDFGHJKLÖÄ", "YXCVBNM,.-"];
+const DVORAK_ROWS: [&str; 4] = ["1234567890[]", "',.PYFGCRL/=", "AOEUIDHTNS-", ";QJKXBMWVZ"];
+
+// The CHIP-8 keys on the first four keys of each row
+const KEYPAD_ROWS: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+impl Layout {
+    // The name in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Qwerty => "qwerty",
+            Layout::Azerty => "azerty",
+            Layout::Qwertz => "qwertz",
+            Layout::Dvorak => "dvorak",
+        }
+    }
+
+    fn rows(self) -> [&'static str; 4] {
+        match self {
+            Layout::Qwerty => QWERTY_ROWS,
+            Layout::Azerty => AZERTY_ROWS,
+            Layout::Qwertz => QWERTZ_ROWS,
+            Layout::Dvorak => DVORAK_ROWS,
+        }
+    }
+
+    // The keypad on the keys where 1234 / QWER / ASDF / ZXCV are on a QWERTY keyboard.
+    pub fn key_map(self) -> KeyMap {
+        let mut keys: [String; KEY_COUNT] = Default::default();
+        for (row, chip8_keys) in self.rows().iter().zip(KEYPAD_ROWS) {
+            for (label, key) in row.chars().zip(chip8_keys) {
+                keys[key as usize] = label.to_string();
+            }
+        }
+        KeyMap {
+            layout: self,
+            keys,
+            hotkeys: Hotkeys::default(),
+        }
+    }
+
+    // The name of the QWERTY key in the same place as the key named name on this layout.
+    pub fn to_qwerty(self, name: &str) -> String {
+        translate(name, self.rows(), QWERTY_ROWS)
+    }
+
+    pub fn from_qwerty(self, name: &str) -> String {
+        translate(name, QWERTY_ROWS, self.rows())
+    }
+}
+
+// Names longer than a character ("Space", "Keypad 4") are the same on every layout.
+fn translate(name: &str, from: [&str; 4], to: [&str; 4]) -> String {
+    let mut chars = name.chars();
+    if let (Some(label), None) = (chars.next(), chars.next()) {
+        for (from, to) in from.iter().zip(to) {
+            if let Some(pos) = from.chars().position(|key| key.to_lowercase().eq(label.to_lowercase())) {
+                return to.chars().nth(pos).map(String::from).unwrap_or_default();
+            }
+        }
+    }
+    name.to_string()
+}
+
+fn key_overrides<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<u8, String>, D::Error> {
+    BTreeMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, name)| {
+            u8::from_str_radix(&key, 16)
+                .ok()
+                .filter(|&index| key.len() == 1 && (index as usize) < KEY_COUNT)
+                .map(|index| (index, name))
+                .ok_or_else(|| serde::de::Error::custom(format!("invalid CHIP-8 key (expected 0 to F): {}", key)))
+        })
+        .collect()
+}
+
+impl Config {
+    // config.toml in the platform's config directory, see paths.
+    pub fn default_path() -> Option<PathBuf> {
+        paths::config_file()
+    }
+
+    // The layout's keypad with the [keys] table applied over it.
+    pub fn key_map(&self) -> KeyMap {
+        let mut map = self.layout.key_map();
+        for (&key, name) in &self.keys {
+            map.keys[key as usize] = name.clone();
+        }
+        map.hotkeys = self.hotkeys.clone();
+        map
+    }
+
+    // Reads the config file at path, falling back to the defaults when it does not exist.
+    pub fn load(path: &Path) -> Result<Self, Chip8Error> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => content.parse(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf, overrides: impl Fn(&mut Config) + 'static) -> Self {
+        ConfigWatcher {
+            modified: modified(&path),
+            path,
+            last_check: Instant::now(),
+            overrides: Box::new(overrides),
+        }
+    }
+
+    // The new config when the file changed since the last call, the error when it no longer parses.
+    pub fn poll(&mut self) -> Option<Result<Config, Chip8Error>> {
+        if self.last_check.elapsed() < WATCH_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Config::load(&self.path).map(|mut config| {
+            (self.overrides)(&mut config);
+            config
+        }))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl FromStr for Config {
+    type Err = Chip8Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let config: Config = toml::from_str(s).map_err(|err| Chip8Error::InvalidConfig(err.to_string()))?;
+        if config.scale == 0 {
+            return Err(Chip8Error::InvalidConfig("scale must be at least 1".to_string()));
+        }
+        if config.ips == 0 {
+            return Err(Chip8Error::InvalidConfig("ips must be at least 1".to_string()));
+        }
+        if config.hotkeys.cheats.len() > CHEAT_COUNT {
+            return Err(Chip8Error::InvalidConfig(format!(
+                "at most {} cheat hotkeys can be bound",
+                CHEAT_COUNT
+            )));
+        }
+        if !(0.0..=1.0).contains(&config.audio.volume) {
+            return Err(Chip8Error::InvalidConfig("volume must be between 0 and 1".to_string()));
+        }
+        Ok(config)
+    }
+}
+
+impl Rgb {
+    // 0x00RRGGBB as used by minifb and most pixel buffers
+    pub fn to_u32(self) -> u32 {
+        (self.0 as u32) << 16 | (self.1 as u32) << 8 | self.2 as u32
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().strip_prefix('#').unwrap_or(s.trim());
+        let value = match hex.len() {
+            6 => u32::from_str_radix(hex, 16).ok(),
+            _ => None,
+        }
+        .ok_or(format!("invalid color (expected #RRGGBB): {}", s))?;
+        Ok(Rgb((value >> 16) as u8, (value >> 8) as u8, value as u8))
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.0, self.1, self.2)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rgb {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_keeps_defaults_for_missing_keys() {
+        let config: Config = "
+            scale = 12
+
+            [palette]
+            foreground = \"#33FF66\"
+            both_planes = \"#FF6600\"
+
+            [quirks]
+            shift_vy = true
+
+            [audio]
+            volume = 0.5
+        "
+        .parse()
+        .unwrap();
+
+        assert_eq!(config.scale, 12);
+        assert_eq!(config.ips, DEFAULT_IPS);
+        assert_eq!(config.palette.foreground, Rgb(0x33, 0xFF, 0x66));
+        assert_eq!(config.palette.background, Rgb(0, 0, 0));
+        assert_eq!(config.palette.both_planes, Rgb(0xFF, 0x66, 0x00));
+        assert_eq!(config.palette.plane2, Palette::default().plane2);
+        assert!(config.quirks.shift_vy && !config.quirks.vf_reset);
+        assert_eq!(config.audio.volume, 0.5);
+        assert!(config.audio.enabled);
+    }
+
+    #[test]
+    fn test_config_rejects_unknown_keys_and_bad_colors() {
+        assert!("scael = 4".parse::<Config>().is_err());
+        assert!("[palette]\nforeground = \"#12345\"".parse::<Config>().is_err());
+        assert!("scale = 0".parse::<Config>().is_err());
+        assert_eq!("#0a0B0c".parse(), Ok(Rgb(0x0A, 0x0B, 0x0C)));
+        assert_eq!(Rgb(0x12, 0x34, 0x56).to_u32(), 0x123456);
+    }
+
+    #[test]
+    fn test_watcher_reloads_changed_file() {
+        let path = std::env::temp_dir().join(format!("chip8-config-{}.toml", std::process::id()));
+        std::fs::write(&path, "ips = 600").unwrap();
+        let mut watcher = ConfigWatcher::new(path.clone(), |config| config.scale = 3);
+        watcher.last_check -= WATCH_INTERVAL;
+        assert!(watcher.poll().is_none());
+
+        std::fs::write(&path, "ips = 900").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(1)).unwrap();
+        watcher.last_check -= WATCH_INTERVAL;
+        let config = watcher.poll().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((config.ips, config.scale), (900, 3));
+        assert!(watcher.poll().is_none());
+    }
+
+    #[test]
+    fn test_key_map_overrides_named_keys() {
+        let config: Config = "[keys]\n5 = \"Up\"\nf = \"Keypad 9\"".parse().unwrap();
+        let keys = config.key_map();
+        assert_eq!(keys.keys[0x5], "Up");
+        assert_eq!(keys.keys[0xF], "Keypad 9");
+        assert_eq!(keys.keys[0x4], "Q");
+        assert!("[keys]\nG = \"Q\"".parse::<Config>().is_err());
+        assert!("[keys]\n10 = \"Q\"".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn test_saved_key_map_keeps_the_rest_of_the_file() {
+        let path = std::env::temp_dir().join(format!("chip8-keys-{}.toml", std::process::id()));
+        std::fs::write(&path, "# big pixels\nscale = 12\n\n[keys]\n5 = \"Q\"\n").unwrap();
+        let mut keys = Layout::Dvorak.key_map();
+        keys.keys[0xA] = "Space".to_string();
+        keys.save(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(content.starts_with("# big pixels\nscale = 12\n"));
+        let config: Config = content.parse().unwrap();
+        assert_eq!((config.scale, config.key_map()), (12, keys));
+    }
+
+    #[test]
+    fn test_layouts_put_the_keypad_in_the_same_place() {
+        let config: Config = "layout = \"azerty\"\n[keys]\n0 = \"Space\"".parse().unwrap();
+        let keys = config.key_map();
+        assert_eq!(keys.layout, Layout::Azerty);
+        assert_eq!(keys.keys[0x4..=0x9], ["A", "Z", "E", "Q", "S", "D"]);
+        assert_eq!((keys.keys[0x0].as_str(), keys.keys[0xA].as_str()), ("Space", "W"));
+
+        let dvorak = Layout::Dvorak.key_map();
+        assert_eq!(dvorak.keys[0x4..=0x6], ["'", ",", "."]);
+        for (layout, keys) in [(Layout::Dvorak, dvorak), (Layout::Qwertz, Layout::Qwertz.key_map())] {
+            let qwerty: Vec<String> = keys.iter().map(|(_, name)| layout.to_qwerty(name)).collect();
+            assert_eq!(qwerty, KeyMap::default().keys);
+        }
+        assert_eq!(Layout::Azerty.to_qwerty("m"), ";");
+        assert_eq!(Layout::Azerty.from_qwerty("Q"), "A");
+        assert_eq!(Layout::Qwertz.to_qwerty("Keypad 4"), "Keypad 4");
+        assert!("layout = \"colemak\"".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn test_hotkeys_follow_the_layout() {
+        let config: Config = "layout = \"azerty\"\n[hotkeys]\npause = \"F1\"\nreset = \"A\""
+            .parse()
+            .unwrap();
+        let hotkeys = config.key_map().to_qwerty().hotkeys;
+        assert_eq!((hotkeys.pause.as_str(), hotkeys.reset.as_str()), ("F1", "Q"));
+        assert_eq!((hotkeys.step_mode.as_str(), hotkeys.quit.as_str()), (";", "Escape"));
+        assert_eq!(hotkeys.iter().find(|&(flag, _)| flag == PAUSE), Some((PAUSE, "F1")));
+        assert!("[hotkeys]\nrewind = \"R\"".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn test_touch_layout_splits_the_window() {
+        let config: Config = "[touch]\nlayout = [\"5\", \"7.9\", \"8\"]".parse().unwrap();
+        let layout = config.touch.layout;
+        assert_eq!(layout.key_at(0.9, 0.1), Some(0x5));
+        assert_eq!(layout.key_at(0.1, 0.5), Some(0x7));
+        assert_eq!(layout.key_at(0.5, 0.5), None);
+        assert_eq!(layout.key_at(1.0, 0.5), Some(0x9));
+        assert_eq!(layout.key_at(0.5, 1.0), Some(0x8));
+        assert_eq!(TouchConfig::default().layout.key_at(0.0, 0.99), Some(0xA));
+        assert!("[touch]\nlayout = [\"12G\"]".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn test_gamepad_keys_replace_the_defaults() {
+        let config: Config = "[gamepad]\nreset = \"\"\n[gamepad.keys]\n2 = \"dpup\"".parse().unwrap();
+        assert_eq!(config.gamepad.keys, BTreeMap::from([(0x2, "dpup".to_string())]));
+        let controls: Vec<_> = config.gamepad.controls().collect();
+        assert_eq!(controls, [(PAUSE, "start"), (FAST_FORWARD, "rightshoulder")]);
+        assert_eq!(Config::default().gamepad.keys.len(), 6);
+    }
+}
@@ -0,0 +1,143 @@
+use std::ops::Range;
+
+use crate::debugger;
+use crate::symbols::Symbols;
+
+pub struct Line {
+    pub addr: usize,
+    pub bytes: String,
+    pub text: String,
+    pub executed: bool,
+}
+
+pub struct Coverage {
+    executed: Vec<bool>,
+    instruction_starts: Vec<bool>,
+}
+
+impl Coverage {
+    pub fn new(memory_size: usize) -> Self {
+        Coverage {
+            executed: vec![false; memory_size],
+            instruction_starts: vec![false; memory_size],
+        }
+    }
+
+    pub fn record(&mut self, pc: u16) {
+        let pc = pc as usize;
+        if pc + 1 < self.executed.len() {
+            self.executed[pc] = true;
+            self.executed[pc + 1] = true;
+            self.instruction_starts[pc] = true;
+        }
+    }
+
+    pub fn report(&self, rom: Range<usize>) -> String {
+        let total = rom.len();
+        let covered = self.executed_bytes(rom.clone());
+        let percentage = if total == 0 {
+            0.0
+        } else {
+            covered as f64 * 100.0 / total as f64
+        };
+
+        let mut result = format!(
+            "ROM bytes executed: {} / {} ({:.2}%)\n\nUnexecuted ranges:",
+            covered, total, percentage
+        );
+        for range in self.unexecuted_ranges(rom) {
+            result.push_str(&format!(
+                "\n  {:#06X}-{:#06X}  ({} bytes)",
+                range.start,
+                range.end - 1,
+                range.len()
+            ));
+        }
+        result
+    }
+
+    pub fn annotated_disassembly(&self, memory: &[u8], rom: Range<usize>, symbols: &Symbols) -> String {
+        let mut lines = Vec::new();
+        for line in self.lines(memory, rom, symbols) {
+            if let Some(label) = symbols.label(line.addr as u16) {
+                lines.push(format!("{}:", label));
+            }
+            let marker = if line.executed { ' ' } else { '!' };
+            lines.push(format!(
+                "{} {:#06X}  {:<4}  {}",
+                marker, line.addr, line.bytes, line.text
+            ));
+        }
+        lines.join("\n")
+    }
+
+    // Splits the ROM into instructions, falling back to single data bytes where execution never started.
+    pub fn lines(&self, memory: &[u8], rom: Range<usize>, symbols: &Symbols) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut addr = rom.start;
+
+        while addr < rom.end {
+            if self.instruction_starts[addr] || (addr + 1 < rom.end && !self.instruction_starts[addr + 1]) {
+                let instruction = ((memory[addr] as u16) << 8) | memory[addr + 1] as u16;
+                lines.push(Line {
+                    addr,
+                    bytes: format!("{:04X}", instruction),
+                    text: debugger::disassemble(memory, addr as u16, symbols),
+                    executed: self.executed[addr],
+                });
+                addr += 2;
+            } else {
+                lines.push(Line {
+                    addr,
+                    bytes: format!("{:02X}", memory[addr]),
+                    text: format!("DB {:#04X}", memory[addr]),
+                    executed: self.executed[addr],
+                });
+                addr += 1;
+            }
+        }
+        lines
+    }
+
+    pub fn executed_bytes(&self, rom: Range<usize>) -> usize {
+        self.executed[rom].iter().filter(|&&executed| executed).count()
+    }
+
+    fn unexecuted_ranges(&self, rom: Range<usize>) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut start = None;
+
+        for addr in rom.clone() {
+            match (self.executed[addr], start) {
+                (false, None) => start = Some(addr),
+                (true, Some(begin)) => {
+                    ranges.push(begin..addr);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(begin) = start {
+            ranges.push(begin..rom.end);
+        }
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unexecuted_ranges() {
+        let mut coverage = Coverage::new(0x220);
+        coverage.record(0x200);
+        coverage.record(0x202);
+        coverage.record(0x210);
+
+        assert_eq!(
+            coverage.unexecuted_ranges(0x200..0x214),
+            vec![0x204..0x210, 0x212..0x214]
+        );
+    }
+}
@@ -0,0 +1,587 @@
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::rc::Rc;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::database::Database;
+use crate::debugger;
+use crate::emulator::{Chip8, DEFAULT_IPS};
+use crate::error::Chip8Error;
+use crate::machine::MEMORY_SIZE;
+use crate::parse::{parse_quirks, parse_u16};
+use crate::symbols::Symbols;
+
+// CHIP-8 has one thread of execution, the adapter reports it under this id
+const THREAD_ID: u64 = 1;
+// The variablesReference of each scope
+const REGISTERS: u64 = 1;
+const STACK: u64 = 2;
+const DISPLAY: u64 = 3;
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// Waits for a debugger on stdin and stdout, or on the given port of localhost, and serves it until it
+// disconnects.
+pub fn run(port: Option<u16>) -> Result<(), Chip8Error> {
+    let Some(port) = port else {
+        return serve(BufReader::new(std::io::stdin()), std::io::stdout());
+    };
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    eprintln!("Waiting for a debugger on port {}", listener.local_addr()?.port());
+    let (stream, _) = listener.accept()?;
+    serve(BufReader::new(stream.try_clone()?), stream)
+}
+
+// Speaks the Debug Adapter Protocol: the requests come from input, the responses and events go to output.
+// The ROM runs at its speed in between, the requests are read on a thread of their own so a pause gets
+// through while it runs.
+pub fn serve(input: impl BufRead + Send + 'static, output: impl Write) -> Result<(), Chip8Error> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut input = input;
+        while let Ok(Some(message)) = read_message(&mut input) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut session = Session::new(output);
+    while !session.done {
+        let request = if session.running() {
+            match receiver.try_recv() {
+                Ok(request) => Some(request),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match receiver.recv() {
+                Ok(request) => Some(request),
+                Err(_) => break,
+            }
+        };
+        match request {
+            Some(request) => session.handle(&request)?,
+            None => {
+                session.run_frame()?;
+                session.wait();
+            }
+        }
+    }
+    Ok(())
+}
+
+// One message of the base protocol: a Content-Length header, an empty line and that many bytes of JSON.
+// None at the end of the input.
+pub fn read_message(input: &mut impl BufRead) -> std::io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() && length.is_some() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let mut body = vec![0; length.unwrap_or_default()];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(std::io::Error::from)
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> std::io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Run {
+    Stopped,
+    Running,
+    // next over a call and stepOut: runs until the stack is no deeper than this
+    Until(usize),
+}
+
+// The state of one debugging session, the ROM is loaded by the launch request.
+pub struct Session<W: Write> {
+    output: W,
+    seq: u64,
+    chip8: Option<Chip8>,
+    ips: u32,
+    stop_on_entry: bool,
+    // the ROM starts once it is launched and the debugger is done setting breakpoints
+    configured: bool,
+    breakpoints: BTreeSet<u16>,
+    run: Run,
+    // set when continuing from a breakpoint, so the instruction under it runs instead of hitting it again
+    skip_breakpoint: bool,
+    halted: Rc<Cell<Option<u16>>>,
+    next_frame: Instant,
+    done: bool,
+}
+
+impl<W: Write> Session<W> {
+    pub fn new(output: W) -> Self {
+        Session {
+            output,
+            seq: 0,
+            chip8: None,
+            ips: DEFAULT_IPS,
+            stop_on_entry: false,
+            configured: false,
+            breakpoints: BTreeSet::new(),
+            run: Run::Stopped,
+            skip_breakpoint: false,
+            halted: Rc::new(Cell::new(None)),
+            next_frame: Instant::now(),
+            done: false,
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        self.run != Run::Stopped
+    }
+
+    pub fn handle(&mut self, request: &Value) -> Result<(), Chip8Error> {
+        let command = request["command"].as_str().unwrap_or_default();
+        let args = &request["arguments"];
+        let mut events = Vec::new();
+        let result = match command {
+            "initialize" => {
+                events.push(("initialized", json!({})));
+                Ok(json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsInstructionBreakpoints": true,
+                    "supportsDisassembleRequest": true,
+                    "supportsTerminateRequest": true,
+                }))
+            }
+            "launch" => self.launch(args).map(|()| json!({})),
+            "setBreakpoints" => {
+                // there is no assembler here, so nothing maps .8o lines to addresses
+                let breakpoints = args["breakpoints"].as_array().map_or(&[][..], Vec::as_slice);
+                let unverified = breakpoints.iter().map(
+                    |_| json!({ "verified": false, "message": "No source map, set breakpoints in the disassembly" }),
+                );
+                Ok(json!({ "breakpoints": unverified.collect::<Vec<_>>() }))
+            }
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(args),
+            "setExceptionBreakpoints" => Ok(json!({})),
+            "configurationDone" => {
+                self.configured = true;
+                Ok(json!({}))
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }] })),
+            "stackTrace" => self.chip8().map(stack_trace),
+            "scopes" => Ok(json!({ "scopes": [
+                { "name": "Registers", "variablesReference": REGISTERS, "expensive": false },
+                { "name": "Stack", "variablesReference": STACK, "expensive": false },
+                { "name": "Display", "variablesReference": DISPLAY, "expensive": false },
+            ]})),
+            "variables" => self
+                .chip8()
+                .map(|chip8| variables(chip8, args["variablesReference"].as_u64())),
+            "disassemble" => self.chip8().and_then(|chip8| disassemble(chip8, args)),
+            "continue" => self.chip8().map(|_| ()).map(|()| {
+                self.resume(Run::Running);
+                json!({ "allThreadsContinued": true })
+            }),
+            "next" | "stepIn" | "stepOut" => self.chip8().map(|chip8| chip8.stack().len()).map(|depth| {
+                match command {
+                    "next" => self.step_over(depth, &mut events),
+                    "stepOut" if depth > 0 => self.resume(Run::Until(depth - 1)),
+                    _ => self.step_once(&mut events),
+                }
+                json!({})
+            }),
+            "pause" => {
+                self.run = Run::Stopped;
+                events.push(("stopped", stopped("pause", None)));
+                Ok(json!({}))
+            }
+            "disconnect" => {
+                self.done = true;
+                Ok(json!({}))
+            }
+            "terminate" => {
+                self.done = true;
+                events.push(("terminated", json!({})));
+                Ok(json!({}))
+            }
+            _ => Err(format!("Unsupported request {}", command)),
+        };
+        self.respond(request, result)?;
+        for (event, body) in events {
+            self.send_event(event, body)?;
+        }
+        self.start()
+    }
+
+    // Runs the instructions of one 60 Hz frame and ticks the timers, unless a breakpoint, a finished step,
+    // a halt or an error stops it first.
+    pub fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        let Some(chip8) = &mut self.chip8 else {
+            return Ok(());
+        };
+        for _ in 0..(self.ips / 60).max(1) {
+            let pc = chip8.pc();
+            if !std::mem::take(&mut self.skip_breakpoint) && self.breakpoints.contains(&pc) {
+                return self.stop("instruction breakpoint", None);
+            }
+            if let Err(err) = chip8.step() {
+                return self.stop("exception", Some(err.to_string()));
+            }
+            if let Some(pc) = self.halted.take() {
+                return self.stop("halt", Some(format!("Program halted at {:#05X}", pc)));
+            }
+            if let Run::Until(depth) = self.run {
+                if chip8.stack().len() <= depth {
+                    return self.stop("step", None);
+                }
+            }
+        }
+        chip8.tick_timers();
+        Ok(())
+    }
+
+    // Keeps a running ROM at 60 frames a second, after a long hitch it goes on from now instead of catching up.
+    fn wait(&mut self) {
+        self.next_frame += FRAME;
+        let now = Instant::now();
+        match self.next_frame.checked_duration_since(now) {
+            Some(wait) => thread::sleep(wait),
+            None if now - self.next_frame > FRAME * 4 => self.next_frame = now,
+            None => {}
+        }
+    }
+
+    fn launch(&mut self, args: &Value) -> Result<(), String> {
+        let program = args["program"].as_str().ok_or("launch needs the ROM as program")?;
+        self.ips = args["ips"]
+            .as_u64()
+            .map_or(DEFAULT_IPS, |ips| ips.clamp(1, u32::MAX as u64) as u32);
+        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+        let mut builder = Chip8::builder().rom_file(program).ips(self.ips);
+        builder = match args["quirks"].as_str() {
+            Some(quirks) => builder.quirks(parse_quirks(quirks)?),
+            None => builder.database(Database::load()),
+        };
+        let mut chip8 = builder.build().map_err(|err| err.to_string())?;
+        let halted = Rc::clone(&self.halted);
+        chip8.on_halt(move |pc| halted.set(Some(pc)));
+        self.chip8 = Some(chip8);
+        Ok(())
+    }
+
+    fn set_instruction_breakpoints(&mut self, args: &Value) -> Result<Value, String> {
+        self.breakpoints.clear();
+        let mut verified = Vec::new();
+        for breakpoint in args["breakpoints"].as_array().map_or(&[][..], Vec::as_slice) {
+            let reference = breakpoint["instructionReference"].as_str().unwrap_or_default();
+            let offset = breakpoint["offset"].as_i64().unwrap_or(0);
+            let addr = parse_u16(reference).map(|addr| addr as i64 + offset);
+            match addr {
+                Ok(addr) if (0..MEMORY_SIZE as i64).contains(&addr) => {
+                    self.breakpoints.insert(addr as u16);
+                    verified.push(json!({ "verified": true, "instructionReference": format!("{:#06X}", addr) }));
+                }
+                _ => verified.push(json!({ "verified": false, "message": "Not an address in memory" })),
+            }
+        }
+        Ok(json!({ "breakpoints": verified }))
+    }
+
+    // Starts the ROM once both the launch and the configuration are done.
+    fn start(&mut self) -> Result<(), Chip8Error> {
+        if !self.configured || self.chip8.is_none() {
+            return Ok(());
+        }
+        self.configured = false;
+        if self.stop_on_entry {
+            return self.stop("entry", None);
+        }
+        self.resume(Run::Running);
+        Ok(())
+    }
+
+    fn resume(&mut self, run: Run) {
+        self.run = run;
+        self.skip_breakpoint = true;
+        self.next_frame = Instant::now();
+    }
+
+    fn step_once(&mut self, events: &mut Vec<(&'static str, Value)>) {
+        let Some(chip8) = &mut self.chip8 else {
+            return;
+        };
+        self.run = Run::Stopped;
+        let description = chip8.step().err().map(|err| err.to_string());
+        let reason = if description.is_some() { "exception" } else { "step" };
+        events.push(("stopped", stopped(reason, description)));
+    }
+
+    // Steps over a call by running until it returns.
+    fn step_over(&mut self, depth: usize, events: &mut Vec<(&'static str, Value)>) {
+        self.step_once(events);
+        if self.chip8.as_ref().is_some_and(|chip8| chip8.stack().len() > depth) {
+            events.clear();
+            self.resume(Run::Until(depth));
+            self.skip_breakpoint = false;
+        }
+    }
+
+    fn stop(&mut self, reason: &str, description: Option<String>) -> Result<(), Chip8Error> {
+        self.run = Run::Stopped;
+        self.send_event("stopped", stopped(reason, description))
+    }
+
+    fn chip8(&self) -> Result<&Chip8, String> {
+        self.chip8.as_ref().ok_or_else(|| "No ROM is launched".to_string())
+    }
+
+    fn respond(&mut self, request: &Value, result: Result<Value, String>) -> Result<(), Chip8Error> {
+        self.seq += 1;
+        let mut response = json!({
+            "seq": self.seq,
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = message.into(),
+        }
+        Ok(write_message(&mut self.output, &response)?)
+    }
+
+    fn send_event(&mut self, event: &str, body: Value) -> Result<(), Chip8Error> {
+        self.seq += 1;
+        let message = json!({ "seq": self.seq, "type": "event", "event": event, "body": body });
+        Ok(write_message(&mut self.output, &message)?)
+    }
+}
+
+fn stopped(reason: &str, description: Option<String>) -> Value {
+    let mut body = json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true });
+    if let Some(description) = description {
+        body["description"] = description.into();
+    }
+    body
+}
+
+// The current instruction, then the call of every active subroutine.
+fn stack_trace(chip8: &Chip8) -> Value {
+    let snapshot = chip8.snapshot();
+    let symbols = Symbols::default();
+    let calls = snapshot
+        .stack
+        .iter()
+        .rev()
+        .map(|&return_addr| return_addr.wrapping_sub(2));
+    let frames: Vec<Value> = std::iter::once(snapshot.pc)
+        .chain(calls)
+        .enumerate()
+        .map(|(id, addr)| {
+            json!({
+                "id": id,
+                "name": format!("{:#06X}  {}", addr, debugger::disassemble(&snapshot.memory, addr, &symbols)),
+                "line": 0,
+                "column": 0,
+                "instructionPointerReference": format!("{:#06X}", addr),
+            })
+        })
+        .collect();
+    json!({ "stackFrames": frames, "totalFrames": frames.len() })
+}
+
+fn variables(chip8: &Chip8, reference: Option<u64>) -> Value {
+    let snapshot = chip8.snapshot();
+    let variable = |name: String, value: String| json!({ "name": name, "value": value, "variablesReference": 0 });
+    let variables: Vec<Value> = match reference {
+        Some(REGISTERS) => {
+            let registers = snapshot.registers.iter().enumerate();
+            registers
+                .map(|(x, value)| variable(format!("V{:X}", x), format!("{:#04X}", value)))
+                .chain([
+                    variable("I".to_string(), format!("{:#06X}", snapshot.i)),
+                    variable("PC".to_string(), format!("{:#06X}", snapshot.pc)),
+                    variable("DT".to_string(), format!("{:#04X}", snapshot.delay_timer)),
+                    variable("ST".to_string(), format!("{:#04X}", snapshot.sound_timer)),
+                ])
+                .collect()
+        }
+        Some(STACK) => snapshot
+            .stack
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(depth, return_addr)| variable(format!("#{}", depth + 1), format!("{:#06X}", return_addr)))
+            .collect(),
+        Some(DISPLAY) => chip8
+            .display_text()
+            .lines()
+            .enumerate()
+            .map(|(row, pixels)| variable(format!("{:02}", row), pixels.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    };
+    json!({ "variables": variables })
+}
+
+// instructionCount instructions from memoryReference plus offset bytes plus instructionOffset instructions,
+// the ones outside memory are marked invalid.
+fn disassemble(chip8: &Chip8, args: &Value) -> Result<Value, String> {
+    let reference = args["memoryReference"].as_str().unwrap_or_default();
+    let start = parse_u16(reference)? as i64
+        + args["offset"].as_i64().unwrap_or(0)
+        + args["instructionOffset"].as_i64().unwrap_or(0) * 2;
+    let memory = chip8.read_mem(0, MEMORY_SIZE).map_err(|err| err.to_string())?;
+    let symbols = Symbols::default();
+    let instructions: Vec<Value> = (0..args["instructionCount"].as_i64().unwrap_or(0))
+        .map(|index| start + index * 2)
+        .map(|addr| match usize::try_from(addr) {
+            Ok(addr) if addr + 1 < MEMORY_SIZE => json!({
+                "address": format!("{:#06X}", addr),
+                "instructionBytes": format!("{:02X} {:02X}", memory[addr], memory[addr + 1]),
+                "instruction": debugger::disassemble(memory, addr as u16, &symbols),
+            }),
+            _ => json!({ "address": format!("{:#06X}", addr), "instruction": "", "presentationHint": "invalid" }),
+        })
+        .collect();
+    Ok(json!({ "instructions": instructions }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LD V0, 1; CALL 0x206; JP 0x204; ADD V0, 1; RET
+    const ROM: [u8; 10] = [0x60, 0x01, 0x22, 0x06, 0x12, 0x04, 0x70, 0x01, 0x00, 0xEE];
+
+    fn launch(name: &str, stop_on_entry: bool) -> Session<Vec<u8>> {
+        let path = std::env::temp_dir().join(format!("chip8-dap-{}-{}.ch8", name, std::process::id()));
+        std::fs::write(&path, ROM).unwrap();
+        let mut session = Session::new(Vec::new());
+        let requests = [
+            json!({ "seq": 1, "command": "initialize", "arguments": {} }),
+            json!({ "seq": 2, "command": "launch", "arguments": {
+                "program": path.to_str().unwrap(), "quirks": "none", "stopOnEntry": stop_on_entry,
+            }}),
+            json!({ "seq": 3, "command": "setInstructionBreakpoints", "arguments": {
+                "breakpoints": [{ "instructionReference": "0x206" }],
+            }}),
+            json!({ "seq": 4, "command": "configurationDone" }),
+        ];
+        for request in &requests {
+            session.handle(request).unwrap();
+        }
+        std::fs::remove_file(&path).unwrap();
+        session
+    }
+
+    fn request(session: &mut Session<Vec<u8>>, command: &str, arguments: Value) -> Value {
+        session.output.clear();
+        session
+            .handle(&json!({ "seq": 9, "command": command, "arguments": arguments }))
+            .unwrap();
+        let response = messages(session).remove(0);
+        assert_eq!(response["success"], true, "{}", response);
+        response["body"].clone()
+    }
+
+    fn messages(session: &Session<Vec<u8>>) -> Vec<Value> {
+        let mut output = session.output.as_slice();
+        std::iter::from_fn(|| read_message(&mut output).unwrap()).collect()
+    }
+
+    fn register(session: &mut Session<Vec<u8>>, name: &str) -> Value {
+        let body = request(session, "variables", json!({ "variablesReference": REGISTERS }));
+        let variables = body["variables"].as_array().unwrap();
+        variables.iter().find(|variable| variable["name"] == name).unwrap()["value"].clone()
+    }
+
+    #[test]
+    fn test_breakpoint_stops_and_step_out_returns() {
+        let mut session = launch("breakpoint", false);
+        assert!(session.running());
+        session.output.clear();
+        session.run_frame().unwrap();
+        let stop = messages(&session).remove(0);
+        assert_eq!(
+            (stop["event"].as_str(), stop["body"]["reason"].as_str()),
+            (Some("stopped"), Some("instruction breakpoint"))
+        );
+
+        let trace = request(&mut session, "stackTrace", json!({ "threadId": THREAD_ID }));
+        let frames: Vec<&Value> = trace["stackFrames"].as_array().unwrap().iter().collect();
+        let addrs: Vec<&str> = frames
+            .iter()
+            .map(|frame| frame["instructionPointerReference"].as_str().unwrap())
+            .collect();
+        assert_eq!(addrs, ["0x0206", "0x0202"]);
+        assert_eq!(register(&mut session, "V0"), "0x01");
+
+        request(&mut session, "stepOut", json!({ "threadId": THREAD_ID }));
+        session.output.clear();
+        session.run_frame().unwrap();
+        assert_eq!(messages(&session)[0]["body"]["reason"], "step");
+        assert_eq!(
+            (register(&mut session, "PC"), register(&mut session, "V0")),
+            ("0x0204".into(), "0x02".into())
+        );
+    }
+
+    #[test]
+    fn test_next_steps_over_calls() {
+        let mut session = launch("next", true);
+        assert!(!session.running());
+
+        request(&mut session, "next", json!({ "threadId": THREAD_ID }));
+        assert_eq!(register(&mut session, "PC"), "0x0202");
+        request(&mut session, "next", json!({ "threadId": THREAD_ID }));
+        assert!(session.running());
+        session.output.clear();
+        session.run_frame().unwrap();
+        // the breakpoint inside the call still stops it
+        assert_eq!(messages(&session)[0]["body"]["reason"], "instruction breakpoint");
+        request(&mut session, "continue", json!({ "threadId": THREAD_ID }));
+        session.run_frame().unwrap();
+        assert_eq!(register(&mut session, "PC"), "0x0204");
+        assert!(!session.running());
+    }
+
+    #[test]
+    fn test_disassembly_and_source_breakpoints() {
+        let mut session = launch("disassemble", true);
+        let body = request(
+            &mut session,
+            "disassemble",
+            json!({
+                "memoryReference": "0x200", "instructionOffset": -1, "instructionCount": 3,
+            }),
+        );
+        let instructions = body["instructions"].as_array().unwrap();
+        assert_eq!(instructions[1]["instruction"], "LD V0, 0x01");
+        assert_eq!(instructions[2]["instructionBytes"], "22 06");
+
+        let body = request(
+            &mut session,
+            "setBreakpoints",
+            json!({
+                "source": { "path": "game.8o" }, "breakpoints": [{ "line": 3 }],
+            }),
+        );
+        assert_eq!(body["breakpoints"][0]["verified"], false);
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+
+use crate::error::Chip8Error;
+use crate::parse::parse_quirks;
+use crate::paths;
+use crate::quirks::Quirks;
+
+const BUNDLED: &str = include_str!("../roms/database.txt");
+
+// What is known about a ROM with a certain hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomInfo {
+    pub title: String,
+    pub platform: String,
+    pub quirks: Quirks,
+}
+
+// ROMs by the SHA-1 hash of their bytes, later entries win over earlier ones for the same hash.
+#[derive(Debug, Clone, Default)]
+pub struct Database {
+    roms: Vec<(String, RomInfo)>,
+}
+
+impl Database {
+    // The entries that come with the emulator followed by the ones in roms.txt in the config directory. A
+    // broken roms.txt is reported and left out.
+    pub fn load() -> Self {
+        let mut database = Self::parse(BUNDLED).expect("the bundled ROM database is valid");
+        if let Some(file) = paths::database_file().filter(|file| file.exists()) {
+            match fs::read_to_string(&file).map_err(Chip8Error::from).and_then(|text| {
+                Self::parse(&text).map_err(|err| Chip8Error::InvalidDatabase(format!("{}: {}", file.display(), err)))
+            }) {
+                Ok(user) => database.roms.extend(user.roms),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        database
+    }
+
+    // One ROM per line: its SHA-1 hash, platform, quirks like for --quirks, and the title as the rest of the
+    // line. Everything after a '#' is a comment.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut roms = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (hash, platform, quirks, title) = match fields.as_slice() {
+                [hash, platform, quirks, title @ ..] if !title.is_empty() => {
+                    (*hash, *platform, *quirks, title.join(" "))
+                }
+                _ => {
+                    return Err(format!(
+                        "line {}: expected a hash, platform, quirks and title",
+                        number + 1
+                    ))
+                }
+            };
+            if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("line {}: not a SHA-1 hash: {}", number + 1, hash));
+            }
+            let quirks = parse_quirks(quirks).map_err(|err| format!("line {}: {}", number + 1, err))?;
+            let info = RomInfo {
+                title,
+                platform: platform.to_string(),
+                quirks,
+            };
+            roms.push((hash.to_ascii_lowercase(), info));
+        }
+        Ok(Database { roms })
+    }
+
+    pub fn lookup(&self, rom: &[u8]) -> Option<&RomInfo> {
+        let hash = sha1(rom);
+        self.roms
+            .iter()
+            .rev()
+            .find(|(known, _)| *known == hash)
+            .map(|(_, info)| info)
+    }
+}
+
+// In lower case hex digits, like sha1sum prints it.
+pub fn sha1(data: &[u8]) -> String {
+    sha1_smol::Sha1::from(data).digest().to_string()
+}
+
+// The CRC-32 ROM sets and zip archives list files by.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtin;
+
+    #[test]
+    fn test_bundled_database_knows_the_builtins() {
+        let database = Database::parse(BUNDLED).unwrap();
+        for builtin in &builtin::BUILTINS {
+            assert!(database.lookup(builtin.data).is_some(), "{}", builtin.name);
+        }
+        assert_eq!(database.lookup(&[0x12, 0x00]), None);
+    }
+
+    #[test]
+    fn test_hashes_match_the_usual_tools() {
+        assert_eq!(sha1(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_parse_reads_quirks_and_titles() {
+        let hash = sha1(&[0x12, 0x00]);
+        let database = Database::parse(&format!(
+            "# comment\n{} originalChip8 vip Jump Forever\n{} superchip jump-vx Jump Again # later wins\n",
+            hash.to_uppercase(),
+            hash
+        ))
+        .unwrap();
+        let info = database.lookup(&[0x12, 0x00]).unwrap();
+        assert_eq!(info.title, "Jump Again");
+        assert_eq!(info.platform, "superchip");
+        assert!(info.quirks.jump_vx && !info.quirks.vf_reset);
+
+        assert!(Database::parse("abc modernChip8 none Short").is_err());
+        assert!(Database::parse(&format!("{} modernChip8 none", hash)).is_err());
+        assert!(Database::parse(&format!("{} modernChip8 sideways Title", hash)).is_err());
+    }
+}
@@ -0,0 +1,657 @@
+use std::fs;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+
+use crate::error::Chip8Error;
+use crate::opcode::{self, Opcode};
+use crate::parse::{parse_range, parse_u16, parse_u8};
+use crate::paths;
+use crate::symbols::Symbols;
+use crate::trace::Registers;
+
+const DEFAULT_MEMORY_LENGTH: u16 = 64;
+const DEFAULT_SPRITE_ROWS: u16 = 15;
+const DEFAULT_PROFILE_ENTRIES: u16 = 10;
+const DEFAULT_TIMELINE_STEPS: u16 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    DelayTimer,
+    SoundTimer,
+}
+
+impl FromStr for Register {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "i" => Ok(Register::I),
+            "pc" => Ok(Register::Pc),
+            "dt" => Ok(Register::DelayTimer),
+            "st" => Ok(Register::SoundTimer),
+            _ => name
+                .strip_prefix('v')
+                .filter(|index| index.len() == 1)
+                .and_then(|index| u8::from_str_radix(index, 16).ok())
+                .map(Register::V)
+                .ok_or(format!("Unknown register: {}", name)),
+        }
+    }
+}
+
+impl std::fmt::Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{:X}", x),
+            Register::I => write!(f, "I"),
+            Register::Pc => write!(f, "PC"),
+            Register::DelayTimer => write!(f, "DT"),
+            Register::SoundTimer => write!(f, "ST"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakCondition {
+    Address(u16),
+    Opcode(&'static str),
+    Write(u16, u16),
+}
+
+impl FromStr for BreakCondition {
+    type Err = String;
+
+    fn from_str(arg: &str) -> Result<Self, Self::Err> {
+        let arg = arg.trim();
+        if let Some(range) = arg.strip_prefix("write ").or_else(|| arg.strip_prefix("w ")) {
+            let range = parse_range(range)?;
+            return Ok(BreakCondition::Write(*range.start(), *range.end()));
+        }
+
+        if let Ok(addr) = parse_u16(arg) {
+            return Ok(BreakCondition::Address(addr));
+        }
+
+        let pattern = arg.to_ascii_uppercase();
+        opcode::PATTERNS
+            .iter()
+            .find(|&&known| known == pattern)
+            .map(|&known| BreakCondition::Opcode(known))
+            .ok_or(format!("Neither an address nor an opcode type (e.g. DXYN): {}", arg))
+    }
+}
+
+impl BreakCondition {
+    pub fn matches(&self, pc: u16, opcode: Option<&Opcode>) -> bool {
+        match self {
+            BreakCondition::Address(addr) => *addr == pc,
+            BreakCondition::Opcode(pattern) => opcode.is_some_and(|opcode| opcode.pattern() == *pattern),
+            BreakCondition::Write(_, _) => false,
+        }
+    }
+
+    pub fn matches_write(&self, addr: u16) -> bool {
+        match self {
+            BreakCondition::Write(start, end) => (*start..=*end).contains(&addr),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for BreakCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreakCondition::Address(addr) => write!(f, "{:#06X}", addr),
+            BreakCondition::Opcode(pattern) => write!(f, "{}", pattern),
+            BreakCondition::Write(start, end) => write!(f, "write {:#06X}-{:#06X}", start, end),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub condition: BreakCondition,
+    pub hits: u32,
+    pub ignore: u32,
+    pub temporary: bool,
+}
+
+impl Breakpoint {
+    pub fn new(condition: BreakCondition) -> Self {
+        Breakpoint {
+            condition,
+            hits: 0,
+            ignore: 0,
+            temporary: false,
+        }
+    }
+
+    pub fn temporary(addr: u16) -> Self {
+        Breakpoint {
+            temporary: true,
+            ..Breakpoint::new(BreakCondition::Address(addr))
+        }
+    }
+
+    // Counts a hit and tells whether execution should stop or the hit is still ignored.
+    pub fn hit(&mut self) -> bool {
+        self.hits += 1;
+        if self.ignore > 0 {
+            self.ignore -= 1;
+            false
+        } else {
+            true
+        }
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<24}hits: {}", self.condition.to_string(), self.hits)?;
+        if self.ignore > 0 {
+            write!(f, ", ignoring next {}", self.ignore)?;
+        }
+        if self.temporary {
+            write!(f, " (temporary)")?;
+        }
+        Ok(())
+    }
+}
+
+pub enum Command {
+    Help,
+    Stack,
+    Memory(u16, u16),
+    Sprite(u16, u16),
+    SetMemory(u16, Vec<u8>),
+    SetRegister(Register, u16),
+    Profile(u16),
+    Break(BreakCondition),
+    Ignore(u16, u32),
+    Until(u16),
+    Who(u16),
+    Timeline(Register, u16),
+    Delete(u16),
+    ListBreakpoints,
+    Step(u32),
+    Explain(Option<bool>),
+    Finish,
+    Registers,
+    Continue,
+    Pause,
+    Quit,
+    Open(String),
+    AddCheat(u16, u8, bool, String),
+    SwitchCheat(u16, Option<bool>),
+    DeleteCheat(u16),
+    ListCheats,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut args = line.split_whitespace();
+        let name = args.next().unwrap_or("");
+
+        match name {
+            "help" | "h" => Ok(Command::Help),
+            "stack" | "bt" => Ok(Command::Stack),
+            "mem" | "m" => {
+                let addr = parse_u16(args.next().ok_or("Usage: mem <addr> [length]")?)?;
+                let length = args.next().map(parse_u16).transpose()?.unwrap_or(DEFAULT_MEMORY_LENGTH);
+                Ok(Command::Memory(addr, length))
+            }
+            "sprite" | "sp" => {
+                let addr = parse_u16(args.next().ok_or("Usage: sprite <addr> [rows]")?)?;
+                let rows = args.next().map(parse_u16).transpose()?.unwrap_or(DEFAULT_SPRITE_ROWS);
+                Ok(Command::Sprite(addr, rows))
+            }
+            "profile" => {
+                let entries = args
+                    .next()
+                    .map(parse_u16)
+                    .transpose()?
+                    .unwrap_or(DEFAULT_PROFILE_ENTRIES);
+                Ok(Command::Profile(entries))
+            }
+            "break" | "b" => {
+                let arg = args.collect::<Vec<&str>>().join(" ");
+                if arg.is_empty() {
+                    return Err("Usage: break <addr|opcode type> | break write <start-end>".to_string());
+                }
+                Ok(Command::Break(arg.parse()?))
+            }
+            "delete" | "d" => {
+                let index = parse_u16(args.next().ok_or("Usage: delete <breakpoint number>")?)?;
+                Ok(Command::Delete(index))
+            }
+            "breaks" => Ok(Command::ListBreakpoints),
+            "until" | "u" => {
+                let addr = parse_u16(args.next().ok_or("Usage: until <addr>")?)?;
+                Ok(Command::Until(addr))
+            }
+            "timeline" | "tl" => {
+                let register = args.next().ok_or("Usage: timeline <register> [steps]")?.parse()?;
+                let steps = args
+                    .next()
+                    .map(parse_u16)
+                    .transpose()?
+                    .unwrap_or(DEFAULT_TIMELINE_STEPS);
+                Ok(Command::Timeline(register, steps))
+            }
+            "who" => {
+                let addr = parse_u16(args.next().ok_or("Usage: who <addr>")?)?;
+                Ok(Command::Who(addr))
+            }
+            "ignore" => {
+                let usage = "Usage: ignore <breakpoint number> <count>";
+                let index = parse_u16(args.next().ok_or(usage)?)?;
+                let count = parse_u16(args.next().ok_or(usage)?)?;
+                Ok(Command::Ignore(index, count as u32))
+            }
+            "explain" | "e" => match args.next() {
+                None => Ok(Command::Explain(None)),
+                Some("on") => Ok(Command::Explain(Some(true))),
+                Some("off") => Ok(Command::Explain(Some(false))),
+                Some(_) => Err("Usage: explain [on|off]".to_string()),
+            },
+            "finish" | "fin" => Ok(Command::Finish),
+            "regs" | "r" => Ok(Command::Registers),
+            "continue" | "c" => Ok(Command::Continue),
+            "pause" | "p" => Ok(Command::Pause),
+            "quit" | "q" => Ok(Command::Quit),
+            "open" => {
+                let path = args.collect::<Vec<&str>>().join(" ");
+                if path.is_empty() {
+                    return Err("Usage: open <rom file>".to_string());
+                }
+                Ok(Command::Open(path))
+            }
+            "step" | "s" => {
+                let count = args.next().map(parse_u16).transpose()?.unwrap_or(1);
+                if count == 0 {
+                    return Err("Usage: step [count > 0]".to_string());
+                }
+                Ok(Command::Step(count as u32))
+            }
+            "poke" | "freeze" => {
+                let usage = format!("Usage: {} <addr> <byte> [description]", name);
+                let addr = parse_u16(args.next().ok_or(&usage)?)?;
+                let value = parse_u8(args.next().ok_or(&usage)?)?;
+                let description = args.collect::<Vec<&str>>().join(" ");
+                Ok(Command::AddCheat(addr, value, name == "freeze", description))
+            }
+            "cheat" => {
+                let usage = "Usage: cheat <number> [on|off|delete]";
+                let index = parse_u16(args.next().ok_or(usage)?)?;
+                match args.next() {
+                    None => Ok(Command::SwitchCheat(index, None)),
+                    Some("on") => Ok(Command::SwitchCheat(index, Some(true))),
+                    Some("off") => Ok(Command::SwitchCheat(index, Some(false))),
+                    Some("delete") => Ok(Command::DeleteCheat(index)),
+                    Some(_) => Err(usage.to_string()),
+                }
+            }
+            "cheats" => Ok(Command::ListCheats),
+            "set" => {
+                let usage = "Usage: set <addr> <byte> [byte...] | set <register> <value>";
+                let target = args.next().ok_or(usage)?;
+                if let Ok(register) = target.parse::<Register>() {
+                    let value = parse_u16(args.next().ok_or(usage)?)?;
+                    return Ok(Command::SetRegister(register, value));
+                }
+
+                let addr = parse_u16(target)?;
+                let bytes = args.map(parse_u8).collect::<Result<Vec<u8>, String>>()?;
+                if bytes.is_empty() {
+                    return Err(usage.to_string());
+                }
+                Ok(Command::SetMemory(addr, bytes))
+            }
+            _ => Err(format!("Unknown command: {} (try 'help')", name)),
+        }
+    }
+}
+
+pub const HELP: &str = "\
+Commands:
+  help, h        Shows this help
+  regs, r        Shows the registers and the next instruction
+  continue, c    Resumes execution
+  pause, p       Pauses execution
+  quit, q        Quits the emulator
+  open <file>    Replaces the running ROM with another one and starts it
+  stack, bt      Shows the call stack
+  mem, m <addr> [length]
+                 Shows a hex dump of memory (default 64 bytes)
+  sprite, sp <addr> [rows]
+                 Renders memory as an 8 pixel wide sprite (default 15 rows)
+  timeline, tl <register> [steps]
+                 Graphs a register over the last steps (default 64) and lists its changes
+  who <addr>     Shows the last instructions that wrote to an address (needs --write-history)
+  set <addr> <byte> [byte...]
+                 Writes bytes into memory (only while paused)
+  set <register> <value>
+                 Sets V0-VF, I, PC, DT or ST (only while paused)
+  poke <addr> <byte> [description]
+                 Writes a byte into memory at the end of the frame, also while running
+  freeze <addr> <byte> [description]
+                 Writes a byte into memory at the end of every frame, e.g. to keep the lives
+  cheats         Lists the poke and freeze cheats
+  cheat <number> [on|off|delete]
+                 Switches a cheat on or off (toggles without an argument) or removes it
+  profile [count]
+                 Shows the hottest addresses and the instruction mix
+  break, b <addr|opcode type>
+                 Pauses before the address or an opcode type (e.g. DXYN) executes
+  break, b write <start-end>
+                 Pauses after an instruction writes into the address range
+  delete, d <number>
+                 Removes a breakpoint
+  breaks         Lists all breakpoints with their hit counts
+  ignore <number> <count>
+                 Ignores the next count hits of a breakpoint
+  step, s [count]
+                 Executes count instructions (default 1) and pauses again
+  explain, e [on|off]
+                 Describes every stepped instruction and the values it changed
+  finish, fin    Runs until the current subroutine returns
+  until, u <addr>
+                 Runs until the address is reached";
+
+pub struct Console {
+    receiver: mpsc::Receiver<String>,
+}
+
+impl Console {
+    pub fn spawn() -> Self {
+        Console::from_reader(std::io::BufReader::new(std::io::stdin()))
+    }
+
+    // Reads the commands from input instead of stdin, e.g. from a script.
+    pub fn from_reader(input: impl BufRead + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in input.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Console { receiver }
+    }
+
+    pub fn poll(&self) -> Option<String> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+// Named after the SHA-1 of the ROM (database::sha1), so ROMs with the same file name in different directories
+// keep their own breakpoints and a ROM keeps them when it is renamed or moved.
+fn breakpoint_file(rom_sha1: &str) -> Option<PathBuf> {
+    Some(paths::breakpoint_dir()?.join(format!("{}.breakpoints", rom_sha1)))
+}
+
+pub fn load_breakpoints(rom_sha1: &str) -> Vec<Breakpoint> {
+    breakpoint_file(rom_sha1)
+        .map(|file| read_breakpoints(&file))
+        .unwrap_or_default()
+}
+
+pub fn save_breakpoints(rom_sha1: &str, breakpoints: &[Breakpoint]) -> Result<(), Chip8Error> {
+    match breakpoint_file(rom_sha1) {
+        Some(file) => write_breakpoints(&file, breakpoints),
+        None => Ok(()),
+    }
+}
+
+fn read_breakpoints(file: &Path) -> Vec<Breakpoint> {
+    let Ok(content) = fs::read_to_string(file) else {
+        return Vec::new();
+    };
+
+    content
+        .lines()
+        .filter_map(|line| match parse_saved(line) {
+            Ok(breakpoint) => Some(breakpoint),
+            Err(err) => {
+                eprintln!("Ignoring saved breakpoint: {}", err);
+                None
+            }
+        })
+        .collect()
+}
+
+// A condition, followed by "ignore <count>" when hits are to be ignored.
+fn parse_saved(line: &str) -> Result<Breakpoint, String> {
+    let (condition, ignore) = match line.rsplit_once(" ignore ") {
+        Some((condition, count)) => {
+            let count = count
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid ignore count: {}", count))?;
+            (condition, count)
+        }
+        None => (line, 0),
+    };
+    Ok(Breakpoint {
+        ignore,
+        ..Breakpoint::new(condition.parse()?)
+    })
+}
+
+fn write_breakpoints(file: &Path, breakpoints: &[Breakpoint]) -> Result<(), Chip8Error> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let lines: Vec<String> = breakpoints
+        .iter()
+        .filter(|b| !b.temporary)
+        .map(|b| match b.ignore {
+            0 => b.condition.to_string(),
+            ignore => format!("{} ignore {}", b.condition, ignore),
+        })
+        .collect();
+    fs::write(file, lines.join("\n"))?;
+    Ok(())
+}
+
+pub fn disassemble(memory: &[u8], addr: u16, symbols: &Symbols) -> String {
+    let addr = addr as usize;
+    if addr + 1 >= memory.len() {
+        return String::from("<out of memory>");
+    }
+
+    let instruction = ((memory[addr] as u16) << 8) | memory[addr + 1] as u16;
+    match Opcode::decode(instruction) {
+        Ok(opcode) => opcode.disassemble(symbols),
+        Err(_) => format!("DW {:#06X}", instruction),
+    }
+}
+
+pub fn format_stack(stack: &[u16], memory: &[u8], pc: u16, symbols: &Symbols) -> String {
+    let mut result = format!("Call stack ({} frames):\n", stack.len());
+    result.push_str(&format!(
+        "  #0  {:#06X}  {}{}",
+        pc,
+        disassemble(memory, pc, symbols),
+        location(pc, symbols)
+    ));
+
+    for (depth, &return_addr) in stack.iter().rev().enumerate() {
+        let call_site = return_addr.wrapping_sub(2);
+        result.push_str(&format!(
+            "\n  #{}  {:#06X}  {:<18}(returns to {:#06X}){}",
+            depth + 1,
+            call_site,
+            disassemble(memory, call_site, symbols),
+            return_addr,
+            location(call_site, symbols)
+        ));
+    }
+    result
+}
+
+fn location(addr: u16, symbols: &Symbols) -> String {
+    symbols
+        .describe(addr)
+        .map(|description| format!("  in {}", description))
+        .unwrap_or_default()
+}
+
+pub fn format_memory(memory: &[u8], addr: u16, length: u16) -> String {
+    let start = (addr as usize).min(memory.len());
+    let end = (start + length as usize).min(memory.len());
+
+    let lines: Vec<String> = memory[start..end]
+        .chunks(16)
+        .enumerate()
+        .map(|(line, bytes)| {
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+            format!("{:#06X}  {}", start + line * 16, hex.join(" "))
+        })
+        .collect();
+    lines.join("\n")
+}
+
+pub fn format_sprite(memory: &[u8], addr: u16, rows: u16) -> String {
+    let start = (addr as usize).min(memory.len());
+    let end = (start + rows as usize).min(memory.len());
+
+    let lines: Vec<String> = memory[start..end]
+        .iter()
+        .enumerate()
+        .map(|(row, byte)| {
+            let pixels: String = (0..8)
+                .rev()
+                .map(|bit| if (byte >> bit) & 1 == 1 { '#' } else { '.' })
+                .collect();
+            format!("{:#06X}  {:02X}  {}", start + row, byte, pixels)
+        })
+        .collect();
+    lines.join("\n")
+}
+
+pub fn format_registers(regs: &Registers, pc: u16, stack_depth: usize, memory: &[u8], symbols: &Symbols) -> String {
+    let v: Vec<String> = regs
+        .v
+        .iter()
+        .enumerate()
+        .map(|(index, value)| format!("V{:X} {:#04X}", index, value))
+        .collect();
+
+    format!(
+        "PC {:#06X}  I {:#06X}  DT {:#04X}  ST {:#04X}  SP {}\n{}\n{}\nNext: {}{}",
+        pc,
+        regs.i,
+        regs.delay_timer,
+        regs.sound_timer,
+        stack_depth,
+        v[..8].join("  "),
+        v[8..].join("  "),
+        disassemble(memory, pc, symbols),
+        location(pc, symbols)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_takes_a_count() {
+        assert!(matches!("step 5".parse(), Ok(Command::Step(5))));
+        assert!(matches!("s 0x10".parse(), Ok(Command::Step(16))));
+        assert!(matches!("step".parse(), Ok(Command::Step(1))));
+    }
+
+    #[test]
+    fn test_step_rejects_bad_counts() {
+        for line in ["step 0", "step five", "step -1", "step 70000"] {
+            assert!(line.parse::<Command>().is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_commands_parse_their_arguments() {
+        assert!(matches!(
+            "mem 0x300".parse(),
+            Ok(Command::Memory(0x300, DEFAULT_MEMORY_LENGTH))
+        ));
+        assert!(matches!(
+            "set vA 0x12".parse(),
+            Ok(Command::SetRegister(Register::V(0xA), 0x12))
+        ));
+        assert!(matches!(
+            "b w 0x300-0x30F".parse(),
+            Ok(Command::Break(BreakCondition::Write(0x300, 0x30F)))
+        ));
+        assert!(matches!("ignore 1".parse::<Command>(), Err(usage) if usage.starts_with("Usage")));
+        assert!(matches!("jump".parse::<Command>(), Err(err) if err.starts_with("Unknown command")));
+    }
+
+    #[test]
+    fn test_console_reads_scripted_commands() {
+        let console = Console::from_reader(std::io::Cursor::new("step 5\nregs\n"));
+        let next = || {
+            let line = console
+                .receiver
+                .recv_timeout(std::time::Duration::from_secs(5))
+                .unwrap();
+            line.parse::<Command>()
+        };
+        assert!(matches!(next(), Ok(Command::Step(5))));
+        assert!(matches!(next(), Ok(Command::Registers)));
+        assert!(console.receiver.recv().is_err(), "the console ends with its input");
+    }
+
+    #[test]
+    fn test_breakpoints_survive_saving_and_loading() {
+        let file = std::env::temp_dir()
+            .join(format!("chip8-breakpoints-{}", std::process::id()))
+            .join("rom.breakpoints");
+        let mut breakpoints: Vec<Breakpoint> = ["0x2A0", "DXYN", "write 0x300-0x30F"]
+            .into_iter()
+            .map(|condition| Breakpoint::new(condition.parse().unwrap()))
+            .collect();
+        // one-shot breakpoints from until and finish are not kept
+        breakpoints.push(Breakpoint::temporary(0x210));
+        breakpoints[1].ignore = 3;
+        breakpoints[2].ignore = 1;
+        write_breakpoints(&file, &breakpoints).unwrap();
+        let loaded = read_breakpoints(&file);
+        let _ = fs::remove_dir_all(file.parent().unwrap());
+
+        let conditions: Vec<BreakCondition> = loaded.iter().map(|b| b.condition).collect();
+        assert_eq!(
+            conditions,
+            [
+                BreakCondition::Address(0x2A0),
+                BreakCondition::Opcode("DXYN"),
+                BreakCondition::Write(0x300, 0x30F)
+            ]
+        );
+        let ignores: Vec<u32> = loaded.iter().map(|b| b.ignore).collect();
+        assert_eq!(ignores, [0, 3, 1]);
+        assert!(read_breakpoints(&file).is_empty());
+    }
+
+    #[test]
+    fn test_breakpoint_files_go_by_content() {
+        let Some(file) = breakpoint_file(&crate::database::sha1(b"pong")) else {
+            return;
+        };
+        assert_eq!(
+            file.file_name().unwrap(),
+            "0e514a0662bcb69dc863953d1ce26e3d40e81a87.breakpoints"
+        );
+    }
+}
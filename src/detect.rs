@@ -0,0 +1,230 @@
+use std::fmt::Write;
+
+use crate::emulator::DEFAULT_IPS;
+use crate::error::Chip8Error;
+use crate::machine::Machine;
+use crate::quirks::Quirks;
+use crate::roms;
+
+// Ten emulated seconds
+pub const DEFAULT_FRAMES: u64 = 600;
+const INSTRUCTIONS_PER_FRAME: u32 = DEFAULT_IPS / 60;
+const SEED: u64 = 0;
+// The quirks that change what a program does without any key being pressed
+const QUIRKS: [&str; 5] = ["vf-reset", "memory-increment", "shift-vy", "jump-vx", "wrap-sprites"];
+
+// How a run under some quirks ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Crashed { frame: u64, error: String },
+    Halted { frame: u64 },
+    WaitingForKey { frame: u64 },
+    Running,
+}
+
+#[derive(Debug, Clone)]
+pub struct Trial {
+    pub quirks: Quirks,
+    pub outcome: Outcome,
+    pub pixels: Vec<u8>,
+}
+
+// Every combination of the quirks in QUIRKS, starting with none of them.
+pub fn combinations() -> impl Iterator<Item = Quirks> {
+    (0..1u32 << QUIRKS.len()).map(|bits| Quirks {
+        vf_reset: bits & 1 != 0,
+        memory_increment: bits & 2 != 0,
+        shift_vy: bits & 4 != 0,
+        jump_vx: bits & 8 != 0,
+        wrap_sprites: bits & 16 != 0,
+        key_wait_on_press: false,
+    })
+}
+
+// Runs the ROM for a number of frames without pressing any key, always with the same random numbers so only
+// the quirks make a difference.
+pub fn trial(rom: &[u8], quirks: Quirks, frames: u64) -> Result<Trial, Chip8Error> {
+    let mut machine = Machine::new(rom, quirks, SEED)?;
+    let mut outcome = Outcome::Running;
+    'frames: for frame in 0..frames {
+        for _ in 0..INSTRUCTIONS_PER_FRAME {
+            match machine.step() {
+                Ok(result) if result.halted => {
+                    outcome = Outcome::Halted { frame };
+                    break 'frames;
+                }
+                Ok(result) if result.waiting_for_key => {
+                    outcome = Outcome::WaitingForKey { frame };
+                    break 'frames;
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    outcome = Outcome::Crashed {
+                        frame,
+                        error: err.to_string(),
+                    };
+                    break 'frames;
+                }
+            }
+        }
+        machine.tick_timers();
+    }
+    Ok(Trial {
+        quirks,
+        outcome,
+        pixels: machine.pixels().to_vec(),
+    })
+}
+
+pub fn run(location: &str, frames: u64) -> Result<(), Chip8Error> {
+    let rom = roms::read(location)?;
+    let trials = combinations()
+        .map(|quirks| trial(&rom, quirks, frames))
+        .collect::<Result<Vec<_>, _>>()?;
+    print!("{}", report(&trials, frames));
+    Ok(())
+}
+
+// Combinations that ended the same way with the same display, the most common first.
+fn groups(trials: &[Trial]) -> Vec<Vec<&Trial>> {
+    let mut groups: Vec<Vec<&Trial>> = Vec::new();
+    for trial in trials {
+        match groups
+            .iter_mut()
+            .find(|group| group[0].outcome == trial.outcome && group[0].pixels == trial.pixels)
+        {
+            Some(group) => group.push(trial),
+            None => groups.push(vec![trial]),
+        }
+    }
+    groups.sort_by_key(|group| std::cmp::Reverse(group.len()));
+    groups
+}
+
+fn enabled(quirks: &Quirks) -> usize {
+    quirks.to_string().split(',').filter(|name| *name != "none").count()
+}
+
+pub fn report(trials: &[Trial], frames: u64) -> String {
+    let mut report = String::new();
+    let groups = groups(trials);
+    let _ = writeln!(
+        report,
+        "Ran {} quirk combinations for {} frames each, {} different {}:",
+        trials.len(),
+        frames,
+        groups.len(),
+        if groups.len() == 1 { "result" } else { "results" }
+    );
+    for group in &groups {
+        let lit = group[0].pixels.iter().filter(|&&pixel| pixel != 0).count();
+        let result = match &group[0].outcome {
+            Outcome::Crashed { frame, error } => format!("crashed at frame {}: {}", frame, error),
+            Outcome::Halted { frame } => format!("halted at frame {} with {} pixels lit", frame, lit),
+            Outcome::WaitingForKey { frame } => {
+                format!("waited for a key from frame {} with {} pixels lit", frame, lit)
+            }
+            Outcome::Running => format!("ran with {} pixels lit", lit),
+        };
+        let simplest = group.iter().min_by_key(|trial| enabled(&trial.quirks)).unwrap();
+        let _ = writeln!(
+            report,
+            "{:>4} x  {}, e.g. --quirks {}",
+            group.len(),
+            result,
+            simplest.quirks
+        );
+    }
+
+    let group_of = |quirks: Quirks| {
+        groups
+            .iter()
+            .position(|group| group.iter().any(|trial| trial.quirks == quirks))
+    };
+    let matters: Vec<&str> = QUIRKS
+        .iter()
+        .enumerate()
+        .filter(|&(bit, _)| {
+            combinations().any(|quirks| {
+                let toggled = combinations().nth(combination_index(quirks) ^ 1 << bit);
+                toggled.is_some_and(|toggled| group_of(quirks) != group_of(toggled))
+            })
+        })
+        .map(|(_, name)| *name)
+        .collect();
+    if matters.is_empty() {
+        let _ = match groups[0][0].outcome {
+            Outcome::WaitingForKey { .. } => {
+                writeln!(report, "No quirk made a difference before the ROM waited for a key")
+            }
+            _ => writeln!(
+                report,
+                "No quirk made a difference, the ROM does the same with any of them"
+            ),
+        };
+        return report;
+    }
+    let _ = writeln!(report, "Quirks that made a difference: {}", matters.join(", "));
+
+    // the result most combinations agree on that neither crashed nor left the screen blank, since a quirk the
+    // ROM does not care about leaves it the same
+    let lit = |trial: &Trial| trial.pixels.iter().any(|&pixel| pixel != 0);
+    let best = groups
+        .iter()
+        .find(|group| !matches!(group[0].outcome, Outcome::Crashed { .. }) && lit(group[0]))
+        .or_else(|| {
+            groups
+                .iter()
+                .find(|group| !matches!(group[0].outcome, Outcome::Crashed { .. }))
+        });
+    let _ = match best.and_then(|group| group.iter().min_by_key(|trial| enabled(&trial.quirks))) {
+        Some(trial) => writeln!(report, "Suggested: --quirks {}", trial.quirks),
+        None => writeln!(report, "Every combination crashed"),
+    };
+    report
+}
+
+// Where quirks is in combinations(), the inverse of its bits.
+fn combination_index(quirks: Quirks) -> usize {
+    [
+        quirks.vf_reset,
+        quirks.memory_increment,
+        quirks.shift_vy,
+        quirks.jump_vx,
+        quirks.wrap_sprites,
+    ]
+    .iter()
+    .enumerate()
+    .map(|(bit, &on)| (on as usize) << bit)
+    .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combinations_cover_every_quirk() {
+        let all: Vec<Quirks> = combinations().collect();
+        assert_eq!(all.len(), 32);
+        assert_eq!(all[0], Quirks::default());
+        for (index, &quirks) in all.iter().enumerate() {
+            assert_eq!(combination_index(quirks), index);
+        }
+    }
+
+    #[test]
+    fn test_report_suggests_the_quirks_that_avoid_a_crash() {
+        // V1 = 3, V2 = 0x80, SHL V1 shifts V2 out with shift-vy and leaves V1 = 0, a skip on V1 = 0 then
+        // avoids the invalid opcode and draws the font 0
+        let rom = [
+            0x61, 0x03, 0x62, 0x80, 0x81, 0x2E, 0x31, 0x00, 0xFF, 0xFF, 0xD0, 0x05, 0x12, 0x0C,
+        ];
+        let trials: Vec<Trial> = combinations().map(|quirks| trial(&rom, quirks, 10).unwrap()).collect();
+        let report = report(&trials, 10);
+        assert!(report.contains("2 different results"));
+        assert!(report.contains("16 x  crashed at frame 0: Invalid opcode: 0xFFFF, e.g. --quirks none"));
+        assert!(report.contains("Quirks that made a difference: shift-vy\n"));
+        assert!(report.contains("Suggested: --quirks shift-vy\n"));
+    }
+}
@@ -0,0 +1,666 @@
+use std::fmt;
+
+use crate::emulator::Chip8;
+use crate::error::Chip8Error;
+use crate::machine::{Machine, StepResult, DISPLAY_HEIGHT, DISPLAY_WIDTH, FONT, MEMORY_SIZE};
+use crate::quirks::Quirks;
+use crate::snapshot::Chip8Snapshot;
+
+// An interpreter the harness can run in lockstep with another one.
+pub trait Core {
+    fn step(&mut self) -> Result<StepResult, Chip8Error>;
+    // Ends a 60 Hz frame: ticks the timers and lets the keypad forget taps that were read.
+    fn end_frame(&mut self);
+    fn set_key(&mut self, key: u8, down: bool);
+    fn set_register(&mut self, x: u8, value: u8);
+    // The instruction the next step executes.
+    fn instruction(&self) -> u16;
+    fn snapshot(&self) -> Chip8Snapshot;
+}
+
+impl Core for Machine {
+    fn step(&mut self) -> Result<StepResult, Chip8Error> {
+        Machine::step(self)
+    }
+
+    fn end_frame(&mut self) {
+        self.tick_timers();
+    }
+
+    fn set_key(&mut self, key: u8, down: bool) {
+        if down {
+            self.keypad_mut().press(key);
+        } else {
+            self.keypad_mut().release(key);
+        }
+    }
+
+    fn set_register(&mut self, x: u8, value: u8) {
+        Machine::set_register(self, x, value);
+    }
+
+    fn instruction(&self) -> u16 {
+        instruction_at(self.memory(), self.pc())
+    }
+
+    fn snapshot(&self) -> Chip8Snapshot {
+        let (delay_timer, sound_timer) = self.timers();
+        Chip8Snapshot {
+            memory: self.memory().to_vec(),
+            registers: self.registers().try_into().unwrap_or_default(),
+            i: self.i(),
+            pc: self.pc(),
+            stack: self.stack().to_vec(),
+            delay_timer,
+            sound_timer,
+            display: self.pixels().to_vec(),
+            quirks: self.quirks(),
+        }
+    }
+}
+
+impl Core for Chip8 {
+    fn step(&mut self) -> Result<StepResult, Chip8Error> {
+        Chip8::step(self)
+    }
+
+    // what the run loop does at the end of every frame
+    fn end_frame(&mut self) {
+        self.tick_timers();
+        self.keypad_mut().end_frame();
+    }
+
+    fn set_key(&mut self, key: u8, down: bool) {
+        if down {
+            self.keypad_mut().press(key);
+        } else {
+            self.keypad_mut().release(key);
+        }
+    }
+
+    fn set_register(&mut self, x: u8, value: u8) {
+        let _ = self.set_reg(x, value);
+    }
+
+    fn instruction(&self) -> u16 {
+        instruction_at(self.read_mem(0, MEMORY_SIZE).unwrap_or_default(), self.pc())
+    }
+
+    fn snapshot(&self) -> Chip8Snapshot {
+        Chip8::snapshot(self)
+    }
+}
+
+fn instruction_at(memory: &[u8], pc: u16) -> u16 {
+    let byte = |addr: u16| memory.get(addr as usize).copied().unwrap_or(0) as u16;
+    byte(pc) << 8 | byte(pc.wrapping_add(1))
+}
+
+const FONT_START: u16 = 0x050;
+const ROM_START: u16 = 0x200;
+// Frames a key going down or up is remembered for FX0A
+const KEY_MEMORY: u64 = 30;
+
+// An interpreter written for the harness from the description of the instruction set. It splits the
+// instructions into nibbles, does the arithmetic and keeps the keys itself, sharing none of that with Machine,
+// which Chip8 runs on, so a mistake there shows up as a divergence instead of being made twice. The keys follow
+// the rules the Keypad documents: a tap counts until the program looked at the key or the frame is over, and
+// FX0A also takes keys that went down and up in the last half second, unless the program already saw them.
+pub struct Reference {
+    memory: [u8; MEMORY_SIZE],
+    v: [u8; 16],
+    i: u16,
+    pc: u16,
+    stack: Vec<u16>,
+    delay: u8,
+    sound: u8,
+    display: [u8; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+    keys: [bool; 16],
+    // pressed this frame and not looked at yet
+    taps: [bool; 16],
+    // the frame, key and whether it went down, for FX0A
+    events: Vec<(u64, u8, bool)>,
+    // keys FX0A saw go down and waits to see go up
+    went_down: [bool; 16],
+    frame: u64,
+    quirks: Quirks,
+    seed: u64,
+}
+
+impl Reference {
+    pub fn new(rom: &[u8], quirks: Quirks, seed: u64) -> Result<Self, Chip8Error> {
+        if rom.len() > MEMORY_SIZE - ROM_START as usize {
+            return Err(Chip8Error::RomTooLarge(rom.len()));
+        }
+        let mut memory = [0; MEMORY_SIZE];
+        memory[FONT_START as usize..FONT_START as usize + FONT.len()].copy_from_slice(&FONT);
+        memory[ROM_START as usize..ROM_START as usize + rom.len()].copy_from_slice(rom);
+        Ok(Reference {
+            memory,
+            v: [0; 16],
+            i: 0,
+            pc: ROM_START,
+            stack: Vec::new(),
+            delay: 0,
+            sound: 0,
+            display: [0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            keys: [false; 16],
+            taps: [false; 16],
+            events: Vec::new(),
+            went_down: [false; 16],
+            frame: 0,
+            quirks,
+            seed,
+        })
+    }
+
+    fn skip(&mut self, condition: bool) {
+        if condition {
+            self.pc += 2;
+        }
+    }
+
+    // VF is written after VX, so the flag wins when X is F
+    fn set_flagged(&mut self, x: usize, value: u8, flag: u8) {
+        self.v[x] = value;
+        self.v[0xF] = flag;
+    }
+
+    fn shift_source(&self, x: usize, y: usize) -> u8 {
+        if self.quirks.shift_vy {
+            self.v[y]
+        } else {
+            self.v[x]
+        }
+    }
+
+    fn peek(&self, offset: usize) -> u8 {
+        self.memory[(self.i as usize + offset) % MEMORY_SIZE]
+    }
+
+    fn poke(&mut self, offset: usize, value: u8) {
+        self.memory[(self.i as usize + offset) % MEMORY_SIZE] = value;
+    }
+
+    // EX9E and EXA1, after which FX0A leaves the key's earlier presses alone.
+    fn read_key(&mut self, key: u8) -> bool {
+        let Some(index) = (key < 16).then_some(key as usize) else {
+            return false;
+        };
+        let pressed = self.keys[index] || self.taps[index];
+        if pressed {
+            self.events.retain(|&(_, event_key, _)| event_key != key);
+            self.went_down[index] = false;
+        }
+        self.taps[index] = false;
+        pressed
+    }
+
+    // A 64-bit LCG, the numbers only have to be the reference's own
+    fn random(&mut self) -> u8 {
+        self.seed = self
+            .seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.seed >> 56) as u8
+    }
+
+    fn draw(&mut self, x: usize, y: usize, rows: u8) {
+        let (left, top) = (self.v[x] as usize % DISPLAY_WIDTH, self.v[y] as usize % DISPLAY_HEIGHT);
+        self.v[0xF] = 0;
+        for row in 0..rows as usize {
+            let sprite = self.peek(row);
+            for column in 0..8 {
+                let (px, py) = (left + column, top + row);
+                let outside = px >= DISPLAY_WIDTH || py >= DISPLAY_HEIGHT;
+                if sprite & (0x80 >> column) == 0 || outside && !self.quirks.wrap_sprites {
+                    continue;
+                }
+                let pixel = &mut self.display[py % DISPLAY_HEIGHT * DISPLAY_WIDTH + px % DISPLAY_WIDTH];
+                if *pixel != 0 {
+                    self.v[0xF] = 1;
+                }
+                *pixel ^= 1;
+            }
+        }
+    }
+
+    // FX0A: the next key that went down and up again, or only down with key_wait_on_press.
+    fn wait_for_key(&mut self) -> Option<u8> {
+        self.taps = [false; 16];
+        while !self.events.is_empty() {
+            let (_, key, down) = self.events.remove(0);
+            let index = key as usize;
+            if down && self.quirks.key_wait_on_press {
+                return Some(key);
+            } else if down {
+                self.went_down[index] = true;
+            } else if self.went_down[index] && !self.quirks.key_wait_on_press {
+                self.went_down[index] = false;
+                return Some(key);
+            }
+        }
+        None
+    }
+}
+
+impl Core for Reference {
+    fn step(&mut self) -> Result<StepResult, Chip8Error> {
+        let pc = self.pc;
+        if pc as usize + 1 >= MEMORY_SIZE {
+            return Err(Chip8Error::PCOutOfBounds(pc));
+        }
+        let word = instruction_at(&self.memory, pc);
+        self.pc += 2;
+
+        let nibbles = [word >> 12, word >> 8 & 0xF, word >> 4 & 0xF, word & 0xF].map(|nibble| nibble as u8);
+        let (x, y, n) = (nibbles[1] as usize, nibbles[2] as usize, nibbles[3]);
+        let (nn, nnn) = ((word & 0xFF) as u8, word & 0xFFF);
+        let mut result = StepResult::default();
+        match nibbles {
+            [0x0, 0x0, 0xE, 0x0] => {
+                self.display = [0; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+                result.display_changed = true;
+            }
+            [0x0, 0x0, 0xE, 0xE] => self.pc = self.stack.pop().ok_or(Chip8Error::StackUnderflow)?,
+            // machine code is skipped
+            [0x0, ..] => {}
+            [0x1, ..] => {
+                result.halted = nnn == pc;
+                self.pc = nnn;
+            }
+            [0x2, ..] => {
+                if self.stack.len() == 16 {
+                    return Err(Chip8Error::StackOverflow);
+                }
+                self.stack.push(self.pc);
+                self.pc = nnn;
+            }
+            [0x3, ..] => self.skip(self.v[x] == nn),
+            [0x4, ..] => self.skip(self.v[x] != nn),
+            [0x5, _, _, 0x0] => self.skip(self.v[x] == self.v[y]),
+            [0x6, ..] => self.v[x] = nn,
+            [0x7, ..] => self.v[x] = self.v[x].wrapping_add(nn),
+            [0x8, _, _, 0x0] => self.v[x] = self.v[y],
+            [0x8, _, _, 0x1..=0x3] => {
+                self.v[x] = match n {
+                    0x1 => self.v[x] | self.v[y],
+                    0x2 => self.v[x] & self.v[y],
+                    _ => self.v[x] ^ self.v[y],
+                };
+                if self.quirks.vf_reset {
+                    self.v[0xF] = 0;
+                }
+            }
+            [0x8, _, _, 0x4] => {
+                let sum = self.v[x] as u16 + self.v[y] as u16;
+                self.set_flagged(x, sum as u8, (sum > 0xFF) as u8);
+            }
+            [0x8, _, _, 0x5] => {
+                let (a, b) = (self.v[x], self.v[y]);
+                self.set_flagged(x, a.wrapping_sub(b), (a >= b) as u8);
+            }
+            [0x8, _, _, 0x7] => {
+                let (a, b) = (self.v[y], self.v[x]);
+                self.set_flagged(x, a.wrapping_sub(b), (a >= b) as u8);
+            }
+            [0x8, _, _, 0x6] => {
+                let value = self.shift_source(x, y);
+                self.set_flagged(x, value >> 1, value & 1);
+            }
+            [0x8, _, _, 0xE] => {
+                let value = self.shift_source(x, y);
+                self.set_flagged(x, value << 1, value >> 7);
+            }
+            [0x9, _, _, 0x0] => self.skip(self.v[x] != self.v[y]),
+            [0xA, ..] => self.i = nnn,
+            [0xB, ..] => {
+                let offset = if self.quirks.jump_vx { self.v[x] } else { self.v[0] };
+                self.pc = nnn + offset as u16;
+            }
+            [0xC, ..] => self.v[x] = self.random() & nn,
+            [0xD, ..] => {
+                self.draw(x, y, n);
+                result.display_changed = true;
+            }
+            [0xE, _, 0x9, 0xE] => {
+                let pressed = self.read_key(self.v[x]);
+                self.skip(pressed);
+            }
+            [0xE, _, 0xA, 0x1] => {
+                let pressed = self.read_key(self.v[x]);
+                self.skip(!pressed);
+            }
+            [0xF, _, 0x0, 0x7] => self.v[x] = self.delay,
+            [0xF, _, 0x0, 0xA] => match self.wait_for_key() {
+                Some(key) => self.v[x] = key,
+                None => {
+                    result.waiting_for_key = true;
+                    self.pc = pc;
+                }
+            },
+            [0xF, _, 0x1, 0x5] => self.delay = self.v[x],
+            [0xF, _, 0x1, 0x8] => self.sound = self.v[x],
+            [0xF, _, 0x1, 0xE] => self.i = self.i.wrapping_add(self.v[x] as u16),
+            [0xF, _, 0x2, 0x9] => self.i = FONT_START + (self.v[x] & 0xF) as u16 * 5,
+            [0xF, _, 0x3, 0x3] => {
+                let value = self.v[x];
+                for (offset, digit) in [value / 100, value / 10 % 10, value % 10].into_iter().enumerate() {
+                    self.poke(offset, digit);
+                }
+            }
+            [0xF, _, 0x5, 0x5] | [0xF, _, 0x6, 0x5] => {
+                for offset in 0..=x {
+                    if nibbles[2] == 0x5 {
+                        self.poke(offset, self.v[offset]);
+                    } else {
+                        self.v[offset] = self.peek(offset);
+                    }
+                }
+                if self.quirks.memory_increment {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            }
+            _ => return Err(Chip8Error::InvalidOpcode(word)),
+        }
+        Ok(result)
+    }
+
+    fn end_frame(&mut self) {
+        self.delay = self.delay.saturating_sub(1);
+        self.sound = self.sound.saturating_sub(1);
+        self.taps = [false; 16];
+        self.frame += 1;
+        while let Some(&(at, key, down)) = self.events.first() {
+            if self.frame - at <= KEY_MEMORY {
+                break;
+            }
+            if !down {
+                self.went_down[key as usize] = false;
+            }
+            self.events.remove(0);
+        }
+    }
+
+    fn set_key(&mut self, key: u8, down: bool) {
+        let index = key as usize;
+        if index >= 16 {
+            return;
+        }
+        if self.keys[index] != down {
+            self.events.push((self.frame, key, down));
+        }
+        self.keys[index] = down;
+        if down {
+            self.taps[index] = true;
+        }
+    }
+
+    fn set_register(&mut self, x: u8, value: u8) {
+        self.v[x as usize & 0xF] = value;
+    }
+
+    fn instruction(&self) -> u16 {
+        instruction_at(&self.memory, self.pc)
+    }
+
+    fn snapshot(&self) -> Chip8Snapshot {
+        Chip8Snapshot {
+            memory: self.memory.to_vec(),
+            registers: self.v,
+            i: self.i,
+            pc: self.pc,
+            stack: self.stack.clone(),
+            delay_timer: self.delay,
+            sound_timer: self.sound,
+            display: self.display.to_vec(),
+            quirks: self.quirks,
+        }
+    }
+}
+
+// Key presses and releases by frame, one per line like "30 5 down" or "42 5 up" with the key in hex. Lines
+// starting with # are comments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    events: Vec<(u64, u8, bool)>,
+}
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut events = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [frame, key, action] = fields[..] else {
+                return Err(error("expected a frame, a key and down or up"));
+            };
+            let frame = frame.parse().map_err(|_| error("the frame is not a number"))?;
+            let key = u8::from_str_radix(key, 16)
+                .ok()
+                .filter(|&key| key < 16)
+                .ok_or_else(|| error("the key is not a hex digit"))?;
+            let down = match action {
+                "down" => true,
+                "up" => false,
+                _ => return Err(error("expected down or up")),
+            };
+            events.push((frame, key, down));
+        }
+        events.sort_by_key(|&(frame, _, _)| frame);
+        Ok(Script { events })
+    }
+
+    fn apply(&self, frame: u64, core: &mut dyn Core) {
+        for &(_, key, down) in self.events.iter().filter(|&&(at, _, _)| at == frame) {
+            core.set_key(key, down);
+        }
+    }
+}
+
+// The first thing that came out differently, with the value in the reference and in the other core.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub frame: u64,
+    pub what: String,
+    pub reference: String,
+    pub actual: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Frame {}: {} is {} in the reference and {} here",
+            self.frame, self.what, self.reference, self.actual
+        )
+    }
+}
+
+// Runs both cores for the given frames of instructions_per_frame instructions, pressing the keys of the script
+// on both, and compares their state after every frame. CXNN results are copied from the reference as the cores
+// have different random number generators. Both cores failing with the same error ends the run without a
+// divergence.
+pub fn compare(
+    reference: &mut dyn Core,
+    core: &mut dyn Core,
+    script: &Script,
+    frames: u64,
+    instructions_per_frame: u32,
+) -> Option<Divergence> {
+    for frame in 0..frames {
+        script.apply(frame, reference);
+        script.apply(frame, core);
+        for _ in 0..instructions_per_frame {
+            let instruction = reference.instruction();
+            let random = (instruction >> 12 == 0xC).then_some((instruction >> 8 & 0xF) as u8);
+            let results = (reference.step(), core.step());
+            if let (Some(x), Ok(_)) = (random, &results.0) {
+                core.set_register(x, reference.snapshot().registers[x as usize]);
+            }
+            match results {
+                (Ok(_), Ok(_)) => {}
+                (Err(expected), Err(actual)) if expected.to_string() == actual.to_string() => return None,
+                (expected, actual) => {
+                    return Some(Divergence {
+                        frame,
+                        what: "the step".to_string(),
+                        reference: outcome(&expected),
+                        actual: outcome(&actual),
+                    })
+                }
+            }
+        }
+        reference.end_frame();
+        core.end_frame();
+        if let Some(divergence) = difference(frame, &reference.snapshot(), &core.snapshot()) {
+            return Some(divergence);
+        }
+    }
+    None
+}
+
+fn outcome(result: &Result<StepResult, Chip8Error>) -> String {
+    match result {
+        Ok(_) => "fine".to_string(),
+        Err(err) => format!("\"{}\"", err),
+    }
+}
+
+fn difference(frame: u64, expected: &Chip8Snapshot, actual: &Chip8Snapshot) -> Option<Divergence> {
+    let divergence = |what: String, reference: String, actual: String| {
+        Some(Divergence {
+            frame,
+            what,
+            reference,
+            actual,
+        })
+    };
+    if let Some(x) = (0..16).find(|&x| expected.registers[x] != actual.registers[x]) {
+        return divergence(
+            format!("V{:X}", x),
+            format!("{:#04X}", expected.registers[x]),
+            format!("{:#04X}", actual.registers[x]),
+        );
+    }
+    let words = [
+        ("I", expected.i, actual.i),
+        ("PC", expected.pc, actual.pc),
+        ("DT", expected.delay_timer as u16, actual.delay_timer as u16),
+        ("ST", expected.sound_timer as u16, actual.sound_timer as u16),
+    ];
+    if let Some(&(name, expected, actual)) = words.iter().find(|(_, expected, actual)| expected != actual) {
+        return divergence(
+            name.to_string(),
+            format!("{:#05X}", expected),
+            format!("{:#05X}", actual),
+        );
+    }
+    if expected.stack != actual.stack {
+        return divergence(
+            "the stack".to_string(),
+            format!("{:04X?}", expected.stack),
+            format!("{:04X?}", actual.stack),
+        );
+    }
+    let cell = |memory: &[u8], addr: usize| memory.get(addr).copied().unwrap_or(0);
+    if let Some(addr) = (0..MEMORY_SIZE).find(|&addr| cell(&expected.memory, addr) != cell(&actual.memory, addr)) {
+        return divergence(
+            format!("memory at {:#05X}", addr),
+            format!("{:#04X}", cell(&expected.memory, addr)),
+            format!("{:#04X}", cell(&actual.memory, addr)),
+        );
+    }
+    if let Some(offset) = (0..expected.display.len())
+        .find(|&offset| expected.display[offset] != actual.display.get(offset).copied().unwrap_or(0))
+    {
+        let lit = |display: &[u8]| {
+            if display.get(offset).copied().unwrap_or(0) != 0 {
+                "lit"
+            } else {
+                "dark"
+            }
+        };
+        return divergence(
+            format!("the pixel at {},{}", offset % DISPLAY_WIDTH, offset / DISPLAY_WIDTH),
+            lit(&expected.display).to_string(),
+            lit(&actual.display).to_string(),
+        );
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtin;
+
+    const FRAMES: u64 = 600;
+    const INSTRUCTIONS_PER_FRAME: u32 = 16;
+
+    // Against Chip8 and against the Machine the embedders run.
+    fn run(rom: &[u8], quirks: Quirks, script: &Script) -> Option<Divergence> {
+        let mut reference = Reference::new(rom, quirks, 1).unwrap();
+        let mut chip8 = Chip8::builder().rom_bytes(rom).quirks(quirks).seed(1).build().unwrap();
+        compare(&mut reference, &mut chip8, script, FRAMES, INSTRUCTIONS_PER_FRAME).or_else(|| {
+            let mut reference = Reference::new(rom, quirks, 1).unwrap();
+            let mut machine = Machine::new(rom, quirks, 1).unwrap();
+            compare(&mut reference, &mut machine, script, FRAMES, INSTRUCTIONS_PER_FRAME)
+        })
+    }
+
+    #[test]
+    fn test_builtin_roms_run_the_same_as_the_reference() {
+        // moves the paddle of catch both ways, then starts a new game once the balls are gone
+        let script = Script::parse("# catch\n60 7 down\n90 7 up\n100 9 down\n160 9 up\n200 7 down\n201 7 up").unwrap();
+        for builtin in builtin::BUILTINS {
+            for quirks in [Quirks::default(), Quirks::cosmac_vip()] {
+                if let Some(divergence) = run(builtin.data, quirks, &script) {
+                    panic!("{} with {}: {}", builtin.name, quirks, divergence);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_divergence_is_reported() {
+        // LD V0, 1; LD V1, 2; ADD V0, V1; LD [I], V1; JP 0x208
+        let rom = [0x60, 0x01, 0x61, 0x02, 0x80, 0x14, 0xF1, 0x55, 0x12, 0x08];
+        let mut reference = Reference::new(&rom, Quirks::default(), 1).unwrap();
+        let mut vip = Machine::new(&rom, Quirks::cosmac_vip(), 1).unwrap();
+        assert_eq!(
+            compare(&mut reference, &mut vip, &Script::default(), 2, 16)
+                .unwrap()
+                .to_string(),
+            "Frame 0: I is 0x000 in the reference and 0x002 here"
+        );
+    }
+
+    #[test]
+    fn test_reference_waits_for_keys_like_the_keypad() {
+        // LD V1, 20; LD DT, V1; LD V2, DT; SE V2, 0; JP 0x204; LD V0, K; JP 0x20C
+        let rom = [
+            0x61, 0x14, 0xF1, 0x15, 0xF2, 0x07, 0x32, 0x00, 0x12, 0x04, 0xF0, 0x0A, 0x12, 0x0C,
+        ];
+        // tapped before the wait starts, then held through it
+        let script = Script::parse("2 5 down\n4 5 up\n25 6 down").unwrap();
+        for quirks in [Quirks::default(), Quirks::cosmac_vip()] {
+            let mut reference = Reference::new(&rom, quirks, 1).unwrap();
+            let mut machine = Machine::new(&rom, quirks, 1).unwrap();
+            assert_eq!(compare(&mut reference, &mut machine, &script, 40, 16), None);
+            assert_eq!(reference.snapshot().registers[0], 5);
+        }
+    }
+
+    #[test]
+    fn test_script_parse() {
+        let script = Script::parse("10 A down\n# comment\n\n5 a up").unwrap();
+        assert_eq!(script.events, vec![(5, 0xA, false), (10, 0xA, true)]);
+        assert!(Script::parse("10 G down").is_err());
+        assert!(Script::parse("10 1 press").unwrap_err().starts_with("line 1"));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::analysis::{self, Analysis};
+use crate::error::Chip8Error;
+use crate::machine::PROGRAM_START;
+use crate::opcode::Opcode;
+use crate::roms;
+use crate::symbols::Symbols;
+
+const BYTES_PER_LINE: usize = 8;
+
+pub fn run(location: &str, symbols: Option<&str>) -> Result<(), Chip8Error> {
+    let rom = roms::read(location)?;
+    let symbols = match symbols {
+        Some(path) => Symbols::load(path)?,
+        None => Symbols::default(),
+    };
+    print!("{}", disassemble(&rom, &analysis::analyze(&rom), symbols));
+    Ok(())
+}
+
+// The code the analysis found as instructions and everything else as .byte lines. Jump and call targets and
+// the addresses loaded into I get a label unless the symbols already have one for them.
+pub fn disassemble(rom: &[u8], analysis: &Analysis, mut symbols: Symbols) -> String {
+    let end = (PROGRAM_START + rom.len()) as u16;
+    for &addr in &analysis.instructions {
+        let label = match analysis::instruction_at(rom, addr).map(Opcode::decode) {
+            Some(Ok(Opcode::Jump(target))) if target != addr => (target, "label"),
+            Some(Ok(Opcode::Call(target))) => (target, "sub"),
+            Some(Ok(Opcode::SetI(target))) => (target, "data"),
+            _ => continue,
+        };
+        if (PROGRAM_START as u16..end).contains(&label.0) {
+            symbols.insert(label.0, format!("{}_{:03X}", label.1, label.0));
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut addr = PROGRAM_START as u16;
+    while addr < end {
+        if let Some(label) = symbols.label(addr) {
+            lines.push(format!("{}:", label));
+        }
+        let instruction = analysis::instruction_at(rom, addr);
+        if let (true, Some(instruction)) = (analysis.instructions.contains(&addr), instruction) {
+            let text = match (analysis::extension(instruction), Opcode::decode(instruction)) {
+                (Some((extension, description)), _) => format!("; {} {}", extension.name(), description),
+                (None, Ok(opcode)) => opcode.disassemble(&symbols),
+                (None, Err(_)) => format!("DW {:#06X}", instruction),
+            };
+            lines.push(format!("{:#05X}  {:04X}  {}", addr, instruction, text));
+            addr += 2;
+            continue;
+        }
+        // data runs up to the next instruction or label
+        let start = addr;
+        let mut bytes = Vec::new();
+        while addr < end && bytes.len() < BYTES_PER_LINE && !analysis.instructions.contains(&addr) {
+            if addr != start && symbols.label(addr).is_some() {
+                break;
+            }
+            bytes.push(format!("{:#04X}", rom[addr as usize - PROGRAM_START]));
+            addr += 1;
+        }
+        lines.push(format!("{:#05X}        .byte {}", start, bytes.join(", ")));
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_is_shown_as_bytes() {
+        // LD I 0x206, DRW, JP 0x204 (halt), then a sprite
+        let rom = [0xA2, 0x06, 0xD0, 0x02, 0x12, 0x04, 0xF0, 0x90];
+        let text = disassemble(&rom, &analysis::analyze(&rom), Symbols::default());
+        assert_eq!(
+            text,
+            "0x200  A206  LD I, data_206\n\
+             0x202  D002  DRW V0, V0, 2\n\
+             0x204  1204  JP 0x204\n\
+             data_206:\n\
+             0x206        .byte 0xF0, 0x90\n"
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use egui::{Color32, ColorImage, EventFilter, Key, Response, Sense, TextureHandle, TextureOptions, Ui, Widget};
+
+use crate::error::Chip8Error;
+use crate::machine::{Machine, DEFAULT_IPS, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+const FRAME_SECS: f64 = 1.0 / 60.0;
+// After a long hitch the emulator skips ahead instead of trying to catch up
+const MAX_FRAMES_PER_UPDATE: u32 = 4;
+
+// Same layout as the other frontends: 1234 / QWER / ASDF / ZXCV
+const KEYS: [(Key, u8); 16] = [
+    (Key::Num1, 0x1),
+    (Key::Num2, 0x2),
+    (Key::Num3, 0x3),
+    (Key::Num4, 0xC),
+    (Key::Q, 0x4),
+    (Key::W, 0x5),
+    (Key::E, 0x6),
+    (Key::R, 0xD),
+    (Key::A, 0x7),
+    (Key::S, 0x8),
+    (Key::D, 0x9),
+    (Key::F, 0xE),
+    (Key::Z, 0xA),
+    (Key::X, 0x0),
+    (Key::C, 0xB),
+    (Key::V, 0xF),
+];
+
+// Shows a running machine in any egui app with `ui.add(&mut widget)`. The machine advances on every
+// repaint and only receives keys while the widget has focus, which it takes when clicked.
+pub struct Chip8Widget {
+    machine: Machine,
+    error: Option<Chip8Error>,
+    paused: bool,
+    cycles_per_frame: u32,
+    lag: f64,
+    foreground: Color32,
+    background: Color32,
+    texture: Option<TextureHandle>,
+}
+
+impl Chip8Widget {
+    pub fn new(machine: Machine) -> Self {
+        Chip8Widget {
+            machine,
+            error: None,
+            paused: false,
+            cycles_per_frame: DEFAULT_IPS / 60,
+            lag: 0.0,
+            foreground: Color32::WHITE,
+            background: Color32::BLACK,
+            texture: None,
+        }
+    }
+
+    pub fn ips(mut self, ips: u32) -> Self {
+        self.cycles_per_frame = (ips / 60).max(1);
+        self
+    }
+
+    pub fn colors(mut self, foreground: Color32, background: Color32) -> Self {
+        self.foreground = foreground;
+        self.background = background;
+        self
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+
+    // The error the ROM crashed with, the machine stops running once there is one.
+    pub fn error(&self) -> Option<&Chip8Error> {
+        self.error.as_ref()
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    fn run_frames(&mut self, dt: f64) {
+        if self.paused || self.error.is_some() {
+            return;
+        }
+        self.lag = (self.lag + dt).min(FRAME_SECS * MAX_FRAMES_PER_UPDATE as f64);
+
+        while self.lag >= FRAME_SECS {
+            self.lag -= FRAME_SECS;
+            if let Err(err) = self.machine.run_frame(self.cycles_per_frame) {
+                self.error = Some(err);
+                return;
+            }
+        }
+    }
+
+    fn image(&self) -> ColorImage {
+        let colors = self
+            .machine
+            .pixels()
+            .iter()
+            .map(|&pixel| if pixel != 0 { self.foreground } else { self.background })
+            .collect();
+        ColorImage::new([DISPLAY_WIDTH, DISPLAY_HEIGHT], colors)
+    }
+}
+
+impl Widget for &mut Chip8Widget {
+    fn ui(self, ui: &mut Ui) -> Response {
+        // Fills the available width, or the height if that is the tighter fit, at the display's 2:1 ratio
+        let available = ui.available_size();
+        let scale = (available.x / DISPLAY_WIDTH as f32)
+            .min(available.y / DISPLAY_HEIGHT as f32)
+            .max(1.0);
+        let size = egui::vec2(DISPLAY_WIDTH as f32, DISPLAY_HEIGHT as f32) * scale;
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+        if response.clicked() {
+            response.request_focus();
+        }
+
+        let focused = response.has_focus();
+        if focused {
+            // Keep arrows and tab for the game instead of moving focus to the next widget
+            let filter = EventFilter {
+                tab: true,
+                horizontal_arrows: true,
+                vertical_arrows: true,
+                escape: false,
+            };
+            ui.memory_mut(|memory| memory.set_focus_lock_filter(response.id, filter));
+        }
+        let keypad = self.machine.keypad_mut();
+        for (code, key) in KEYS {
+            if focused && ui.input(|input| input.key_down(code)) {
+                keypad.press(key);
+            } else {
+                keypad.release(key);
+            }
+        }
+
+        self.run_frames(ui.input(|input| input.stable_dt) as f64);
+
+        let image = self.image();
+        let texture = match &mut self.texture {
+            Some(texture) => {
+                texture.set(image, TextureOptions::NEAREST);
+                texture
+            }
+            None => self
+                .texture
+                .insert(ui.ctx().load_texture("chip8-screen", image, TextureOptions::NEAREST)),
+        };
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        ui.painter().image(texture.id(), rect, uv, Color32::WHITE);
+        if focused {
+            let stroke = ui.visuals().selection.stroke;
+            ui.painter().rect_stroke(rect, 0.0, stroke, egui::StrokeKind::Inside);
+        }
+
+        if !self.paused && self.error.is_none() {
+            ui.ctx().request_repaint();
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quirks::Quirks;
+
+    fn run(widget: &mut Chip8Widget, ctx: &egui::Context, input: egui::RawInput) {
+        let _ = ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(&mut *widget);
+            });
+        });
+    }
+
+    #[test]
+    fn test_widget_runs_machine_and_uploads_texture() {
+        // LD V0, 8; LD F, V0; DRW V1, V1, 5; JP 0x206
+        let rom = [0x60, 0x08, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x06];
+        let mut widget = Chip8Widget::new(Machine::new(&rom, Quirks::default(), 1).unwrap());
+        let ctx = egui::Context::default();
+        for _ in 0..3 {
+            run(&mut widget, &ctx, egui::RawInput::default());
+        }
+
+        assert!(widget.texture.is_some());
+        assert!(widget.error().is_none());
+        let lit = widget
+            .image()
+            .pixels
+            .iter()
+            .filter(|&&color| color == Color32::WHITE)
+            .count();
+        assert_eq!(lit, 16);
+    }
+
+    #[test]
+    fn test_keys_need_focus() {
+        // SKNP V0; JP 0x200
+        let rom = [0xE0, 0xA1, 0x12, 0x00];
+        let mut widget = Chip8Widget::new(Machine::new(&rom, Quirks::default(), 1).unwrap());
+        let ctx = egui::Context::default();
+        let input = egui::RawInput {
+            events: vec![egui::Event::Key {
+                key: Key::X,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::NONE,
+            }],
+            ..Default::default()
+        };
+        run(&mut widget, &ctx, input);
+        run(&mut widget, &ctx, egui::RawInput::default());
+
+        assert!(!widget.machine_mut().keypad_mut().is_pressed(0x0));
+    }
+}
@@ -1,58 +1,107 @@
-use rand::Rng;
-use std::fs;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
 use std::thread;
 
+use crate::builder::Chip8Builder;
+use crate::cheats::{self, Cheat, Cheats};
+use crate::config::{Config, ConfigWatcher, Palette};
+use crate::coverage::Coverage;
+use crate::database::{self, Database, RomInfo};
+use crate::debugger::{self, Breakpoint, Command, Console, Register};
 use crate::error::Chip8Error;
-use crate::io;
+use crate::frames::Frames;
+use crate::frontend::{self, Audio, Input};
+use crate::handle::{Chip8Handle, HandleCommand, Remote, State};
+use crate::history::{InitializedMemory, RegisterTimeline, WriteHistory};
+use crate::hooks::Hooks;
+use crate::keypad::Keypad;
+use crate::machine::{Bus, Machine, FONT, FONT_OFFSET, MEMORY_SIZE, NUMBER_REGS, PROGRAM_START, STACK_SIZE};
+pub use crate::machine::{StepResult, DEFAULT_IPS, DISPLAY_HEIGHT, DISPLAY_WIDTH};
 use crate::opcode::Opcode;
+use crate::peripheral::Peripheral;
+use crate::profiler::Profiler;
+use crate::quirks::Quirks;
+use crate::report::Report;
+use crate::roms;
+use crate::snapshot::Chip8Snapshot;
+use crate::symbols::Symbols;
+use crate::theme::{self, Theme};
+use crate::trace::{RecentSteps, Registers, Tracer};
 
 type Memory = [u8; MEMORY_SIZE];
 type Display = [u8; DISPLAY_WIDTH * DISPLAY_HEIGHT];
-type Regs = [u8; NUMBER_REGS];
-type Stack = Vec<u16>;
-
-const DISPLAY_WIDTH: usize = 64;
-const DISPLAY_HEIGHT: usize = 32;
-const NUMBER_REGS: usize = 16;
-const STACK_SIZE: usize = 16;
-const MEMORY_SIZE: usize = 4096;
-const FONT_OFFSET: usize = 0x050;
-const PROGRAM_START: usize = 0x200;
-const INSTRUCTION_FREQ: u64 = 1000;
+
 const TIMER_FREQ: u64 = 60;
+const HEATMAP_FREQ: u64 = 30;
+// How often the frontend is asked for new events, also while waiting for the next instruction
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(2);
+const DEFAULT_STEP_COUNT: u32 = 10;
+const TIMELINE_CAPACITY: usize = 4096;
+const RECENT_STEPS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HaltPolicy {
+    #[default]
+    Continue,
+    Pause,
+    Exit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidPolicy {
+    #[default]
+    Halt,
+    Warn,
+    Nop,
+}
+
+// What to do with 0NNN calls to machine code that no peripheral or handler took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MachineCallPolicy {
+    #[default]
+    Ignore,
+    Warn,
+    Halt,
+}
+
+// How a run that hit its instruction limit ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CycleLimitPolicy {
+    #[default]
+    Error,
+    Exit,
+}
 
-const FONT: [u8; 80] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-];
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UninitPolicy {
+    #[default]
+    Ignore,
+    Warn,
+    Break,
+}
+
+// What came with the ROM when it was loaded, for the frontend to tell the user about.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadedRom {
+    // the IPS patch applied to it
+    pub patch: Option<PathBuf>,
+    // its cheat file and the number of cheats in it
+    pub cheats: Option<(PathBuf, usize)>,
+    // why its cheat file was left out
+    pub cheat_error: Option<String>,
+    // its entry in the ROM database
+    pub recognized: Option<RomInfo>,
+}
 
 pub struct Chip8 {
-    display: Display,
-    memory: Memory,
-    regs: Regs,
-    stack: Stack,
-    pc: u16,
-    i: u16,
+    core: Machine,
+    bus: Chip8Bus,
     current_instruction: u16,
-    acc: i32,
 
-    delay_timer: u8,
-    sound_timer: u8,
     last_timer_update: std::time::Instant,
+    last_poll: std::time::Instant,
 
     running: bool,
     paused: bool,
@@ -60,619 +109,1727 @@ pub struct Chip8 {
     should_step: bool,
     debug_mode: bool,
 
-    io: Option<io::IO>,
+    screen: Option<Box<dyn frontend::Display>>,
+    input: Option<Box<dyn Input>>,
+    audio: Option<Box<dyn Audio>>,
+    beeping: bool,
+    tracer: Option<Tracer>,
+    recent: RecentSteps,
+    console: Option<Console>,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    rom_size: usize,
+    last_heatmap_update: std::time::Instant,
+    breakpoints: Vec<Breakpoint>,
+    skip_breakpoint: bool,
+    on_halt: HaltPolicy,
+    halted_at: Option<u16>,
+    on_invalid: InvalidPolicy,
+    steps_remaining: u32,
+    step_count: u32,
+    finish_depth: Option<usize>,
+    symbols: Symbols,
+    rom_path: String,
+    // of the program as loaded, with its patch, the saved breakpoints go by it
+    rom_sha1: String,
+    timeline: Option<RegisterTimeline>,
+    cycles: u64,
+    on_uninit_read: UninitPolicy,
+    explain: bool,
+    throttle: bool,
+    // runs unthrottled until toggled off again, without changing the throttle setting
+    fast_forward: bool,
+    frame_cycles: u32,
+    frames: u64,
+    max_frames: Option<u64>,
+    max_cycles: Option<u64>,
+    on_max_cycles: CycleLimitPolicy,
+    exit_at: Vec<u16>,
+    exited_at: Option<u16>,
+    display_changed: bool,
+    waiting_for_key: bool,
+    ips: u32,
+    hooks: Hooks,
+    remote: Option<Remote>,
+    on_machine_call: MachineCallPolicy,
+    config_watcher: Option<ConfigWatcher>,
+    // the colors the screen was last given, the theme hotkey goes on from them
+    palette: Palette,
+    rom_picker: Option<Box<dyn FnMut() -> Option<String>>>,
+    // with the quirks for the ROMs it does not know
+    database: Option<(Database, Quirks)>,
+    cheats: Cheats,
+    loaded: LoadedRom,
+}
+
+// Chip8::display_text for any display, like the pixels of a Machine.
+pub fn display_text(pixels: &[u8]) -> String {
+    pixels
+        .chunks(DISPLAY_WIDTH)
+        .map(|row| {
+            row.iter()
+                .map(|&pixel| if pixel != 0 { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
 }
 
 impl Chip8 {
     pub fn new(rom: &str, debug: bool) -> Result<Self, Chip8Error> {
-        let mut memory = [0; MEMORY_SIZE];
-        memory[FONT_OFFSET..FONT_OFFSET + FONT.len()].copy_from_slice(&FONT);
-
-        let data = fs::read(rom)?;
-        if (data.len() + PROGRAM_START) > MEMORY_SIZE {
-            return Err(Chip8Error::RomTooLarge(data.len()));
-        }
-        memory[PROGRAM_START..PROGRAM_START + data.len()].copy_from_slice(&data);
-
-        Ok(Self {
-            display: [0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
-            memory,
-            regs: [0; NUMBER_REGS],
-            stack: Vec::with_capacity(STACK_SIZE),
-            delay_timer: 0,
-            sound_timer: 0,
+        Chip8::builder().rom_file(rom).debug(debug).build()
+    }
+
+    pub fn builder() -> Chip8Builder {
+        Chip8Builder::default()
+    }
+
+    pub(crate) fn from_config(
+        data: &[u8],
+        rom_path: Option<String>,
+        patch: Option<PathBuf>,
+        debug: bool,
+        quirks: Quirks,
+        ips: u32,
+        seed: Option<u64>,
+    ) -> Result<Self, Chip8Error> {
+        let mut chip8 = Self {
+            // the random numbers come from the bus, the machine's own generator goes unused
+            core: Machine::new(data, quirks, 0)?,
+            bus: Chip8Bus {
+                pc: PROGRAM_START as u16,
+                peripherals: Vec::new(),
+                writes: Vec::new(),
+                write_history: None,
+                initialized: None,
+                uninit_reads: Vec::new(),
+                rng: match seed {
+                    Some(seed) => StdRng::seed_from_u64(seed),
+                    None => StdRng::from_os_rng(),
+                },
+                machine_call: None,
+            },
             last_timer_update: std::time::Instant::now(),
+            last_poll: std::time::Instant::now(),
             running: true,
             debug_mode: debug,
             paused: debug,
             step_mode: false,
             should_step: false,
-            pc: 0x200,
-            i: 0x0,
-            acc: 0,
             current_instruction: 0x0000,
-            io: Some(io::IO::new(DISPLAY_WIDTH, DISPLAY_HEIGHT)?),
-        })
+            screen: None,
+            input: None,
+            audio: None,
+            beeping: false,
+            tracer: None,
+            recent: RecentSteps::new(RECENT_STEPS),
+            console: debug.then(Console::spawn),
+            profiler: debug.then(|| Profiler::new(MEMORY_SIZE)),
+            coverage: None,
+            rom_size: data.len(),
+            last_heatmap_update: std::time::Instant::now(),
+            breakpoints: match &rom_path {
+                Some(_) if debug => debugger::load_breakpoints(&database::sha1(data)),
+                _ => Vec::new(),
+            },
+            skip_breakpoint: false,
+            on_halt: HaltPolicy::default(),
+            halted_at: None,
+            on_invalid: InvalidPolicy::default(),
+            steps_remaining: 0,
+            step_count: DEFAULT_STEP_COUNT,
+            finish_depth: None,
+            symbols: Symbols::default(),
+            timeline: debug.then(|| RegisterTimeline::new(TIMELINE_CAPACITY)),
+            cycles: 0,
+            on_uninit_read: UninitPolicy::default(),
+            explain: false,
+            throttle: true,
+            fast_forward: false,
+            frame_cycles: 0,
+            frames: 0,
+            max_frames: None,
+            max_cycles: None,
+            on_max_cycles: CycleLimitPolicy::default(),
+            exit_at: Vec::new(),
+            exited_at: None,
+            display_changed: false,
+            waiting_for_key: false,
+            ips: ips.max(1),
+            hooks: Hooks::default(),
+            remote: None,
+            on_machine_call: MachineCallPolicy::default(),
+            config_watcher: None,
+            palette: Palette::default(),
+            rom_picker: None,
+            database: None,
+            cheats: Cheats::default(),
+            loaded: LoadedRom {
+                patch,
+                ..LoadedRom::default()
+            },
+            rom_path: rom_path.unwrap_or_default(),
+            rom_sha1: database::sha1(data),
+        };
+        chip8.load_cheats();
+        Ok(chip8)
     }
 
-    pub fn run(&mut self) -> Result<(), Chip8Error> {
-        while self.running {
-            if !self.paused && (!self.step_mode || self.should_step) {
-                self.handle_timer();
+    pub fn set_display(&mut self, screen: Box<dyn frontend::Display>) {
+        self.screen = Some(screen);
+    }
 
-                if self.pc as usize >= MEMORY_SIZE - 1 {
-                    return Err(Chip8Error::PCOutOfBounds(self.pc));
-                }
+    pub fn set_input(&mut self, input: Box<dyn Input>) {
+        self.input = Some(input);
+    }
 
-                self.fetch();
-                self.decode_execute()?;
-            }
+    pub fn set_audio(&mut self, audio: Box<dyn Audio>) {
+        self.audio = Some(audio);
+    }
 
-            thread::sleep(std::time::Duration::from_secs_f64(1_f64 / INSTRUCTION_FREQ as f64));
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
 
-            if self.step_mode && self.should_step {
-                self.draw()?;
-                self.should_step = false;
-            }
+    pub fn enable_write_history(&mut self, depth: usize) {
+        self.bus.write_history = Some(WriteHistory::new(depth));
+    }
 
-            if let Some(io) = &mut self.io {
-                let result = io.poll()?;
+    pub fn set_halt_policy(&mut self, policy: HaltPolicy) {
+        self.on_halt = policy;
+    }
 
-                if result == crate::io::QUIT {
-                    self.running = false;
-                    continue;
-                }
+    // Without throttling the emulator runs as fast as possible and the timers tick once per frame of
+    // ips / 60 instructions instead of following the wall clock, which makes runs reproducible.
+    pub fn set_throttle(&mut self, throttle: bool) {
+        self.throttle = throttle;
+    }
 
-                if result & crate::io::PAUSE != 0 && self.debug_mode {
-                    self.paused = !self.paused;
-                    self.draw()?;
-                }
+    pub fn set_max_frames(&mut self, max_frames: Option<u64>) {
+        self.max_frames = max_frames;
+    }
 
-                if result & crate::io::STEP_MODE != 0 && self.debug_mode {
-                    self.step_mode = !self.step_mode;
-                    self.draw()?;
-                }
+    // Stops the run after this many instructions, with an error or without depending on the policy.
+    pub fn set_max_cycles(&mut self, max_cycles: Option<u64>, policy: CycleLimitPolicy) {
+        self.max_cycles = max_cycles;
+        self.on_max_cycles = policy;
+    }
 
-                if result & crate::io::SHOULD_STEP != 0 {
-                    self.should_step = true;
-                }
+    // Stops the run once the program counter reaches one of these addresses, like the label a test ROM jumps to
+    // when it is done.
+    pub fn set_exit_at(&mut self, addrs: Vec<u16>) {
+        self.exit_at = addrs;
+    }
 
-                if result & crate::io::RESET != 0 {
-                    self.reset()?;
-                }
-            }
-        }
-        Ok(())
+    // Where the run stopped for set_exit_at, None when it did not.
+    pub fn exited_at(&self) -> Option<u16> {
+        self.exited_at
     }
 
-    fn draw(&mut self) -> Result<(), Chip8Error> {
-        if let Some(io) = &mut self.io {
-            io.draw(&mut self.display)?;
+    pub fn set_ips(&mut self, ips: u32) {
+        self.ips = ips.max(1);
+    }
+
+    // Applies the palette, speed and key map from the config file whenever it is saved while running.
+    pub fn watch_config(&mut self, watcher: ConfigWatcher) {
+        self.config_watcher = Some(watcher);
+    }
+
+    // The settings from the config that can change while running, the others only take effect on start.
+    pub fn apply_config(&mut self, config: &Config) -> Result<(), Chip8Error> {
+        self.set_ips(config.ips);
+        if let Some(input) = &mut self.input {
+            input.set_key_map(&config.key_map())?;
         }
-        Ok(())
+        self.set_palette(config.palette);
+        self.draw()
     }
 
-    fn reset(&mut self) -> Result<(), Chip8Error> {
-        self.display = [0; DISPLAY_WIDTH * DISPLAY_HEIGHT];
-        self.regs = [0; NUMBER_REGS];
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        if let Some(screen) = &mut self.screen {
+            screen.set_palette(palette);
+        }
+    }
 
-        self.pc = 0x200;
-        self.i = 0x0;
-        self.acc = 0;
-        self.current_instruction = 0x0000;
+    pub fn frame_count(&self) -> u64 {
+        self.frames
+    }
 
-        self.delay_timer = 0;
-        self.sound_timer = 0;
+    pub fn cycle_count(&self) -> u64 {
+        self.cycles
+    }
 
-        self.running = true;
-        self.paused = self.debug_mode;
-        self.step_mode = false;
-        self.should_step = false;
+    // Iterates over the display after every 60 Hz frame of ips / 60 instructions, for consumers like
+    // video encoders that want whole frames instead of single steps.
+    pub fn frames(&mut self) -> Frames<'_> {
+        Frames::new(self)
+    }
 
-        self.draw()?;
+    pub(crate) fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        for _ in 0..self.cycles_per_frame() {
+            self.step()?;
+        }
+        self.tick_timers();
+        self.frames += 1;
+        self.core.keypad.end_frame();
+        self.end_frame();
         Ok(())
     }
 
-    fn fetch(&mut self) {
-        let high_byte = self.memory[self.pc as usize] as u16;
-        let low_byte = self.memory[(self.pc + 1) as usize] as u16;
-
-        self.current_instruction = (high_byte << 8) | low_byte;
-        self.pc += 2;
+    // Renders the display with '#' for lit and '.' for dark pixels, one line per row.
+    pub fn display_text(&self) -> String {
+        display_text(&self.core.display)
     }
 
-    fn decode_execute(&mut self) -> Result<(), Chip8Error> {
-        let opcode = self.decode()?;
-        self.execute(opcode)?;
-        Ok(())
+    pub fn enable_explain(&mut self) {
+        self.explain = true;
     }
 
-    fn decode(&self) -> Result<Opcode, Chip8Error> {
-        let first_nibble = (self.current_instruction & 0xF000) >> 12;
-        let x = ((self.current_instruction & 0x0F00) >> 8) as u8;
-        let y = ((self.current_instruction & 0x00F0) >> 4) as u8;
-        let n = (self.current_instruction & 0x000F) as u8;
-        let nn = (self.current_instruction & 0x00FF) as u8;
-        let nnn = self.current_instruction & 0x0FFF;
-
-        match (first_nibble, x, y, n) {
-            (0x0, 0x0, 0xE, 0x0) => Ok(Opcode::Clear),
-            (0x0, 0x0, 0xE, 0xE) => Ok(Opcode::Return),
-            (0x1, _, _, _) => Ok(Opcode::Jump(nnn)),
-            (0x2, _, _, _) => Ok(Opcode::Call(nnn)),
-            (0x3, _, _, _) => Ok(Opcode::SkipEqualVal(x, nn)),
-            (0x4, _, _, _) => Ok(Opcode::SkipNotEqualVal(x, nn)),
-            (0x5, _, _, 0x0) => Ok(Opcode::SkipEqual(x, y)),
-            (0x6, _, _, _) => Ok(Opcode::SetVal(x, nn)),
-            (0x7, _, _, _) => Ok(Opcode::AddVal(x, nn)),
-            (0x8, _, _, 0x0) => Ok(Opcode::Set(x, y)),
-            (0x8, _, _, 0x1) => Ok(Opcode::Or(x, y)),
-            (0x8, _, _, 0x2) => Ok(Opcode::And(x, y)),
-            (0x8, _, _, 0x3) => Ok(Opcode::Xor(x, y)),
-            (0x8, _, _, 0x4) => Ok(Opcode::Add(x, y)),
-            (0x8, _, _, 0x5) => Ok(Opcode::SubY(x, y)),
-            (0x8, _, _, 0x6) => Ok(Opcode::ShiftRight(x)),
-            (0x8, _, _, 0x7) => Ok(Opcode::SubX(x, y)),
-            (0x8, _, _, 0xE) => Ok(Opcode::ShiftLeft(x)),
-            (0x9, _, _, 0x0) => Ok(Opcode::SkipNotEqual(x, y)),
-            (0xA, _, _, _) => Ok(Opcode::SetI(nnn)),
-            (0xB, _, _, _) => Ok(Opcode::JumpV0(nnn)),
-            (0xC, _, _, _) => Ok(Opcode::Random(x, nn)),
-            (0xD, _, _, _) => Ok(Opcode::Draw(x, y, n)),
-            (0xE, _, 0x9, 0xE) => Ok(Opcode::SkipKey(x)),
-            (0xE, _, 0xA, 0x1) => Ok(Opcode::SkipNotKey(x)),
-            (0xF, _, 0x0, 0x7) => Ok(Opcode::GetDelay(x)),
-            (0xF, _, 0x0, 0xA) => Ok(Opcode::WaitKey(x)),
-            (0xF, _, 0x1, 0x5) => Ok(Opcode::SetDelay(x)),
-            (0xF, _, 0x1, 0x8) => Ok(Opcode::SetSound(x)),
-            (0xF, _, 0x1, 0xE) => Ok(Opcode::AddI(x)),
-            (0xF, _, 0x2, 0x9) => Ok(Opcode::SetSprite(x)),
-            (0xF, _, 0x3, 0x3) => Ok(Opcode::StoreBCD(x)),
-            (0xF, _, 0x5, 0x5) => Ok(Opcode::StoreRegs(x)),
-            (0xF, _, 0x6, 0x5) => Ok(Opcode::LoadRegs(x)),
-            _ => Err(Chip8Error::InvalidOpcode(self.current_instruction)),
-        }
-    }
-
-    fn execute(&mut self, opcode: Opcode) -> Result<(), Chip8Error> {
-        match opcode {
-            Opcode::Clear => self.clear(),
-            Opcode::Return => self.stack_pop(),
-            Opcode::Jump(addr) => set(addr, &mut self.pc),
-            Opcode::Call(addr) => self.call(addr),
-            Opcode::SkipEqualVal(x, nn) => self.skip_if(self.regs[x as usize] == nn),
-            Opcode::SkipNotEqualVal(x, nn) => self.skip_if(self.regs[x as usize] != nn),
-            Opcode::SkipEqual(x, y) => self.skip_if(self.regs[x as usize] == self.regs[y as usize]),
-            Opcode::SetVal(x, nn) => set(nn, &mut self.regs[x as usize]),
-            Opcode::AddVal(x, nn) => set_fn(nn, &mut self.regs[x as usize], |a, b| a.wrapping_add(b)),
-            Opcode::Set(x, y) => set(self.regs[y as usize], &mut self.regs[x as usize]),
-            Opcode::Or(x, y) => set_fn(self.regs[y as usize], &mut self.regs[x as usize], |a, b| a | b),
-            Opcode::And(x, y) => set_fn(self.regs[y as usize], &mut self.regs[x as usize], |a, b| a & b),
-            Opcode::Xor(x, y) => set_fn(self.regs[y as usize], &mut self.regs[x as usize], |a, b| a ^ b),
-            Opcode::Add(x, y) => self.add(x, y),
-            Opcode::SubY(x, y) => self.sub(x, x, y),
-            Opcode::ShiftRight(x) => self.shift(x, ShiftOp::Right),
-            Opcode::SubX(x, y) => self.sub(x, y, x),
-            Opcode::ShiftLeft(x) => self.shift(x, ShiftOp::Left),
-            Opcode::SkipNotEqual(x, y) => self.skip_if(self.regs[x as usize] != self.regs[y as usize]),
-            Opcode::SetI(addr) => set(addr, &mut self.i),
-            Opcode::JumpV0(nnn) => self.jump_rel(nnn),
-            Opcode::Random(x, nn) => self.random(x, nn),
-            Opcode::Draw(x, y, n) => self.draw_mem(x, y, n),
-            Opcode::SkipKey(x) => self.handle_key_skip(x, true),
-            Opcode::SkipNotKey(x) => self.handle_key_skip(x, false),
-            Opcode::GetDelay(x) => set(self.delay_timer, &mut self.regs[x as usize]),
-            Opcode::WaitKey(x) => self.wait_key(x),
-            Opcode::SetDelay(x) => set(x, &mut self.delay_timer),
-            Opcode::SetSound(x) => set(x, &mut self.sound_timer),
-            Opcode::AddI(x) => set_fn(self.regs[x as usize] as u16, &mut self.i, |a, b| a + b),
-            Opcode::SetSprite(x) => self.set_sprite(x),
-            Opcode::StoreBCD(x) => self.store_bcd(x),
-            Opcode::StoreRegs(x) => self.store_regs(x as u16),
-            Opcode::LoadRegs(x) => self.load_regs(x as u16),
-        }
+    pub fn set_uninit_policy(&mut self, policy: UninitPolicy) {
+        self.on_uninit_read = policy;
+        self.bus.initialized = (policy != UninitPolicy::Ignore).then(|| {
+            let mut initialized = InitializedMemory::new(MEMORY_SIZE);
+            initialized.mark(FONT_OFFSET..FONT_OFFSET + FONT.len());
+            initialized.mark(PROGRAM_START..PROGRAM_START + self.rom_size);
+            initialized
+        });
     }
 
-    fn handle_timer(&mut self) {
-        let ticks = self.timer_60_hz();
-        if ticks > 0 {
-            if self.delay_timer > 0 {
-                self.delay_timer -= ticks.min(self.delay_timer as u32) as u8;
-            }
-            if self.sound_timer > 0 {
-                self.sound_timer -= ticks.min(self.sound_timer as u32) as u8;
-            }
-        }
+    pub fn set_invalid_policy(&mut self, policy: InvalidPolicy) {
+        self.on_invalid = policy;
     }
 
-    fn timer_60_hz(&mut self) -> u32 {
-        use std::time::{Duration, Instant};
-        let now = Instant::now();
-        let interval = Duration::from_secs_f64(1.0 / TIMER_FREQ as f64);
+    pub fn set_machine_call_policy(&mut self, policy: MachineCallPolicy) {
+        self.on_machine_call = policy;
+    }
 
-        let mut ticks = 0u32;
-        while now.duration_since(self.last_timer_update) >= interval {
-            self.last_timer_update += interval;
-            ticks = ticks.saturating_add(1);
+    pub fn enable_heatmap(&mut self) -> Result<(), Chip8Error> {
+        self.enable_profiler();
+        if let Some(screen) = &mut self.screen {
+            screen.open_heatmap(MEMORY_SIZE)?;
         }
-        ticks
+        Ok(())
     }
 
-    fn display(&mut self, vx: usize, vy: usize, n: u8) {
-        self.regs[0xF] = 0;
+    pub fn enable_coverage(&mut self) {
+        self.coverage.get_or_insert_with(|| Coverage::new(MEMORY_SIZE));
+    }
 
-        for byte_index in 0..n as usize {
-            let byte = self.memory[self.i as usize + byte_index];
-            for bit_index in (0..8).rev() {
-                let bit = (byte >> bit_index) & 1;
-                let screen_x = (vx + (7 - bit_index)) % DISPLAY_WIDTH;
-                let screen_y = (vy + byte_index) % DISPLAY_HEIGHT;
-                let screen_offset = screen_y * DISPLAY_WIDTH + screen_x;
+    pub fn coverage_report(&self) -> Option<String> {
+        let rom = PROGRAM_START..PROGRAM_START + self.rom_size;
+        self.coverage.as_ref().map(|coverage| coverage.report(rom))
+    }
 
-                if bit == 1 && self.display[screen_offset] == 1 {
-                    self.regs[0xF] = 1;
-                }
+    pub fn coverage_disassembly(&self) -> Option<String> {
+        let rom = PROGRAM_START..PROGRAM_START + self.rom_size;
+        self.coverage
+            .as_ref()
+            .map(|coverage| coverage.annotated_disassembly(&self.core.memory, rom, &self.symbols))
+    }
 
-                self.display[screen_offset] ^= bit;
+    pub fn html_report(&self, title: &str) -> Option<String> {
+        let (Some(coverage), Some(profiler)) = (&self.coverage, &self.profiler) else {
+            return None;
+        };
+        let report = Report {
+            title,
+            memory: &self.core.memory,
+            rom: PROGRAM_START..PROGRAM_START + self.rom_size,
+            coverage,
+            profiler,
+            display: &self.core.display,
+            display_width: DISPLAY_WIDTH,
+            symbols: &self.symbols,
+        };
+        Some(report.html())
+    }
 
-                if screen_x == DISPLAY_WIDTH - 1 {
-                    break;
-                }
-            }
+    pub fn enable_profiler(&mut self) {
+        self.profiler.get_or_insert_with(|| Profiler::new(MEMORY_SIZE));
+    }
 
-            if vy + byte_index == DISPLAY_HEIGHT - 1 {
-                break;
-            }
-        }
+    pub fn profile_report(&self, top: usize) -> Option<String> {
+        self.profiler
+            .as_ref()
+            .map(|profiler| profiler.report(top, &self.core.memory, &self.symbols))
     }
 
-    fn validate_register(&self, x: u8) -> Result<(), Chip8Error> {
-        if x > 0xF {
-            Err(Chip8Error::InvalidRegister(x))
-        } else {
-            Ok(())
-        }
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = Some(tracer);
     }
-}
 
-fn set<T>(value: T, loc: &mut T) -> Result<(), Chip8Error> {
-    *loc = value;
-    Ok(())
-}
+    pub fn dump_recent_steps(&self, path: impl AsRef<Path>, err: &Chip8Error) -> Result<(), Chip8Error> {
+        self.recent.dump(path.as_ref(), &self.symbols, err)
+    }
 
-fn set_fn<T: Copy>(value: T, loc: &mut T, f: fn(T, T) -> T) -> Result<(), Chip8Error> {
-    *loc = f(*loc, value);
-    Ok(())
-}
+    // Executes a single instruction. Timers are not touched, call tick_timers 60 times per second.
+    pub fn step(&mut self) -> Result<StepResult, Chip8Error> {
+        let was_waiting = self.waiting_for_key;
+        self.display_changed = false;
+        self.waiting_for_key = false;
+        self.cycle()?;
+        let sound = self.update_audio()?;
+
+        if self.display_changed {
+            for hook in &mut self.hooks.draw {
+                hook(&self.core.display);
+            }
+        }
+        if self.waiting_for_key && !was_waiting {
+            let x = ((self.current_instruction >> 8) & 0xF) as u8;
+            for hook in &mut self.hooks.key_wait {
+                hook(x);
+            }
+        }
 
-enum ShiftOp {
-    Right,
-    Left,
-}
+        Ok(StepResult {
+            display_changed: self.display_changed,
+            sound_started: sound == Some(true),
+            sound_stopped: sound == Some(false),
+            waiting_for_key: self.waiting_for_key,
+            halted: self.halted_at.is_some(),
+        })
+    }
 
-impl Chip8 {
-    fn clear(&mut self) -> Result<(), Chip8Error> {
-        self.display.fill(0);
-        if let Some(io) = &mut self.io {
-            io.draw(&mut self.display)?;
-        }
-        Ok(())
+    // Called with the display after every instruction that changed it.
+    pub fn on_draw(&mut self, hook: impl FnMut(&[u8]) + 'static) {
+        self.hooks.draw.push(Box::new(hook));
     }
 
-    fn skip_if(&mut self, condition: bool) -> Result<(), Chip8Error> {
-        if condition {
-            self.pc += 2;
-        }
-        Ok(())
+    // Called with true when the sound timer starts running and with false when it runs out.
+    pub fn on_sound_change(&mut self, hook: impl FnMut(bool) + 'static) {
+        self.hooks.sound_change.push(Box::new(hook));
     }
 
-    fn jump_rel(&mut self, nnn: u16) -> Result<(), Chip8Error> {
-        self.pc = (self.regs[0x0] as u16) + nnn;
-        Ok(())
+    // Called with the target register when FX0A starts waiting for a key.
+    pub fn on_key_wait(&mut self, hook: impl FnMut(u8) + 'static) {
+        self.hooks.key_wait.push(Box::new(hook));
+    }
+
+    // Poke and freeze cheats, written at the end of every frame. They come from the ROM's cheat file, opening
+    // another ROM replaces them with its own.
+    pub fn cheats_mut(&mut self) -> &mut Cheats {
+        &mut self.cheats
     }
 
-    fn stack_push(&mut self, value: u16) -> Result<(), Chip8Error> {
-        if self.stack.len() >= STACK_SIZE {
-            return Err(Chip8Error::StackOverflow);
+    fn end_frame(&mut self) {
+        for (addr, value) in self.cheats.due() {
+            // cheats are only added for addresses inside the memory
+            let _ = self.write_mem(addr, &[value]);
+        }
+        for hook in &mut self.hooks.frame {
+            hook(self.frames, &self.core.display);
         }
-        self.stack.push(value);
-        Ok(())
     }
 
-    fn stack_pop(&mut self) -> Result<(), Chip8Error> {
-        self.pc = self.stack.pop().ok_or(Chip8Error::StackUnderflow)?;
-        Ok(())
+    // Called with the number of the frame and the display at the end of every 60 Hz frame.
+    pub fn on_frame(&mut self, hook: impl FnMut(u64, &[u8]) + 'static) {
+        self.hooks.frame.push(Box::new(hook));
     }
 
-    fn call(&mut self, addr: u16) -> Result<(), Chip8Error> {
-        self.stack_push(self.pc)?;
-        self.pc = addr;
-        Ok(())
+    // Called with the address and opcode of every executed instruction.
+    pub fn on_instruction(&mut self, hook: impl FnMut(u16, &Opcode) + 'static) {
+        self.hooks.instruction.push(Box::new(hook));
     }
 
-    fn store_bcd(&mut self, x: u8) -> Result<(), Chip8Error> {
-        self.validate_register(x)?;
-        self.memory[self.i as usize] = (self.regs[x as usize] / 100) % 10;
-        self.memory[(self.i + 1) as usize] = (self.regs[x as usize] / 10) % 10;
-        self.memory[(self.i + 2) as usize] = self.regs[x as usize] % 10;
-        Ok(())
+    pub fn read_mem(&self, addr: u16, len: usize) -> Result<&[u8], Chip8Error> {
+        let start = addr as usize;
+        self.core
+            .memory
+            .get(start..start + len)
+            .ok_or(Chip8Error::MemoryOutOfBounds(addr))
     }
 
-    fn store_regs(&mut self, x: u16) -> Result<(), Chip8Error> {
-        self.validate_register(x as u8)?;
-        for i in 0u16..=x {
-            self.memory[(self.i + i) as usize] = self.regs[i as usize];
+    // Counts as initialized for --on-uninit-read, like the ROM and the program's own writes.
+    pub fn write_mem(&mut self, addr: u16, bytes: &[u8]) -> Result<(), Chip8Error> {
+        let start = addr as usize;
+        let target = self
+            .core
+            .memory
+            .get_mut(start..start + bytes.len())
+            .ok_or(Chip8Error::MemoryOutOfBounds(addr))?;
+        target.copy_from_slice(bytes);
+        if let Some(initialized) = &mut self.bus.initialized {
+            initialized.mark(start..start + bytes.len());
         }
         Ok(())
     }
 
-    fn load_regs(&mut self, x: u16) -> Result<(), Chip8Error> {
-        self.validate_register(x as u8)?;
-        for i in 0u16..=x {
-            self.regs[i as usize] = self.memory[(self.i + i) as usize];
-        }
-        Ok(())
+    pub fn reg(&self, x: u8) -> Result<u8, Chip8Error> {
+        self.core
+            .regs
+            .get(x as usize)
+            .copied()
+            .ok_or(Chip8Error::InvalidRegister(x))
     }
 
-    fn random(&mut self, x: u8, nn: u8) -> Result<(), Chip8Error> {
-        self.validate_register(x)?;
-        let mut rng = rand::rng();
-        let random_number: u8 = rng.random();
-        self.regs[x as usize] = random_number & nn;
+    pub fn set_reg(&mut self, x: u8, value: u8) -> Result<(), Chip8Error> {
+        *self
+            .core
+            .regs
+            .get_mut(x as usize)
+            .ok_or(Chip8Error::InvalidRegister(x))? = value;
         Ok(())
     }
 
-    fn draw_mem(&mut self, x: u8, y: u8, n: u8) -> Result<(), Chip8Error> {
-        self.validate_register(x)?;
-        self.validate_register(y)?;
-        let vx = self.regs[x as usize] as usize % DISPLAY_WIDTH;
-        let vy = self.regs[y as usize] as usize % DISPLAY_HEIGHT;
+    pub fn pc(&self) -> u16 {
+        self.core.pc
+    }
 
-        self.display(vx, vy, n);
+    pub fn i(&self) -> u16 {
+        self.core.i
+    }
 
-        if let Some(io) = &mut self.io {
-            io.draw(&mut self.display)?;
-        }
-        Ok(())
+    // The return addresses of the active calls, innermost last.
+    pub fn stack(&self) -> &[u16] {
+        self.core.stack()
     }
 
-    fn set_sprite(&mut self, x: u8) -> Result<(), Chip8Error> {
-        let value = (self.regs[(x as usize) & 0xF] * 5) as u16;
-        self.i = MEMORY_SIZE as u16 + value;
-        Ok(())
+    pub fn quirks(&self) -> Quirks {
+        self.core.quirks()
     }
 
-    fn wait_key(&mut self, x: u8) -> Result<(), Chip8Error> {
-        self.validate_register(x)?;
-        if let Some(io) = &mut self.io {
-            let result = io.get_key_pressed();
-            if result == crate::io::NO_KEY_PRESSED {
-                self.pc -= 2;
-            } else {
-                self.regs[x as usize] = result as u8;
-            }
-        }
-        Ok(())
+    // Takes effect with the next instruction. Opening another ROM gives it the database's quirks again.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.core.set_quirks(quirks);
     }
 
-    fn handle_key_skip(&mut self, x: u8, should_skip_if_pressed: bool) -> Result<(), Chip8Error> {
-        self.validate_register(x)?;
-        if let Some(io) = &mut self.io {
-            let is_pressed = io.check_key_pressed(self.regs[x as usize]);
-            if is_pressed == should_skip_if_pressed {
-                self.pc += 2;
-            }
+    pub fn snapshot(&self) -> Chip8Snapshot {
+        Chip8Snapshot {
+            memory: self.core.memory.to_vec(),
+            registers: self.core.regs,
+            i: self.core.i,
+            pc: self.core.pc,
+            stack: self.core.stack().to_vec(),
+            delay_timer: self.core.delay_timer,
+            sound_timer: self.core.sound_timer,
+            display: self.core.display.to_vec(),
+            quirks: self.core.quirks,
         }
-        Ok(())
     }
 
-    fn add(&mut self, x: u8, y: u8) -> Result<(), Chip8Error> {
-        self.validate_register(x)?;
-        self.validate_register(y)?;
-        let (result, overflow) = self.regs[x as usize].overflowing_add(self.regs[y as usize]);
-        self.regs[x as usize] = result;
-        self.regs[0xF] = overflow as u8;
-        Ok(())
-    }
+    // Puts the machine back into the snapshot's state. Debugging tools, hooks and frontends are kept.
+    pub fn restore(&mut self, snapshot: &Chip8Snapshot) -> Result<(), Chip8Error> {
+        let invalid = |err: String| Err(Chip8Error::InvalidSnapshot(err));
+        let memory: Memory = match snapshot.memory.as_slice().try_into() {
+            Ok(memory) => memory,
+            Err(_) => return invalid(format!("memory has {} bytes", snapshot.memory.len())),
+        };
+        let display: Display = match snapshot.display.as_slice().try_into() {
+            Ok(display) => display,
+            Err(_) => return invalid(format!("display has {} pixels", snapshot.display.len())),
+        };
+        if snapshot.stack.len() > STACK_SIZE {
+            return invalid(format!("stack has {} entries", snapshot.stack.len()));
+        }
 
-    fn sub(&mut self, loc: u8, x: u8, y: u8) -> Result<(), Chip8Error> {
-        self.validate_register(loc)?;
-        self.validate_register(x)?;
-        self.validate_register(y)?;
-        let (result, underflow) = self.regs[x as usize].overflowing_sub(self.regs[y as usize]);
-        self.regs[loc as usize] = result;
-        self.regs[0xF] = !underflow as u8;
-        Ok(())
+        self.core.memory = memory;
+        self.core.display = display;
+        self.core.regs = snapshot.registers;
+        self.core.i = snapshot.i;
+        self.core.pc = snapshot.pc;
+        self.core.stack[..snapshot.stack.len()].copy_from_slice(&snapshot.stack);
+        self.core.sp = snapshot.stack.len();
+        self.core.delay_timer = snapshot.delay_timer;
+        self.core.sound_timer = snapshot.sound_timer;
+        self.core.quirks = snapshot.quirks;
+        self.halted_at = None;
+        self.waiting_for_key = false;
+        self.draw()
     }
 
-    fn shift(&mut self, x: u8, direction: ShiftOp) -> Result<(), Chip8Error> {
-        self.validate_register(x)?;
-        let acc = self.regs[x as usize];
-        let op = match direction {
-            ShiftOp::Right => |a: u8, _: u8| a >> 1,
-            ShiftOp::Left => |a: u8, _: u8| a << 1,
+    // Replaces the program with the ROM at path, patched by the .ips file next to it if there is one, and starts it
+    // from the beginning. The current one keeps running when the file cannot be loaded.
+    pub fn open_rom(&mut self, path: &str) -> Result<(), Chip8Error> {
+        let original = roms::read(path)?;
+        let (data, patch) = roms::patch(Some(path), None, &original)?;
+        self.core.load(&data)?;
+        self.rom_size = data.len();
+        self.rom_path = path.to_string();
+        self.rom_sha1 = database::sha1(&data);
+        self.loaded = LoadedRom {
+            patch,
+            ..LoadedRom::default()
         };
-        self.regs[x as usize] = op(acc, 1);
-        match direction {
-            ShiftOp::Right => self.regs[0xF] = acc & 0x1,
-            ShiftOp::Left => self.regs[0xF] = (acc >> 7) & 0x1,
+        self.detect_rom(&original);
+        self.load_cheats();
+        if self.debug_mode {
+            self.breakpoints = debugger::load_breakpoints(&self.rom_sha1);
         }
-        Ok(())
+        // the new ROM's bytes count as initialized instead of the old one's
+        self.set_uninit_policy(self.on_uninit_read);
+        self.core.sp = 0;
+        self.halted_at = None;
+        self.waiting_for_key = false;
+        self.reset()
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    fn new_headless_chip8() -> Chip8 {
-        let mut memory = [0; MEMORY_SIZE];
-        memory[FONT_OFFSET..FONT_OFFSET + FONT.len()].copy_from_slice(&FONT);
-
-        Chip8 {
-            display: [0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
-            memory,
-            regs: [0; NUMBER_REGS],
-            stack: Vec::with_capacity(STACK_SIZE),
-            delay_timer: 0,
-            sound_timer: 0,
-            last_timer_update: std::time::Instant::now(),
-            running: true,
-            debug_mode: false,
-            paused: false,
-            step_mode: false,
-            should_step: false,
-            pc: PROGRAM_START as u16,
-            i: 0x0,
-            acc: 0,
-            current_instruction: 0x0000,
-            io: None,
-        }
+    // From now on the ROMs found in the database run with the quirks it has for them, the others with the
+    // current quirks.
+    pub fn set_database(&mut self, database: Database) {
+        self.database = Some((database, self.core.quirks));
     }
 
-    #[test]
-    fn test_opcode_add() {
-        let mut chip8 = new_headless_chip8();
-        chip8.regs[0xF] = 200;
-        chip8.current_instruction = 0x7F64;
+    pub(crate) fn detect_rom(&mut self, data: &[u8]) {
+        let Some((database, quirks)) = &self.database else {
+            return;
+        };
+        let info = database.lookup(data);
+        self.core.quirks = info.map_or(*quirks, |info| info.quirks);
+        self.loaded.recognized = info.cloned();
+    }
 
-        let opcode = chip8.decode().unwrap();
-        chip8.execute(opcode).unwrap();
+    // What came with the current ROM, see on_rom_open for the ones opened while running.
+    pub fn loaded_rom(&self) -> &LoadedRom {
+        &self.loaded
+    }
 
-        // no overflow in this instruction
-        assert_eq!(chip8.regs[0xF], 44);
+    // Called after another ROM was opened while running, e.g. by the open ROM hotkey or the open command.
+    pub fn on_rom_open(&mut self, hook: impl FnMut(&str, &LoadedRom) + 'static) {
+        self.hooks.rom_open.push(Box::new(hook));
+    }
 
-        chip8.regs[0] = 20;
-        chip8.current_instruction = 0x7064;
+    // Called with the path and the error when a ROM could not be opened while running, the current one keeps
+    // running.
+    pub fn on_rom_open_error(&mut self, hook: impl FnMut(&str, &Chip8Error) + 'static) {
+        self.hooks.rom_open_error.push(Box::new(hook));
+    }
 
-        let opcode = chip8.decode().unwrap();
-        chip8.execute(opcode).unwrap();
+    // Called with the address of the jump to itself when the program halts, before the halt policy pauses or
+    // stops the run.
+    pub fn on_halt(&mut self, hook: impl FnMut(u16) + 'static) {
+        self.hooks.halt.push(Box::new(hook));
+    }
 
-        assert_eq!(chip8.regs[0], 120);
+    // Called with the theme the next theme hotkey switched to.
+    pub fn on_theme_change(&mut self, hook: impl FnMut(&Theme) + 'static) {
+        self.hooks.theme_change.push(Box::new(hook));
     }
 
-    #[test]
-    fn test_opcode_sub_y() {
-        let mut chip8 = new_headless_chip8();
-        chip8.regs[0xF] = 10;
-        chip8.current_instruction = 0x8FF7;
+    // Called with the error when the watched config file changed but could not be applied, the run keeps
+    // the settings it had.
+    pub fn on_config_error(&mut self, hook: impl FnMut(&Chip8Error) + 'static) {
+        self.hooks.config_error.push(Box::new(hook));
+    }
 
-        let opcode = chip8.decode().unwrap();
-        chip8.execute(opcode).unwrap();
+    // A cheat file that cannot be read is left out, the ROM runs without cheats.
+    fn load_cheats(&mut self) {
+        self.cheats = Cheats::default();
+        if self.rom_path.is_empty() {
+            return;
+        }
+        match cheats::load(&self.rom_path) {
+            Ok(Some((file, cheats))) => {
+                self.loaded.cheats = Some((file, cheats.iter().count()));
+                self.cheats = cheats;
+            }
+            Ok(None) => {}
+            Err(err) => self.loaded.cheat_error = Some(err.to_string()),
+        }
+    }
 
-        assert_eq!(chip8.regs[0xF], 1);
+    // Called by the open ROM hotkey, e.g. with a file dialog. None keeps the current ROM.
+    pub fn set_rom_picker(&mut self, picker: impl FnMut() -> Option<String> + 'static) {
+        self.rom_picker = Some(Box::new(picker));
+    }
 
-        chip8.regs[0x1] = 10;
-        chip8.regs[0x2] = 15;
-        chip8.current_instruction = 0x8125;
+    fn switch_rom(&mut self, path: &str) {
+        if let Err(err) = self.open_rom(path) {
+            for hook in &mut self.hooks.rom_open_error {
+                hook(path, &err);
+            }
+            return;
+        }
+        for hook in &mut self.hooks.rom_open {
+            hook(path, &self.loaded);
+        }
+    }
 
-        let opcode = chip8.decode().unwrap();
-        chip8.execute(opcode).unwrap();
+    // A handle for controlling the emulator from another thread while run() is busy on this one.
+    pub fn handle(&mut self) -> Chip8Handle {
+        let handle = self.remote.get_or_insert_with(Remote::new).handle();
+        self.publish();
+        handle
+    }
 
-        assert_eq!(chip8.regs[1], 251);
-        assert_eq!(chip8.regs[0xF], 0);
+    // Called with NNN for every 0NNN that no peripheral handled, instead of applying the machine call policy.
+    // The handler gets the emulator to read and change its state. A second handler replaces the first.
+    pub fn on_machine_call(&mut self, hook: impl FnMut(u16, &mut Chip8) + 'static) {
+        self.hooks.machine_call = Some(Box::new(hook));
     }
 
-    #[test]
-    fn test_opcode_clear() {
-        let mut chip8 = new_headless_chip8();
-        chip8.display = [1; DISPLAY_WIDTH * DISPLAY_HEIGHT];
-        chip8.current_instruction = 0x00E0;
+    // Like the break command, without saving the breakpoint for the ROM.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
 
-        let opcode = chip8.decode().unwrap();
-        chip8.execute(opcode).unwrap();
+    // Peripherals registered first win when their memory ranges overlap or more than one handles a 0NNN call.
+    pub fn add_peripheral(&mut self, peripheral: impl Peripheral + 'static) {
+        self.bus.peripherals.push(Box::new(peripheral));
+    }
 
-        assert_eq!(chip8.display, [0; DISPLAY_WIDTH * DISPLAY_HEIGHT]);
+    pub fn tick_timers(&mut self) {
+        self.core.delay_timer = self.core.delay_timer.saturating_sub(1);
+        self.core.sound_timer = self.core.sound_timer.saturating_sub(1);
     }
 
-    #[test]
-    fn test_opcode_jump() {
-        let mut chip8 = new_headless_chip8();
-        chip8.current_instruction = 0x1234;
+    pub fn pixels(&self) -> &[u8] {
+        &self.core.display
+    }
 
-        let opcode = chip8.decode().unwrap();
-        chip8.execute(opcode).unwrap();
+    pub fn keypad_mut(&mut self) -> &mut Keypad {
+        &mut self.core.keypad
+    }
 
-        assert_eq!(chip8.pc, 0x234);
+    pub fn run(&mut self) -> Result<(), Chip8Error> {
+        let result = self.run_loop();
+        self.running = false;
+        self.publish();
+        // Dropping the channel drops the requests that arrived too late and fails the ones a handle sends
+        // because it saw the emulator running just before, so no handle waits for a state forever. handle()
+        // starts a new channel for the next run.
+        self.remote = None;
+        result
     }
 
-    #[test]
-    fn test_opcode_store_bcd() {
-        let mut chip8 = new_headless_chip8();
-        chip8.i = 0x300;
-        chip8.regs[6] = 137;
-        chip8.current_instruction = 0xF633;
+    fn run_loop(&mut self) -> Result<(), Chip8Error> {
+        while self.running {
+            self.poll_handle()?;
+            let stepping = self.steps_remaining > 0 || self.finish_depth.is_some();
+            if stepping || (!self.paused && (!self.step_mode || self.should_step)) {
+                self.handle_timer();
 
-        let opcode = chip8.decode().unwrap();
-        chip8.execute(opcode).unwrap();
+                if self.check_breakpoints() {
+                    self.pause()?;
+                } else {
+                    self.step()?;
+                    let write_hit = self.check_write_breakpoints();
+                    if self.check_uninit_reads() || write_hit {
+                        self.pause()?;
+                    }
+                    if self.exit_at.contains(&self.core.pc) {
+                        self.exited_at = Some(self.core.pc);
+                        self.running = false;
+                        continue;
+                    }
+                }
 
-        assert_eq!(chip8.memory[0x300], 1);
-        assert_eq!(chip8.memory[0x301], 3);
-        assert_eq!(chip8.memory[0x302], 7);
+                if stepping && self.steps_remaining > 0 {
+                    self.steps_remaining -= 1;
+                    if self.steps_remaining == 0 {
+                        println!("Stopped at {:#06X}  {}", self.core.pc, self.disassemble(self.core.pc));
+                        self.pause()?;
+                    }
+                }
+            }
 
-        chip8.regs[6] = 65;
-        chip8.current_instruction = 0xF633;
+            if self.max_frames.is_some_and(|max| self.frames >= max) {
+                self.running = false;
+                continue;
+            }
+            if let Some(max) = self.max_cycles.filter(|&max| self.cycles >= max) {
+                self.running = false;
+                if self.on_max_cycles == CycleLimitPolicy::Error {
+                    return Err(Chip8Error::CycleLimit(max));
+                }
+                continue;
+            }
 
-        let opcode = chip8.decode().unwrap();
-        chip8.execute(opcode).unwrap();
+            if self.throttle && !self.fast_forward {
+                self.wait(std::time::Duration::from_secs_f64(1_f64 / self.ips as f64))?;
+            }
+
+            if self.step_mode && self.should_step {
+                self.draw()?;
+                self.should_step = false;
+            }
 
-        assert_eq!(chip8.memory[0x300], 0);
-        assert_eq!(chip8.memory[0x301], 6);
-        assert_eq!(chip8.memory[0x302], 5);
+            self.update_audio()?;
 
-        chip8.regs[6] = 4;
-        chip8.current_instruction = 0xF633;
+            if self.last_poll.elapsed() >= POLL_INTERVAL {
+                self.handle_input()?;
+            }
 
-        let opcode = chip8.decode().unwrap();
-        chip8.execute(opcode).unwrap();
+            self.update_heatmap()?;
+            self.reload_config();
 
-        assert_eq!(chip8.memory[0x300], 0);
-        assert_eq!(chip8.memory[0x301], 0);
-        assert_eq!(chip8.memory[0x302], 4);
+            while let Some(line) = self.console.as_ref().and_then(Console::poll) {
+                self.run_command(&line);
+            }
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_opcode_store_regs_detailed() {
-        let mut chip8 = new_headless_chip8();
+    // Sleeps until the next instruction is due, polling the frontend in between at a slow speed.
+    fn wait(&mut self, duration: std::time::Duration) -> Result<(), Chip8Error> {
+        let deadline = std::time::Instant::now() + duration;
+        loop {
+            let now = std::time::Instant::now();
+            if now >= deadline || !self.running {
+                return Ok(());
+            }
+            thread::sleep((deadline - now).min(POLL_INTERVAL));
+            if self.last_poll.elapsed() >= POLL_INTERVAL {
+                self.handle_input()?;
+            }
+        }
+    }
 
-        chip8.regs[0] = 0;
-        chip8.regs[1] = 48;
+    // Polls the frontend on its own clock, so keys are read just as often whatever the emulation speed.
+    fn handle_input(&mut self) -> Result<(), Chip8Error> {
+        self.last_poll = std::time::Instant::now();
+        let Some(input) = &mut self.input else {
+            return Ok(());
+        };
+        let result = input.poll(&mut self.core.keypad)?;
+        let dropped = (result & frontend::OPEN_ROM != 0)
+            .then(|| input.dropped_rom())
+            .flatten();
+
+        if result & frontend::QUIT != 0 {
+            self.running = false;
+            return Ok(());
+        }
 
-        let scratchpad = 0x300;
-        chip8.i = scratchpad;
+        if result & frontend::SUSPEND != 0 && !self.paused {
+            println!("Paused, press pause to continue");
+            self.paused = true;
+            self.draw()?;
+        }
 
-        chip8.current_instruction = 0xF155;
-        let opcode = chip8.decode().unwrap();
-        chip8.execute(opcode).unwrap();
+        // Pausing is for debugging, but whatever paused the game can always be undone
+        if result & frontend::PAUSE != 0 && (self.debug_mode || self.paused) {
+            self.paused = !self.paused;
+            self.draw()?;
+        }
 
-        assert_eq!(chip8.memory[scratchpad as usize], 0, "Memory[I] should be 0");
-        assert_eq!(chip8.memory[(scratchpad + 1) as usize], 48, "Memory[I+1] should be 48");
+        if result & frontend::STEP_MODE != 0 && self.debug_mode {
+            self.step_mode = !self.step_mode;
+            self.draw()?;
+        }
+
+        if result & frontend::SHOULD_STEP != 0 {
+            self.should_step = true;
+        }
+
+        if result & frontend::STEP_MANY != 0 && self.debug_mode {
+            self.steps_remaining = self.step_count;
+        }
+
+        if result & frontend::RESET != 0 {
+            self.reset()?;
+        }
+
+        if let Some(path) = dropped {
+            self.switch_rom(&path);
+        }
+
+        if result & frontend::PICK_ROM != 0 {
+            if let Some(pick) = &mut self.rom_picker {
+                let picked = pick();
+                // the time spent choosing does not count for the timers
+                self.last_timer_update = std::time::Instant::now();
+                if let Some(path) = picked {
+                    self.switch_rom(&path);
+                }
+            }
+        }
+
+        for index in 0..frontend::CHEAT_COUNT {
+            if result & frontend::CHEAT << index != 0 {
+                self.switch_cheat(index, None);
+            }
+        }
+
+        if result & frontend::FAST_FORWARD != 0 {
+            self.fast_forward = !self.fast_forward;
+            // the wall clock timers pick up from now instead of catching up on the skipped time
+            self.last_timer_update = std::time::Instant::now();
+        }
+
+        if result & frontend::NEXT_THEME != 0 {
+            let theme = theme::next(self.palette);
+            for hook in &mut self.hooks.theme_change {
+                hook(theme);
+            }
+            self.set_palette(theme.palette());
+            self.draw()?;
+        }
+
+        if result & frontend::CLOSE_HEATMAP != 0 {
+            if let Some(screen) = &mut self.screen {
+                screen.close_heatmap();
+            }
+        }
+        Ok(())
+    }
+
+    fn poll_handle(&mut self) -> Result<(), Chip8Error> {
+        while let Some(command) = self.remote.as_ref().and_then(|remote| remote.receiver.try_recv().ok()) {
+            match command {
+                HandleCommand::Pause => self.paused = true,
+                HandleCommand::Resume => self.paused = false,
+                HandleCommand::Reset => self.reset()?,
+                HandleCommand::Quit => self.running = false,
+                HandleCommand::Key(key, true) => self.core.keypad.press(key),
+                HandleCommand::Key(key, false) => self.core.keypad.release(key),
+                HandleCommand::State(reply) => {
+                    // what the handle reads afterwards must not be older than the state it got
+                    self.publish();
+                    let _ = reply.send(State {
+                        pc: self.core.pc,
+                        registers: self.registers(),
+                        stack: self.core.stack().to_vec(),
+                        display: self.core.display.to_vec(),
+                        frames: self.frames,
+                        paused: self.paused,
+                    });
+                }
+            }
+        }
+        self.publish();
+        Ok(())
+    }
+
+    fn reload_config(&mut self) {
+        let result = match self.config_watcher.as_mut().and_then(ConfigWatcher::poll) {
+            Some(Ok(config)) => self.apply_config(&config),
+            Some(Err(err)) => Err(err),
+            None => Ok(()),
+        };
+        // A typo in the file must not end the game, it keeps running with the settings it had
+        if let Err(err) = result {
+            for hook in &mut self.hooks.config_error {
+                hook(&err);
+            }
+        }
+    }
+
+    fn publish(&self) {
+        if let Some(remote) = &self.remote {
+            remote.shared.running.store(self.running, Ordering::Relaxed);
+            remote.shared.paused.store(self.paused, Ordering::Relaxed);
+            remote.shared.frames.store(self.frames, Ordering::Relaxed);
+        }
+    }
+
+    fn run_command(&mut self, line: &str) {
+        match line.parse::<Command>() {
+            Ok(Command::Help) => println!("{}", debugger::HELP),
+            Ok(Command::Stack) => println!(
+                "{}",
+                debugger::format_stack(self.core.stack(), &self.core.memory, self.core.pc, &self.symbols)
+            ),
+            Ok(Command::Memory(addr, length)) => {
+                println!("{}", debugger::format_memory(&self.core.memory, addr, length))
+            }
+            Ok(Command::Sprite(addr, rows)) => println!("{}", debugger::format_sprite(&self.core.memory, addr, rows)),
+            Ok(Command::SetMemory(addr, bytes)) => self.set_memory(addr, &bytes),
+            Ok(Command::SetRegister(register, value)) => self.set_register(register, value),
+            Ok(Command::Profile(entries)) => match self.profile_report(entries as usize) {
+                Some(report) => println!("{}", report),
+                None => println!("Profiling is not enabled"),
+            },
+            Ok(Command::Break(condition)) => {
+                self.breakpoints.push(Breakpoint::new(condition));
+                println!("Breakpoint {} set on {}", self.breakpoints.len() - 1, condition);
+                self.save_breakpoints();
+            }
+            Ok(Command::Delete(index)) if (index as usize) < self.breakpoints.len() => {
+                let breakpoint = self.breakpoints.remove(index as usize);
+                println!("Breakpoint {} ({}) deleted", index, breakpoint.condition);
+                self.save_breakpoints();
+            }
+            Ok(Command::Delete(index)) => println!("No breakpoint {}", index),
+            Ok(Command::Step(count)) => {
+                self.step_count = count;
+                self.steps_remaining = count;
+            }
+            Ok(Command::Explain(enabled)) => {
+                self.explain = enabled.unwrap_or(!self.explain);
+                println!("Explain mode {}", if self.explain { "on" } else { "off" });
+            }
+            Ok(Command::Finish) if self.core.sp == 0 => println!("Not inside a subroutine"),
+            Ok(Command::Finish) => self.finish_depth = Some(self.core.sp),
+            Ok(Command::Registers) => println!(
+                "{}",
+                debugger::format_registers(
+                    &self.registers(),
+                    self.core.pc,
+                    self.core.sp,
+                    &self.core.memory,
+                    &self.symbols
+                )
+            ),
+            Ok(Command::Continue) => self.paused = false,
+            Ok(Command::Pause) => {
+                self.paused = true;
+                println!("Paused at {:#06X}  {}", self.core.pc, self.disassemble(self.core.pc));
+            }
+            Ok(Command::Quit) => self.running = false,
+            Ok(Command::Open(path)) => self.switch_rom(&path),
+            Ok(Command::Until(addr)) => {
+                self.breakpoints.push(Breakpoint::temporary(addr));
+                self.paused = false;
+            }
+            Ok(Command::Who(addr)) => self.show_write_history(addr),
+            Ok(Command::Timeline(register, steps)) => match &self.timeline {
+                Some(timeline) => println!("{}", timeline.render(register, steps as usize)),
+                None => println!("The register timeline is only recorded in debug mode"),
+            },
+            Ok(Command::Ignore(index, count)) => match self.breakpoints.get_mut(index as usize) {
+                Some(breakpoint) => {
+                    breakpoint.ignore = count;
+                    println!("Ignoring the next {} hits of breakpoint {}", count, index);
+                    self.save_breakpoints();
+                }
+                None => println!("No breakpoint {}", index),
+            },
+            Ok(Command::ListBreakpoints) => {
+                for (index, breakpoint) in self.breakpoints.iter().enumerate() {
+                    println!("  {}  {}", index, breakpoint);
+                }
+            }
+            Ok(Command::AddCheat(addr, _, _, _)) if addr as usize >= MEMORY_SIZE => {
+                println!("Address out of bounds: {:#06X}", addr)
+            }
+            Ok(Command::AddCheat(addr, value, freeze, description)) => {
+                let index = self.cheats.add(Cheat::new(addr, value, freeze, &description));
+                println!("Cheat {}: {}", index, self.cheats.get(index).unwrap());
+                self.save_cheats();
+            }
+            Ok(Command::SwitchCheat(index, enabled)) => self.switch_cheat(index as usize, enabled),
+            Ok(Command::DeleteCheat(index)) => match self.cheats.remove(index as usize) {
+                Some(cheat) => {
+                    println!("Cheat {} ({}) deleted", index, cheat.description);
+                    self.save_cheats();
+                }
+                None => println!("No cheat {}", index),
+            },
+            Ok(Command::ListCheats) => {
+                for (index, cheat) in self.cheats.iter().enumerate() {
+                    println!("  {}  {}", index, cheat);
+                }
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    fn show_write_history(&self, addr: u16) {
+        let Some(history) = &self.bus.write_history else {
+            println!("Write history is not enabled (start with --write-history <N>)");
+            return;
+        };
+
+        let mut writes = history.writes(addr).peekable();
+        if writes.peek().is_none() {
+            println!("No recorded writes to {:#06X}", addr);
+        }
+        for write in writes {
+            println!(
+                "  {:#04X} written by {:#06X}  {}",
+                write.value,
+                write.pc,
+                self.disassemble(write.pc)
+            );
+        }
+    }
+
+    fn save_breakpoints(&self) {
+        if !self.debug_mode || self.rom_path.is_empty() {
+            return;
+        }
+        if let Err(err) = debugger::save_breakpoints(&self.rom_sha1, &self.breakpoints) {
+            println!("Could not save breakpoints: {}", err);
+        }
+    }
+
+    // None toggles the cheat.
+    fn switch_cheat(&mut self, index: usize, enabled: Option<bool>) {
+        let enabled = enabled.unwrap_or_else(|| self.cheats.get(index).is_some_and(|cheat| !cheat.enabled));
+        match self.cheats.set_enabled(index, enabled) {
+            Some(cheat) => println!("Cheat {}: {}", index, cheat),
+            None => {
+                println!("No cheat {}", index);
+                return;
+            }
+        }
+        self.save_cheats();
+    }
+
+    fn save_cheats(&self) {
+        if self.rom_path.is_empty() {
+            return;
+        }
+        if let Err(err) = cheats::save(&self.rom_path, &self.cheats) {
+            println!("Could not save cheats: {}", err);
+        }
+    }
+
+    fn set_memory(&mut self, addr: u16, bytes: &[u8]) {
+        if !self.paused {
+            println!("Memory can only be edited while paused");
+            return;
+        }
+
+        match self.write_mem(addr, bytes) {
+            Ok(()) => println!("Wrote {} byte(s) at {:#06X}", bytes.len(), addr),
+            Err(_) => println!("Write out of bounds: {:#06X} + {} bytes", addr, bytes.len()),
+        }
+    }
+
+    fn set_register(&mut self, register: Register, value: u16) {
+        if !self.paused {
+            println!("Registers can only be edited while paused");
+            return;
+        }
+
+        let byte_value = u8::try_from(value);
+        match (register, byte_value) {
+            (Register::V(x), Ok(byte)) => self.core.regs[x as usize] = byte,
+            (Register::DelayTimer, Ok(byte)) => self.core.delay_timer = byte,
+            (Register::SoundTimer, Ok(byte)) => self.core.sound_timer = byte,
+            (Register::I, _) => self.core.i = value,
+            (Register::Pc, _) if (value as usize) < MEMORY_SIZE - 1 => {
+                self.core.pc = value;
+                self.current_instruction = self.instruction_at(value).unwrap_or_default();
+                println!("PC = {:#06X}  {}", value, self.disassemble(value));
+                return;
+            }
+            (Register::Pc, _) => {
+                println!("PC out of bounds: {:#06X}", value);
+                return;
+            }
+            (_, Err(_)) => {
+                println!("Value does not fit in a byte: {:#X}", value);
+                return;
+            }
+        }
+        println!("{} = {:#X}", register, value);
+    }
+
+    fn pause(&mut self) -> Result<(), Chip8Error> {
+        self.paused = true;
+        self.steps_remaining = 0;
+        self.finish_depth = None;
+        self.draw()
+    }
+
+    fn check_breakpoints(&mut self) -> bool {
+        if self.skip_breakpoint {
+            self.skip_breakpoint = false;
+            return false;
+        }
+
+        let pc = self.core.pc;
+        // the PC may be past the end here, step reports that once the breakpoints are checked
+        let opcode = self
+            .instruction_at(pc)
+            .and_then(|instruction| Opcode::decode(instruction).ok());
+        let mut hit = None;
+        for (index, breakpoint) in self.breakpoints.iter_mut().enumerate() {
+            if breakpoint.condition.matches(pc, opcode.as_ref()) && breakpoint.hit() && hit.is_none() {
+                hit = Some(index);
+            }
+        }
+
+        if let Some(index) = hit {
+            println!(
+                "Breakpoint {} ({}) hit at {:#06X}  {}",
+                index,
+                self.breakpoints[index].condition,
+                pc,
+                self.disassemble(pc)
+            );
+            self.skip_breakpoint = true;
+
+            if self.breakpoints[index].temporary {
+                self.breakpoints.remove(index);
+            }
+        }
+        hit.is_some()
+    }
+
+    fn check_write_breakpoints(&mut self) -> bool {
+        let mut hit = None;
+        for (index, breakpoint) in self.breakpoints.iter_mut().enumerate() {
+            let written = self
+                .bus
+                .writes
+                .iter()
+                .find(|&&addr| breakpoint.condition.matches_write(addr));
+            if let Some(&addr) = written {
+                if breakpoint.hit() && hit.is_none() {
+                    hit = Some((index, addr));
+                }
+            }
+        }
+
+        if let Some((index, addr)) = hit {
+            let pc = self.core.pc.wrapping_sub(2);
+            println!(
+                "Breakpoint {} ({}) hit: {:#06X} written by {:#06X}  {}",
+                index,
+                self.breakpoints[index].condition,
+                addr,
+                pc,
+                self.disassemble(pc)
+            );
+        }
+        hit.is_some()
+    }
+
+    fn check_uninit_reads(&mut self) -> bool {
+        for &(addr, pc) in &self.bus.uninit_reads {
+            let message = format!(
+                "{:#06X}  {} read uninitialized memory at {:#06X}",
+                pc,
+                self.disassemble(pc),
+                addr
+            );
+            match self.on_uninit_read {
+                UninitPolicy::Break => println!("{}", message),
+                _ => eprintln!("Warning: {}", message),
+            }
+        }
+        self.on_uninit_read == UninitPolicy::Break && !self.bus.uninit_reads.is_empty()
+    }
+
+    fn update_heatmap(&mut self) -> Result<(), Chip8Error> {
+        let interval = std::time::Duration::from_secs_f64(1.0 / HEATMAP_FREQ as f64);
+        if self.last_heatmap_update.elapsed() < interval {
+            return Ok(());
+        }
+        self.last_heatmap_update = std::time::Instant::now();
+
+        if let (Some(screen), Some(profiler)) = (&mut self.screen, &self.profiler) {
+            screen.draw_heatmap(profiler.pc_counts())?;
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self) -> Result<(), Chip8Error> {
+        if let Some(screen) = &mut self.screen {
+            screen.draw(&self.core.display)?;
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<(), Chip8Error> {
+        self.core.display = [0; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+        self.core.regs = [0; NUMBER_REGS];
+
+        self.core.pc = PROGRAM_START as u16;
+        self.core.i = 0x0;
+        self.current_instruction = 0x0000;
+
+        self.core.delay_timer = 0;
+        self.core.sound_timer = 0;
+
+        self.running = true;
+        self.paused = self.debug_mode;
+        self.step_mode = false;
+        self.should_step = false;
+
+        self.draw()?;
+        Ok(())
+    }
+
+    fn fetch(&mut self) -> Result<(), Chip8Error> {
+        let pc = self.core.pc;
+        self.current_instruction = self.core.fetch()?;
+        self.bus.note_read(pc, pc);
+        self.bus.note_read(pc + 1, pc);
+        Ok(())
+    }
+
+    fn disassemble(&self, addr: u16) -> String {
+        debugger::disassemble(&self.core.memory, addr, &self.symbols)
+    }
+
+    // None when the instruction would reach past the end of memory.
+    fn instruction_at(&self, addr: u16) -> Option<u16> {
+        let high_byte = *self.core.memory.get(addr as usize)? as u16;
+        let low_byte = *self.core.memory.get(addr as usize + 1)? as u16;
+        Some((high_byte << 8) | low_byte)
+    }
+
+    fn cycle(&mut self) -> Result<(), Chip8Error> {
+        let pc = self.core.pc;
+        let before = self.registers();
+
+        self.bus.writes.clear();
+        self.bus.uninit_reads.clear();
+        self.fetch()?;
+        let opcode = match self.decode() {
+            Ok(opcode) => opcode,
+            Err(err) => {
+                return match self.on_invalid {
+                    InvalidPolicy::Halt => Err(err),
+                    InvalidPolicy::Warn => {
+                        eprintln!(
+                            "Skipping invalid opcode {:#06X} at {:#05X}",
+                            self.current_instruction, pc
+                        );
+                        Ok(())
+                    }
+                    InvalidPolicy::Nop => Ok(()),
+                }
+            }
+        };
+        if self.execute(opcode)?.halted {
+            self.handle_halt(pc)?;
+        } else {
+            self.halted_at = None;
+        }
+
+        if self.finish_depth.is_some_and(|depth| self.core.sp < depth) {
+            println!("Returned to {:#06X}  {}", self.core.pc, self.disassemble(self.core.pc));
+            self.pause()?;
+        }
+
+        self.cycles += 1;
+        let regs = self.registers();
+        if let Some(timeline) = &mut self.timeline {
+            timeline.record(self.cycles, pc, regs);
+        }
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(pc, &opcode);
+        }
+
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(pc);
+        }
+
+        if self.explain && (self.steps_remaining > 0 || self.step_mode) {
+            self.explain_step(pc, &opcode, &before, &regs);
+        }
+
+        for hook in &mut self.hooks.instruction {
+            hook(pc, &opcode);
+        }
+
+        self.recent.record(pc, self.current_instruction, before, regs);
+        if let Some(tracer) = &mut self.tracer {
+            tracer.record(
+                pc,
+                self.current_instruction,
+                &opcode.disassemble(&self.symbols),
+                &before,
+                &regs,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn explain_step(&self, pc: u16, opcode: &Opcode, before: &Registers, after: &Registers) {
+        print!("{}", self.explanation(pc, opcode, before, after));
+    }
+
+    fn explanation(&self, pc: u16, opcode: &Opcode, before: &Registers, after: &Registers) -> String {
+        let mut lines = vec![
+            format!("{:#06X}  {}", pc, opcode.disassemble(&self.symbols)),
+            format!("  {}", opcode.explain()),
+        ];
+        for (reg, (old, new)) in before.v.iter().zip(after.v.iter()).enumerate() {
+            if old != new {
+                lines.push(format!("  V{:X}: {:#04X} → {:#04X}", reg, old, new));
+            }
+        }
+        if before.i != after.i {
+            lines.push(format!("  I: {:#05X} → {:#05X}", before.i, after.i));
+        }
+        if before.delay_timer != after.delay_timer {
+            lines.push(format!(
+                "  DT: {:#04X} → {:#04X}",
+                before.delay_timer, after.delay_timer
+            ));
+        }
+        if before.sound_timer != after.sound_timer {
+            lines.push(format!(
+                "  ST: {:#04X} → {:#04X}",
+                before.sound_timer, after.sound_timer
+            ));
+        }
+        for &addr in &self.bus.writes {
+            lines.push(format!("  [{:#05X}] ← {:#04X}", addr, self.core.memory[addr as usize]));
+        }
+        if self.core.pc != pc.wrapping_add(2) {
+            lines.push(format!("  PC → {:#06X}", self.core.pc));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    fn handle_halt(&mut self, pc: u16) -> Result<(), Chip8Error> {
+        if self.halted_at == Some(pc) {
+            return Ok(());
+        }
+        self.halted_at = Some(pc);
+        for hook in &mut self.hooks.halt {
+            hook(pc);
+        }
+
+        match self.on_halt {
+            HaltPolicy::Continue => {}
+            HaltPolicy::Pause => self.pause()?,
+            HaltPolicy::Exit => self.running = false,
+        }
+        Ok(())
+    }
+
+    fn registers(&self) -> Registers {
+        Registers {
+            v: self.core.regs,
+            i: self.core.i,
+            delay_timer: self.core.delay_timer,
+            sound_timer: self.core.sound_timer,
+        }
+    }
+
+    fn decode(&self) -> Result<Opcode, Chip8Error> {
+        Opcode::decode(self.current_instruction)
+    }
+
+    // Runs the instruction on the core, then shows what it drew and hands a 0NNN call on.
+    fn execute(&mut self, opcode: Opcode) -> Result<StepResult, Chip8Error> {
+        self.bus.pc = self.core.pc.wrapping_sub(2);
+        let result = self.core.execute(opcode, &mut self.bus)?;
+        self.display_changed |= result.display_changed;
+        self.waiting_for_key |= result.waiting_for_key;
+        if result.display_changed {
+            self.draw()?;
+        }
+        if let Some(nnn) = self.bus.machine_call.take() {
+            self.machine_call(nnn)?;
+        }
+        Ok(result)
+    }
+
+    // Tells the audio frontend when the sound timer starts or stops and returns the new state on a change.
+    fn update_audio(&mut self) -> Result<Option<bool>, Chip8Error> {
+        let beeping = self.core.sound_timer > 0;
+        if beeping == self.beeping {
+            return Ok(None);
+        }
+        self.beeping = beeping;
+        if let Some(audio) = &mut self.audio {
+            audio.set_beeping(beeping)?;
+        }
+        if let Some(input) = &mut self.input {
+            input.set_rumble(beeping);
+        }
+        for hook in &mut self.hooks.sound_change {
+            hook(beeping);
+        }
+        Ok(Some(beeping))
+    }
+
+    fn handle_timer(&mut self) {
+        let ticks = if self.throttle && !self.fast_forward {
+            self.timer_60_hz()
+        } else {
+            self.frame_cycles += 1;
+            if self.frame_cycles >= self.cycles_per_frame() {
+                self.frame_cycles = 0;
+                1
+            } else {
+                0
+            }
+        };
+        self.frames += ticks as u64;
+        if ticks > 0 {
+            self.core.keypad.end_frame();
+            self.end_frame();
+            if self.core.delay_timer > 0 {
+                self.core.delay_timer -= ticks.min(self.core.delay_timer as u32) as u8;
+            }
+            if self.core.sound_timer > 0 {
+                self.core.sound_timer -= ticks.min(self.core.sound_timer as u32) as u8;
+            }
+        }
+    }
+
+    fn cycles_per_frame(&self) -> u32 {
+        (self.ips / TIMER_FREQ as u32).max(1)
+    }
+
+    fn timer_60_hz(&mut self) -> u32 {
+        use std::time::{Duration, Instant};
+        let now = Instant::now();
+        let interval = Duration::from_secs_f64(1.0 / TIMER_FREQ as f64);
+
+        let mut ticks = 0u32;
+        while now.duration_since(self.last_timer_update) >= interval {
+            self.last_timer_update += interval;
+            ticks = ticks.saturating_add(1);
+        }
+        ticks
+    }
+
+    // Offers 0NNN to the peripherals, then to the handler and finally applies the policy. Peripherals and
+    // handler get the emulator to themselves meanwhile.
+    fn machine_call(&mut self, nnn: u16) -> Result<(), Chip8Error> {
+        let mut peripherals = std::mem::take(&mut self.bus.peripherals);
+        let handled = peripherals
+            .iter_mut()
+            .any(|peripheral| peripheral.machine_call(nnn, self));
+        peripherals.append(&mut self.bus.peripherals);
+        self.bus.peripherals = peripherals;
+        if handled {
+            return Ok(());
+        }
+
+        if let Some(mut hook) = self.hooks.machine_call.take() {
+            hook(nnn, self);
+            // unless the handler registered a new one
+            self.hooks.machine_call.get_or_insert(hook);
+            return Ok(());
+        }
+
+        match self.on_machine_call {
+            MachineCallPolicy::Ignore => Ok(()),
+            MachineCallPolicy::Warn => {
+                eprintln!(
+                    "Ignoring machine code call to {:#05X} at {:#05X}",
+                    nnn,
+                    self.core.pc.wrapping_sub(2)
+                );
+                Ok(())
+            }
+            MachineCallPolicy::Halt => Err(Chip8Error::InvalidOpcode(self.current_instruction)),
+        }
+    }
+}
+
+// The peripherals and debugging tools between the core and its memory, and the random numbers.
+struct Chip8Bus {
+    // of the executing instruction, for the write history and the uninitialized reads
+    pc: u16,
+    peripherals: Vec<Box<dyn Peripheral>>,
+    // of the executing instruction
+    writes: Vec<u16>,
+    write_history: Option<WriteHistory>,
+    initialized: Option<InitializedMemory>,
+    uninit_reads: Vec<(u16, u16)>,
+    rng: StdRng,
+    // left for Chip8 to offer to the peripherals and the handler, which get the whole emulator
+    machine_call: Option<u16>,
+}
+
+impl Chip8Bus {
+    fn note_read(&mut self, addr: u16, pc: u16) {
+        if let Some(initialized) = &mut self.initialized {
+            if initialized.read(addr as usize) {
+                self.uninit_reads.push((addr, pc));
+            }
+        }
+    }
+
+    fn peripheral_at(&mut self, addr: u16) -> Option<&mut Box<dyn Peripheral>> {
+        self.peripherals
+            .iter_mut()
+            .find(|peripheral| peripheral.memory_range().is_some_and(|range| range.contains(&addr)))
+    }
+}
+
+// Addresses past the end of memory wrap around to the start, as I only has 12 bits on the COSMAC VIP.
+// Peripherals see the full 16-bit address, only memory wraps around at 4 KB.
+impl Bus for Chip8Bus {
+    fn read(&mut self, memory: &Memory, addr: u16) -> u8 {
+        if let Some(peripheral) = self.peripheral_at(addr) {
+            return peripheral.read(addr);
+        }
+        let addr = addr % MEMORY_SIZE as u16;
+        self.note_read(addr, self.pc);
+        memory[addr as usize]
+    }
+
+    fn write(&mut self, memory: &mut Memory, addr: u16, value: u8) {
+        if let Some(peripheral) = self.peripheral_at(addr) {
+            peripheral.write(addr, value);
+            return;
+        }
+
+        let addr = addr % MEMORY_SIZE as u16;
+        self.writes.push(addr);
+        if let Some(history) = &mut self.write_history {
+            history.record(addr, self.pc, value);
+        }
+        memory[addr as usize] = value;
+        if let Some(initialized) = &mut self.initialized {
+            initialized.mark(addr as usize..addr as usize + 1);
+        }
+    }
+
+    fn random(&mut self) -> u8 {
+        self.rng.random()
+    }
+
+    fn machine_call(&mut self, nnn: u16) {
+        self.machine_call = Some(nnn);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_headless_chip8() -> Chip8 {
+        Chip8::builder().seed(0).build().unwrap()
+    }
+
+    #[test]
+    fn test_opcode_add() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.regs[0xF] = 200;
+        chip8.current_instruction = 0x7F64;
+
+        let opcode = chip8.decode().unwrap();
+        chip8.execute(opcode).unwrap();
+
+        // no overflow in this instruction
+        assert_eq!(chip8.core.regs[0xF], 44);
+
+        chip8.core.regs[0] = 20;
+        chip8.current_instruction = 0x7064;
+
+        let opcode = chip8.decode().unwrap();
+        chip8.execute(opcode).unwrap();
+
+        assert_eq!(chip8.core.regs[0], 120);
+    }
+
+    #[test]
+    fn test_opcode_sub_y() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.regs[0xF] = 10;
+        chip8.current_instruction = 0x8FF7;
+
+        let opcode = chip8.decode().unwrap();
+        chip8.execute(opcode).unwrap();
+
+        assert_eq!(chip8.core.regs[0xF], 1);
+
+        chip8.core.regs[0x1] = 10;
+        chip8.core.regs[0x2] = 15;
+        chip8.current_instruction = 0x8125;
+
+        let opcode = chip8.decode().unwrap();
+        chip8.execute(opcode).unwrap();
+
+        assert_eq!(chip8.core.regs[1], 251);
+        assert_eq!(chip8.core.regs[0xF], 0);
+    }
+
+    #[test]
+    fn test_opcode_clear() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.display = [1; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+        chip8.current_instruction = 0x00E0;
+
+        let opcode = chip8.decode().unwrap();
+        chip8.execute(opcode).unwrap();
+
+        assert_eq!(chip8.core.display, [0; DISPLAY_WIDTH * DISPLAY_HEIGHT]);
+    }
+
+    #[test]
+    fn test_opcode_jump() {
+        let mut chip8 = new_headless_chip8();
+        chip8.current_instruction = 0x1234;
+
+        let opcode = chip8.decode().unwrap();
+        chip8.execute(opcode).unwrap();
+
+        assert_eq!(chip8.core.pc, 0x234);
+    }
+
+    #[test]
+    fn test_opcode_store_bcd() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.i = 0x300;
+        chip8.core.regs[6] = 137;
+        chip8.current_instruction = 0xF633;
+
+        let opcode = chip8.decode().unwrap();
+        chip8.execute(opcode).unwrap();
+
+        assert_eq!(chip8.core.memory[0x300], 1);
+        assert_eq!(chip8.core.memory[0x301], 3);
+        assert_eq!(chip8.core.memory[0x302], 7);
+
+        chip8.core.regs[6] = 65;
+        chip8.current_instruction = 0xF633;
+
+        let opcode = chip8.decode().unwrap();
+        chip8.execute(opcode).unwrap();
+
+        assert_eq!(chip8.core.memory[0x300], 0);
+        assert_eq!(chip8.core.memory[0x301], 6);
+        assert_eq!(chip8.core.memory[0x302], 5);
+
+        chip8.core.regs[6] = 4;
+        chip8.current_instruction = 0xF633;
+
+        let opcode = chip8.decode().unwrap();
+        chip8.execute(opcode).unwrap();
+
+        assert_eq!(chip8.core.memory[0x300], 0);
+        assert_eq!(chip8.core.memory[0x301], 0);
+        assert_eq!(chip8.core.memory[0x302], 4);
+    }
 
-        chip8.regs[0] = 0xFF;
-        chip8.regs[1] = 0xFF;
+    #[test]
+    fn test_opcode_store_regs_detailed() {
+        let mut chip8 = new_headless_chip8();
+
+        chip8.core.regs[0] = 0;
+        chip8.core.regs[1] = 48;
+
+        let scratchpad = 0x300;
+        chip8.core.i = scratchpad;
+
+        chip8.current_instruction = 0xF155;
+        let opcode = chip8.decode().unwrap();
+        chip8.execute(opcode).unwrap();
+
+        assert_eq!(chip8.core.memory[scratchpad as usize], 0, "Memory[I] should be 0");
+        assert_eq!(
+            chip8.core.memory[(scratchpad + 1) as usize],
+            48,
+            "Memory[I+1] should be 48"
+        );
+
+        chip8.core.regs[0] = 0xFF;
+        chip8.core.regs[1] = 0xFF;
 
-        chip8.i = scratchpad;
+        chip8.core.i = scratchpad;
         chip8.current_instruction = 0xF065;
         let opcode = chip8.decode().unwrap();
         chip8.execute(opcode).unwrap();
 
-        let v1_temp = chip8.regs[0];
+        let v1_temp = chip8.core.regs[0];
 
-        chip8.i = scratchpad + 1;
+        chip8.core.i = scratchpad + 1;
         chip8.current_instruction = 0xF065;
         let opcode = chip8.decode().unwrap();
         chip8.execute(opcode).unwrap();
 
-        assert_eq!(chip8.regs[0], 48, "v0 should be 48 after loading from scratchpad+1");
+        assert_eq!(
+            chip8.core.regs[0], 48,
+            "v0 should be 48 after loading from scratchpad+1"
+        );
         assert_eq!(v1_temp, 0, "v1_temp should be 0 after loading from scratchpad");
     }
 
     #[test]
     fn test_opcode_load_regs() {
         let mut chip8 = new_headless_chip8();
-        chip8.i = 0x300;
+        chip8.core.i = 0x300;
         for i in 0..=5 {
-            chip8.memory[0x300 + i] = i as u8 * 10;
+            chip8.core.memory[0x300 + i] = i as u8 * 10;
         }
         chip8.current_instruction = 0xF565;
 
@@ -680,7 +1837,852 @@ mod tests {
         chip8.execute(opcode).unwrap();
 
         for i in 0..=5 {
-            assert_eq!(chip8.regs[i], i as u8 * 10);
+            assert_eq!(chip8.core.regs[i], i as u8 * 10);
+        }
+    }
+
+    #[test]
+    fn test_stack_view_resolves_call_sites() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.memory[0x200] = 0x23;
+        chip8.core.memory[0x201] = 0x00;
+        chip8.fetch().unwrap();
+
+        let opcode = chip8.decode().unwrap();
+        chip8.execute(opcode).unwrap();
+
+        let view = debugger::format_stack(chip8.core.stack(), &chip8.core.memory, chip8.core.pc, &chip8.symbols);
+        assert!(view.contains("#1  0x0200  CALL 0x300"));
+        assert!(view.contains("(returns to 0x0202)"));
+    }
+
+    #[test]
+    fn test_sprite_view_renders_font() {
+        let chip8 = new_headless_chip8();
+        let view = debugger::format_sprite(&chip8.core.memory, FONT_OFFSET as u16, 5);
+
+        let expected = "0x0050  F0  ####....\n\
+                        0x0051  90  #..#....\n\
+                        0x0052  90  #..#....\n\
+                        0x0053  90  #..#....\n\
+                        0x0054  F0  ####....";
+        assert_eq!(view, expected);
+    }
+
+    #[test]
+    fn test_set_pc_refetches_instruction() {
+        let mut chip8 = new_headless_chip8();
+        chip8.paused = true;
+        chip8.core.memory[0x240] = 0x6A;
+        chip8.core.memory[0x241] = 0x02;
+
+        chip8.run_command("set pc 0x240");
+        chip8.run_command("set vb 0x10");
+
+        assert_eq!(chip8.core.pc, 0x240);
+        assert_eq!(chip8.current_instruction, 0x6A02);
+        assert_eq!(chip8.core.regs[0xB], 0x10);
+    }
+
+    #[test]
+    fn test_frame_hook_sees_every_frame() {
+        let mut chip8 = Chip8::builder()
+            .rom_bytes(&[0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04])
+            .build()
+            .unwrap();
+        let seen = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let hook_seen = seen.clone();
+        chip8.on_frame(move |frame, pixels| hook_seen.borrow_mut().push((frame, pixels.iter().sum::<u8>())));
+
+        chip8.run_frame().unwrap();
+        chip8.run_frame().unwrap();
+        assert_eq!(*seen.borrow(), [(1, 14), (2, 14)]);
+    }
+
+    #[test]
+    fn test_cheats_write_memory_at_the_end_of_frames() {
+        let mut chip8 = new_headless_chip8();
+        // a loop counting V0 down and storing it at 0x300
+        chip8.core.memory[0x200..0x208].copy_from_slice(&[0xA3, 0x00, 0x70, 0xFF, 0xF0, 0x55, 0x12, 0x02]);
+        chip8.run_command("freeze 0x300 0x09 lives");
+        chip8.run_command("poke 0x301 0x05");
+
+        chip8.run_frame().unwrap();
+        assert_eq!(chip8.core.memory[0x300..0x302], [0x09, 0x05]);
+        chip8.core.memory[0x301] = 0;
+        chip8.run_frame().unwrap();
+        assert_eq!(chip8.core.memory[0x300..0x302], [0x09, 0x00]);
+
+        chip8.run_command("cheat 0");
+        chip8.run_frame().unwrap();
+        assert_ne!(chip8.core.memory[0x300], 0x09);
+        chip8.run_command("cheat 1 delete");
+        assert_eq!(chip8.cheats.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_break_on_opcode_type() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.memory[0x200..0x204].copy_from_slice(&[0x60, 0x01, 0xD0, 0x15]);
+        chip8.run_command("break dxyn");
+
+        assert!(!chip8.check_breakpoints());
+        chip8.cycle().unwrap();
+
+        assert!(chip8.check_breakpoints());
+        assert!(
+            !chip8.check_breakpoints(),
+            "resuming must execute the instruction first"
+        );
+    }
+
+    #[test]
+    fn test_run_reports_pc_past_the_end_with_breakpoints() {
+        // LD V0, 0xFF; JP V0, 0xF00 lands on the last byte of memory
+        let mut chip8 = Chip8::builder().rom_bytes(&[0x60, 0xFF, 0xBF, 0x00]).build().unwrap();
+        chip8.set_throttle(false);
+        chip8.run_command("break dxyn");
+
+        assert!(matches!(chip8.run(), Err(Chip8Error::PCOutOfBounds(0xFFF))));
+    }
+
+    #[test]
+    fn test_break_on_write_range() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.memory[0x200..0x204].copy_from_slice(&[0xF1, 0x55, 0xF1, 0x55]);
+        chip8.run_command("break write 0x301-0x3FF");
+
+        chip8.core.i = 0x2F0;
+        chip8.cycle().unwrap();
+        assert!(!chip8.check_write_breakpoints());
+
+        chip8.core.i = 0x300;
+        chip8.cycle().unwrap();
+        assert!(chip8.check_write_breakpoints());
+    }
+
+    #[test]
+    fn test_halt_detection_exits() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.memory[0x200..0x204].copy_from_slice(&[0x12, 0x02, 0x12, 0x02]);
+        chip8.set_halt_policy(HaltPolicy::Exit);
+        let halts = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let recorded = std::rc::Rc::clone(&halts);
+        chip8.on_halt(move |pc| recorded.borrow_mut().push(pc));
+
+        chip8.cycle().unwrap();
+        assert!(chip8.running);
+
+        chip8.cycle().unwrap();
+        assert!(!chip8.running);
+        assert_eq!(chip8.halted_at, Some(0x202));
+        assert_eq!(*halts.borrow(), [0x202]);
+    }
+
+    #[test]
+    fn test_exit_at_stops_the_run() {
+        // LD V0, 1; JP 0x206; JP 0x204; JP 0x206
+        let mut chip8 = Chip8::builder()
+            .rom_bytes(&[0x60, 0x01, 0x12, 0x06, 0x12, 0x04, 0x12, 0x06])
+            .build()
+            .unwrap();
+        chip8.set_throttle(false);
+        chip8.set_max_frames(Some(10));
+        chip8.set_exit_at(vec![0x204, 0x206]);
+
+        chip8.run().unwrap();
+        assert_eq!(chip8.exited_at(), Some(0x206));
+        assert_eq!(chip8.frame_count(), 0);
+    }
+
+    #[test]
+    fn test_max_cycles_policies() {
+        // JP 0x200
+        let mut chip8 = Chip8::builder().rom_bytes(&[0x12, 0x00]).build().unwrap();
+        chip8.set_throttle(false);
+        chip8.set_max_cycles(Some(100), CycleLimitPolicy::Error);
+        assert!(matches!(chip8.run(), Err(Chip8Error::CycleLimit(100))));
+        assert_eq!(chip8.cycles, 100);
+
+        chip8.running = true;
+        chip8.set_max_cycles(Some(150), CycleLimitPolicy::Exit);
+        chip8.run().unwrap();
+        assert_eq!(chip8.cycles, 150);
+    }
+
+    #[test]
+    fn test_memory_past_the_end_wraps() {
+        let mut chip8 = new_headless_chip8();
+        // LD [I], V1; ADD I, V2; LD V1, [I]
+        chip8.core.memory[0x200..0x206].copy_from_slice(&[0xF1, 0x55, 0xF2, 0x1E, 0xF1, 0x65]);
+        chip8.core.i = 0xFFF;
+        chip8.core.regs[..3].copy_from_slice(&[7, 8, 0xFF]);
+
+        chip8.cycle().unwrap();
+        assert_eq!((chip8.core.memory[0xFFF], chip8.core.memory[0x000]), (7, 8));
+        chip8.core.i = 0xFFFF;
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.core.i, 0xFE);
+        chip8.core.i = 0x1FFF;
+        chip8.cycle().unwrap();
+        assert_eq!(&chip8.core.regs[..2], &[7, 8]);
+    }
+
+    #[test]
+    fn test_invalid_opcode_policy() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.memory[0x200..0x204].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]);
+
+        assert!(matches!(chip8.cycle(), Err(Chip8Error::InvalidOpcode(0xFFFF))));
+
+        chip8.set_invalid_policy(InvalidPolicy::Nop);
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.core.pc, 0x204);
+    }
+
+    #[test]
+    fn test_finish_stops_after_return() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.memory[0x200..0x202].copy_from_slice(&[0x23, 0x00]);
+        chip8.core.memory[0x300..0x306].copy_from_slice(&[0x23, 0x10, 0x60, 0x01, 0x00, 0xEE]);
+        chip8.core.memory[0x310..0x312].copy_from_slice(&[0x00, 0xEE]);
+
+        chip8.cycle().unwrap();
+        chip8.run_command("finish");
+
+        for _ in 0..3 {
+            chip8.cycle().unwrap();
+            assert!(!chip8.paused, "returning from a nested call must not stop");
+        }
+        chip8.cycle().unwrap();
+        assert!(chip8.paused);
+        assert_eq!(chip8.core.pc, 0x202);
+    }
+
+    #[test]
+    fn test_breakpoint_ignore_count() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.memory[0x200..0x202].copy_from_slice(&[0x12, 0x00]);
+        chip8.run_command("break 0x200");
+        chip8.run_command("ignore 0 2");
+
+        assert!(!chip8.check_breakpoints());
+        assert!(!chip8.check_breakpoints());
+        assert!(chip8.check_breakpoints());
+        assert_eq!(chip8.breakpoints[0].hits, 3);
+    }
+
+    #[test]
+    fn test_until_removes_temporary_breakpoint() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.memory[0x200..0x206].copy_from_slice(&[0x60, 0x01, 0x60, 0x02, 0x60, 0x03]);
+        chip8.paused = true;
+        chip8.run_command("until 0x204");
+        assert!(!chip8.paused);
+
+        while !chip8.check_breakpoints() {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.core.pc, 0x204);
+        assert!(chip8.breakpoints.is_empty());
+    }
+
+    #[test]
+    fn test_write_history_keeps_newest_writes() {
+        let mut chip8 = new_headless_chip8();
+        chip8.enable_write_history(2);
+        chip8.core.memory[0x200..0x206].copy_from_slice(&[0xF0, 0x55, 0xF0, 0x55, 0xF0, 0x55]);
+        chip8.core.i = 0x300;
+
+        for value in 1..=3 {
+            chip8.core.regs[0] = value;
+            chip8.cycle().unwrap();
+        }
+
+        let history = chip8.bus.write_history.as_ref().unwrap();
+        let writes: Vec<(u16, u8)> = history.writes(0x300).map(|w| (w.pc, w.value)).collect();
+        assert_eq!(writes, vec![(0x204, 3), (0x202, 2)]);
+    }
+
+    #[test]
+    fn test_open_rom_replaces_the_program() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.memory[0x200..0x206].copy_from_slice(&[0x22, 0x04, 0x00, 0x00, 0x60, 0x07]);
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.core.regs[0x0], 0x07);
+
+        let path = std::env::temp_dir().join("chip8-open-rom-test.ch8");
+        std::fs::write(&path, [0x61, 0x02]).unwrap();
+        chip8.open_rom(path.to_str().unwrap()).unwrap();
+        assert_eq!(chip8.core.regs[0x0], 0);
+        assert!(chip8.core.stack().is_empty());
+        assert_eq!(chip8.core.memory[0x200..0x206], [0x61, 0x02, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(chip8.rom_size, 2);
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.core.regs[0x1], 0x02);
+
+        std::fs::write(&path, vec![0; MEMORY_SIZE]).unwrap();
+        assert!(chip8.open_rom(path.to_str().unwrap()).is_err());
+        assert_eq!(chip8.core.regs[0x1], 0x02);
+    }
+
+    #[test]
+    fn test_database_sets_the_quirks_of_known_roms() {
+        let known = [0x12, 0x00];
+        let line = format!("{} originalChip8 vip Jump", crate::database::sha1(&known));
+        let mut chip8 = Chip8::builder()
+            .rom_bytes(&known)
+            .quirks(Quirks {
+                jump_vx: true,
+                ..Quirks::default()
+            })
+            .database(Database::parse(&line).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(chip8.core.quirks, Quirks::cosmac_vip());
+        // told to the caller instead of printed
+        assert_eq!(chip8.loaded_rom().recognized.as_ref().unwrap().title, "Jump");
+
+        let path = std::env::temp_dir().join("chip8-database-test.ch8");
+        std::fs::write(&path, [0x12, 0x02]).unwrap();
+        chip8.open_rom(path.to_str().unwrap()).unwrap();
+        assert!(chip8.core.quirks.jump_vx && !chip8.core.quirks.vf_reset);
+        assert_eq!(chip8.loaded_rom(), &LoadedRom::default());
+    }
+
+    #[test]
+    fn test_crash_dump_lists_last_steps() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.memory[0x200..0x206].copy_from_slice(&[0x60, 0x05, 0x70, 0x01, 0xFF, 0xFF]);
+
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        let err = chip8.cycle().unwrap_err();
+
+        let path = std::env::temp_dir().join("chip8-crash-test.log");
+        let path = path.to_str().unwrap();
+        chip8.dump_recent_steps(path, &err).unwrap();
+        let dump = std::fs::read_to_string(path).unwrap();
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("0200  6005  LD V0, 0x05"));
+        assert!(lines[1].ends_with("V0=0x06"));
+        assert!(lines[2].starts_with("Error: "));
+    }
+
+    #[test]
+    fn test_uninitialized_read_reported_once() {
+        let mut chip8 = new_headless_chip8();
+        chip8.rom_size = 8;
+        chip8.core.memory[0x200..0x208].copy_from_slice(&[0xA3, 0x00, 0xF1, 0x65, 0xF0, 0x55, 0xF1, 0x65]);
+        chip8.set_uninit_policy(UninitPolicy::Break);
+
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.bus.uninit_reads, vec![(0x300, 0x202), (0x301, 0x202)]);
+        assert!(chip8.check_uninit_reads());
+
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        assert!(chip8.bus.uninit_reads.is_empty());
+    }
+
+    #[test]
+    fn test_timers_are_set_to_the_value_of_vx() {
+        let mut chip8 = new_headless_chip8();
+        // LD V3, 0x3C; LD DT, V3; LD V4, 0x05; LD ST, V4
+        chip8.core.memory[0x200..0x208].copy_from_slice(&[0x63, 0x3C, 0xF3, 0x15, 0x64, 0x05, 0xF4, 0x18]);
+        for _ in 0..4 {
+            chip8.cycle().unwrap();
+        }
+
+        // not the register numbers 3 and 4
+        assert_eq!(chip8.core.delay_timer, 0x3C);
+        assert_eq!(chip8.core.sound_timer, 0x05);
+    }
+
+    #[test]
+    fn test_explanation_lists_what_changed() {
+        let mut chip8 = new_headless_chip8();
+        // LD V0, 0xF0; LD V1, 0x20; LD I, 0x300; ADD V0, V1; LD [I], V1; JP 0x200
+        chip8.core.memory[0x200..0x20C]
+            .copy_from_slice(&[0x60, 0xF0, 0x61, 0x20, 0xA3, 0x00, 0x80, 0x14, 0xF1, 0x55, 0x12, 0x00]);
+        let explain = |chip8: &mut Chip8| {
+            let (pc, before) = (chip8.core.pc, chip8.registers());
+            let opcode = Opcode::decode(chip8.instruction_at(pc).unwrap()).unwrap();
+            chip8.cycle().unwrap();
+            chip8.explanation(pc, &opcode, &before, &chip8.registers())
+        };
+        for _ in 0..3 {
+            explain(&mut chip8);
+        }
+
+        assert_eq!(
+            explain(&mut chip8),
+            "0x0206  ADD V0, V1\n  V0 ← V0 + V1, VF ← 1 on carry, else 0\n  V0: 0xF0 → 0x10\n  VF: 0x00 → 0x01\n"
+        );
+        assert_eq!(
+            explain(&mut chip8),
+            "0x0208  LD [I], V1\n  memory[I..I+1] ← V0..V1\n  [0x300] ← 0x10\n  [0x301] ← 0x20\n"
+        );
+        assert_eq!(
+            explain(&mut chip8),
+            "0x020A  JP 0x200\n  PC ← 0x200 (jump)\n  PC → 0x0200\n"
+        );
+    }
+
+    #[test]
+    fn test_font_sprite_of_the_digit_in_vx() {
+        let mut chip8 = new_headless_chip8();
+        // LD V4, 0x1A; LD F, V4
+        chip8.core.memory[0x200..0x204].copy_from_slice(&[0x64, 0x1A, 0xF4, 0x29]);
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+
+        // only the low nibble counts, the sprite of A is the eleventh one of the font
+        let i = chip8.core.i as usize;
+        assert_eq!(i, FONT_OFFSET + 0xA * 5);
+        assert_eq!(chip8.core.memory[i..i + 5], FONT[0xA * 5..0xA * 5 + 5]);
+    }
+
+    struct RecordingAudio(std::rc::Rc<std::cell::RefCell<Vec<bool>>>);
+
+    impl Audio for RecordingAudio {
+        fn set_beeping(&mut self, beeping: bool) -> Result<(), Chip8Error> {
+            self.0.borrow_mut().push(beeping);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_audio_follows_sound_timer() {
+        let mut chip8 = new_headless_chip8();
+        let events = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        chip8.set_audio(Box::new(RecordingAudio(events.clone())));
+
+        chip8.core.regs[0x0] = 2;
+        chip8.execute(Opcode::SetSound(0x0)).unwrap();
+        chip8.update_audio().unwrap();
+        chip8.update_audio().unwrap();
+        chip8.core.sound_timer = 0;
+        chip8.update_audio().unwrap();
+
+        assert_eq!(*events.borrow(), vec![true, false]);
+    }
+
+    #[test]
+    fn test_key_skip_reads_keypad() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.regs[0x1] = 0x5;
+        chip8.core.keypad.press(0x5);
+
+        chip8.execute(Opcode::SkipKey(0x1)).unwrap();
+        assert_eq!(chip8.core.pc, 0x202);
+        chip8.execute(Opcode::SkipNotKey(0x1)).unwrap();
+        assert_eq!(chip8.core.pc, 0x202);
+    }
+
+    #[test]
+    fn test_unthrottled_timers_tick_per_frame() {
+        let mut chip8 = new_headless_chip8();
+        chip8.set_throttle(false);
+        chip8.core.delay_timer = 5;
+
+        for _ in 0..chip8.cycles_per_frame() * 3 {
+            chip8.handle_timer();
+        }
+        assert_eq!(chip8.frame_count(), 3);
+        assert_eq!(chip8.core.delay_timer, 2);
+    }
+
+    #[test]
+    fn test_step_reports_side_effects() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.memory[0x200..0x20A].copy_from_slice(&[0x00, 0xE0, 0x60, 0x02, 0xF0, 0x18, 0xF1, 0x0A, 0x12, 0x08]);
+
+        assert!(chip8.step().unwrap().display_changed);
+        assert_eq!(chip8.step().unwrap(), StepResult::default());
+        assert!(chip8.step().unwrap().sound_started);
+        assert!(chip8.step().unwrap().waiting_for_key);
+
+        chip8.tick_timers();
+        chip8.tick_timers();
+        chip8.keypad_mut().press(0x3);
+        assert!(chip8.step().unwrap().sound_stopped);
+        chip8.keypad_mut().release(0x3);
+        chip8.step().unwrap();
+        assert_eq!(chip8.core.regs[0x1], 0x3);
+        assert!(chip8.step().unwrap().halted);
+    }
+
+    #[test]
+    fn test_builder_loads_rom_bytes_and_seed() {
+        let rom = [0xC0, 0xFF, 0xC1, 0xFF];
+        let mut first = Chip8::builder().rom_bytes(&rom).seed(42).build().unwrap();
+        let mut second = Chip8::builder().rom_bytes(&rom).seed(42).build().unwrap();
+        assert_eq!(&first.core.memory[0x200..0x204], &rom);
+
+        for _ in 0..2 {
+            first.step().unwrap();
+            second.step().unwrap();
+        }
+        assert_eq!(first.core.regs, second.core.regs);
+    }
+
+    #[test]
+    fn test_cosmac_vip_quirks() {
+        let mut chip8 = Chip8::builder().quirks(Quirks::cosmac_vip()).build().unwrap();
+        chip8.core.regs[0x1] = 0x0F;
+        chip8.core.regs[0x2] = 0x81;
+        chip8.core.regs[0xF] = 1;
+        chip8.core.i = 0x300;
+
+        chip8.execute(Opcode::Or(0x1, 0x2)).unwrap();
+        assert_eq!(chip8.core.regs[0xF], 0);
+
+        chip8.execute(Opcode::ShiftRight(0x3, 0x2)).unwrap();
+        assert_eq!((chip8.core.regs[0x3], chip8.core.regs[0xF]), (0x40, 1));
+
+        chip8.execute(Opcode::StoreRegs(0x2)).unwrap();
+        assert_eq!(chip8.core.i, 0x303);
+    }
+
+    #[test]
+    fn test_hooks_observe_emulation() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let rom = [0x00, 0xE0, 0x60, 0x01, 0xF0, 0x18, 0xF3, 0x0A];
+        let mut chip8 = Chip8::builder().rom_bytes(&rom).build().unwrap();
+        let events = Rc::new(RefCell::new(Vec::new()));
+
+        let log = events.clone();
+        chip8.on_instruction(move |pc, opcode| log.borrow_mut().push(format!("{:#05X} {}", pc, opcode)));
+        let log = events.clone();
+        chip8.on_draw(move |pixels| log.borrow_mut().push(format!("draw {}", pixels.len())));
+        let log = events.clone();
+        chip8.on_sound_change(move |beeping| log.borrow_mut().push(format!("sound {}", beeping)));
+        let log = events.clone();
+        chip8.on_key_wait(move |x| log.borrow_mut().push(format!("wait V{:X}", x)));
+
+        for _ in 0..5 {
+            chip8.step().unwrap();
+        }
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                "0x200 CLS",
+                "draw 2048",
+                "0x202 LD V0, 0x01",
+                "0x204 LD ST, V0",
+                "sound true",
+                "0x206 LD V3, K",
+                "wait V3",
+                "0x206 LD V3, K",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_handle_controls_emulator_on_other_thread() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let emulator = thread::spawn(move || {
+            // LD V0, 5; JP 0x202
+            let mut chip8 = Chip8::builder().rom_bytes(&[0x60, 0x05, 0x12, 0x02]).build()?;
+            sender.send(chip8.handle()).unwrap();
+            chip8.run()
+        });
+        let handle = receiver.recv().unwrap();
+
+        let state = loop {
+            let state = handle.state().unwrap();
+            if state.registers.v[0] == 5 {
+                break state;
+            }
+        };
+        assert_eq!(state.pc, 0x202);
+        handle.pause();
+        assert!(handle.state().unwrap().paused);
+        assert!(handle.is_paused());
+
+        handle.quit();
+        emulator.join().unwrap().unwrap();
+        assert!(!handle.is_running());
+        assert!(handle.state().is_none());
+    }
+
+    #[test]
+    fn test_handle_state_returns_when_the_run_ends() {
+        // JP 0x200
+        let mut chip8 = Chip8::builder().rom_bytes(&[0x12, 0x00]).build().unwrap();
+        let handle = chip8.handle();
+        let shared = chip8.remote.as_ref().unwrap().shared.clone();
+        handle.quit();
+        chip8.run().unwrap();
+        assert!(handle.state().is_none());
+
+        // a handle that saw the emulator running just before the run ended sends its request anyway
+        shared.running.store(true, Ordering::Relaxed);
+        let (done, finished) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let _ = done.send(handle.state());
+        });
+        let state = finished.recv_timeout(std::time::Duration::from_secs(5));
+        assert_eq!(state, Ok(None), "state() still waits after the run ended");
+        drop(chip8);
+    }
+
+    #[test]
+    fn test_snapshot_round_trips_through_json() {
+        let mut chip8 = new_headless_chip8();
+        chip8.core.regs[3] = 0x42;
+        chip8.core.memory[0x300] = 0xAB;
+        chip8.core.stack[0] = 0x204;
+        chip8.core.sp = 1;
+        chip8.core.display[7] = 1;
+        chip8.core.quirks = Quirks::cosmac_vip();
+        let snapshot = chip8.snapshot();
+        let json = serde_json::to_string(&snapshot).unwrap();
+
+        let mut restored = new_headless_chip8();
+        restored.restore(&serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(restored.snapshot(), snapshot);
+        assert_eq!(restored.core.regs[3], 0x42);
+        assert_eq!(restored.core.quirks, Quirks::cosmac_vip());
+    }
+
+    #[test]
+    fn test_restore_rejects_wrong_sizes() {
+        let mut chip8 = new_headless_chip8();
+        let mut snapshot = chip8.snapshot();
+        snapshot.memory.truncate(100);
+        assert!(matches!(chip8.restore(&snapshot), Err(Chip8Error::InvalidSnapshot(_))));
+        assert_eq!(chip8.core.memory[FONT_OFFSET], FONT[0]);
+    }
+
+    #[test]
+    fn test_accessors_check_bounds() {
+        let mut chip8 = new_headless_chip8();
+        chip8.write_mem(0xFFE, &[0x12, 0x34]).unwrap();
+        assert_eq!(chip8.read_mem(0xFFE, 2).unwrap(), &[0x12, 0x34]);
+        assert!(matches!(
+            chip8.write_mem(0xFFF, &[1, 2]),
+            Err(Chip8Error::MemoryOutOfBounds(0xFFF))
+        ));
+        assert!(matches!(
+            chip8.read_mem(0x1000, 1),
+            Err(Chip8Error::MemoryOutOfBounds(0x1000))
+        ));
+
+        chip8.set_reg(0xF, 7).unwrap();
+        assert_eq!(chip8.reg(0xF).unwrap(), 7);
+        assert!(matches!(chip8.set_reg(0x10, 1), Err(Chip8Error::InvalidRegister(0x10))));
+        assert!(matches!(chip8.reg(0x10), Err(Chip8Error::InvalidRegister(0x10))));
+        assert_eq!((chip8.pc(), chip8.i()), (0x200, 0));
+    }
+
+    #[test]
+    fn test_peripherals_claim_memory_and_machine_calls() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        struct Latch(Rc<RefCell<Vec<(u16, u8)>>>);
+
+        impl Peripheral for Latch {
+            fn memory_range(&self) -> Option<std::ops::RangeInclusive<u16>> {
+                Some(0xF00..=0xF0F)
+            }
+
+            fn read(&mut self, _addr: u16) -> u8 {
+                0x07
+            }
+
+            fn write(&mut self, addr: u16, value: u8) {
+                self.0.borrow_mut().push((addr, value));
+            }
+
+            fn machine_call(&mut self, nnn: u16, chip8: &mut Chip8) -> bool {
+                nnn == 0x123 && chip8.set_reg(5, 9).is_ok()
+            }
+        }
+
+        // LD V0, 0x41; LD I, 0xF00; LD [I], V0; LD V1, [I]; SYS 0x123
+        let rom = [0x60, 0x41, 0xAF, 0x00, 0xF0, 0x55, 0xF1, 0x65, 0x01, 0x23];
+        let mut chip8 = Chip8::builder().rom_bytes(&rom).build().unwrap();
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        chip8.add_peripheral(Latch(writes.clone()));
+        for _ in 0..5 {
+            chip8.step().unwrap();
+        }
+
+        assert_eq!(*writes.borrow(), vec![(0xF00, 0x41)]);
+        assert_eq!(chip8.core.memory[0xF00], 0);
+        assert_eq!(chip8.core.regs[..2], [0x07, 0x07]);
+        assert_eq!(chip8.core.regs[5], 9);
+        assert_eq!(chip8.bus.peripherals.len(), 1);
+    }
+
+    #[test]
+    fn test_peripherals_mapped_past_the_end_of_memory() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        struct Port(Rc<RefCell<Vec<(u16, u8)>>>);
+
+        impl Peripheral for Port {
+            fn memory_range(&self) -> Option<std::ops::RangeInclusive<u16>> {
+                Some(0x1000..=0x1001)
+            }
+
+            fn read(&mut self, addr: u16) -> u8 {
+                addr as u8 + 1
+            }
+
+            fn write(&mut self, addr: u16, value: u8) {
+                self.0.borrow_mut().push((addr, value));
+            }
+        }
+
+        // LD V0, 0x41; LD V4, 0xFF; LD I, 0xF01; ADD I, V4; LD [I], V0; LD I, 0xF01; ADD I, V4; LD V1, [I]
+        let rom = [
+            0x60, 0x41, 0x64, 0xFF, 0xAF, 0x01, 0xF4, 0x1E, 0xF0, 0x55, 0xAF, 0x01, 0xF4, 0x1E, 0xF1, 0x65,
+        ];
+        let mut chip8 = Chip8::builder().rom_bytes(&rom).build().unwrap();
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        chip8.add_peripheral(Port(writes.clone()));
+        let low_memory = chip8.core.memory[..2].to_vec();
+        for _ in 0..8 {
+            chip8.step().unwrap();
+        }
+
+        // 0x1000 and 0x1001 wrap around to 0x000 and 0x001, which the peripheral hides.
+        assert_eq!(*writes.borrow(), vec![(0x1000, 0x41)]);
+        assert_eq!(chip8.core.memory[..2], low_memory[..]);
+        assert_eq!(chip8.core.regs[..2], [0x01, 0x02]);
+    }
+
+    #[test]
+    fn test_machine_calls_follow_handler_then_policy() {
+        // SYS 0x123; SYS 0x456
+        let rom = [0x01, 0x23, 0x04, 0x56];
+        let mut chip8 = Chip8::builder().rom_bytes(&rom).build().unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.core.pc, 0x202);
+
+        chip8.set_machine_call_policy(MachineCallPolicy::Halt);
+        assert!(matches!(chip8.step(), Err(Chip8Error::InvalidOpcode(0x0456))));
+
+        let mut chip8 = Chip8::builder().rom_bytes(&rom).build().unwrap();
+        chip8.set_machine_call_policy(MachineCallPolicy::Halt);
+        chip8.on_machine_call(|nnn, chip8| chip8.set_reg(0, (nnn & 0xFF) as u8).unwrap());
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.core.regs[0], 0x56);
+    }
+
+    #[test]
+    fn test_apply_config_changes_speed_and_repaints() {
+        use crate::config::{Palette, Rgb};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        struct Screen(Rc<RefCell<Vec<Palette>>>);
+
+        impl frontend::Display for Screen {
+            fn draw(&mut self, _pixels: &[u8]) -> Result<(), Chip8Error> {
+                let last = self.0.borrow().last().copied();
+                self.0.borrow_mut().extend(last);
+                Ok(())
+            }
+
+            fn set_palette(&mut self, palette: Palette) {
+                self.0.borrow_mut().push(palette);
+            }
+        }
+
+        let mut chip8 = new_headless_chip8();
+        let palettes = Rc::new(RefCell::new(Vec::new()));
+        chip8.set_display(Box::new(Screen(palettes.clone())));
+        let mut config = Config {
+            ips: 1200,
+            ..Config::default()
+        };
+        config.palette.foreground = Rgb(0xFF, 0xB0, 0x00);
+        chip8.apply_config(&config).unwrap();
+
+        assert_eq!(chip8.cycles_per_frame(), 20);
+        assert_eq!(*palettes.borrow(), vec![config.palette; 2]);
+    }
+
+    #[test]
+    fn test_theme_hotkey_goes_to_the_next_theme() {
+        use crate::config::Palette;
+        use crate::keypad::Keypad;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        struct Screen(Rc<RefCell<Vec<Palette>>>);
+
+        impl frontend::Display for Screen {
+            fn draw(&mut self, _pixels: &[u8]) -> Result<(), Chip8Error> {
+                Ok(())
+            }
+
+            fn set_palette(&mut self, palette: Palette) {
+                self.0.borrow_mut().push(palette);
+            }
+        }
+
+        struct ThemeKey;
+
+        impl Input for ThemeKey {
+            fn poll(&mut self, _keypad: &mut Keypad) -> Result<u32, Chip8Error> {
+                Ok(frontend::NEXT_THEME)
+            }
+        }
+
+        let mut chip8 = new_headless_chip8();
+        let palettes = Rc::new(RefCell::new(Vec::new()));
+        chip8.set_display(Box::new(Screen(palettes.clone())));
+        chip8.set_input(Box::new(ThemeKey));
+        chip8.set_palette(theme::find("amber").unwrap().palette());
+        let names = Rc::new(RefCell::new(Vec::new()));
+        let recorded = names.clone();
+        chip8.on_theme_change(move |theme| recorded.borrow_mut().push(theme.name));
+        chip8.handle_input().unwrap();
+        chip8.handle_input().unwrap();
+
+        let c64 = theme::find("c64").unwrap().palette();
+        let high_contrast = theme::find("high-contrast").unwrap().palette();
+        assert_eq!(palettes.borrow()[1..], [c64, high_contrast]);
+        assert_eq!(*names.borrow(), ["c64", "high-contrast"]);
+    }
+
+    #[test]
+    fn test_failed_rom_switch_goes_to_the_hook() {
+        use crate::keypad::Keypad;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        struct PickKey;
+
+        impl Input for PickKey {
+            fn poll(&mut self, _keypad: &mut Keypad) -> Result<u32, Chip8Error> {
+                Ok(frontend::PICK_ROM)
+            }
         }
+
+        let mut chip8 = new_headless_chip8();
+        let failures = Rc::new(RefCell::new(Vec::new()));
+        let recorded = failures.clone();
+        chip8.on_rom_open_error(move |path, _err| recorded.borrow_mut().push(path.to_string()));
+        chip8.set_input(Box::new(PickKey));
+        chip8.set_rom_picker(|| Some("does/not/exist.ch8".to_string()));
+        let pc = chip8.pc();
+        chip8.handle_input().unwrap();
+
+        assert_eq!(*failures.borrow(), ["does/not/exist.ch8"]);
+        assert_eq!(chip8.pc(), pc, "the current ROM keeps running");
     }
 }
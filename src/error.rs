@@ -1,4 +1,4 @@
-use sdl3::video::WindowBuildError;
+use alloc::string::String;
 
 #[derive(Debug)]
 pub enum Chip8Error {
@@ -7,40 +7,55 @@ pub enum Chip8Error {
     InvalidOpcode(u16),
     StackOverflow,
     StackUnderflow,
+    CycleLimit(u64),
     PCOutOfBounds(u16),
+    MemoryOutOfBounds(u16),
+    #[cfg(feature = "std")]
     IoError(std::io::Error),
+    InvalidSymbols(String),
+    InvalidTrace(String),
+    InvalidSnapshot(String),
+    InvalidConfig(String),
+    InvalidArchive(String),
+    InvalidDatabase(String),
+    InvalidPatch(String),
+    InvalidCheats(String),
+    InvalidImage(String),
+    InvalidVectors(String),
 }
 
-impl std::error::Error for Chip8Error {}
+impl core::error::Error for Chip8Error {}
 
+#[cfg(feature = "std")]
 impl From<std::io::Error> for Chip8Error {
     fn from(err: std::io::Error) -> Self {
         Chip8Error::IoError(err)
     }
 }
 
-impl From<sdl3::Error> for Chip8Error {
-    fn from(err: sdl3::Error) -> Self {
-        Chip8Error::IoError(std::io::Error::other(err))
-    }
-}
-
-impl From<WindowBuildError> for Chip8Error {
-    fn from(err: WindowBuildError) -> Self {
-        Chip8Error::IoError(std::io::Error::other(err))
-    }
-}
-
-impl std::fmt::Display for Chip8Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Chip8Error::RomTooLarge(size) => write!(f, "ROM is too large to fit in memory (size: {})", size),
             Chip8Error::InvalidRegister(reg) => write!(f, "Invalid register: V{:#X}", reg),
             Chip8Error::PCOutOfBounds(pc) => write!(f, "Program Counter is out of bounds (PC: {:#X})", pc),
+            Chip8Error::MemoryOutOfBounds(addr) => write!(f, "Memory access out of bounds (address: {:#X})", addr),
             Chip8Error::InvalidOpcode(opcode) => write!(f, "Invalid opcode: {:#X}", opcode),
             Chip8Error::StackOverflow => write!(f, "Stack overflow"),
             Chip8Error::StackUnderflow => write!(f, "Stack underflow"),
+            Chip8Error::CycleLimit(max) => write!(f, "Still running after the limit of {} instructions", max),
+            #[cfg(feature = "std")]
             Chip8Error::IoError(err) => write!(f, "IO Error: {}", err),
+            Chip8Error::InvalidSymbols(err) => write!(f, "Invalid symbol file: {}", err),
+            Chip8Error::InvalidTrace(err) => write!(f, "Invalid trace file: {}", err),
+            Chip8Error::InvalidSnapshot(err) => write!(f, "Invalid snapshot: {}", err),
+            Chip8Error::InvalidConfig(err) => write!(f, "Invalid config file: {}", err),
+            Chip8Error::InvalidArchive(err) => write!(f, "Invalid ROM archive: {}", err),
+            Chip8Error::InvalidDatabase(err) => write!(f, "Invalid ROM database: {}", err),
+            Chip8Error::InvalidPatch(err) => write!(f, "Invalid IPS patch: {}", err),
+            Chip8Error::InvalidCheats(err) => write!(f, "Invalid cheat file: {}", err),
+            Chip8Error::InvalidImage(err) => write!(f, "Invalid image: {}", err),
+            Chip8Error::InvalidVectors(err) => write!(f, "Invalid test vectors: {}", err),
         }
     }
 }
@@ -0,0 +1,107 @@
+use crate::emulator::Chip8;
+use crate::error::Chip8Error;
+
+// Runs the emulator one 60 Hz frame at a time and yields the display after each, see Chip8::frames.
+// Ends after yielding the first error.
+pub struct Frames<'a> {
+    chip8: &'a mut Chip8,
+    failed: bool,
+}
+
+impl<'a> Frames<'a> {
+    pub(crate) fn new(chip8: &'a mut Chip8) -> Self {
+        Frames { chip8, failed: false }
+    }
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Result<Vec<u8>, Chip8Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.chip8.run_frame() {
+            Ok(()) => Some(Ok(self.chip8.pixels().to_vec())),
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+// The frames are computed on the spot, so the stream is always ready.
+#[cfg(feature = "stream")]
+impl futures_core::Stream for Frames<'_> {
+    type Item = Result<Vec<u8>, Chip8Error>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        std::task::Poll::Ready(self.get_mut().next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LD V0, 8; LD F, V0; DRW V1, V1, 5; ADD V1, 5; JP 0x204
+    const ROM: [u8; 10] = [0x60, 0x08, 0xF0, 0x29, 0xD1, 0x15, 0x71, 0x05, 0x12, 0x04];
+
+    #[test]
+    fn test_frames_yield_display_per_frame() {
+        let mut chip8 = Chip8::builder().rom_bytes(&ROM).ips(60 * 3).build().unwrap();
+        let frames: Vec<Vec<u8>> = chip8.frames().take(2).collect::<Result<_, _>>().unwrap();
+
+        // Three instructions per frame: the first frame draws one 8, the second one more
+        let lit = |frame: &Vec<u8>| frame.iter().filter(|&&pixel| pixel != 0).count();
+        assert_eq!(lit(&frames[0]), 16);
+        assert_eq!(lit(&frames[1]), 32);
+        assert_eq!(chip8.frame_count(), 2);
+    }
+
+    #[test]
+    fn test_frames_end_after_error() {
+        // RET with an empty stack
+        let mut chip8 = Chip8::builder().rom_bytes(&[0x00, 0xEE]).build().unwrap();
+        let mut frames = chip8.frames();
+        assert!(matches!(frames.next(), Some(Err(Chip8Error::StackUnderflow))));
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn test_key_taps_end_with_the_frame() {
+        // JP 0x200, never reads the keys
+        let mut chip8 = Chip8::builder().rom_bytes(&[0x12, 0x00]).build().unwrap();
+        chip8.keypad_mut().press(5);
+        chip8.frames().next().unwrap().unwrap();
+        assert!(chip8.keypad_mut().is_pressed(5), "held keys stay down");
+        chip8.keypad_mut().release(5);
+
+        // a tap the program did not read is over at the end of the frame
+        chip8.keypad_mut().press(7);
+        chip8.keypad_mut().release(7);
+        assert!(chip8.keypad_mut().is_pressed(7));
+        chip8.frames().next().unwrap().unwrap();
+        assert!(!chip8.keypad_mut().is_pressed(7));
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn test_frames_stream_is_always_ready() {
+        use futures_core::Stream;
+        use std::pin::Pin;
+        use std::task::{Context, Poll, Waker};
+
+        let mut chip8 = Chip8::builder().rom_bytes(&ROM).ips(60 * 3).build().unwrap();
+        let mut frames = chip8.frames();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(matches!(
+            Pin::new(&mut frames).poll_next(&mut cx),
+            Poll::Ready(Some(Ok(_)))
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::config::{KeyMap, Palette};
+use crate::error::Chip8Error;
+use crate::keypad::Keypad;
+
+pub const PAUSE: u32 = 0x02;
+pub const STEP_MODE: u32 = 0x04;
+pub const SHOULD_STEP: u32 = 0x08;
+pub const RESET: u32 = 0x10;
+pub const QUIT: u32 = 0x20;
+pub const STEP_MANY: u32 = 0x40;
+pub const CLOSE_HEATMAP: u32 = 0x80;
+pub const FAST_FORWARD: u32 = 0x100;
+// Pauses even outside debug mode, for when the player cannot play on. PAUSE resumes.
+pub const SUSPEND: u32 = 0x200;
+// A file was dropped on the window, Input::dropped_rom has its path.
+pub const OPEN_ROM: u32 = 0x400;
+// Asks for another ROM with the picker given to Chip8::set_rom_picker.
+pub const PICK_ROM: u32 = 0x800;
+// Switches cheat n on or off as CHEAT << n, for the first CHEAT_COUNT cheats.
+pub const CHEAT: u32 = 0x1000;
+pub const CHEAT_COUNT: usize = 9;
+// Switches to the next theme of theme::THEMES.
+pub const NEXT_THEME: u32 = CHEAT << CHEAT_COUNT;
+
+// What Input::capture_key saw while binding keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Capture {
+    // The name of the key in the form KeyMap uses
+    Key(String),
+    // Escape was pressed or the window closed
+    Cancel,
+}
+
+pub trait Display {
+    fn draw(&mut self, pixels: &[u8]) -> Result<(), Chip8Error>;
+
+    fn open_heatmap(&mut self, _memory_size: usize) -> Result<(), Chip8Error> {
+        Ok(())
+    }
+
+    fn draw_heatmap(&mut self, _counts: &[u64]) -> Result<(), Chip8Error> {
+        Ok(())
+    }
+
+    fn close_heatmap(&mut self) {}
+
+    // Called when the palette changes while running, followed by a draw with the same pixels.
+    fn set_palette(&mut self, _palette: Palette) {}
+}
+
+pub trait Input {
+    // Handles pending events, updates the keypad and returns the control flags above that were triggered.
+    fn poll(&mut self, keypad: &mut Keypad) -> Result<u32, Chip8Error>;
+
+    // Binds the keypad to other keyboard keys, fails on key names the frontend does not know.
+    fn set_key_map(&mut self, _keys: &KeyMap) -> Result<(), Chip8Error> {
+        Ok(())
+    }
+
+    // The file that came with OPEN_ROM, for frontends that accept dropped files.
+    fn dropped_rom(&mut self) -> Option<String> {
+        None
+    }
+
+    // Follows the sound timer like Audio::set_beeping, for frontends that can shake a gamepad.
+    fn set_rumble(&mut self, _rumble: bool) {}
+
+    // Handles pending events like poll, but reports the first keyboard key that went down instead of
+    // updating the keypad. None while no key was pressed, keys that cannot be named are skipped.
+    fn capture_key(&mut self) -> Result<Option<Capture>, Chip8Error> {
+        Err(Chip8Error::IoError(std::io::Error::other(
+            "this frontend cannot bind keys",
+        )))
+    }
+}
+
+pub trait Audio {
+    // Called whenever the sound timer starts or stops running.
+    fn set_beeping(&mut self, beeping: bool) -> Result<(), Chip8Error>;
+}
@@ -0,0 +1,118 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+
+use crate::trace::Registers;
+
+pub(crate) enum HandleCommand {
+    Pause,
+    Resume,
+    Reset,
+    Quit,
+    Key(u8, bool),
+    State(mpsc::Sender<State>),
+}
+
+// The emulator as seen from another thread, taken between two instructions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State {
+    pub pc: u16,
+    pub registers: Registers,
+    pub stack: Vec<u16>,
+    pub display: Vec<u8>,
+    pub frames: u64,
+    pub paused: bool,
+}
+
+// Published by the emulator on every pass through its loop, readable without waiting for it.
+#[derive(Default)]
+pub(crate) struct Shared {
+    pub running: AtomicBool,
+    pub paused: AtomicBool,
+    pub frames: AtomicU64,
+}
+
+// The emulator's end of the channel, kept by Chip8 and drained by Chip8::run until the run ends.
+pub(crate) struct Remote {
+    pub sender: mpsc::Sender<HandleCommand>,
+    pub receiver: mpsc::Receiver<HandleCommand>,
+    pub shared: Arc<Shared>,
+}
+
+impl Remote {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Remote {
+            sender,
+            receiver,
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    pub fn handle(&self) -> Chip8Handle {
+        Chip8Handle {
+            sender: self.sender.clone(),
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+// Controls an emulator running Chip8::run on another thread. Commands are carried out the next time the
+// emulator goes through its loop, and are dropped silently once it has stopped. A handle only controls the run
+// it was made for, Chip8::handle makes a new one for the next run.
+#[derive(Clone)]
+pub struct Chip8Handle {
+    sender: mpsc::Sender<HandleCommand>,
+    shared: Arc<Shared>,
+}
+
+impl Chip8Handle {
+    pub fn pause(&self) {
+        self.send(HandleCommand::Pause);
+    }
+
+    pub fn resume(&self) {
+        self.send(HandleCommand::Resume);
+    }
+
+    pub fn reset(&self) {
+        self.send(HandleCommand::Reset);
+    }
+
+    pub fn quit(&self) {
+        self.send(HandleCommand::Quit);
+    }
+
+    pub fn press_key(&self, key: u8) {
+        self.send(HandleCommand::Key(key, true));
+    }
+
+    pub fn release_key(&self, key: u8) {
+        self.send(HandleCommand::Key(key, false));
+    }
+
+    // Waits for the emulator to report its state, None when it is no longer running.
+    pub fn state(&self) -> Option<State> {
+        if !self.is_running() {
+            return None;
+        }
+        let (sender, receiver) = mpsc::channel();
+        self.sender.send(HandleCommand::State(sender)).ok()?;
+        receiver.recv().ok()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.shared.running.load(Ordering::Relaxed)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.shared.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.shared.frames.load(Ordering::Relaxed)
+    }
+
+    fn send(&self, command: HandleCommand) {
+        let _ = self.sender.send(command);
+    }
+}
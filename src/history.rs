@@ -0,0 +1,218 @@
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+
+use crate::debugger::Register;
+use crate::trace::Registers;
+
+pub struct Write {
+    pub pc: u16,
+    pub value: u8,
+}
+
+pub struct WriteHistory {
+    depth: usize,
+    cells: HashMap<u16, VecDeque<Write>>,
+}
+
+impl WriteHistory {
+    pub fn new(depth: usize) -> Self {
+        WriteHistory {
+            depth,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, addr: u16, pc: u16, value: u8) {
+        if self.depth == 0 {
+            return;
+        }
+
+        let writes = self.cells.entry(addr).or_default();
+        if writes.len() == self.depth {
+            writes.pop_front();
+        }
+        writes.push_back(Write { pc, value });
+    }
+
+    // Returns the recorded writes to a cell, newest first.
+    pub fn writes(&self, addr: u16) -> impl Iterator<Item = &Write> {
+        self.cells.get(&addr).into_iter().flat_map(|writes| writes.iter().rev())
+    }
+}
+
+pub struct Sample {
+    pub step: u64,
+    pub pc: u16,
+    pub regs: Registers,
+}
+
+impl Sample {
+    pub fn value(&self, register: Register) -> u16 {
+        match register {
+            Register::V(x) => self.regs.v[x as usize] as u16,
+            Register::I => self.regs.i,
+            Register::Pc => self.pc,
+            Register::DelayTimer => self.regs.delay_timer as u16,
+            Register::SoundTimer => self.regs.sound_timer as u16,
+        }
+    }
+}
+
+pub struct RegisterTimeline {
+    capacity: usize,
+    samples: VecDeque<Sample>,
+}
+
+impl RegisterTimeline {
+    pub fn new(capacity: usize) -> Self {
+        RegisterTimeline {
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn record(&mut self, step: u64, pc: u16, regs: Registers) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample { step, pc, regs });
+    }
+
+    pub fn render(&self, register: Register, steps: usize) -> String {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        const MAX_CHANGES: usize = 10;
+
+        let start = self.samples.len().saturating_sub(steps);
+        let samples: Vec<&Sample> = self.samples.range(start..).collect();
+        let Some(first) = samples.first() else {
+            return String::from("No steps recorded yet");
+        };
+
+        let values: Vec<u16> = samples.iter().map(|sample| sample.value(register)).collect();
+        let min = values.iter().copied().min().unwrap_or(0);
+        let max = values.iter().copied().max().unwrap_or(0);
+        let graph: String = values
+            .iter()
+            .map(|&value| {
+                let level = if max == min {
+                    0
+                } else {
+                    (value - min) as usize * (BARS.len() - 1) / (max - min) as usize
+                };
+                BARS[level]
+            })
+            .collect();
+
+        let mut result = format!(
+            "{} over the last {} steps (min {:#X}, max {:#X}):\n{}",
+            register,
+            samples.len(),
+            min,
+            max,
+            graph
+        );
+
+        // A sample holds the state after its instruction, so a change is caused by the later sample's instruction.
+        let changes: Vec<String> = samples
+            .windows(2)
+            .filter(|pair| pair[0].value(register) != pair[1].value(register))
+            .map(|pair| {
+                format!(
+                    "  step {:>8}  {:#06X}  {:#X} -> {:#X}",
+                    pair[1].step,
+                    pair[1].pc,
+                    pair[0].value(register),
+                    pair[1].value(register)
+                )
+            })
+            .collect();
+
+        result.push_str(&format!(
+            "\nStarted at {:#X} (step {})",
+            first.value(register),
+            first.step
+        ));
+        if !changes.is_empty() {
+            result.push_str("\nLast changes:\n");
+            result.push_str(&changes[changes.len().saturating_sub(MAX_CHANGES)..].join("\n"));
+        }
+        result
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Uninitialized,
+    Initialized,
+    Reported,
+}
+
+// Remembers which memory cells were ever written, by the ROM, the font or the program itself.
+pub struct InitializedMemory {
+    cells: Vec<Cell>,
+}
+
+impl InitializedMemory {
+    pub fn new(size: usize) -> Self {
+        InitializedMemory {
+            cells: vec![Cell::Uninitialized; size],
+        }
+    }
+
+    pub fn mark(&mut self, range: Range<usize>) {
+        self.cells[range].fill(Cell::Initialized);
+    }
+
+    // Returns true the first time a never written cell is read, so every cell is only reported once.
+    pub fn read(&mut self, addr: usize) -> bool {
+        match self.cells.get(addr) {
+            Some(Cell::Uninitialized) => {
+                self.cells[addr] = Cell::Reported;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registers(v0: u8) -> Registers {
+        let mut v = [0; 16];
+        v[0] = v0;
+        Registers {
+            v,
+            i: 0,
+            delay_timer: 0,
+            sound_timer: 0,
+        }
+    }
+
+    #[test]
+    fn test_render_graphs_and_lists_changes() {
+        let mut timeline = RegisterTimeline::new(4);
+        for (step, v0) in [9, 0, 0, 7, 7, 14].into_iter().enumerate() {
+            timeline.record(step as u64, 0x200 + 2 * step as u16, registers(v0));
+        }
+        // the capacity keeps the last four steps, the oldest ones are gone
+        assert_eq!(
+            timeline.render(Register::V(0), 10),
+            "V0 over the last 4 steps (min 0x0, max 0xE):\n\
+             ▁▄▄█\n\
+             Started at 0x0 (step 2)\n\
+             Last changes:\n  \
+             step        3  0x0206  0x0 -> 0x7\n  \
+             step        5  0x020A  0x7 -> 0xE"
+        );
+        assert!(timeline
+            .render(Register::V(0), 2)
+            .starts_with("V0 over the last 2 steps (min 0x7, max 0xE)"));
+        assert_eq!(timeline.render(Register::V(1), 2).lines().nth(1), Some("▁▁"));
+        assert_eq!(
+            RegisterTimeline::new(4).render(Register::I, 10),
+            "No steps recorded yet"
+        );
+    }
+}
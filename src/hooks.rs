@@ -0,0 +1,35 @@
+use crate::emulator::{Chip8, LoadedRom};
+use crate::error::Chip8Error;
+use crate::opcode::Opcode;
+use crate::theme::Theme;
+
+pub type DrawHook = Box<dyn FnMut(&[u8])>;
+pub type SoundHook = Box<dyn FnMut(bool)>;
+pub type KeyWaitHook = Box<dyn FnMut(u8)>;
+pub type InstructionHook = Box<dyn FnMut(u16, &Opcode)>;
+pub type MachineCallHook = Box<dyn FnMut(u16, &mut Chip8)>;
+pub type FrameHook = Box<dyn FnMut(u64, &[u8])>;
+pub type RomOpenHook = Box<dyn FnMut(&str, &LoadedRom)>;
+pub type RomOpenErrorHook = Box<dyn FnMut(&str, &Chip8Error)>;
+pub type HaltHook = Box<dyn FnMut(u16)>;
+pub type ThemeHook = Box<dyn FnMut(&Theme)>;
+pub type ConfigErrorHook = Box<dyn FnMut(&Chip8Error)>;
+
+// Callbacks registered on the emulator, called from Chip8::step, at the end of every frame, when the run
+// opens another ROM, when the program halts, when
+// the theme hotkey switches the theme and when a changed config file is ignored.
+#[derive(Default)]
+pub struct Hooks {
+    pub draw: Vec<DrawHook>,
+    pub sound_change: Vec<SoundHook>,
+    pub key_wait: Vec<KeyWaitHook>,
+    pub instruction: Vec<InstructionHook>,
+    pub frame: Vec<FrameHook>,
+    pub rom_open: Vec<RomOpenHook>,
+    pub rom_open_error: Vec<RomOpenErrorHook>,
+    pub halt: Vec<HaltHook>,
+    pub theme_change: Vec<ThemeHook>,
+    pub config_error: Vec<ConfigErrorHook>,
+    // Handles 0NNN instead of the machine call policy, there can only be one.
+    pub machine_call: Option<MachineCallHook>,
+}
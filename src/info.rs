@@ -0,0 +1,105 @@
+use std::fmt::Write;
+
+use crate::analysis::{self, Extension};
+use crate::database::{self, Database};
+use crate::error::Chip8Error;
+use crate::machine::{MEMORY_SIZE, PROGRAM_START};
+use crate::opcode::Opcode;
+use crate::roms;
+
+// How many places of an extension instruction are listed before the rest is only counted
+const MAX_PLACES: usize = 4;
+
+pub fn run(location: &str) -> Result<(), Chip8Error> {
+    let rom = roms::read(location)?;
+    print!("{}", report(&rom, &Database::load()));
+    Ok(())
+}
+
+// What can be told about a ROM without running it.
+pub fn report(rom: &[u8], database: &Database) -> String {
+    let mut report = String::new();
+    let end = PROGRAM_START + rom.len();
+    let room = match MEMORY_SIZE.checked_sub(end) {
+        Some(free) => format!("{} bytes of memory left", free),
+        None => format!("{} bytes too large to fit in memory", end - MEMORY_SIZE),
+    };
+    let _ = writeln!(
+        report,
+        "Size        {} bytes at {:#05X}-{:#05X}, {}",
+        rom.len(),
+        PROGRAM_START,
+        end.saturating_sub(1).max(PROGRAM_START),
+        room
+    );
+    let _ = writeln!(report, "SHA-1       {}", database::sha1(rom));
+    let _ = writeln!(report, "CRC-32      {:08x}", database::crc32(rom));
+    let _ = match database.lookup(rom) {
+        Some(info) => writeln!(
+            report,
+            "Title       {} ({}), runs with quirks: {}",
+            info.title, info.platform, info.quirks
+        ),
+        None => writeln!(report, "Title       not in the ROM database"),
+    };
+
+    let entry = analysis::entry_block(rom);
+    let _ = writeln!(report, "Entry       {:#05X}", PROGRAM_START);
+    for &(addr, instruction) in &entry {
+        let text = match (analysis::extension(instruction), Opcode::decode(instruction)) {
+            (Some((_, description)), _) => description.to_string(),
+            (None, Ok(opcode)) => opcode.to_string(),
+            (None, Err(_)) => format!("DW {:#06X}", instruction),
+        };
+        let _ = writeln!(report, "            {:#05X}  {:04X}  {}", addr, instruction, text);
+    }
+    let _ = match entry.first() {
+        None => writeln!(report, "            the ROM holds no instruction"),
+        Some(&(_, instruction)) if Opcode::decode(instruction).is_err() => writeln!(
+            report,
+            "            not a CHIP-8 instruction, this may not be a program or be loaded at another address"
+        ),
+        _ => Ok(()),
+    };
+
+    let uses = analysis::extension_uses(rom, &analysis::analyze(rom));
+    if uses.is_empty() {
+        let _ = writeln!(report, "Extensions  none found, plain CHIP-8");
+    }
+    for extension in [Extension::SuperChip, Extension::XoChip] {
+        let found: Vec<_> = uses.iter().filter(|found| found.extension == extension).collect();
+        if found.is_empty() {
+            continue;
+        }
+        let mut places: Vec<String> = found
+            .iter()
+            .take(MAX_PLACES)
+            .map(|found| format!("{} at {:#05X}", found.description, found.addr))
+            .collect();
+        if found.len() > MAX_PLACES {
+            places.push(format!("{} more", found.len() - MAX_PLACES));
+        }
+        let _ = writeln!(report, "Extensions  {}: {}", extension.name(), places.join(", "));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_describes_the_rom() {
+        let database = Database::parse(&format!("{} modernChip8 none Clear", database::sha1(&[0x00, 0xE0]))).unwrap();
+        let text = report(&[0x00, 0xE0], &database);
+        assert!(text.contains("Size        2 bytes at 0x200-0x201, 3582 bytes of memory left"));
+        assert!(text.contains("Title       Clear (modernChip8), runs with quirks: none"));
+        assert!(text.contains("0x200  00E0  CLS"));
+        assert!(text.contains("Extensions  none"));
+
+        let text = report(&[0x00, 0xFF, 0x12, 0x00], &Database::default());
+        assert!(text.contains("Title       not in the ROM database"));
+        assert!(text.contains("0x200  00FF  high resolution\n"));
+        assert!(text.contains("Extensions  SUPER-CHIP: high resolution at 0x200"));
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use chip8_emulator_rs::config::KeyMap;
+use chip8_emulator_rs::frontend::Capture;
+use chip8_emulator_rs::machine::{DISPLAY_HEIGHT, DISPLAY_WIDTH, FONT};
+use chip8_emulator_rs::{Chip8Error, Display, Input};
+
+// Each pixel of the hex digit sprite becomes a square this size on the display
+const GLYPH_SCALE: usize = 4;
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// Walks through the CHIP-8 keys 0 to F, showing each one on the display and binding it to the next keyboard key
+// that is pressed. None when it was cancelled with Escape or by closing the window.
+pub fn configure_keys(
+    display: &mut dyn Display,
+    input: &mut dyn Input,
+    keys: &KeyMap,
+) -> Result<Option<KeyMap>, Chip8Error> {
+    let mut keys = keys.clone();
+    for key in 0..16u8 {
+        println!(
+            "Press the key for CHIP-8 key {:X} (now {}), Escape cancels",
+            key, keys.keys[key as usize]
+        );
+        display.draw(&glyph(key))?;
+        let name = loop {
+            match input.capture_key()? {
+                Some(Capture::Key(name)) => break name,
+                Some(Capture::Cancel) => return Ok(None),
+                None => std::thread::sleep(POLL_INTERVAL),
+            }
+        };
+        println!("  {:X} = {}", key, name);
+        keys.keys[key as usize] = name;
+    }
+    Ok(Some(keys))
+}
+
+// The display with the font sprite of key scaled up in its center.
+fn glyph(key: u8) -> Vec<u8> {
+    let mut pixels = vec![0; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+    let left = (DISPLAY_WIDTH - 4 * GLYPH_SCALE) / 2;
+    let top = (DISPLAY_HEIGHT - 5 * GLYPH_SCALE) / 2;
+    for (row, &bits) in FONT[key as usize * 5..][..5].iter().enumerate() {
+        for column in (0..4).filter(|column| bits & (0x80 >> column) != 0) {
+            for y in 0..GLYPH_SCALE {
+                let start = (top + row * GLYPH_SCALE + y) * DISPLAY_WIDTH + left + column * GLYPH_SCALE;
+                pixels[start..start + GLYPH_SCALE].fill(1);
+            }
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Script(Vec<Option<Capture>>);
+
+    impl Input for Script {
+        fn poll(&mut self, _keypad: &mut chip8_emulator_rs::Keypad) -> Result<u32, Chip8Error> {
+            Ok(0)
+        }
+
+        fn capture_key(&mut self) -> Result<Option<Capture>, Chip8Error> {
+            Ok(self.0.remove(0))
+        }
+    }
+
+    struct NoDisplay;
+
+    impl Display for NoDisplay {
+        fn draw(&mut self, _pixels: &[u8]) -> Result<(), Chip8Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_configure_keys_binds_in_order() {
+        let mut presses: Vec<_> = (0..16).map(|key| Some(Capture::Key(format!("Keypad {key}")))).collect();
+        presses.insert(3, None);
+        let keys = configure_keys(&mut NoDisplay, &mut Script(presses), &KeyMap::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(keys.keys[0x0], "Keypad 0");
+        assert_eq!(keys.keys[0xF], "Keypad 15");
+
+        let cancelled = vec![Some(Capture::Key("Q".to_string())), Some(Capture::Cancel)];
+        assert!(
+            configure_keys(&mut NoDisplay, &mut Script(cancelled), &KeyMap::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_glyph_scales_the_font() {
+        // The top row of the 1 sprite is 0x20, a single lit column
+        let pixels = glyph(1);
+        let top = (DISPLAY_HEIGHT - 20) / 2;
+        let row: Vec<u8> = pixels[top * DISPLAY_WIDTH..][..DISPLAY_WIDTH].to_vec();
+        assert_eq!(row.iter().filter(|&&pixel| pixel != 0).count(), GLYPH_SCALE);
+        assert_eq!(row[(DISPLAY_WIDTH - 16) / 2 + 2 * GLYPH_SCALE], 1);
+    }
+}
@@ -0,0 +1,203 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chip8_emulator_rs::config::KeyMap;
+use chip8_emulator_rs::frontend::QUIT;
+use chip8_emulator_rs::machine::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use chip8_emulator_rs::{Chip8Error, Display, Input, Keypad};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+// Every entry is a band of lit or unlit pixels with a line of 3x5 letters in it
+const LINE_HEIGHT: usize = 6;
+const GLYPH_WIDTH: usize = 4;
+const VISIBLE_LINES: usize = DISPLAY_HEIGHT / LINE_HEIGHT;
+const MAX_NAME: usize = (DISPLAY_WIDTH - 1) / GLYPH_WIDTH;
+
+// The keypad keys the menu listens to, the ones games use for up, down, left, right and fire
+const UP: u8 = 0x5;
+const DOWN: u8 = 0x8;
+const PAGE_UP: u8 = 0x7;
+const PAGE_DOWN: u8 = 0x9;
+const OPEN: u8 = 0x6;
+
+// Rows of three pixels, the highest bit is the left one. Letters are shown in upper case.
+const GLYPHS: [(char, [u8; 5]); 39] = [
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b110, 0b001, 0b010, 0b100, 0b111]),
+    ('3', [0b110, 0b001, 0b010, 0b001, 0b110]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b110, 0b001, 0b110]),
+    ('6', [0b011, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b110]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+];
+
+// Lists the ROMs on the display, one name per line with the selected one lit up, until one is opened with
+// the keypad. None when the window was closed or the quit hotkey pressed.
+pub fn browse(
+    display: &mut dyn Display,
+    input: &mut dyn Input,
+    keys: &KeyMap,
+    roms: &[PathBuf],
+) -> Result<Option<PathBuf>, Chip8Error> {
+    println!(
+        "{} / {} select a ROM, {} / {} skip a page, {} starts it, {} quits",
+        keys.keys[UP as usize],
+        keys.keys[DOWN as usize],
+        keys.keys[PAGE_UP as usize],
+        keys.keys[PAGE_DOWN as usize],
+        keys.keys[OPEN as usize],
+        keys.hotkeys.quit
+    );
+    let mut menu = Menu::new(roms.len());
+    let mut keypad = Keypad::default();
+    let mut redraw = true;
+    loop {
+        if redraw {
+            display.draw(&menu.render(roms))?;
+            redraw = false;
+        }
+        if input.poll(&mut keypad)? & QUIT != 0 {
+            return Ok(None);
+        }
+        while let Some(key) = keypad.wait_for_press() {
+            if key == OPEN {
+                return Ok(roms.get(menu.selected).cloned());
+            }
+            redraw |= menu.press(key);
+        }
+        keypad.end_frame();
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// Which entry is selected and which one is on the top line.
+struct Menu {
+    len: usize,
+    selected: usize,
+    top: usize,
+}
+
+impl Menu {
+    fn new(len: usize) -> Self {
+        Menu {
+            len,
+            selected: 0,
+            top: 0,
+        }
+    }
+
+    // Moves the selection, true when it changed.
+    fn press(&mut self, key: u8) -> bool {
+        let last = self.len.saturating_sub(1);
+        let selected = match key {
+            UP => self.selected.saturating_sub(1),
+            DOWN => (self.selected + 1).min(last),
+            PAGE_UP => self.selected.saturating_sub(VISIBLE_LINES),
+            PAGE_DOWN => (self.selected + VISIBLE_LINES).min(last),
+            _ => self.selected,
+        };
+        if selected == self.selected {
+            return false;
+        }
+        self.selected = selected;
+        self.top = self.top.clamp(selected.saturating_sub(VISIBLE_LINES - 1), selected);
+        true
+    }
+
+    fn render(&self, roms: &[PathBuf]) -> Vec<u8> {
+        let mut pixels = vec![0; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+        for (line, rom) in roms.iter().enumerate().skip(self.top).take(VISIBLE_LINES) {
+            let top = (line - self.top) * LINE_HEIGHT;
+            let lit = line == self.selected;
+            if lit {
+                pixels[top * DISPLAY_WIDTH..(top + LINE_HEIGHT) * DISPLAY_WIDTH].fill(1);
+            }
+            for (column, letter) in name(rom).chars().take(MAX_NAME).enumerate() {
+                draw_glyph(&mut pixels, 1 + column * GLYPH_WIDTH, top + 1, letter, !lit);
+            }
+        }
+        pixels
+    }
+}
+
+fn name(rom: &Path) -> String {
+    rom.file_stem().unwrap_or_default().to_string_lossy().to_uppercase()
+}
+
+// Letters without a glyph, spaces included, are left blank.
+fn draw_glyph(pixels: &mut [u8], left: usize, top: usize, letter: char, lit: bool) {
+    let Some((_, rows)) = GLYPHS.iter().find(|&&(known, _)| known == letter) else {
+        return;
+    };
+    for (row, &bits) in rows.iter().enumerate() {
+        for column in (0..3).filter(|column| bits & (0b100 >> column) != 0) {
+            pixels[(top + row) * DISPLAY_WIDTH + left + column] = lit as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu_scrolls_with_the_selection() {
+        let mut menu = Menu::new(8);
+        assert!(!menu.press(UP));
+        for _ in 0..VISIBLE_LINES {
+            assert!(menu.press(DOWN));
+        }
+        assert_eq!((menu.selected, menu.top), (VISIBLE_LINES, 1));
+        assert!(menu.press(PAGE_DOWN));
+        assert_eq!((menu.selected, menu.top), (7, 3));
+        assert!(!menu.press(DOWN));
+        assert!(menu.press(PAGE_UP));
+        assert_eq!((menu.selected, menu.top), (2, 2));
+    }
+
+    #[test]
+    fn test_render_lights_up_the_selected_line() {
+        let roms = [PathBuf::from("roms/pong.ch8"), PathBuf::from("roms/tetris.ch8")];
+        let mut menu = Menu::new(roms.len());
+        menu.press(DOWN);
+        let pixels = menu.render(&roms);
+        let row = |y: usize| &pixels[y * DISPLAY_WIDTH..(y + 1) * DISPLAY_WIDTH];
+        // the top of the P of PONG, then the unlit top of the T of TETRIS in a lit band
+        assert_eq!(row(1)[..5], [0, 1, 1, 0, 0]);
+        assert_eq!(row(LINE_HEIGHT)[..5], [1, 1, 1, 1, 1]);
+        assert_eq!(row(LINE_HEIGHT + 1)[..5], [1, 0, 0, 0, 1]);
+        assert!(row(2 * LINE_HEIGHT).iter().all(|&pixel| pixel == 0));
+    }
+}
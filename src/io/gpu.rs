@@ -0,0 +1,518 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::platform::pump_events::{EventLoopExtPumpEvents, PumpStatus};
+use winit::window::{Window, WindowId};
+
+use chip8_emulator_rs::config::{Config, KeyMap, Layout, Palette};
+use chip8_emulator_rs::frontend::{Capture, QUIT};
+use chip8_emulator_rs::{Chip8Error, Display, Input, Keypad};
+
+use super::keys::KeyTable;
+
+// Window creation is asynchronous on some platforms, give up if it takes longer than this
+const OPEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+const KEY_TABLE: KeyTable<KeyCode> = KeyTable {
+    letters: [
+        KeyCode::KeyA,
+        KeyCode::KeyB,
+        KeyCode::KeyC,
+        KeyCode::KeyD,
+        KeyCode::KeyE,
+        KeyCode::KeyF,
+        KeyCode::KeyG,
+        KeyCode::KeyH,
+        KeyCode::KeyI,
+        KeyCode::KeyJ,
+        KeyCode::KeyK,
+        KeyCode::KeyL,
+        KeyCode::KeyM,
+        KeyCode::KeyN,
+        KeyCode::KeyO,
+        KeyCode::KeyP,
+        KeyCode::KeyQ,
+        KeyCode::KeyR,
+        KeyCode::KeyS,
+        KeyCode::KeyT,
+        KeyCode::KeyU,
+        KeyCode::KeyV,
+        KeyCode::KeyW,
+        KeyCode::KeyX,
+        KeyCode::KeyY,
+        KeyCode::KeyZ,
+    ],
+    digits: [
+        KeyCode::Digit0,
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ],
+    named: &[
+        ("Space", KeyCode::Space),
+        ("Escape", KeyCode::Escape),
+        ("Return", KeyCode::Enter),
+        ("Tab", KeyCode::Tab),
+        ("Backspace", KeyCode::Backspace),
+        ("Up", KeyCode::ArrowUp),
+        ("Down", KeyCode::ArrowDown),
+        ("Left", KeyCode::ArrowLeft),
+        ("Right", KeyCode::ArrowRight),
+        ("Keypad 0", KeyCode::Numpad0),
+        ("Keypad 1", KeyCode::Numpad1),
+        ("Keypad 2", KeyCode::Numpad2),
+        ("Keypad 3", KeyCode::Numpad3),
+        ("Keypad 4", KeyCode::Numpad4),
+        ("Keypad 5", KeyCode::Numpad5),
+        ("Keypad 6", KeyCode::Numpad6),
+        ("Keypad 7", KeyCode::Numpad7),
+        ("Keypad 8", KeyCode::Numpad8),
+        ("Keypad 9", KeyCode::Numpad9),
+        (",", KeyCode::Comma),
+        (".", KeyCode::Period),
+        ("/", KeyCode::Slash),
+        (";", KeyCode::Semicolon),
+        ("'", KeyCode::Quote),
+        ("[", KeyCode::BracketLeft),
+        ("]", KeyCode::BracketRight),
+        ("-", KeyCode::Minus),
+        ("=", KeyCode::Equal),
+        ("`", KeyCode::Backquote),
+        ("\\", KeyCode::Backslash),
+        ("F1", KeyCode::F1),
+        ("F2", KeyCode::F2),
+        ("F3", KeyCode::F3),
+        ("F4", KeyCode::F4),
+        ("F5", KeyCode::F5),
+        ("F6", KeyCode::F6),
+        ("F7", KeyCode::F7),
+        ("F8", KeyCode::F8),
+        ("F9", KeyCode::F9),
+        ("F10", KeyCode::F10),
+        ("F11", KeyCode::F11),
+        ("F12", KeyCode::F12),
+    ],
+};
+
+struct Renderer {
+    window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+// Receives the winit events, pumped from Input::poll since the emulator owns the main loop.
+struct App {
+    width: u32,
+    height: u32,
+    scale: u32,
+    palette: Palette,
+    renderer: Option<Renderer>,
+    error: Option<Chip8Error>,
+    // the display as an sRGB texture, four bytes per pixel
+    texels: Vec<u8>,
+    // the key codes are positions, the key map names keys by their label on this layout
+    layout: Layout,
+    bindings: Vec<(KeyCode, u8)>,
+    controls: Vec<(KeyCode, u32)>,
+    keys: Vec<(u8, bool)>,
+    // every key that went down since the last capture_key, while binding keys
+    captured: Vec<KeyCode>,
+    status: u32,
+}
+
+struct Shared {
+    event_loop: EventLoop<()>,
+    app: App,
+}
+
+pub struct Screen(Rc<RefCell<Shared>>);
+
+pub struct Keyboard(Rc<RefCell<Shared>>);
+
+// There is no audio output, this frontend is about the picture.
+pub fn open(width: usize, height: usize, config: &Config) -> Result<super::Frontends, Chip8Error> {
+    let mut event_loop = EventLoop::new().map_err(gpu_error)?;
+    let positions = config.key_map().to_qwerty();
+    let mut app = App {
+        width: width as u32,
+        height: height as u32,
+        scale: config.scale,
+        palette: config.palette,
+        renderer: None,
+        error: None,
+        texels: vec![0; width * height * 4],
+        layout: config.layout,
+        bindings: KEY_TABLE.bindings(&positions)?,
+        controls: KEY_TABLE.controls(&positions)?,
+        keys: Vec::new(),
+        captured: Vec::new(),
+        status: 0,
+    };
+
+    let started = std::time::Instant::now();
+    while app.renderer.is_none() && app.error.is_none() {
+        if let PumpStatus::Exit(_) = event_loop.pump_app_events(Some(Duration::from_millis(10)), &mut app) {
+            break;
+        }
+        if started.elapsed() > OPEN_TIMEOUT {
+            break;
+        }
+    }
+    if let Some(err) = app.error.take() {
+        return Err(err);
+    }
+    if app.renderer.is_none() {
+        return Err(Chip8Error::IoError(std::io::Error::other(
+            "the window could not be opened",
+        )));
+    }
+
+    let shared = Rc::new(RefCell::new(Shared { event_loop, app }));
+    Ok((Box::new(Screen(shared.clone())), Box::new(Keyboard(shared)), None))
+}
+
+impl Display for Screen {
+    fn draw(&mut self, pixels: &[u8]) -> Result<(), Chip8Error> {
+        let app = &mut self.0.borrow_mut().app;
+        let (foreground, background) = (app.palette.foreground, app.palette.background);
+        for (texel, &pixel) in app.texels.chunks_exact_mut(4).zip(pixels) {
+            let color = if pixel != 0 { foreground } else { background };
+            texel.copy_from_slice(&[color.0, color.1, color.2, 0xFF]);
+        }
+        app.render()
+    }
+
+    fn set_palette(&mut self, palette: Palette) {
+        self.0.borrow_mut().app.palette = palette;
+    }
+}
+
+impl Input for Keyboard {
+    fn poll(&mut self, keypad: &mut Keypad) -> Result<u32, Chip8Error> {
+        let Shared { event_loop, app } = &mut *self.0.borrow_mut();
+        if let PumpStatus::Exit(_) = event_loop.pump_app_events(Some(Duration::ZERO), app) {
+            return Ok(QUIT);
+        }
+        if let Some(err) = app.error.take() {
+            return Err(err);
+        }
+        for (key, pressed) in app.keys.drain(..) {
+            if pressed {
+                keypad.press(key);
+            } else {
+                keypad.release(key);
+            }
+        }
+        Ok(std::mem::take(&mut app.status))
+    }
+
+    fn set_key_map(&mut self, keys: &KeyMap) -> Result<(), Chip8Error> {
+        let app = &mut self.0.borrow_mut().app;
+        let positions = keys.to_qwerty();
+        app.bindings = KEY_TABLE.bindings(&positions)?;
+        app.controls = KEY_TABLE.controls(&positions)?;
+        app.layout = keys.layout;
+        Ok(())
+    }
+
+    fn capture_key(&mut self) -> Result<Option<Capture>, Chip8Error> {
+        let Shared { event_loop, app } = &mut *self.0.borrow_mut();
+        app.captured.clear();
+        if let PumpStatus::Exit(_) = event_loop.pump_app_events(Some(Duration::ZERO), app) {
+            return Ok(Some(Capture::Cancel));
+        }
+        if let Some(err) = app.error.take() {
+            return Err(err);
+        }
+        app.keys.clear();
+        app.status = 0;
+        if app.captured.contains(&KeyCode::Escape) {
+            return Ok(Some(Capture::Cancel));
+        }
+        Ok(app
+            .captured
+            .iter()
+            .find_map(|&code| KEY_TABLE.name(code))
+            .map(|name| Capture::Key(app.layout.from_qwerty(&name))))
+    }
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.renderer.is_some() {
+            return;
+        }
+        let attributes = Window::default_attributes()
+            .with_title("chip8-emulator-rs")
+            .with_inner_size(LogicalSize::new(self.width * self.scale, self.height * self.scale));
+        let renderer = event_loop
+            .create_window(attributes)
+            .map_err(gpu_error)
+            .and_then(|window| Renderer::new(Arc::new(window), self.width, self.height));
+        match renderer {
+            Ok(renderer) => self.renderer = Some(renderer),
+            Err(err) => {
+                self.error = Some(err);
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => {
+                self.status |= QUIT;
+                event_loop.exit();
+            }
+            WindowEvent::Resized(size) => {
+                if let Some(renderer) = &mut self.renderer {
+                    renderer.resize(size.width, size.height);
+                }
+            }
+            WindowEvent::RedrawRequested => {
+                if let Err(err) = self.render() {
+                    self.error = Some(err);
+                }
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(code),
+                        state,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                let pressed = state == ElementState::Pressed;
+                if pressed {
+                    self.captured.push(code);
+                }
+                // Keypad bindings win over the controls, a remapped key must not pause the game
+                let keys = chip8_keys(&self.bindings, code);
+                if !keys.is_empty() {
+                    self.keys.extend(keys.into_iter().map(|key| (key, pressed)));
+                } else if let Some(&(_, flag)) = self.controls.iter().find(|(control, _)| *control == code) {
+                    if pressed {
+                        self.status |= flag;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl App {
+    fn render(&mut self) -> Result<(), Chip8Error> {
+        match &mut self.renderer {
+            Some(renderer) => renderer.render(&self.texels, self.width, self.height),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Renderer {
+    fn new(window: Arc<Window>, width: u32, height: u32) -> Result<Self, Chip8Error> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let surface = instance.create_surface(window.clone()).map_err(gpu_error)?;
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            compatible_surface: Some(&surface),
+            ..Default::default()
+        }))
+        .map_err(gpu_error)?;
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).map_err(gpu_error)?;
+
+        let size = window.inner_size();
+        let config = surface
+            .get_default_config(&adapter, size.width.max(1), size.height.max(1))
+            .ok_or_else(|| Chip8Error::IoError(std::io::Error::other("the window surface is not supported")))?;
+        surface.configure(&device, &config);
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("display"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        // Nearest keeps the pixels sharp, Linear blurs them when scaling up
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("gpu.wgsl"));
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("display"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(config.format.into())],
+            }),
+            multiview: None,
+            cache: None,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("display"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.create_view(&Default::default())),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Ok(Renderer {
+            window,
+            surface,
+            device,
+            queue,
+            config,
+            texture,
+            bind_group,
+            pipeline,
+        })
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.config.width = width;
+            self.config.height = height;
+            self.surface.configure(&self.device, &self.config);
+            self.window.request_redraw();
+        }
+    }
+
+    fn render(&mut self, texels: &[u8], width: u32, height: u32) -> Result<(), Chip8Error> {
+        self.queue.write_texture(
+            self.texture.as_image_copy(),
+            texels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 4),
+                rows_per_image: Some(height),
+            },
+            self.texture.size(),
+        );
+
+        let frame = match self.surface.get_current_texture() {
+            Ok(frame) => frame,
+            // the window was resized or minimized, the next frame gets a fresh surface
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.config);
+                return Ok(());
+            }
+            Err(wgpu::SurfaceError::Timeout) => return Ok(()),
+            Err(err) => return Err(gpu_error(err)),
+        };
+        let view = frame.texture.create_view(&Default::default());
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("display"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        self.queue.submit([encoder.finish()]);
+        self.window.pre_present_notify();
+        frame.present();
+        Ok(())
+    }
+}
+
+fn chip8_keys(bindings: &[(KeyCode, u8)], code: KeyCode) -> Vec<u8> {
+    bindings
+        .iter()
+        .filter(|&&(bound, _)| bound == code)
+        .map(|&(_, key)| key)
+        .collect()
+}
+
+fn gpu_error<E: std::error::Error + Send + Sync + 'static>(err: E) -> Chip8Error {
+    Chip8Error::IoError(std::io::Error::other(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_follow_key_map() {
+        let mut keys = KeyMap::default();
+        let bindings = KEY_TABLE.bindings(&keys).unwrap();
+        assert_eq!(chip8_keys(&bindings, KeyCode::Digit1), [0x1]);
+        assert_eq!(chip8_keys(&bindings, KeyCode::KeyR), [0xD]);
+        assert_eq!(chip8_keys(&bindings, KeyCode::KeyX), [0x0]);
+        assert_eq!(chip8_keys(&bindings, KeyCode::KeyV), [0xF]);
+        assert!(chip8_keys(&bindings, KeyCode::KeyP).is_empty());
+
+        keys.keys[0x5] = "Up".to_string();
+        keys.keys[0x8] = "keypad 2".to_string();
+        let bindings = KEY_TABLE.bindings(&keys).unwrap();
+        assert_eq!(chip8_keys(&bindings, KeyCode::ArrowUp), [0x5]);
+        assert_eq!(chip8_keys(&bindings, KeyCode::Numpad2), [0x8]);
+        assert!(chip8_keys(&bindings, KeyCode::KeyW).is_empty());
+
+        let bindings = KEY_TABLE.bindings(&Layout::Azerty.key_map().to_qwerty()).unwrap();
+        assert_eq!(chip8_keys(&bindings, KeyCode::KeyQ), [0x4]);
+        assert_eq!(chip8_keys(&bindings, KeyCode::KeyZ), [0xA]);
+    }
+}
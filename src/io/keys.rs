@@ -0,0 +1,118 @@
+use chip8_emulator_rs::config::KeyMap;
+use chip8_emulator_rs::Chip8Error;
+
+// How a frontend without key names of its own finds the keys of a key map: letters and digits by their
+// character, everything else in its list of named keys. The names are SDL's scancode names, matched
+// without regard to case.
+pub struct KeyTable<K: 'static> {
+    pub letters: [K; 26],
+    pub digits: [K; 10],
+    pub named: &'static [(&'static str, K)],
+}
+
+impl<K: Copy + PartialEq> KeyTable<K> {
+    pub fn code(&self, name: &str) -> Option<K> {
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => {
+                Some(self.letters[(c.to_ascii_uppercase() as u8 - b'A') as usize])
+            }
+            (Some(c), None) if c.is_ascii_digit() => Some(self.digits[(c as u8 - b'0') as usize]),
+            _ => self
+                .named
+                .iter()
+                .find(|(named, _)| named.eq_ignore_ascii_case(name))
+                .map(|&(_, code)| code),
+        }
+    }
+
+    // The name code goes by in a key map, None for keys the table does not have.
+    pub fn name(&self, code: K) -> Option<String> {
+        if let Some(pos) = self.letters.iter().position(|&letter| letter == code) {
+            return Some(((b'A' + pos as u8) as char).to_string());
+        }
+        if let Some(pos) = self.digits.iter().position(|&digit| digit == code) {
+            return Some(pos.to_string());
+        }
+        self.named
+            .iter()
+            .find(|&&(_, named)| named == code)
+            .map(|(name, _)| name.to_string())
+    }
+
+    // The keyboard key and the CHIP-8 key it presses for every entry of the key map.
+    pub fn bindings(&self, keys: &KeyMap) -> Result<Vec<(K, u8)>, Chip8Error> {
+        keys.iter()
+            .map(|(key, name)| {
+                self.code(name)
+                    .map(|code| (code, key))
+                    .ok_or_else(|| crate::io::unknown_key(key, name))
+            })
+            .collect()
+    }
+
+    // The keyboard key and the control flag it triggers for every hotkey of the key map.
+    pub fn controls(&self, keys: &KeyMap) -> Result<Vec<(K, u32)>, Chip8Error> {
+        keys.hotkeys
+            .iter()
+            .map(|(flag, name)| {
+                self.code(name)
+                    .map(|code| (code, flag))
+                    .ok_or_else(|| crate::io::unknown_hotkey(name))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_emulator_rs::frontend::{CHEAT, PAUSE};
+
+    const TABLE: KeyTable<char> = KeyTable {
+        letters: [
+            'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u',
+            'v', 'w', 'x', 'y', 'z',
+        ],
+        digits: ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'],
+        // F1 to F9 for the default cheat hotkeys, as characters of the private use area
+        named: &[
+            ("Space", ' '),
+            (",", ','),
+            ("Escape", '\u{1b}'),
+            ("Tab", '\t'),
+            ("F1", '\u{E001}'),
+            ("F2", '\u{E002}'),
+            ("F3", '\u{E003}'),
+            ("F4", '\u{E004}'),
+            ("F5", '\u{E005}'),
+            ("F6", '\u{E006}'),
+            ("F7", '\u{E007}'),
+            ("F8", '\u{E008}'),
+            ("F9", '\u{E009}'),
+        ],
+    };
+
+    #[test]
+    fn test_key_table_resolves_names() {
+        assert_eq!(TABLE.code("q"), Some('q'));
+        assert_eq!(TABLE.code("Q"), Some('q'));
+        assert_eq!(TABLE.code("7"), Some('7'));
+        assert_eq!(TABLE.code("space"), Some(' '));
+        assert_eq!(TABLE.code(","), Some(','));
+        assert_eq!(TABLE.code("Keypad 1"), None);
+        assert_eq!(TABLE.name('q').as_deref(), Some("Q"));
+        assert_eq!(TABLE.name('7').as_deref(), Some("7"));
+        assert_eq!(TABLE.name(' ').as_deref(), Some("Space"));
+        assert_eq!(TABLE.name('!'), None);
+
+        let mut keys = KeyMap::default();
+        assert_eq!(TABLE.bindings(&keys).unwrap()[0xA], ('z', 0xA));
+        assert_eq!(TABLE.controls(&keys).unwrap()[0], ('p', PAUSE));
+        assert!(TABLE.controls(&keys).unwrap().contains(&('\u{E001}', CHEAT)));
+        keys.keys[3] = "Enter".to_string();
+        assert!(TABLE.bindings(&keys).is_err());
+        keys.hotkeys.quit = "F12".to_string();
+        assert!(TABLE.controls(&keys).is_err());
+    }
+}
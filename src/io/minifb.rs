@@ -0,0 +1,243 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use minifb::{Key, KeyRepeat, Scale, Window, WindowOptions};
+
+use chip8_emulator_rs::config::{Config, KeyMap, Palette};
+use chip8_emulator_rs::frontend::{Capture, QUIT};
+use chip8_emulator_rs::{Chip8Error, Display, Input, Keypad};
+
+use super::keys::KeyTable;
+
+const KEY_TABLE: KeyTable<Key> = KeyTable {
+    letters: [
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+    ],
+    digits: [
+        Key::Key0,
+        Key::Key1,
+        Key::Key2,
+        Key::Key3,
+        Key::Key4,
+        Key::Key5,
+        Key::Key6,
+        Key::Key7,
+        Key::Key8,
+        Key::Key9,
+    ],
+    named: &[
+        ("Space", Key::Space),
+        ("Escape", Key::Escape),
+        ("Return", Key::Enter),
+        ("Tab", Key::Tab),
+        ("Backspace", Key::Backspace),
+        ("Up", Key::Up),
+        ("Down", Key::Down),
+        ("Left", Key::Left),
+        ("Right", Key::Right),
+        ("Keypad 0", Key::NumPad0),
+        ("Keypad 1", Key::NumPad1),
+        ("Keypad 2", Key::NumPad2),
+        ("Keypad 3", Key::NumPad3),
+        ("Keypad 4", Key::NumPad4),
+        ("Keypad 5", Key::NumPad5),
+        ("Keypad 6", Key::NumPad6),
+        ("Keypad 7", Key::NumPad7),
+        ("Keypad 8", Key::NumPad8),
+        ("Keypad 9", Key::NumPad9),
+        (",", Key::Comma),
+        (".", Key::Period),
+        ("/", Key::Slash),
+        (";", Key::Semicolon),
+        ("'", Key::Apostrophe),
+        ("[", Key::LeftBracket),
+        ("]", Key::RightBracket),
+        ("-", Key::Minus),
+        ("=", Key::Equal),
+        ("`", Key::Backquote),
+        ("\\", Key::Backslash),
+        ("F1", Key::F1),
+        ("F2", Key::F2),
+        ("F3", Key::F3),
+        ("F4", Key::F4),
+        ("F5", Key::F5),
+        ("F6", Key::F6),
+        ("F7", Key::F7),
+        ("F8", Key::F8),
+        ("F9", Key::F9),
+        ("F10", Key::F10),
+        ("F11", Key::F11),
+        ("F12", Key::F12),
+    ],
+};
+
+// minifb draws and reads keys through the same window, the display and the input share it.
+pub struct Screen {
+    window: Rc<RefCell<Window>>,
+    buffer: Vec<u32>,
+    width: usize,
+    height: usize,
+    palette: Palette,
+}
+
+pub struct Keyboard {
+    window: Rc<RefCell<Window>>,
+    keys: Vec<(Key, u8)>,
+    controls: Vec<(Key, u32)>,
+}
+
+// There is no audio output, minifb only does windows.
+pub fn open(width: usize, height: usize, config: &Config) -> Result<super::Frontends, Chip8Error> {
+    let options = WindowOptions {
+        scale: window_scale(config.scale),
+        ..WindowOptions::default()
+    };
+    let mut window = Window::new("chip8-emulator-rs", width, height, options).map_err(minifb_error)?;
+    // poll is called after every instruction, it must not wait for the next frame
+    window.set_target_fps(0);
+
+    let window = Rc::new(RefCell::new(window));
+    let screen = Screen {
+        window: window.clone(),
+        buffer: vec![config.palette.background.to_u32(); width * height],
+        width,
+        height,
+        palette: config.palette,
+    };
+    let keys = config.key_map();
+    let keyboard = Keyboard {
+        window,
+        keys: KEY_TABLE.bindings(&keys)?,
+        controls: KEY_TABLE.controls(&keys)?,
+    };
+    Ok((Box::new(screen), Box::new(keyboard), None))
+}
+
+impl Display for Screen {
+    fn draw(&mut self, pixels: &[u8]) -> Result<(), Chip8Error> {
+        fill_buffer(&mut self.buffer, pixels, self.palette);
+        self.window
+            .borrow_mut()
+            .update_with_buffer(&self.buffer, self.width, self.height)
+            .map_err(minifb_error)
+    }
+
+    fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+}
+
+impl Input for Keyboard {
+    fn poll(&mut self, keypad: &mut Keypad) -> Result<u32, Chip8Error> {
+        let mut window = self.window.borrow_mut();
+        window.update();
+        if !window.is_open() {
+            return Ok(QUIT);
+        }
+
+        let mut status = 0;
+        // Keypad bindings win over the controls, a remapped key must not pause the game
+        for &(code, flag) in &self.controls {
+            if window.is_key_pressed(code, KeyRepeat::No) && !self.keys.iter().any(|&(bound, _)| bound == code) {
+                status |= flag;
+            }
+        }
+        let mut pressed = [false; 16];
+        for &(code, key) in &self.keys {
+            pressed[key as usize] |= window.is_key_down(code);
+        }
+        for (key, pressed) in pressed.into_iter().enumerate() {
+            if pressed {
+                keypad.press(key as u8);
+            } else {
+                keypad.release(key as u8);
+            }
+        }
+        Ok(status)
+    }
+
+    fn set_key_map(&mut self, keys: &KeyMap) -> Result<(), Chip8Error> {
+        self.keys = KEY_TABLE.bindings(keys)?;
+        self.controls = KEY_TABLE.controls(keys)?;
+        Ok(())
+    }
+
+    fn capture_key(&mut self) -> Result<Option<Capture>, Chip8Error> {
+        let mut window = self.window.borrow_mut();
+        window.update();
+        if !window.is_open() || window.is_key_down(Key::Escape) {
+            return Ok(Some(Capture::Cancel));
+        }
+        let pressed = window.get_keys_pressed(KeyRepeat::No);
+        Ok(pressed
+            .into_iter()
+            .find_map(|code| KEY_TABLE.name(code))
+            .map(Capture::Key))
+    }
+}
+
+fn fill_buffer(buffer: &mut [u32], pixels: &[u8], palette: Palette) {
+    let (foreground, background) = (palette.foreground.to_u32(), palette.background.to_u32());
+    for (color, &pixel) in buffer.iter_mut().zip(pixels) {
+        *color = if pixel != 0 { foreground } else { background };
+    }
+}
+
+// minifb only scales by powers of two, the largest one not above the configured scale is used.
+fn window_scale(scale: u32) -> Scale {
+    match scale {
+        0..=1 => Scale::X1,
+        2..=3 => Scale::X2,
+        4..=7 => Scale::X4,
+        8..=15 => Scale::X8,
+        16..=31 => Scale::X16,
+        _ => Scale::X32,
+    }
+}
+
+fn minifb_error(err: minifb::Error) -> Chip8Error {
+    Chip8Error::IoError(std::io::Error::other(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_emulator_rs::config::Rgb;
+
+    #[test]
+    fn test_buffer_follows_pixels() {
+        let palette = Palette {
+            foreground: Rgb(0x33, 0xFF, 0x66),
+            background: Rgb(0x10, 0x10, 0x10),
+            ..Palette::default()
+        };
+        let mut buffer = [0; 4];
+        fill_buffer(&mut buffer, &[0, 1, 1, 0], palette);
+        assert_eq!(buffer, [0x101010, 0x33FF66, 0x33FF66, 0x101010]);
+    }
+}
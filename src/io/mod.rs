@@ -0,0 +1,76 @@
+mod bind;
+mod browse;
+#[cfg(feature = "wgpu")]
+mod gpu;
+#[cfg(any(feature = "minifb", feature = "wgpu", feature = "terminal"))]
+mod keys;
+#[cfg(feature = "minifb")]
+mod minifb;
+#[cfg(feature = "sdl")]
+mod sdl;
+#[cfg(feature = "terminal")]
+mod terminal;
+
+use clap::ValueEnum;
+
+pub use bind::configure_keys;
+pub use browse::browse;
+
+use chip8_emulator_rs::config::Config;
+use chip8_emulator_rs::{Audio, Chip8Error, Display, Input};
+
+// The display, the input reading its events and the audio output if there is one.
+pub type Frontends = (Box<dyn Display>, Box<dyn Input>, Option<Box<dyn Audio>>);
+
+// Only the frontends enabled with cargo features can be selected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Frontend {
+    #[cfg(feature = "sdl")]
+    Sdl,
+    #[cfg(feature = "minifb")]
+    Minifb,
+    #[cfg(feature = "wgpu")]
+    Wgpu,
+    #[cfg(feature = "terminal")]
+    Terminal,
+}
+
+// Opens the given frontend, or the first one that was compiled in, with the window size, colors and sound from
+// the config.
+#[cfg_attr(
+    not(any(feature = "sdl", feature = "minifb", feature = "wgpu", feature = "terminal")),
+    allow(unused_variables)
+)]
+pub fn open(frontend: Option<Frontend>, width: usize, height: usize, config: &Config) -> Result<Frontends, Chip8Error> {
+    let Some(frontend) = frontend.or_else(|| Frontend::value_variants().first().copied()) else {
+        return Err(Chip8Error::IoError(std::io::Error::other(
+            "no frontend was compiled in, run with --headless or build with --features sdl, minifb, wgpu or terminal",
+        )));
+    };
+    match frontend {
+        #[cfg(feature = "sdl")]
+        Frontend::Sdl => sdl::open(width, height, config),
+        #[cfg(feature = "minifb")]
+        Frontend::Minifb => minifb::open(width, height, config),
+        #[cfg(feature = "wgpu")]
+        Frontend::Wgpu => gpu::open(width, height, config),
+        #[cfg(feature = "terminal")]
+        Frontend::Terminal => terminal::open(width, height, config),
+    }
+}
+
+#[cfg_attr(
+    not(any(feature = "sdl", feature = "minifb", feature = "wgpu", feature = "terminal")),
+    allow(dead_code)
+)]
+fn unknown_key(key: u8, name: &str) -> Chip8Error {
+    Chip8Error::InvalidConfig(format!("unknown key for CHIP-8 key {:X}: {}", key, name))
+}
+
+#[cfg_attr(
+    not(any(feature = "sdl", feature = "minifb", feature = "wgpu", feature = "terminal")),
+    allow(dead_code)
+)]
+fn unknown_hotkey(name: &str) -> Chip8Error {
+    Chip8Error::InvalidConfig(format!("unknown hotkey: {}", name))
+}
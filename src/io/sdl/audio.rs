@@ -0,0 +1,88 @@
+use sdl3::audio::{AudioCallback, AudioFormat, AudioSpec, AudioStream, AudioStreamWithCallback};
+
+use chip8_emulator_rs::frontend::Audio;
+use chip8_emulator_rs::Chip8Error;
+
+use super::sdl_error;
+
+const SAMPLE_RATE: i32 = 44100;
+
+struct SquareWave {
+    phase: f32,
+    phase_inc: f32,
+    volume: f32,
+    buffer: Vec<f32>,
+}
+
+impl SquareWave {
+    fn new(freq: f32, volume: f32, sample_rate: i32) -> Self {
+        SquareWave {
+            phase: 0.0,
+            phase_inc: freq / sample_rate as f32,
+            volume,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn fill(&mut self, samples: usize) -> &[f32] {
+        self.buffer.resize(samples, 0.0);
+        for sample in self.buffer.iter_mut() {
+            *sample = if self.phase < 0.5 { self.volume } else { -self.volume };
+            self.phase = (self.phase + self.phase_inc) % 1.0;
+        }
+        &self.buffer
+    }
+}
+
+impl AudioCallback<f32> for SquareWave {
+    fn callback(&mut self, stream: &mut AudioStream, requested: i32) {
+        let samples = self.fill(requested.max(0) as usize);
+        let _ = stream.put_data_f32(samples);
+    }
+}
+
+// Plays a square wave tone while the sound timer is running.
+pub struct Beeper {
+    stream: AudioStreamWithCallback<SquareWave>,
+}
+
+impl Beeper {
+    pub fn open(context: &sdl3::Sdl, volume: f32, tone: f32) -> Result<Self, Chip8Error> {
+        let spec = AudioSpec {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            format: Some(AudioFormat::f32_sys()),
+        };
+        let stream = context
+            .audio()
+            .map_err(sdl_error)?
+            .open_playback_stream(&spec, SquareWave::new(tone, volume, SAMPLE_RATE))
+            .map_err(sdl_error)?;
+        Ok(Beeper { stream })
+    }
+}
+
+impl Audio for Beeper {
+    fn set_beeping(&mut self, beeping: bool) -> Result<(), Chip8Error> {
+        if beeping {
+            self.stream.resume().map_err(sdl_error)
+        } else {
+            self.stream.pause().map_err(sdl_error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square_wave_period() {
+        let mut wave = SquareWave::new(1000.0, 0.25, 8000);
+        let samples = wave.fill(16).to_vec();
+
+        assert_eq!(&samples[..4], &[0.25; 4]);
+        assert_eq!(&samples[4..8], &[-0.25; 4]);
+        assert_eq!(&samples[8..], &samples[..8]);
+    }
+}
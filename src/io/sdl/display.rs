@@ -0,0 +1,164 @@
+use sdl3::pixels::Color;
+
+use chip8_emulator_rs::config::{Palette, Rgb};
+use chip8_emulator_rs::frontend::Display;
+use chip8_emulator_rs::Chip8Error;
+
+use super::sdl_error;
+
+const HEATMAP_COLUMNS: u32 = 64;
+const HEATMAP_CELL_SIZE: u32 = 8;
+
+pub struct Screen {
+    video: sdl3::VideoSubsystem,
+    canvas: sdl3::render::Canvas<sdl3::video::Window>,
+    heatmap: Option<sdl3::render::Canvas<sdl3::video::Window>>,
+
+    width: u32,
+    height: u32,
+    scale: u32,
+    foreground: Color,
+    background: Color,
+}
+
+impl Screen {
+    pub fn open(
+        video: sdl3::VideoSubsystem,
+        width: usize,
+        height: usize,
+        scale: u32,
+        palette: Palette,
+    ) -> Result<Self, Chip8Error> {
+        let window = video
+            .window("chip8-emulator-rs", width as u32 * scale, height as u32 * scale)
+            .position_centered()
+            .build()
+            .map_err(sdl_error)?;
+
+        let background = color(palette.background);
+        let mut canvas = window.into_canvas();
+        canvas.set_draw_color(background);
+        canvas.clear();
+        canvas.present();
+
+        Ok(Screen {
+            video,
+            canvas,
+            heatmap: None,
+            width: width as u32,
+            height: height as u32,
+            scale,
+            foreground: color(palette.foreground),
+            background,
+        })
+    }
+
+    pub fn window_id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+}
+
+impl Display for Screen {
+    fn draw(&mut self, pixels: &[u8]) -> Result<(), Chip8Error> {
+        self.canvas.set_draw_color(self.background);
+        self.canvas.clear();
+
+        self.canvas.set_draw_color(self.foreground);
+
+        for x in 0..self.width {
+            for y in 0..self.height {
+                let pixel_index = (y * self.width + x) as usize;
+
+                if pixels[pixel_index] != 0 {
+                    let rect =
+                        sdl3::rect::Rect::new((x * self.scale) as i32, (y * self.scale) as i32, self.scale, self.scale);
+                    self.canvas.fill_rect(rect).map_err(sdl_error)?;
+                }
+            }
+        }
+
+        self.canvas.present();
+        Ok(())
+    }
+
+    fn set_palette(&mut self, palette: Palette) {
+        self.foreground = color(palette.foreground);
+        self.background = color(palette.background);
+    }
+
+    fn open_heatmap(&mut self, memory_size: usize) -> Result<(), Chip8Error> {
+        let rows = memory_size as u32 / HEATMAP_COLUMNS;
+
+        let window = self
+            .video
+            .window(
+                "chip8-emulator-rs heatmap",
+                HEATMAP_COLUMNS * HEATMAP_CELL_SIZE,
+                rows * HEATMAP_CELL_SIZE,
+            )
+            .build()
+            .map_err(sdl_error)?;
+
+        self.heatmap = Some(window.into_canvas());
+        Ok(())
+    }
+
+    fn draw_heatmap(&mut self, counts: &[u64]) -> Result<(), Chip8Error> {
+        let Some(canvas) = &mut self.heatmap else {
+            return Ok(());
+        };
+
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.clear();
+
+        let max = counts.iter().copied().max().unwrap_or(0);
+        for (addr, &count) in counts.iter().enumerate().filter(|&(_, &count)| count > 0) {
+            let x = addr as u32 % HEATMAP_COLUMNS;
+            let y = addr as u32 / HEATMAP_COLUMNS;
+
+            canvas.set_draw_color(heat_color(count, max));
+            let rect = sdl3::rect::Rect::new(
+                (x * HEATMAP_CELL_SIZE) as i32,
+                (y * HEATMAP_CELL_SIZE) as i32,
+                HEATMAP_CELL_SIZE,
+                HEATMAP_CELL_SIZE,
+            );
+            canvas.fill_rect(rect).map_err(sdl_error)?;
+        }
+
+        canvas.present();
+        Ok(())
+    }
+
+    fn close_heatmap(&mut self) {
+        self.heatmap = None;
+    }
+}
+
+fn color(rgb: Rgb) -> Color {
+    Color::RGB(rgb.0, rgb.1, rgb.2)
+}
+
+// Maps execution counts on a logarithmic scale from dark blue (rarely) over red to yellow (hottest).
+fn heat_color(count: u64, max: u64) -> Color {
+    let t = ((count as f64).ln_1p() / (max as f64).ln_1p()).clamp(0.0, 1.0);
+    if t < 0.5 {
+        let t = t * 2.0;
+        Color::RGB((255.0 * t) as u8, 0, (160.0 * (1.0 - t)) as u8 + 40)
+    } else {
+        let t = (t - 0.5) * 2.0;
+        Color::RGB(255, (255.0 * t) as u8, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heat_color_scale() {
+        assert_eq!(heat_color(0, 1000), Color::RGB(0, 0, 200));
+        assert_eq!(heat_color(1000, 1000), Color::RGB(255, 255, 0));
+        assert_eq!(heat_color(31, 1000).r, 255);
+    }
+}
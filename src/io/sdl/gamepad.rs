@@ -0,0 +1,207 @@
+use sdl3::gamepad::{Button, Gamepad};
+use sdl3::GamepadSubsystem;
+
+use chip8_emulator_rs::config::GamepadConfig;
+use chip8_emulator_rs::frontend::SUSPEND;
+use chip8_emulator_rs::{Chip8Error, Keypad};
+
+use super::sdl_error;
+
+// Rumble strength for both motors, and how long it lasts unless stopped earlier. The sound timer never
+// runs longer than 255 / 60 seconds, the limit only matters if the stop gets lost.
+const RUMBLE_STRENGTH: u16 = 0x6000;
+const RUMBLE_MAX_MS: u32 = 5000;
+
+// Named like the buttons in SDL's gamepad mappings, looked up here so a typo in the config is reported the
+// same way whether a gamepad is connected or not.
+const BUTTONS: [(&str, Button); 15] = [
+    ("a", Button::South),
+    ("b", Button::East),
+    ("x", Button::West),
+    ("y", Button::North),
+    ("back", Button::Back),
+    ("guide", Button::Guide),
+    ("start", Button::Start),
+    ("leftstick", Button::LeftStick),
+    ("rightstick", Button::RightStick),
+    ("leftshoulder", Button::LeftShoulder),
+    ("rightshoulder", Button::RightShoulder),
+    ("dpup", Button::DPadUp),
+    ("dpdown", Button::DPadDown),
+    ("dpleft", Button::DPadLeft),
+    ("dpright", Button::DPadRight),
+];
+
+// Every connected gamepad, opened as it is plugged in. All of them play on the same keypad.
+pub struct Gamepads {
+    subsystem: GamepadSubsystem,
+    // SDL only reports the buttons of opened gamepads
+    opened: Vec<(u32, Gamepad)>,
+    // the one whose button was pressed last
+    active: Option<u32>,
+    pause_on_disconnect: bool,
+    rumble: bool,
+    bindings: ButtonBindings,
+}
+
+struct ButtonBindings {
+    keys: Vec<(Button, u8)>,
+    controls: Vec<(Button, u32)>,
+}
+
+impl Gamepads {
+    pub fn open(context: &sdl3::Sdl, config: &GamepadConfig) -> Result<Self, Chip8Error> {
+        let bindings = ButtonBindings::new(config)?;
+        let subsystem = context.gamepad().map_err(sdl_error)?;
+        let mut gamepads = Gamepads {
+            subsystem,
+            opened: Vec::new(),
+            active: None,
+            pause_on_disconnect: config.pause_on_disconnect,
+            rumble: config.rumble,
+            bindings,
+        };
+        for id in gamepads.subsystem.gamepads().map_err(sdl_error)? {
+            gamepads.connected(id);
+        }
+        Ok(gamepads)
+    }
+
+    // SDL also announces the gamepads that were there at startup, those are already open.
+    pub fn connected(&mut self, id: u32) {
+        if self.opened.iter().any(|&(opened, _)| opened == id) {
+            return;
+        }
+        match self.subsystem.open(id) {
+            Ok(gamepad) => {
+                println!("Gamepad connected: {}", gamepad.name().unwrap_or_default());
+                self.opened.push((id, gamepad));
+            }
+            Err(err) => eprintln!("Gamepad could not be opened: {}", err),
+        }
+    }
+
+    // Lets go of the keys the gamepad may have been holding. SUSPEND when it was the one being played on.
+    pub fn disconnected(&mut self, id: u32, keypad: &mut Keypad) -> u32 {
+        let Some(index) = self.opened.iter().position(|&(opened, _)| opened == id) else {
+            return 0;
+        };
+        let (_, gamepad) = self.opened.remove(index);
+        println!("Gamepad disconnected: {}", gamepad.name().unwrap_or_default());
+        if self.active != Some(id) {
+            return 0;
+        }
+        self.active = None;
+        self.bindings.release_all(keypad);
+        if self.pause_on_disconnect {
+            SUSPEND
+        } else {
+            0
+        }
+    }
+
+    // Gamepads without motors are left alone.
+    pub fn set_rumble(&mut self, rumble: bool) {
+        if !self.rumble {
+            return;
+        }
+        let Some((_, gamepad)) = self.opened.iter_mut().find(|(id, _)| Some(*id) == self.active) else {
+            return;
+        };
+        let strength = if rumble { RUMBLE_STRENGTH } else { 0 };
+        let _ = gamepad.set_rumble(strength, strength, RUMBLE_MAX_MS);
+    }
+
+    // Presses or releases the CHIP-8 keys bound to button, returns the control flags it triggered.
+    pub fn button(&mut self, id: u32, button: Button, pressed: bool, keypad: &mut Keypad) -> u32 {
+        self.active = Some(id);
+        self.bindings.button(button, pressed, keypad)
+    }
+}
+
+impl ButtonBindings {
+    fn new(config: &GamepadConfig) -> Result<Self, Chip8Error> {
+        let keys = config
+            .keys
+            .iter()
+            .map(|(&key, name)| {
+                button(name).map(|button| (button, key)).ok_or_else(|| {
+                    Chip8Error::InvalidConfig(format!("unknown gamepad button for CHIP-8 key {:X}: {}", key, name))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        let controls = config
+            .controls()
+            .map(|(flag, name)| {
+                button(name)
+                    .map(|button| (button, flag))
+                    .ok_or_else(|| Chip8Error::InvalidConfig(format!("unknown gamepad button: {}", name)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ButtonBindings { keys, controls })
+    }
+
+    fn release_all(&self, keypad: &mut Keypad) {
+        for &(_, key) in &self.keys {
+            keypad.release(key);
+        }
+    }
+
+    // Like on the keyboard, a button bound to the keypad does not trigger a control as well.
+    fn button(&self, button: Button, pressed: bool, keypad: &mut Keypad) -> u32 {
+        let mut bound = false;
+        for &(_, key) in self.keys.iter().filter(|&&(bound_button, _)| bound_button == button) {
+            if pressed {
+                keypad.press(key);
+            } else {
+                keypad.release(key);
+            }
+            bound = true;
+        }
+        match self.controls.iter().find(|&&(control, _)| control == button) {
+            Some(&(_, flag)) if pressed && !bound => flag,
+            _ => 0,
+        }
+    }
+}
+
+fn button(name: &str) -> Option<Button> {
+    BUTTONS
+        .iter()
+        .find(|(button, _)| button.eq_ignore_ascii_case(name))
+        .map(|&(_, button)| button)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_emulator_rs::frontend::{FAST_FORWARD, PAUSE};
+
+    #[test]
+    fn test_buttons_press_keys_and_trigger_controls() {
+        let bindings = ButtonBindings::new(&GamepadConfig::default()).unwrap();
+        let mut keypad = Keypad::default();
+        assert_eq!(bindings.button(Button::DPadUp, true, &mut keypad), 0);
+        assert!(keypad.is_pressed(0x5));
+        bindings.button(Button::DPadUp, false, &mut keypad);
+        keypad.end_frame();
+        assert!(!keypad.is_pressed(0x5));
+
+        assert_eq!(bindings.button(Button::Start, true, &mut keypad), PAUSE);
+        assert_eq!(bindings.button(Button::Start, false, &mut keypad), 0);
+        assert_eq!(bindings.button(Button::RightShoulder, true, &mut keypad), FAST_FORWARD);
+
+        let mut config = GamepadConfig::default();
+        config.keys.insert(0x1, "Start".to_string());
+        let bindings = ButtonBindings::new(&config).unwrap();
+        assert_eq!(bindings.button(Button::Start, true, &mut keypad), 0);
+        assert!(keypad.is_pressed(0x1));
+
+        bindings.release_all(&mut keypad);
+        keypad.end_frame();
+        assert!(!keypad.is_pressed(0x1));
+
+        config.pause = "select".to_string();
+        assert!(ButtonBindings::new(&config).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use sdl3::event::{Event, WindowEvent};
+use sdl3::keyboard::Scancode;
+
+use chip8_emulator_rs::config::{KeyMap, Layout};
+use chip8_emulator_rs::frontend::{Capture, Input, CLOSE_HEATMAP, OPEN_ROM, QUIT};
+use chip8_emulator_rs::{Chip8Error, Keypad};
+
+use super::gamepad::Gamepads;
+use super::sdl_error;
+use super::touch::Touches;
+
+pub struct Keyboard {
+    context: sdl3::Sdl,
+    main_window: u32,
+    // scancodes are key positions, the key map names keys by their label on this layout
+    layout: Layout,
+    keys: Vec<(Scancode, u8)>,
+    controls: Vec<(Scancode, u32)>,
+    gamepads: Option<Gamepads>,
+    touches: Option<Touches>,
+    // the last file dropped on a window, until the emulator asks for it
+    dropped: Option<String>,
+}
+
+impl Keyboard {
+    // The main window is the one whose close button quits the emulator.
+    pub fn new(
+        context: sdl3::Sdl,
+        main_window: u32,
+        keys: &KeyMap,
+        gamepads: Option<Gamepads>,
+        touches: Option<Touches>,
+    ) -> Result<Self, Chip8Error> {
+        Ok(Keyboard {
+            context,
+            main_window,
+            layout: keys.layout,
+            keys: bindings(keys)?,
+            controls: controls(keys)?,
+            gamepads,
+            touches,
+            dropped: None,
+        })
+    }
+}
+
+impl Input for Keyboard {
+    fn poll(&mut self, keypad: &mut Keypad) -> Result<u32, Chip8Error> {
+        let mut event_pump = self.context.event_pump().map_err(sdl_error)?;
+        let mut status = 0;
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => return Ok(QUIT),
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::CloseRequested,
+                    ..
+                } => {
+                    if window_id == self.main_window {
+                        return Ok(QUIT);
+                    }
+                    status |= CLOSE_HEATMAP;
+                }
+                Event::KeyDown {
+                    scancode: Some(code), ..
+                } => {
+                    // Keypad bindings win over the controls, a remapped key must not pause the game
+                    let mut bound = false;
+                    for key in chip8_keys(&self.keys, code) {
+                        keypad.press(key);
+                        bound = true;
+                    }
+                    if let Some(&(_, flag)) = self.controls.iter().find(|(control, _)| !bound && *control == code) {
+                        status |= flag;
+                    }
+                }
+                Event::KeyUp {
+                    scancode: Some(code), ..
+                } => {
+                    for key in chip8_keys(&self.keys, code) {
+                        keypad.release(key);
+                    }
+                }
+                Event::ControllerButtonDown { which, button, .. } => {
+                    if let Some(gamepads) = &mut self.gamepads {
+                        status |= gamepads.button(which, button, true, keypad);
+                    }
+                }
+                Event::ControllerButtonUp { which, button, .. } => {
+                    if let Some(gamepads) = &mut self.gamepads {
+                        gamepads.button(which, button, false, keypad);
+                    }
+                }
+                Event::FingerDown { finger_id, x, y, .. } | Event::FingerMotion { finger_id, x, y, .. } => {
+                    if let Some(touches) = &mut self.touches {
+                        touches.touch(finger_id, x, y, keypad);
+                    }
+                }
+                Event::FingerUp { finger_id, .. } => {
+                    if let Some(touches) = &mut self.touches {
+                        touches.lift(finger_id, keypad);
+                    }
+                }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    if let Some(gamepads) = &mut self.gamepads {
+                        gamepads.connected(which);
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    if let Some(gamepads) = &mut self.gamepads {
+                        status |= gamepads.disconnected(which, keypad);
+                    }
+                }
+                Event::DropFile { filename, .. } => {
+                    self.dropped = Some(filename);
+                    status |= OPEN_ROM;
+                }
+                _ => {}
+            }
+        }
+        Ok(status)
+    }
+
+    fn dropped_rom(&mut self) -> Option<String> {
+        self.dropped.take()
+    }
+
+    fn set_key_map(&mut self, keys: &KeyMap) -> Result<(), Chip8Error> {
+        self.keys = bindings(keys)?;
+        self.controls = controls(keys)?;
+        self.layout = keys.layout;
+        Ok(())
+    }
+
+    fn set_rumble(&mut self, rumble: bool) {
+        if let Some(gamepads) = &mut self.gamepads {
+            gamepads.set_rumble(rumble);
+        }
+    }
+
+    fn capture_key(&mut self) -> Result<Option<Capture>, Chip8Error> {
+        let mut event_pump = self.context.event_pump().map_err(sdl_error)?;
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    scancode: Some(Scancode::Escape),
+                    ..
+                } => return Ok(Some(Capture::Cancel)),
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::CloseRequested,
+                    ..
+                } if window_id == self.main_window => return Ok(Some(Capture::Cancel)),
+                Event::KeyDown {
+                    scancode: Some(code),
+                    repeat: false,
+                    ..
+                } => return Ok(Some(Capture::Key(self.layout.from_qwerty(code.name())))),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+// Any name SDL has a scancode for can be bound.
+fn bindings(keys: &KeyMap) -> Result<Vec<(Scancode, u8)>, Chip8Error> {
+    keys.to_qwerty()
+        .iter()
+        .map(|(key, name)| {
+            Scancode::from_name(name)
+                .map(|code| (code, key))
+                .ok_or_else(|| crate::io::unknown_key(key, name))
+        })
+        .collect()
+}
+
+fn controls(keys: &KeyMap) -> Result<Vec<(Scancode, u32)>, Chip8Error> {
+    keys.to_qwerty()
+        .hotkeys
+        .iter()
+        .map(|(flag, name)| {
+            Scancode::from_name(name)
+                .map(|code| (code, flag))
+                .ok_or_else(|| crate::io::unknown_hotkey(name))
+        })
+        .collect()
+}
+
+fn chip8_keys(bindings: &[(Scancode, u8)], code: Scancode) -> impl Iterator<Item = u8> + '_ {
+    bindings
+        .iter()
+        .filter(move |&&(bound, _)| bound == code)
+        .map(|&(_, key)| key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_presses_every_bound_chip8_key() {
+        // Resolving names needs the SDL library, the bindings are written out here
+        let bindings = [(Scancode::X, 0x0), (Scancode::P, 0x5), (Scancode::P, 0x6)];
+        assert_eq!(chip8_keys(&bindings, Scancode::P).collect::<Vec<_>>(), [0x5, 0x6]);
+        assert_eq!(chip8_keys(&bindings, Scancode::X).collect::<Vec<_>>(), [0x0]);
+        assert_eq!(chip8_keys(&bindings, Scancode::Q).count(), 0);
+    }
+}
@@ -0,0 +1,68 @@
+extern crate sdl3;
+
+pub mod audio;
+pub mod display;
+pub mod gamepad;
+pub mod input;
+pub mod touch;
+
+use chip8_emulator_rs::config::Config;
+use chip8_emulator_rs::{Audio, Chip8Error, Display, Input};
+
+use audio::Beeper;
+use display::Screen;
+use gamepad::Gamepads;
+use input::Keyboard;
+use touch::Touches;
+
+// Opens the emulator window and returns its display, the keyboard reading its events (and those of the
+// gamepads and touch screens) and the beeper if sound is enabled and an audio device is available.
+pub fn open(width: usize, height: usize, config: &Config) -> Result<super::Frontends, Chip8Error> {
+    let context = sdl3::init().map_err(sdl_error)?;
+    let screen = Screen::open(
+        context.video().map_err(sdl_error)?,
+        width,
+        height,
+        config.scale,
+        config.palette,
+    )?;
+
+    let beeper = match config
+        .audio
+        .enabled
+        .then(|| Beeper::open(&context, config.audio.volume, config.audio.tone))
+    {
+        Some(Ok(beeper)) => Some(Box::new(beeper) as Box<dyn Audio>),
+        Some(Err(err)) => {
+            eprintln!("Sound disabled: {}", err);
+            None
+        }
+        None => None,
+    };
+
+    let gamepads = match config
+        .gamepad
+        .enabled
+        .then(|| Gamepads::open(&context, &config.gamepad))
+    {
+        Some(Ok(gamepads)) => Some(gamepads),
+        Some(Err(err)) => {
+            eprintln!("Gamepads disabled: {}", err);
+            None
+        }
+        None => None,
+    };
+
+    let touches = config.touch.enabled.then(|| Touches::new(config.touch.layout.clone()));
+
+    let keyboard = Keyboard::new(context, screen.window_id(), &config.key_map(), gamepads, touches)?;
+    Ok((
+        Box::new(screen) as Box<dyn Display>,
+        Box::new(keyboard) as Box<dyn Input>,
+        beeper,
+    ))
+}
+
+fn sdl_error<E: std::error::Error + Send + Sync + 'static>(err: E) -> Chip8Error {
+    Chip8Error::IoError(std::io::Error::other(err))
+}
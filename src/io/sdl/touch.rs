@@ -0,0 +1,78 @@
+use chip8_emulator_rs::config::TouchLayout;
+use chip8_emulator_rs::Keypad;
+
+// Follows the fingers on a touch screen, each one holds the key of the region it is on.
+pub struct Touches {
+    layout: TouchLayout,
+    // the finger id SDL gave each finger, with the key under it
+    fingers: Vec<(u64, Option<u8>)>,
+}
+
+impl Touches {
+    pub fn new(layout: TouchLayout) -> Self {
+        Touches {
+            layout,
+            fingers: Vec::new(),
+        }
+    }
+
+    // Called for a finger that went down or moved, x and y are fractions of the window size.
+    pub fn touch(&mut self, finger: u64, x: f32, y: f32, keypad: &mut Keypad) {
+        let key = self.layout.key_at(x, y);
+        match self.fingers.iter().position(|&(id, _)| id == finger) {
+            Some(index) => {
+                let old = std::mem::replace(&mut self.fingers[index].1, key);
+                if old != key {
+                    self.release(old, keypad);
+                }
+            }
+            None => self.fingers.push((finger, key)),
+        }
+        if let Some(key) = key {
+            keypad.press(key);
+        }
+    }
+
+    pub fn lift(&mut self, finger: u64, keypad: &mut Keypad) {
+        if let Some(index) = self.fingers.iter().position(|&(id, _)| id == finger) {
+            let (_, key) = self.fingers.remove(index);
+            self.release(key, keypad);
+        }
+    }
+
+    // Another finger may still be on the same key.
+    fn release(&self, key: Option<u8>, keypad: &mut Keypad) {
+        if let Some(key) = key {
+            if !self.fingers.iter().any(|&(_, held)| held == Some(key)) {
+                keypad.release(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingers_hold_the_key_under_them() {
+        let mut touches = Touches::new("12 34".parse().unwrap());
+        let mut keypad = Keypad::default();
+        touches.touch(1, 0.2, 0.2, &mut keypad);
+        touches.touch(2, 0.3, 0.3, &mut keypad);
+        assert!(keypad.is_pressed(0x1));
+
+        touches.touch(1, 0.8, 0.2, &mut keypad);
+        assert!(keypad.is_pressed(0x1) && keypad.is_pressed(0x2));
+        touches.lift(2, &mut keypad);
+        keypad.end_frame();
+        assert!(!keypad.is_pressed(0x1));
+
+        touches.touch(1, 0.8, 0.8, &mut keypad);
+        keypad.end_frame();
+        assert!(!keypad.is_pressed(0x2) && keypad.is_pressed(0x4));
+        touches.lift(1, &mut keypad);
+        keypad.end_frame();
+        assert!(!keypad.is_pressed(0x4));
+    }
+}
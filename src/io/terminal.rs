@@ -0,0 +1,360 @@
+use std::io::{Stdout, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+    PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{self, Color, Colors};
+use crossterm::{cursor, queue, terminal};
+
+use chip8_emulator_rs::config::{Config, KeyMap, Palette, Rgb};
+use chip8_emulator_rs::frontend::{Capture, QUIT};
+use chip8_emulator_rs::{Chip8Error, Display, Input, Keypad};
+
+use super::keys::KeyTable;
+
+// Most terminals only report presses and repeats, a key counts as held until this long after its last event
+const KEY_HOLD: Duration = Duration::from_millis(150);
+
+// Terminals report characters, the keypad digits are the same as the others
+const KEY_TABLE: KeyTable<KeyCode> = KeyTable {
+    letters: [
+        KeyCode::Char('a'),
+        KeyCode::Char('b'),
+        KeyCode::Char('c'),
+        KeyCode::Char('d'),
+        KeyCode::Char('e'),
+        KeyCode::Char('f'),
+        KeyCode::Char('g'),
+        KeyCode::Char('h'),
+        KeyCode::Char('i'),
+        KeyCode::Char('j'),
+        KeyCode::Char('k'),
+        KeyCode::Char('l'),
+        KeyCode::Char('m'),
+        KeyCode::Char('n'),
+        KeyCode::Char('o'),
+        KeyCode::Char('p'),
+        KeyCode::Char('q'),
+        KeyCode::Char('r'),
+        KeyCode::Char('s'),
+        KeyCode::Char('t'),
+        KeyCode::Char('u'),
+        KeyCode::Char('v'),
+        KeyCode::Char('w'),
+        KeyCode::Char('x'),
+        KeyCode::Char('y'),
+        KeyCode::Char('z'),
+    ],
+    digits: [
+        KeyCode::Char('0'),
+        KeyCode::Char('1'),
+        KeyCode::Char('2'),
+        KeyCode::Char('3'),
+        KeyCode::Char('4'),
+        KeyCode::Char('5'),
+        KeyCode::Char('6'),
+        KeyCode::Char('7'),
+        KeyCode::Char('8'),
+        KeyCode::Char('9'),
+    ],
+    named: &[
+        ("Space", KeyCode::Char(' ')),
+        ("Escape", KeyCode::Esc),
+        ("Return", KeyCode::Enter),
+        ("Tab", KeyCode::Tab),
+        ("Backspace", KeyCode::Backspace),
+        ("Up", KeyCode::Up),
+        ("Down", KeyCode::Down),
+        ("Left", KeyCode::Left),
+        ("Right", KeyCode::Right),
+        ("Keypad 0", KeyCode::Char('0')),
+        ("Keypad 1", KeyCode::Char('1')),
+        ("Keypad 2", KeyCode::Char('2')),
+        ("Keypad 3", KeyCode::Char('3')),
+        ("Keypad 4", KeyCode::Char('4')),
+        ("Keypad 5", KeyCode::Char('5')),
+        ("Keypad 6", KeyCode::Char('6')),
+        ("Keypad 7", KeyCode::Char('7')),
+        ("Keypad 8", KeyCode::Char('8')),
+        ("Keypad 9", KeyCode::Char('9')),
+        (",", KeyCode::Char(',')),
+        (".", KeyCode::Char('.')),
+        ("/", KeyCode::Char('/')),
+        (";", KeyCode::Char(';')),
+        ("'", KeyCode::Char('\'')),
+        ("[", KeyCode::Char('[')),
+        ("]", KeyCode::Char(']')),
+        ("-", KeyCode::Char('-')),
+        ("=", KeyCode::Char('=')),
+        ("`", KeyCode::Char('`')),
+        ("\\", KeyCode::Char('\\')),
+        ("F1", KeyCode::F(1)),
+        ("F2", KeyCode::F(2)),
+        ("F3", KeyCode::F(3)),
+        ("F4", KeyCode::F(4)),
+        ("F5", KeyCode::F(5)),
+        ("F6", KeyCode::F(6)),
+        ("F7", KeyCode::F(7)),
+        ("F8", KeyCode::F(8)),
+        ("F9", KeyCode::F(9)),
+        ("F10", KeyCode::F(10)),
+        ("F11", KeyCode::F(11)),
+        ("F12", KeyCode::F(12)),
+    ],
+};
+
+// Puts the terminal in raw mode on the alternate screen and restores it once the display and the input are
+// both dropped.
+struct Session {
+    enhanced_keys: bool,
+}
+
+pub struct Screen {
+    _session: Rc<Session>,
+    stdout: Stdout,
+    width: usize,
+    height: usize,
+    colors: Colors,
+}
+
+pub struct Keyboard {
+    _session: Rc<Session>,
+    enhanced_keys: bool,
+    bindings: Vec<(KeyCode, u8)>,
+    controls: Vec<(KeyCode, u32)>,
+    // indexed by CHIP-8 key
+    pressed_at: [Option<Instant>; 16],
+}
+
+// There is no audio output, the terminal bell would be the only option. The scale is up to the terminal's font.
+pub fn open(width: usize, height: usize, config: &Config) -> Result<super::Frontends, Chip8Error> {
+    let keys = config.key_map();
+    let (bindings, controls) = (KEY_TABLE.bindings(&keys)?, KEY_TABLE.controls(&keys)?);
+    terminal::enable_raw_mode()?;
+    // Terminals speaking the kitty keyboard protocol report releases, the others get KEY_HOLD
+    let enhanced_keys = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    let session = Rc::new(Session { enhanced_keys });
+
+    let mut stdout = std::io::stdout();
+    queue!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    if enhanced_keys {
+        queue!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+        )?;
+    }
+    stdout.flush()?;
+
+    let screen = Screen {
+        _session: session.clone(),
+        stdout,
+        width,
+        height,
+        colors: Colors::new(color(config.palette.foreground), color(config.palette.background)),
+    };
+    let keyboard = Keyboard {
+        _session: session,
+        enhanced_keys,
+        bindings,
+        controls,
+        pressed_at: [None; 16],
+    };
+    Ok((Box::new(screen), Box::new(keyboard), None))
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let mut stdout = std::io::stdout();
+        if self.enhanced_keys {
+            let _ = queue!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = queue!(stdout, style::ResetColor, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = stdout.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl Display for Screen {
+    fn draw(&mut self, pixels: &[u8]) -> Result<(), Chip8Error> {
+        // Half-blocks keep the pixels square, braille is used when the terminal is too small for them
+        let (columns, rows) = terminal::size()?;
+        let lines = if columns as usize >= self.width && rows as usize >= self.height.div_ceil(2) {
+            half_blocks(pixels, self.width, self.height)
+        } else {
+            braille(pixels, self.width, self.height)
+        };
+
+        queue!(
+            self.stdout,
+            style::SetColors(self.colors),
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+        for (row, line) in lines.iter().enumerate() {
+            queue!(self.stdout, cursor::MoveTo(0, row as u16))?;
+            self.stdout.write_all(line.as_bytes())?;
+        }
+        self.stdout.flush()?;
+        Ok(())
+    }
+
+    fn set_palette(&mut self, palette: Palette) {
+        self.colors = Colors::new(color(palette.foreground), color(palette.background));
+    }
+}
+
+impl Input for Keyboard {
+    fn poll(&mut self, keypad: &mut Keypad) -> Result<u32, Chip8Error> {
+        let mut status = 0;
+        let now = Instant::now();
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(KeyEvent {
+                code, modifiers, kind, ..
+            }) = event::read()?
+            else {
+                continue;
+            };
+            let pressed = kind != KeyEventKind::Release;
+            let code = lowercase(code);
+            let mut bound = false;
+            for &(_, key) in self.bindings.iter().filter(|&&(binding, _)| binding == code) {
+                self.pressed_at[key as usize] = pressed.then_some(now);
+                bound = true;
+            }
+            // raw mode turns Ctrl-C into a key press
+            if code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL) {
+                return Ok(QUIT);
+            }
+            // Keypad bindings win over the controls, a remapped key must not pause the game
+            if let Some(&(_, flag)) = self.controls.iter().find(|&&(control, _)| !bound && control == code) {
+                if kind == KeyEventKind::Press {
+                    status |= flag;
+                }
+            }
+        }
+
+        for (key, pressed_at) in self.pressed_at.iter_mut().enumerate() {
+            if !self.enhanced_keys && pressed_at.is_some_and(|at| now.duration_since(at) > KEY_HOLD) {
+                *pressed_at = None;
+            }
+            if pressed_at.is_some() {
+                keypad.press(key as u8);
+            } else {
+                keypad.release(key as u8);
+            }
+        }
+        Ok(status)
+    }
+
+    fn capture_key(&mut self) -> Result<Option<Capture>, Chip8Error> {
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(KeyEvent {
+                code, modifiers, kind, ..
+            }) = event::read()?
+            else {
+                continue;
+            };
+            match code {
+                KeyCode::Esc => return Ok(Some(Capture::Cancel)),
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(Some(Capture::Cancel)),
+                code if kind == KeyEventKind::Press => {
+                    if let Some(name) = KEY_TABLE.name(lowercase(code)) {
+                        return Ok(Some(Capture::Key(name)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    fn set_key_map(&mut self, keys: &KeyMap) -> Result<(), Chip8Error> {
+        self.bindings = KEY_TABLE.bindings(keys)?;
+        self.controls = KEY_TABLE.controls(keys)?;
+        self.pressed_at = [None; 16];
+        Ok(())
+    }
+}
+
+// Shift turns letters into capitals, the key table only has the lowercase ones
+fn lowercase(code: KeyCode) -> KeyCode {
+    match code {
+        KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+        code => code,
+    }
+}
+
+fn color(rgb: Rgb) -> Color {
+    Color::Rgb {
+        r: rgb.0,
+        g: rgb.1,
+        b: rgb.2,
+    }
+}
+
+// One character per column and two rows of pixels.
+fn half_blocks(pixels: &[u8], width: usize, height: usize) -> Vec<String> {
+    let lit = |x: usize, y: usize| y < height && pixels[y * width + x] != 0;
+    (0..height)
+        .step_by(2)
+        .map(|y| {
+            (0..width)
+                .map(|x| match (lit(x, y), lit(x, y + 1)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// One character per 2x4 pixels, a quarter of the space half-blocks need.
+fn braille(pixels: &[u8], width: usize, height: usize) -> Vec<String> {
+    // Bit of every dot in a braille cell, indexed by [row][column]
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+    let lit = |x: usize, y: usize| x < width && y < height && pixels[y * width + x] != 0;
+    (0..height)
+        .step_by(4)
+        .map(|y| {
+            (0..width)
+                .step_by(2)
+                .map(|x| {
+                    let mut bits = 0;
+                    for (dy, row) in DOTS.iter().enumerate() {
+                        for (dx, bit) in row.iter().enumerate() {
+                            if lit(x + dx, y + dy) {
+                                bits |= bit;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + bits).unwrap_or(' ')
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_half_blocks_pack_two_rows() {
+        // 2x4 display: left column lit on rows 0 and 1, right column on rows 1 and 2
+        let pixels = [1, 0, 1, 1, 0, 1, 0, 0];
+        assert_eq!(half_blocks(&pixels, 2, 4), vec!["█▄", " ▀"]);
+    }
+
+    #[test]
+    fn test_braille_packs_two_by_four() {
+        let mut pixels = [0; 4 * 4];
+        pixels[0] = 1;
+        pixels[3 * 4 + 1] = 1;
+        pixels[3] = 1;
+        assert_eq!(braille(&pixels, 4, 4), vec!["\u{2881}\u{2808}"]);
+    }
+}
@@ -0,0 +1,99 @@
+use std::fs;
+
+use crate::error::Chip8Error;
+
+const HEADER: &[u8] = b"PATCH";
+const FOOTER: &[u8] = b"EOF";
+
+// An IPS patch: bytes to write at offsets into the ROM, optionally followed by the size to cut the ROM to.
+#[derive(Debug, Default, PartialEq)]
+pub struct Patch {
+    records: Vec<(usize, Vec<u8>)>,
+    truncate: Option<usize>,
+}
+
+impl Patch {
+    pub fn load(path: &str) -> Result<Self, Chip8Error> {
+        Self::parse(&fs::read(path)?).map_err(Chip8Error::InvalidPatch)
+    }
+
+    // "PATCH", then records of a 3 byte offset and 2 byte size followed by that many bytes, or by a 2 byte count
+    // and the byte to repeat when the size is 0, then "EOF". All numbers are big-endian.
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut rest = data.strip_prefix(HEADER).ok_or("does not start with PATCH")?;
+        let mut take = |count: usize| -> Result<&[u8], String> {
+            if rest.len() < count {
+                return Err(format!("ends after {} bytes without EOF", data.len()));
+            }
+            let (taken, left) = rest.split_at(count);
+            rest = left;
+            Ok(taken)
+        };
+        let number = |bytes: &[u8]| bytes.iter().fold(0, |number, &byte| number << 8 | byte as usize);
+
+        let mut records = Vec::new();
+        loop {
+            let offset = take(3)?;
+            if offset == FOOTER {
+                break;
+            }
+            let offset = number(offset);
+            let size = number(take(2)?);
+            let bytes = if size == 0 {
+                let count = number(take(2)?);
+                vec![take(1)?[0]; count]
+            } else {
+                take(size)?.to_vec()
+            };
+            records.push((offset, bytes));
+        }
+        let truncate = match rest.len() {
+            0 => None,
+            3 => Some(number(rest)),
+            _ => return Err("unexpected bytes after EOF".to_string()),
+        };
+        Ok(Patch { records, truncate })
+    }
+
+    // The ROM grows with zeros where a record writes past its end.
+    pub fn apply(&self, rom: &[u8]) -> Vec<u8> {
+        let mut patched = rom.to_vec();
+        for (offset, bytes) in &self.records {
+            let end = offset + bytes.len();
+            if patched.len() < end {
+                patched.resize(end, 0);
+            }
+            patched[*offset..end].copy_from_slice(bytes);
+        }
+        if let Some(size) = self.truncate {
+            patched.truncate(size);
+        }
+        patched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_writes_records_and_runs() {
+        let patch = Patch::parse(b"PATCH\x00\x00\x01\x00\x02\xAA\xBB\x00\x00\x05\x00\x00\x00\x03\xCCEOF").unwrap();
+        assert_eq!(
+            patch.apply(&[0x12, 0x00, 0x00]),
+            [0x12, 0xAA, 0xBB, 0x00, 0x00, 0xCC, 0xCC, 0xCC]
+        );
+
+        let patch = Patch::parse(b"PATCH\x00\x00\x00\x00\x01\x60EOF\x00\x00\x02").unwrap();
+        assert_eq!(patch.apply(&[0x00, 0xE0, 0x12, 0x00]), [0x60, 0xE0]);
+    }
+
+    #[test]
+    fn test_parse_rejects_broken_patches() {
+        assert!(Patch::parse(b"PATCH").is_err());
+        assert!(Patch::parse(b"IPS\x00\x00\x00EOF").is_err());
+        assert!(Patch::parse(b"PATCH\x00\x00\x00\x00\x04\x60EOF").is_err());
+        assert!(Patch::parse(b"PATCHEOF\x00").is_err());
+        assert_eq!(Patch::parse(b"PATCHEOF").unwrap(), Patch::default());
+    }
+}
@@ -0,0 +1,331 @@
+pub const KEY_COUNT: usize = 16;
+// Key events kept for FX0A, the oldest are dropped once this many are queued.
+pub const EVENT_CAPACITY: usize = 32;
+// Frames an event waits for FX0A before it is stale, half a second at 60 Hz.
+const EVENT_LIFETIME: u8 = 30;
+
+// A key going down or up, in the order the frontend reported them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEvent {
+    Pressed(u8),
+    Released(u8),
+}
+
+impl KeyEvent {
+    pub fn key(self) -> u8 {
+        match self {
+            KeyEvent::Pressed(key) | KeyEvent::Released(key) => key,
+        }
+    }
+}
+
+// State of the 16 key hex keypad, updated by the frontend and read by the key instructions.
+#[derive(Debug, Clone, Default)]
+pub struct Keypad {
+    pressed: [bool; KEY_COUNT],
+    // Keys pressed since the program last read them. A tap shorter than the time between two reads is
+    // released only once the program saw it, or at the end of the frame if it never looks.
+    unread: [bool; KEY_COUNT],
+    releasing: [bool; KEY_COUNT],
+    events: EventQueue,
+    // Keys FX0A saw go down and waits to see released again
+    went_down: [bool; KEY_COUNT],
+}
+
+impl Keypad {
+    pub fn press(&mut self, key: u8) {
+        let index = key as usize;
+        if index < KEY_COUNT {
+            if !self.is_down(index) {
+                self.events.push(KeyEvent::Pressed(key));
+            }
+            self.pressed[index] = true;
+            self.unread[index] = true;
+            self.releasing[index] = false;
+        }
+    }
+
+    pub fn release(&mut self, key: u8) {
+        let index = key as usize;
+        if index < KEY_COUNT {
+            if self.is_down(index) {
+                self.events.push(KeyEvent::Released(key));
+            }
+            if self.unread[index] {
+                self.releasing[index] = true;
+            } else {
+                self.pressed[index] = false;
+            }
+        }
+    }
+
+    // Used by the key instructions: whether key is down, after which a tap of it is over. FX0A does not
+    // report presses of a key the program already saw this way.
+    pub fn read(&mut self, key: u8) -> bool {
+        let pressed = self.is_pressed(key);
+        if pressed {
+            self.events.retain(|event| event.key() != key);
+            self.went_down[key as usize] = false;
+        }
+        self.settle(key as usize);
+        pressed
+    }
+
+    // Lets go of the taps the program did not read during the frame and forgets the key events nothing
+    // waited for in time.
+    pub fn end_frame(&mut self) {
+        self.settle_all();
+        for event in self.events.age() {
+            if let KeyEvent::Released(key) = event {
+                self.went_down[key as usize] = false;
+            }
+        }
+    }
+
+    fn settle_all(&mut self) {
+        for key in 0..KEY_COUNT {
+            self.settle(key);
+        }
+    }
+
+    fn settle(&mut self, key: usize) {
+        if key < KEY_COUNT {
+            self.unread[key] = false;
+            if core::mem::take(&mut self.releasing[key]) {
+                self.pressed[key] = false;
+            }
+        }
+    }
+
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.pressed.get(key as usize).copied().unwrap_or(false)
+    }
+
+    // Whether the frontend holds the key, unlike is_pressed this ignores taps waiting to be read.
+    fn is_down(&self, key: usize) -> bool {
+        self.pressed[key] && !self.releasing[key]
+    }
+
+    // The key events FX0A has not consumed yet, oldest first.
+    pub fn pending_events(&self) -> impl Iterator<Item = KeyEvent> + '_ {
+        self.events.iter()
+    }
+
+    // FX0A with the key_wait_on_press quirk: the next key that went down. Keys pressed together are
+    // returned one after the other by the following waits.
+    pub fn wait_for_press(&mut self) -> Option<u8> {
+        self.settle_all();
+        while let Some(event) = self.events.pop() {
+            if let KeyEvent::Pressed(key) = event {
+                return Some(key);
+            }
+        }
+        None
+    }
+
+    // Used by FX0A: the next key that was pressed and released again. A key already held when the wait
+    // started does not count, it has to go down again.
+    pub fn wait_for_key(&mut self) -> Option<u8> {
+        self.settle_all();
+        while let Some(event) = self.events.pop() {
+            let key = event.key() as usize;
+            match event {
+                KeyEvent::Pressed(_) => self.went_down[key] = true,
+                KeyEvent::Released(_) if self.went_down[key] => {
+                    self.went_down[key] = false;
+                    return Some(key as u8);
+                }
+                KeyEvent::Released(_) => {}
+            }
+        }
+        None
+    }
+}
+
+// A ring buffer so the interpreter never allocates, each event with the number of frames it has waited.
+#[derive(Debug, Clone)]
+struct EventQueue {
+    events: [(KeyEvent, u8); EVENT_CAPACITY],
+    start: usize,
+    len: usize,
+}
+
+impl Default for EventQueue {
+    fn default() -> Self {
+        EventQueue {
+            events: [(KeyEvent::Pressed(0), 0); EVENT_CAPACITY],
+            start: 0,
+            len: 0,
+        }
+    }
+}
+
+impl EventQueue {
+    fn push(&mut self, event: KeyEvent) {
+        if self.len == EVENT_CAPACITY {
+            self.pop();
+        }
+        self.events[(self.start + self.len) % EVENT_CAPACITY] = (event, 0);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<KeyEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let (event, _) = self.events[self.start];
+        self.start = (self.start + 1) % EVENT_CAPACITY;
+        self.len -= 1;
+        Some(event)
+    }
+
+    fn retain(&mut self, keep: impl Fn(KeyEvent) -> bool) {
+        let mut kept = EventQueue::default();
+        for i in 0..self.len {
+            let (event, age) = self.events[(self.start + i) % EVENT_CAPACITY];
+            if keep(event) {
+                kept.events[kept.len] = (event, age);
+                kept.len += 1;
+            }
+        }
+        *self = kept;
+    }
+
+    fn iter(&self) -> impl Iterator<Item = KeyEvent> + '_ {
+        (0..self.len).map(|i| self.events[(self.start + i) % EVENT_CAPACITY].0)
+    }
+
+    // Counts a frame for every event and drops the ones that got too old, returning them. They are the
+    // oldest, so they are always at the front.
+    fn age(&mut self) -> impl Iterator<Item = KeyEvent> + '_ {
+        for i in 0..self.len {
+            let (_, age) = &mut self.events[(self.start + i) % EVENT_CAPACITY];
+            *age = age.saturating_add(1);
+        }
+        core::iter::from_fn(move || match self.events[self.start] {
+            (_, age) if self.len > 0 && age > EVENT_LIFETIME => self.pop(),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_wait_for_key_returns_on_release() {
+        let mut keypad = Keypad::default();
+        assert_eq!(keypad.wait_for_key(), None);
+
+        keypad.press(0xA);
+        assert_eq!(keypad.wait_for_key(), None);
+        assert_eq!(keypad.wait_for_key(), None);
+
+        keypad.release(0xA);
+        assert_eq!(keypad.wait_for_key(), Some(0xA));
+        assert_eq!(keypad.wait_for_key(), None);
+    }
+
+    #[test]
+    fn test_wait_for_press_counts_each_press_once() {
+        let mut keypad = Keypad::default();
+        keypad.press(0x3);
+        assert_eq!(keypad.wait_for_press(), Some(0x3));
+        keypad.press(0x3);
+        assert_eq!(keypad.wait_for_press(), None);
+
+        keypad.release(0x3);
+        keypad.press(0xB);
+        keypad.press(0x3);
+        assert_eq!(keypad.wait_for_press(), Some(0xB));
+        assert_eq!(keypad.wait_for_press(), Some(0x3));
+        assert_eq!(keypad.wait_for_press(), None);
+    }
+
+    #[test]
+    fn test_wait_for_key_keeps_simultaneous_presses() {
+        let mut keypad = Keypad::default();
+        keypad.press(0x1);
+        keypad.press(0x2);
+        assert_eq!(keypad.wait_for_key(), None);
+        keypad.release(0x2);
+        keypad.release(0x1);
+        assert_eq!(keypad.wait_for_key(), Some(0x2));
+        assert_eq!(keypad.wait_for_key(), Some(0x1));
+        assert_eq!(keypad.wait_for_key(), None);
+        assert_eq!(keypad.pending_events().count(), 0);
+    }
+
+    #[test]
+    fn test_wait_for_key_ignores_stale_keys() {
+        let mut keypad = Keypad::default();
+        keypad.press(0x4);
+        for _ in 0..=EVENT_LIFETIME {
+            keypad.end_frame();
+        }
+        assert_eq!(keypad.pending_events().count(), 0);
+        keypad.release(0x4);
+        assert_eq!(keypad.wait_for_key(), None);
+
+        // held when the wait started, released and pressed again
+        keypad.press(0x6);
+        assert_eq!(keypad.wait_for_key(), None);
+        keypad.release(0x6);
+        assert_eq!(keypad.wait_for_key(), Some(0x6));
+        keypad.press(0x6);
+        keypad.end_frame();
+        keypad.release(0x6);
+        assert_eq!(
+            keypad.pending_events().collect::<Vec<_>>(),
+            [KeyEvent::Pressed(0x6), KeyEvent::Released(0x6)]
+        );
+
+        // the program already saw it with EX9E
+        keypad.press(0x8);
+        assert!(keypad.read(0x8));
+        keypad.release(0x8);
+        assert_eq!(keypad.wait_for_key(), Some(0x6));
+        assert_eq!(keypad.wait_for_key(), None);
+    }
+
+    #[test]
+    fn test_event_queue_drops_the_oldest_when_full() {
+        let mut keypad = Keypad::default();
+        for _ in 0..EVENT_CAPACITY {
+            keypad.press(0x1);
+            keypad.release(0x1);
+        }
+        keypad.press(0x2);
+        keypad.release(0x2);
+        let events: Vec<_> = keypad.pending_events().collect();
+        assert_eq!(events.len(), EVENT_CAPACITY);
+        assert_eq!(events[..2], [KeyEvent::Pressed(0x1), KeyEvent::Released(0x1)]);
+        assert_eq!(
+            events[EVENT_CAPACITY - 2..],
+            [KeyEvent::Pressed(0x2), KeyEvent::Released(0x2)]
+        );
+    }
+
+    #[test]
+    fn test_taps_last_until_read() {
+        let mut keypad = Keypad::default();
+        keypad.press(0x5);
+        keypad.release(0x5);
+        assert!(keypad.is_pressed(0x5));
+        assert!(keypad.read(0x5));
+        assert!(!keypad.read(0x5));
+
+        keypad.press(0x7);
+        keypad.release(0x7);
+        keypad.end_frame();
+        assert!(!keypad.is_pressed(0x7));
+
+        // a key held across a read goes up as soon as it is released
+        keypad.press(0x2);
+        assert!(keypad.read(0x2));
+        keypad.release(0x2);
+        assert!(!keypad.is_pressed(0x2));
+    }
+}
@@ -0,0 +1,106 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+// Without the std feature only the bare interpreter in `machine` and the types it needs are built.
+extern crate alloc;
+
+pub mod alu;
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod bench;
+#[cfg(feature = "bevy")]
+pub mod bevy_plugin;
+#[cfg(feature = "std")]
+pub mod builder;
+pub mod builtin;
+#[cfg(feature = "std")]
+pub mod cheats;
+#[cfg(feature = "std")]
+pub mod check;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod coverage;
+#[cfg(feature = "std")]
+pub mod dap;
+#[cfg(feature = "std")]
+pub mod database;
+#[cfg(feature = "std")]
+pub mod debugger;
+#[cfg(feature = "std")]
+pub mod detect;
+#[cfg(feature = "differential")]
+pub mod differential;
+#[cfg(feature = "std")]
+pub mod disasm;
+#[cfg(feature = "egui")]
+pub mod egui_widget;
+#[cfg(feature = "std")]
+pub mod emulator;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod frames;
+#[cfg(feature = "std")]
+pub mod frontend;
+#[cfg(feature = "std")]
+pub mod handle;
+#[cfg(feature = "std")]
+pub mod history;
+#[cfg(feature = "std")]
+pub mod hooks;
+#[cfg(feature = "std")]
+pub mod info;
+#[cfg(feature = "std")]
+pub mod ips;
+pub mod keypad;
+pub mod machine;
+pub mod opcode;
+#[cfg(feature = "std")]
+pub mod parse;
+#[cfg(feature = "std")]
+pub mod paths;
+#[cfg(feature = "std")]
+pub mod peripheral;
+#[cfg(feature = "std")]
+pub mod profiler;
+pub mod quirks;
+#[cfg(feature = "std")]
+pub mod recent;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "std")]
+pub mod roms;
+#[cfg(feature = "png")]
+pub mod screen;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "std")]
+pub mod symbols;
+#[cfg(feature = "std")]
+pub mod testsuite;
+#[cfg(feature = "std")]
+pub mod theme;
+#[cfg(feature = "std")]
+pub mod trace;
+#[cfg(feature = "std")]
+pub mod tracediff;
+#[cfg(feature = "std")]
+pub mod vectors;
+
+#[cfg(feature = "std")]
+pub use builder::Chip8Builder;
+#[cfg(feature = "std")]
+pub use emulator::Chip8;
+pub use error::Chip8Error;
+#[cfg(feature = "std")]
+pub use frontend::{Audio, Display, Input};
+#[cfg(feature = "std")]
+pub use handle::Chip8Handle;
+pub use keypad::{KeyEvent, Keypad};
+pub use machine::{Machine, StepResult};
+pub use opcode::Opcode;
+#[cfg(feature = "std")]
+pub use peripheral::Peripheral;
+pub use quirks::Quirks;
+#[cfg(feature = "std")]
+pub use snapshot::Chip8Snapshot;
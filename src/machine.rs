@@ -0,0 +1,470 @@
+use crate::alu;
+use crate::error::Chip8Error;
+use crate::keypad::Keypad;
+use crate::opcode::Opcode;
+use crate::quirks::Quirks;
+
+// How fast the frontends run the interpreter unless told otherwise, DEFAULT_IPS / 60 instructions per frame.
+pub const DEFAULT_IPS: u32 = 1000;
+pub const DISPLAY_WIDTH: usize = 64;
+pub const DISPLAY_HEIGHT: usize = 32;
+pub(crate) const NUMBER_REGS: usize = 16;
+pub(crate) const STACK_SIZE: usize = 16;
+pub(crate) const MEMORY_SIZE: usize = 4096;
+pub(crate) const FONT_OFFSET: usize = 0x050;
+pub(crate) const PROGRAM_START: usize = 0x200;
+
+// The built-in 4x5 hex digit sprites, five bytes per digit
+pub const FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// What happened during a call to step, for embedders driving the emulator from their own loop.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StepResult {
+    pub display_changed: bool,
+    pub sound_started: bool,
+    pub sound_stopped: bool,
+    pub waiting_for_key: bool,
+    pub halted: bool,
+}
+
+// Where the memory accesses and random numbers of the instructions go. A bare Machine uses its memory as
+// is and xorshift, Chip8 puts peripherals and its debugging tools in between.
+pub(crate) trait Bus {
+    // addr is the full 16-bit address, only the memory wraps around at 4 KB
+    fn read(&mut self, memory: &[u8; MEMORY_SIZE], addr: u16) -> u8 {
+        memory[addr as usize % MEMORY_SIZE]
+    }
+
+    fn write(&mut self, memory: &mut [u8; MEMORY_SIZE], addr: u16, value: u8) {
+        memory[addr as usize % MEMORY_SIZE] = value;
+    }
+
+    fn random(&mut self) -> u8;
+
+    // There is no CPU to run machine code on, like most interpreters the call is skipped
+    fn machine_call(&mut self, _nnn: u16) {}
+}
+
+// xorshift64, good enough for games and needs no entropy source
+struct Xorshift(u64);
+
+impl Bus for Xorshift {
+    fn random(&mut self) -> u8 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 56) as u8
+    }
+}
+
+// The interpreter state and the instruction set, without any debugging tools. It has a fixed size, never
+// allocates and builds without std, so it can run on a microcontroller driving a small display. Chip8 runs
+// its instructions on one as well.
+#[derive(Clone)]
+pub struct Machine {
+    pub(crate) memory: [u8; MEMORY_SIZE],
+    pub(crate) regs: [u8; NUMBER_REGS],
+    pub(crate) stack: [u16; STACK_SIZE],
+    pub(crate) sp: usize,
+    pub(crate) pc: u16,
+    pub(crate) i: u16,
+    pub(crate) delay_timer: u8,
+    pub(crate) sound_timer: u8,
+    pub(crate) display: [u8; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+    pub(crate) keypad: Keypad,
+    pub(crate) quirks: Quirks,
+    rng: u64,
+}
+
+impl Machine {
+    pub fn new(rom: &[u8], quirks: Quirks, seed: u64) -> Result<Self, Chip8Error> {
+        let mut machine = Machine {
+            memory: [0; MEMORY_SIZE],
+            regs: [0; NUMBER_REGS],
+            stack: [0; STACK_SIZE],
+            sp: 0,
+            pc: PROGRAM_START as u16,
+            i: 0,
+            delay_timer: 0,
+            sound_timer: 0,
+            display: [0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            keypad: Keypad::default(),
+            quirks,
+            // xorshift gets stuck on 0
+            rng: seed.max(1),
+        };
+        machine.load(rom)?;
+        Ok(machine)
+    }
+
+    // Replaces the memory with the font and the ROM. The rest of the state is left alone.
+    pub(crate) fn load(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        if rom.len() > MEMORY_SIZE - PROGRAM_START {
+            return Err(Chip8Error::RomTooLarge(rom.len()));
+        }
+        self.memory = [0; MEMORY_SIZE];
+        self.memory[FONT_OFFSET..FONT_OFFSET + FONT.len()].copy_from_slice(&FONT);
+        self.memory[PROGRAM_START..PROGRAM_START + rom.len()].copy_from_slice(rom);
+        Ok(())
+    }
+
+    // Executes a single instruction. Timers are not touched, call tick_timers 60 times per second.
+    pub fn step(&mut self) -> Result<StepResult, Chip8Error> {
+        let instruction = self.fetch()?;
+        let opcode = Opcode::decode(instruction)?;
+        let beeping = self.sound_timer > 0;
+        let mut rng = Xorshift(self.rng);
+        let result = self.execute(opcode, &mut rng);
+        self.rng = rng.0;
+        let mut result = result?;
+        result.sound_started = !beeping && self.sound_timer > 0;
+        Ok(result)
+    }
+
+    // Executes the instructions of one 60 Hz frame, stopping early when the program halts, and ticks the timers.
+    // The result tells whether anything of the frame changed the display or the sound.
+    pub fn run_frame(&mut self, instructions: u32) -> Result<StepResult, Chip8Error> {
+        let mut frame = StepResult::default();
+        for _ in 0..instructions {
+            let result = self.step()?;
+            frame.display_changed |= result.display_changed;
+            frame.sound_started |= result.sound_started;
+            frame.waiting_for_key = result.waiting_for_key;
+            if result.halted {
+                frame.halted = true;
+                break;
+            }
+        }
+        frame.sound_stopped = self.tick_timers();
+        Ok(frame)
+    }
+
+    // Returns true when the sound timer ran out with this tick.
+    pub fn tick_timers(&mut self) -> bool {
+        let beeping = self.sound_timer > 0;
+        self.keypad.end_frame();
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+        beeping && self.sound_timer == 0
+    }
+
+    // One byte per pixel, row by row, 0 is off and 1 is on.
+    pub fn pixels(&self) -> &[u8] {
+        &self.display
+    }
+
+    pub fn keypad_mut(&mut self) -> &mut Keypad {
+        &mut self.keypad
+    }
+
+    pub fn beeping(&self) -> bool {
+        self.sound_timer > 0
+    }
+
+    pub fn registers(&self) -> &[u8] {
+        &self.regs
+    }
+
+    // Registers past VF are ignored.
+    pub fn set_register(&mut self, x: u8, value: u8) {
+        if let Some(reg) = self.regs.get_mut(x as usize) {
+            *reg = value;
+        }
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    // The return addresses of the active calls, innermost last.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp]
+    }
+
+    pub fn timers(&self) -> (u8, u8) {
+        (self.delay_timer, self.sound_timer)
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    // Reads the instruction at the PC and moves the PC past it.
+    pub(crate) fn fetch(&mut self) -> Result<u16, Chip8Error> {
+        let pc = self.pc as usize;
+        if pc >= MEMORY_SIZE - 1 {
+            return Err(Chip8Error::PCOutOfBounds(self.pc));
+        }
+        self.pc += 2;
+        Ok((self.memory[pc] as u16) << 8 | self.memory[pc + 1] as u16)
+    }
+
+    // Executes an instruction fetch already moved the PC past. Only sound_started is left for the caller.
+    pub(crate) fn execute(&mut self, opcode: Opcode, bus: &mut impl Bus) -> Result<StepResult, Chip8Error> {
+        let mut result = StepResult {
+            halted: opcode == Opcode::Jump(self.pc.wrapping_sub(2)),
+            ..StepResult::default()
+        };
+        match opcode {
+            Opcode::Clear => {
+                self.display.fill(0);
+                result.display_changed = true;
+            }
+            Opcode::Return => {
+                self.sp = self.sp.checked_sub(1).ok_or(Chip8Error::StackUnderflow)?;
+                self.pc = self.stack[self.sp];
+            }
+            Opcode::MachineCall(nnn) => bus.machine_call(nnn),
+            Opcode::Jump(addr) => self.pc = addr,
+            Opcode::Call(addr) => {
+                if self.sp >= STACK_SIZE {
+                    return Err(Chip8Error::StackOverflow);
+                }
+                self.stack[self.sp] = self.pc;
+                self.sp += 1;
+                self.pc = addr;
+            }
+            Opcode::SkipEqualVal(x, nn) => self.skip_if(self.regs[x as usize] == nn),
+            Opcode::SkipNotEqualVal(x, nn) => self.skip_if(self.regs[x as usize] != nn),
+            Opcode::SkipEqual(x, y) => self.skip_if(self.regs[x as usize] == self.regs[y as usize]),
+            Opcode::SetVal(x, nn) => self.regs[x as usize] = nn,
+            Opcode::AddVal(x, nn) => self.regs[x as usize] = self.regs[x as usize].wrapping_add(nn),
+            Opcode::Set(x, y) => self.regs[x as usize] = self.regs[y as usize],
+            Opcode::Or(x, y) => self.logic(x, y, |a, b| a | b),
+            Opcode::And(x, y) => self.logic(x, y, |a, b| a & b),
+            Opcode::Xor(x, y) => self.logic(x, y, |a, b| a ^ b),
+            Opcode::Add(x, y) => self.set_with_flag(x, alu::add(self.regs[x as usize], self.regs[y as usize])),
+            Opcode::SubY(x, y) => self.set_with_flag(x, alu::sub(self.regs[x as usize], self.regs[y as usize])),
+            Opcode::ShiftRight(x, y) => self.set_with_flag(x, alu::shift_right(self.shift_source(x, y))),
+            Opcode::SubX(x, y) => self.set_with_flag(x, alu::sub(self.regs[y as usize], self.regs[x as usize])),
+            Opcode::ShiftLeft(x, y) => self.set_with_flag(x, alu::shift_left(self.shift_source(x, y))),
+            Opcode::SkipNotEqual(x, y) => self.skip_if(self.regs[x as usize] != self.regs[y as usize]),
+            Opcode::SetI(addr) => self.i = addr,
+            Opcode::JumpV0(nnn) => {
+                let x = if self.quirks.jump_vx { (nnn >> 8) as usize } else { 0 };
+                self.pc = self.regs[x] as u16 + nnn;
+            }
+            Opcode::Random(x, nn) => self.regs[x as usize] = bus.random() & nn,
+            Opcode::Draw(x, y, n) => {
+                self.draw(x, y, n, bus);
+                result.display_changed = true;
+            }
+            Opcode::SkipKey(x) => {
+                let pressed = self.keypad.read(self.regs[x as usize]);
+                self.skip_if(pressed)
+            }
+            Opcode::SkipNotKey(x) => {
+                let pressed = self.keypad.read(self.regs[x as usize]);
+                self.skip_if(!pressed)
+            }
+            Opcode::GetDelay(x) => self.regs[x as usize] = self.delay_timer,
+            Opcode::WaitKey(x) => match self.wait_for_key() {
+                Some(key) => self.regs[x as usize] = key,
+                None => {
+                    result.waiting_for_key = true;
+                    self.pc -= 2;
+                }
+            },
+            Opcode::SetDelay(x) => self.delay_timer = self.regs[x as usize],
+            Opcode::SetSound(x) => self.sound_timer = self.regs[x as usize],
+            Opcode::AddI(x) => self.i = self.i.wrapping_add(self.regs[x as usize] as u16),
+            Opcode::SetSprite(x) => self.i = FONT_OFFSET as u16 + (self.regs[x as usize] & 0xF) as u16 * 5,
+            Opcode::StoreBCD(x) => {
+                let value = self.regs[x as usize];
+                bus.write(&mut self.memory, self.i, value / 100);
+                bus.write(&mut self.memory, self.i.wrapping_add(1), (value / 10) % 10);
+                bus.write(&mut self.memory, self.i.wrapping_add(2), value % 10);
+            }
+            Opcode::StoreRegs(x) => {
+                for reg in 0..=x as u16 {
+                    bus.write(&mut self.memory, self.i.wrapping_add(reg), self.regs[reg as usize]);
+                }
+                if self.quirks.memory_increment {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            }
+            Opcode::LoadRegs(x) => {
+                for reg in 0..=x as u16 {
+                    self.regs[reg as usize] = bus.read(&self.memory, self.i.wrapping_add(reg));
+                }
+                if self.quirks.memory_increment {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn wait_for_key(&mut self) -> Option<u8> {
+        if self.quirks.key_wait_on_press {
+            self.keypad.wait_for_press()
+        } else {
+            self.keypad.wait_for_key()
+        }
+    }
+
+    fn skip_if(&mut self, condition: bool) {
+        if condition {
+            self.pc += 2;
+        }
+    }
+
+    fn logic(&mut self, x: u8, y: u8, f: fn(u8, u8) -> u8) {
+        self.regs[x as usize] = f(self.regs[x as usize], self.regs[y as usize]);
+        if self.quirks.vf_reset {
+            self.regs[0xF] = 0;
+        }
+    }
+
+    // VF goes last, it keeps the flag when it is VX as well
+    fn set_with_flag(&mut self, x: u8, (value, flag): (u8, u8)) {
+        self.regs[x as usize] = value;
+        self.regs[0xF] = flag;
+    }
+
+    fn shift_source(&self, x: u8, y: u8) -> u8 {
+        if self.quirks.shift_vy {
+            self.regs[y as usize]
+        } else {
+            self.regs[x as usize]
+        }
+    }
+
+    // Without wrap_sprites the rows below the bottom edge are not even read.
+    fn draw(&mut self, x: u8, y: u8, n: u8, bus: &mut impl Bus) {
+        let vx = self.regs[x as usize] as usize % DISPLAY_WIDTH;
+        let vy = self.regs[y as usize] as usize % DISPLAY_HEIGHT;
+        self.regs[0xF] = 0;
+
+        for row in 0..n as usize {
+            if !self.quirks.wrap_sprites && vy + row >= DISPLAY_HEIGHT {
+                break;
+            }
+            let byte = bus.read(&self.memory, self.i.wrapping_add(row as u16));
+            for bit in 0..8 {
+                if !self.quirks.wrap_sprites && vx + bit >= DISPLAY_WIDTH {
+                    break;
+                }
+                if (byte >> (7 - bit)) & 1 == 0 {
+                    continue;
+                }
+                let offset = ((vy + row) % DISPLAY_HEIGHT) * DISPLAY_WIDTH + (vx + bit) % DISPLAY_WIDTH;
+                if self.display[offset] == 1 {
+                    self.regs[0xF] = 1;
+                }
+                self.display[offset] ^= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_machine_runs_without_std_state() {
+        // LD V0, 5; LD F, V0; LD V1, 0; DRW V1, V1, 5; LD ST, V0; JP 0x20A
+        let rom = [0x60, 0x05, 0xF0, 0x29, 0x61, 0x00, 0xD1, 0x15, 0xF0, 0x18, 0x12, 0x0A];
+        let mut machine = Machine::new(&rom, Quirks::default(), 1).unwrap();
+
+        let results: [StepResult; 6] = core::array::from_fn(|_| machine.step().unwrap());
+        assert!(results[3].display_changed);
+        assert!(results[4].sound_started);
+        assert!(results[5].halted);
+        assert_eq!(&machine.pixels()[..4], &[1, 1, 1, 1]);
+        assert_eq!(&machine.pixels()[DISPLAY_WIDTH..DISPLAY_WIDTH + 4], &[1, 0, 0, 0]);
+
+        for _ in 0..4 {
+            assert!(!machine.tick_timers());
+        }
+        assert!(machine.tick_timers());
+        assert!(!machine.beeping());
+    }
+
+    #[test]
+    fn test_run_frame_stops_at_halt() {
+        // same program, the sound lasts 5 frames
+        let rom = [0x60, 0x05, 0xF0, 0x29, 0x61, 0x00, 0xD1, 0x15, 0xF0, 0x18, 0x12, 0x0A];
+        let mut machine = Machine::new(&rom, Quirks::default(), 1).unwrap();
+
+        let frame = machine.run_frame(16).unwrap();
+        assert!(frame.display_changed && frame.sound_started && frame.halted);
+        assert_eq!(machine.pc(), 0x20A);
+        assert_eq!(machine.timers(), (0, 4));
+
+        let frames: [StepResult; 4] = core::array::from_fn(|_| machine.run_frame(16).unwrap());
+        assert!(!frames[0].display_changed && !frames[0].sound_started);
+        assert!(frames[3].sound_stopped && !frames[2].sound_stopped);
+    }
+
+    #[test]
+    fn test_bus_sees_memory_accesses_and_machine_calls() {
+        #[derive(Default)]
+        struct Recording {
+            reads: [u16; 4],
+            read_count: usize,
+            written: Option<(u16, u8)>,
+            machine_call: Option<u16>,
+        }
+        impl Bus for Recording {
+            fn read(&mut self, _memory: &[u8; MEMORY_SIZE], addr: u16) -> u8 {
+                self.reads[self.read_count] = addr;
+                self.read_count += 1;
+                0xFF
+            }
+            fn write(&mut self, _memory: &mut [u8; MEMORY_SIZE], addr: u16, value: u8) {
+                self.written = Some((addr, value));
+            }
+            fn random(&mut self) -> u8 {
+                0x0F
+            }
+            fn machine_call(&mut self, nnn: u16) {
+                self.machine_call = Some(nnn);
+            }
+        }
+
+        let mut machine = Machine::new(&[], Quirks::default(), 1).unwrap();
+        let mut bus = Recording::default();
+        machine.i = 0xFFF;
+        machine.execute(Opcode::LoadRegs(1), &mut bus).unwrap();
+        machine.execute(Opcode::StoreRegs(0), &mut bus).unwrap();
+        machine.execute(Opcode::Random(2, 0x3C), &mut bus).unwrap();
+        machine.execute(Opcode::MachineCall(0x123), &mut bus).unwrap();
+
+        // the bus gets the address before the memory wraps it around
+        assert_eq!(&bus.reads[..bus.read_count], &[0xFFF, 0x1000]);
+        assert_eq!(machine.registers()[..3], [0xFF, 0xFF, 0x0C]);
+        assert_eq!(bus.written, Some((0xFFF, 0xFF)));
+        assert_eq!(bus.machine_call, Some(0x123));
+    }
+}
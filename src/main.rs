@@ -1,24 +1,772 @@
-use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::builder::PossibleValuesParser;
+use clap::{Parser, Subcommand};
+
+use chip8_emulator_rs::config::{Config, ConfigWatcher, Layout, Rgb};
+use chip8_emulator_rs::database::{self, Database};
+use chip8_emulator_rs::peripheral::SerialConsole;
+use chip8_emulator_rs::recent::RecentRoms;
+#[cfg(feature = "png")]
+use chip8_emulator_rs::screen;
+use chip8_emulator_rs::{
+    bench, builtin, check, dap, detect, disasm, emulator, info, parse, paths, roms, symbols, testsuite, theme, trace,
+    tracediff, vectors, Chip8, Chip8Error, Display, Input,
+};
 
-mod emulator;
-mod error;
 mod io;
-mod opcode;
 
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+const PROFILE_ENTRIES: usize = 32;
+
+#[derive(Parser, Debug, Clone)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
-    /// Rom file to emulate
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Rom file to emulate, or a directory to pick one from a list of its ROMs. A file dialog asks for one when
+    /// it is left out
     #[arg(short, long, value_name = "ROM-FILE")]
-    rom: String,
+    #[cfg_attr(
+        not(feature = "file-dialog"),
+        arg(required_unless_present_any = ["configure_keys", "last", "recent", "builtin", "list_builtins", "list_themes"])
+    )]
+    rom: Option<String>,
+
+    /// Runs the ROM that was played last again
+    #[arg(long, default_value_t = false, conflicts_with = "rom")]
+    last: bool,
+
+    /// Lists the recently played ROMs, newest first
+    #[arg(long, default_value_t = false)]
+    recent: bool,
+
+    /// Runs one of the ROMs that come with the emulator
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with_all = ["rom", "last"],
+        value_parser = PossibleValuesParser::new(builtin::BUILTINS.map(|builtin| builtin.name))
+    )]
+    builtin: Option<String>,
+
+    /// Lists the ROMs that come with the emulator
+    #[arg(long, default_value_t = false)]
+    list_builtins: bool,
+
+    /// IPS patch to apply to the ROM before running it [default: the ROM's name with .ips for its extension, when
+    /// that file exists]
+    #[arg(long, value_name = "IPS-FILE")]
+    patch: Option<String>,
 
     /// Enables debug mode
     #[arg(short, long, default_value_t = false)]
     debug: bool,
+
+    /// Prints a plain-English description of every instruction executed while stepping
+    #[arg(long, default_value_t = false)]
+    explain: bool,
+
+    /// Runs without opening a window (combine with --debug to get a command prompt on stdin). Without --debug
+    /// the emulator runs as fast as possible with the timers ticking once per frame of --ips / 60 instructions
+    #[arg(long, default_value_t = false)]
+    headless: bool,
+
+    /// Window and input backend, defaults to the first one compiled in
+    #[arg(long, value_enum)]
+    frontend: Option<io::Frontend>,
+
+    /// Config file with defaults for the options below, changes to the palette, speed and keys apply while running
+    /// [default: config.toml in the config directory, ~/.config/chip8-emulator-rs on Linux]
+    #[arg(long, value_name = "CONFIG-FILE")]
+    config: Option<String>,
+
+    /// Instructions executed per second [default: 1000]
+    #[arg(long, value_name = "N")]
+    ips: Option<u32>,
+
+    /// Size of a CHIP-8 pixel in window pixels [default: 8]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    scale: Option<u32>,
+
+    /// Color theme to start with, T switches to the next one while running; --foreground and --background
+    /// change its colors
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_parser = PossibleValuesParser::new(theme::THEMES.map(|theme| theme.name))
+    )]
+    theme: Option<String>,
+
+    /// Lists the color themes with their colors: unlit, lit, and the second plane and both planes of XO-CHIP
+    #[arg(long, default_value_t = false)]
+    list_themes: bool,
+
+    /// Color of lit pixels as #RRGGBB, e.g. #33FF66 for green phosphor or #FFB000 for amber [default: #FFFFFF]
+    #[arg(long, visible_alias = "fg", value_name = "COLOR")]
+    foreground: Option<Rgb>,
+
+    /// Color of unlit pixels as #RRGGBB [default: #000000]
+    #[arg(long, visible_alias = "bg", value_name = "COLOR")]
+    background: Option<Rgb>,
+
+    /// XO-CHIP color of pixels lit on the second plane only, as #RRGGBB [default: #AAAAAA]
+    #[arg(long, value_name = "COLOR")]
+    plane2: Option<Rgb>,
+
+    /// XO-CHIP color of pixels lit on both planes, as #RRGGBB [default: #555555]
+    #[arg(long, value_name = "COLOR")]
+    both_planes: Option<Rgb>,
+
+    /// Interpreter quirks to enable, comma separated: vf-reset, memory-increment, shift-vy, jump-vx, wrap-sprites,
+    /// key-wait-on-press, or vip for the COSMAC VIP set and none for neither
+    #[arg(long, value_name = "LIST", value_parser = parse::parse_quirks)]
+    quirks: Option<chip8_emulator_rs::Quirks>,
+
+    /// Volume of the beep between 0 and 1 [default: 0.1]
+    #[arg(long, value_name = "V")]
+    volume: Option<f32>,
+
+    /// Turns the beep off
+    #[arg(long, default_value_t = false)]
+    mute: bool,
+
+    /// Keyboard layout the key names in the config file refer to, the default keypad sits where 1234 / QWER /
+    /// ASDF / ZXCV are on QWERTY [default: qwerty]
+    #[arg(long, value_enum)]
+    layout: Option<LayoutArg>,
+
+    /// Asks for a keyboard key for each CHIP-8 key and saves them to the config file, then runs the ROM if one
+    /// was given
+    #[arg(long, default_value_t = false, conflicts_with = "headless")]
+    configure_keys: bool,
+
+    /// Seed for the random number generator (CXNN), makes runs reproducible
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+
+    /// Stops the emulator after this many frames (60 per emulated second)
+    #[arg(long, value_name = "N")]
+    max_frames: Option<u64>,
+
+    /// Stops the emulator after this many instructions, for ROMs that never halt; see --on-max-cycles
+    #[arg(long, value_name = "N")]
+    max_cycles: Option<u64>,
+
+    /// Whether reaching --max-cycles is an error (exit code 1) or a clean stop
+    #[arg(long, value_enum, default_value_t = CycleLimitArg::Error, requires = "max_cycles")]
+    on_max_cycles: CycleLimitArg,
+
+    /// Stops the emulator when the program halts, same as --on-halt exit
+    #[arg(long, default_value_t = false)]
+    exit_on_halt: bool,
+
+    /// Stops the emulator with success when the program counter reaches this address, like the label a test ROM
+    /// jumps to when it passes; may be given several times. Exits with 1 when the run ends without reaching one
+    #[arg(long, value_name = "ADDR", value_parser = parse::parse_u16)]
+    exit_at: Vec<u16>,
+
+    /// Writes the final display as text ('#' for lit pixels) to this file on exit, '-' prints it, or as a picture
+    /// when the file name ends with .png
+    #[arg(long, value_name = "FILE")]
+    dump_display: Option<String>,
+
+    /// Compares the final display with a PNG of the expected screen, light pixels on dark at any whole scale,
+    /// and exits with 1 and the differing rows when they do not match
+    #[arg(long, value_name = "PNG-FILE")]
+    expect_screen: Option<String>,
+
+    /// Prints a CRC-32 of the display after every frame, or only of the final display with "exit", to compare
+    /// runs of a ROM with a known good one
+    #[arg(long, value_enum, value_name = "WHEN", num_args = 0..=1, default_missing_value = "every", requires = "headless")]
+    hash_frames: Option<HashFrames>,
+
+    /// Symbol file with one "label address" pair per line, used to show label names instead of addresses
+    #[arg(long, value_name = "SYMBOL-FILE")]
+    symbols: Option<String>,
+
+    /// Prints every byte the ROM writes to this address (0xFFF when given without a value) to stdout
+    #[arg(long, value_name = "ADDR", value_parser = parse::parse_u16, num_args = 0..=1, default_missing_value = "0xFFF")]
+    serial_console: Option<u16>,
+
+    /// Writes a log line for every executed instruction to this file
+    #[arg(long, value_name = "TRACE-FILE")]
+    trace: Option<String>,
+
+    /// Only traces instructions whose address lies in this range (e.g. 0x200-0x2FF)
+    #[arg(long, value_name = "START-END", value_parser = parse::parse_range, requires = "trace")]
+    trace_range: Option<std::ops::RangeInclusive<u16>>,
+
+    /// Format of the trace file
+    #[arg(long, value_enum, default_value_t = TraceFormatArg::Text, requires = "trace")]
+    trace_format: TraceFormatArg,
+
+    /// Writes a profile of the hottest addresses and the instruction mix to this file on exit
+    #[arg(long, value_name = "REPORT-FILE")]
+    profile_out: Option<String>,
+
+    /// Writes a report of the ROM bytes that were never executed to this file on exit
+    #[arg(long, value_name = "REPORT-FILE")]
+    coverage_out: Option<String>,
+
+    /// Writes a disassembly of the ROM marking never executed bytes with '!' to this file on exit
+    #[arg(long, value_name = "DISASM-FILE")]
+    coverage_disasm: Option<String>,
+
+    /// Writes an HTML page with the disassembly colored by coverage, the hot spots and the final display on exit
+    #[arg(long, value_name = "HTML-FILE")]
+    html_report: Option<String>,
+
+    /// Opens a second window showing how often every memory address is executed
+    #[arg(long, default_value_t = false)]
+    heatmap: bool,
+
+    /// Remembers the last N writes (instruction and value) to every memory cell for the `who` debugger command
+    #[arg(long, value_name = "N")]
+    write_history: Option<usize>,
+
+    /// File the last executed instructions are written to when the emulator stops with an error [default:
+    /// crash.log in the data directory]
+    #[arg(long, value_name = "TRACE-FILE")]
+    crash_log: Option<PathBuf>,
+
+    /// What to do when the program jumps to itself (the usual way to halt)
+    #[arg(long, value_enum, default_value_t = HaltArg::Continue)]
+    on_halt: HaltArg,
+
+    /// What to do with 0NNN calls to machine code of the original CPU, which cannot run here
+    #[arg(long, value_enum, default_value_t = MachineCallArg::Ignore)]
+    on_machine_call: MachineCallArg,
+
+    /// What to do when an instruction reads memory that was never written by the ROM or the program
+    #[arg(long, value_enum, default_value_t = UninitArg::Ignore)]
+    on_uninit_read: UninitArg,
+
+    /// What to do with unknown opcodes: abort, log and skip them, or skip them silently
+    #[arg(long, value_enum, default_value_t = InvalidArg::Halt)]
+    on_invalid: InvalidArg,
+}
+
+// When --hash-frames prints the hash of the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum HashFrames {
+    Every,
+    Exit,
+}
+
+// Copies an enum of the library as a command line value, the library itself does not depend on clap
+macro_rules! value_enum {
+    ($name:ident => $target:ty { $($variant:ident),+ }) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+        enum $name {
+            $($variant),+
+        }
+
+        impl From<$name> for $target {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => <$target>::$variant),+
+                }
+            }
+        }
+    };
+}
+
+value_enum!(HaltArg => emulator::HaltPolicy { Continue, Pause, Exit });
+value_enum!(MachineCallArg => emulator::MachineCallPolicy { Ignore, Warn, Halt });
+value_enum!(CycleLimitArg => emulator::CycleLimitPolicy { Error, Exit });
+value_enum!(TraceFormatArg => trace::TraceFormat { Text, Json });
+value_enum!(LayoutArg => Layout { Qwerty, Azerty, Qwertz, Dvorak });
+value_enum!(UninitArg => emulator::UninitPolicy { Ignore, Warn, Break });
+value_enum!(InvalidArg => emulator::InvalidPolicy { Halt, Warn, Nop });
+
+#[derive(Subcommand, Debug, Clone)]
+enum Commands {
+    /// Runs a ROM headless as fast as it goes for a while and reports instructions and frames per second and the
+    /// time taken by each opcode class
+    Bench {
+        /// ROM to run, like for --rom
+        rom: String,
+        /// How long to run for
+        #[arg(long, value_name = "N", default_value_t = bench::DEFAULT_SECONDS)]
+        seconds: f64,
+        /// Quirks to run with, like for --quirks; the ROM database's or none by default
+        #[arg(long, value_name = "LIST", value_parser = parse::parse_quirks)]
+        quirks: Option<chip8_emulator_rs::Quirks>,
+    },
+    /// Checks a ROM for unknown instructions, bad jumps, stack mistakes and sprites past its end without running
+    /// it, exits with 1 when it finds any
+    Check {
+        /// ROM to check, like for --rom
+        rom: String,
+    },
+    /// Serves a debugger like VS Code over the Debug Adapter Protocol on stdin and stdout, which launches ROMs
+    /// headless and stops them at addresses
+    Dap {
+        /// Listens for the debugger on this port of localhost instead
+        #[arg(long)]
+        port: Option<u16>,
+    },
+    /// Runs a ROM headless under every combination of quirks and suggests the one that works
+    DetectQuirks {
+        /// ROM to run, like for --rom
+        rom: String,
+        /// Frames to run each combination for, at 60 frames a second
+        #[arg(long, default_value_t = detect::DEFAULT_FRAMES)]
+        frames: u64,
+    },
+    /// Disassembles a ROM, with the bytes no path through the program reaches shown as data
+    Disasm {
+        /// ROM to disassemble, like for --rom
+        rom: String,
+        /// Symbol file with labels to use, others are made up for jump, call and data targets
+        #[arg(long, value_name = "SYMBOL-FILE")]
+        symbols: Option<String>,
+    },
+    /// Writes JSON test vectors, a state before and after one instruction, made by running every instruction on
+    /// random states, to compare other emulators with this one
+    ExportVectors {
+        /// JSON file to write
+        file: String,
+        /// Quirks to run the instructions with, like for --quirks
+        #[arg(long, value_name = "LIST", value_parser = parse::parse_quirks, default_value = "none")]
+        quirks: chip8_emulator_rs::Quirks,
+        /// Vectors per instruction
+        #[arg(long, value_name = "N", default_value_t = vectors::DEFAULT_COUNT)]
+        count: usize,
+        /// Seed for the random states, the same seed writes the same vectors
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        seed: u64,
+    },
+    /// Prints the size, hashes, database entry, first instructions and extension instructions of a ROM
+    Info {
+        /// ROM to look at, like for --rom
+        rom: String,
+    },
+    /// Runs the JSON test vectors of a file and reports the ones whose state after the instruction is not the
+    /// expected one, exits with 1 when any fails
+    RunVectors {
+        /// JSON file written by export-vectors or in the same format
+        file: String,
+    },
+    /// Runs test ROMs headless under each quirk configuration and compares their final screens with their pass
+    /// screens, exits with 1 when any fails
+    TestSuite {
+        /// Directory of test ROMs, like the Timendus CHIP-8 test suite, with a ROM.golden or ROM.<quirks>.golden
+        /// pass screen next to each ROM [default: the built-in self-test]
+        dir: Option<String>,
+        /// Frames to run each ROM for, at 60 frames a second
+        #[arg(long, default_value_t = detect::DEFAULT_FRAMES)]
+        frames: u64,
+        /// Saves the final screens as the pass screens instead of comparing them
+        #[arg(long, default_value_t = false, requires = "dir")]
+        record: bool,
+    },
+    /// Compares two trace files and reports the first step where they diverge
+    Tracediff {
+        /// Trace of the first run
+        first: String,
+        /// Trace of the second run
+        second: String,
+    },
 }
 
-fn main() -> Result<(), error::Chip8Error> {
+fn main() -> Result<ExitCode, Chip8Error> {
     let args = Args::parse();
-    emulator::Chip8::new(args.rom.as_str(), args.debug)?.run()?;
+    match &args.command {
+        Some(Commands::Tracediff { first, second }) => return tracediff::run(first, second).map(exit_code),
+        Some(Commands::Check { rom }) => return check::run(rom).map(exit_code),
+        Some(Commands::Dap { port }) => return dap::run(*port).map(|()| ExitCode::SUCCESS),
+        Some(Commands::Disasm { rom, symbols }) => {
+            return disasm::run(rom, symbols.as_deref()).map(|()| ExitCode::SUCCESS)
+        }
+        Some(Commands::Bench { rom, seconds, quirks }) => {
+            return bench::run(rom, *seconds, *quirks).map(|()| ExitCode::SUCCESS)
+        }
+        Some(Commands::DetectQuirks { rom, frames }) => return detect::run(rom, *frames).map(|()| ExitCode::SUCCESS),
+        Some(Commands::ExportVectors {
+            file,
+            quirks,
+            count,
+            seed,
+        }) => return vectors::export(file, *quirks, *count, *seed).map(|()| ExitCode::SUCCESS),
+        Some(Commands::Info { rom }) => return info::run(rom).map(|()| ExitCode::SUCCESS),
+        Some(Commands::RunVectors { file }) => return vectors::run(file).map(exit_code),
+        Some(Commands::TestSuite { dir, frames, record }) => {
+            return testsuite::run(dir.as_deref(), *frames, *record).map(exit_code)
+        }
+        None => {}
+    }
+    if args.recent {
+        for (index, rom) in RecentRoms::load().roms().iter().enumerate() {
+            println!("{:>2}  {}", index + 1, rom);
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.list_builtins {
+        for builtin in &builtin::BUILTINS {
+            println!("{:<8}{}", builtin.name, builtin.description);
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.list_themes {
+        for theme in &theme::THEMES {
+            let colors: Vec<String> = theme.colors.iter().map(|color| color.to_string()).collect();
+            println!("{:<15}{}  {}", theme.name, colors.join(" "), theme.description);
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+    let rom = if let Some(name) = &args.builtin {
+        Some(format!("{}{}", roms::BUILTIN_PREFIX, name))
+    } else if args.last {
+        let recent = RecentRoms::load();
+        let last = recent
+            .last()
+            .ok_or_else(|| Chip8Error::IoError(std::io::Error::other("no ROM was played yet")))?;
+        Some(last.to_string())
+    } else {
+        args.rom.clone()
+    };
+
+    let mut config = load_config(&args)?;
+    let mut frontends = None;
+    if args.configure_keys {
+        let mut opened = io::open(
+            args.frontend,
+            emulator::DISPLAY_WIDTH,
+            emulator::DISPLAY_HEIGHT,
+            &config,
+        )?;
+        configure_keys(&args, &mut config, opened.0.as_mut(), opened.1.as_mut())?;
+        if rom.is_none() {
+            return Ok(ExitCode::SUCCESS);
+        }
+        frontends = Some(opened);
+    }
+    let Some(mut rom) = rom.or_else(pick_rom) else {
+        return Ok(ExitCode::SUCCESS);
+    };
+    if Path::new(&rom).is_dir() {
+        if args.headless {
+            return Err(Chip8Error::IoError(std::io::Error::other(
+                "picking a ROM from a directory needs a window, leave out --headless",
+            )));
+        }
+        let roms = roms::list(Path::new(&rom))?;
+        if roms.is_empty() {
+            return Err(Chip8Error::IoError(std::io::Error::other(format!(
+                "no ROMs in {}",
+                rom
+            ))));
+        }
+        let mut opened = match frontends.take() {
+            Some(opened) => opened,
+            None => io::open(
+                args.frontend,
+                emulator::DISPLAY_WIDTH,
+                emulator::DISPLAY_HEIGHT,
+                &config,
+            )?,
+        };
+        match io::browse(opened.0.as_mut(), opened.1.as_mut(), &config.key_map(), &roms)? {
+            Some(path) => rom = path.to_string_lossy().into_owned(),
+            None => return Ok(ExitCode::SUCCESS),
+        }
+        frontends = Some(opened);
+    }
+    let rom = rom.as_str();
+    let mut builder = Chip8::builder()
+        .rom_file(rom)
+        .debug(args.debug)
+        .ips(config.ips)
+        .quirks(config.quirks);
+    if let Some(seed) = args.seed {
+        builder = builder.seed(seed);
+    }
+    if let Some(patch) = &args.patch {
+        builder = builder.patch(patch);
+    }
+    // quirks given on the command line are meant for this ROM, the database only replaces the config's
+    if args.quirks.is_none() {
+        builder = builder.database(Database::load());
+    }
+    let mut chip8 = builder.build()?;
+    print_loaded_rom(rom, chip8.loaded_rom());
+    chip8.on_rom_open(print_loaded_rom);
+    chip8.on_rom_open_error(|path, err| println!("Could not open {}: {}", path, err));
+    chip8.on_theme_change(|theme| println!("Theme: {}", theme.name));
+    if !args.headless {
+        RecentRoms::remember(rom);
+        let (screen, keyboard, beeper) = match frontends {
+            Some(opened) => opened,
+            None => io::open(
+                args.frontend,
+                emulator::DISPLAY_WIDTH,
+                emulator::DISPLAY_HEIGHT,
+                &config,
+            )?,
+        };
+        chip8.set_display(screen);
+        chip8.set_palette(config.palette);
+        chip8.set_input(keyboard);
+        if let Some(beeper) = beeper {
+            chip8.set_audio(beeper);
+        }
+    }
+    if let Some(path) = config_path(&args) {
+        let overrides = args.clone();
+        chip8.watch_config(ConfigWatcher::new(path, move |config| apply_args(config, &overrides)));
+        chip8.on_config_error(|err| eprintln!("Ignoring config change: {}", err));
+    }
+    if cfg!(feature = "file-dialog") {
+        chip8.set_rom_picker(pick_rom);
+    }
+    if let Some(path) = &args.trace {
+        chip8.set_tracer(trace::Tracer::new(path, args.trace_range, args.trace_format.into())?);
+    }
+    if args.profile_out.is_some() || args.html_report.is_some() {
+        chip8.enable_profiler();
+    }
+    if let Some(path) = &args.symbols {
+        chip8.set_symbols(symbols::Symbols::load(path)?);
+    }
+    if let Some(depth) = args.write_history {
+        chip8.enable_write_history(depth);
+    }
+    if args.explain {
+        chip8.enable_explain();
+    }
+    if let Some(addr) = args.serial_console {
+        chip8.add_peripheral(SerialConsole::with_output(addr, std::io::stdout()));
+    }
+    let on_halt = if args.exit_on_halt {
+        emulator::HaltPolicy::Exit
+    } else {
+        args.on_halt.into()
+    };
+    chip8.set_halt_policy(on_halt);
+    if on_halt != emulator::HaltPolicy::Continue {
+        chip8.on_halt(|pc| println!("Program halted at {:#05X}", pc));
+    }
+    chip8.set_throttle(!args.headless || args.debug);
+    chip8.set_max_frames(args.max_frames);
+    chip8.set_max_cycles(args.max_cycles, args.on_max_cycles.into());
+    chip8.set_exit_at(args.exit_at.clone());
+    chip8.set_invalid_policy(args.on_invalid.into());
+    chip8.set_machine_call_policy(args.on_machine_call.into());
+    chip8.set_uninit_policy(args.on_uninit_read.into());
+    if args.heatmap {
+        chip8.enable_heatmap()?;
+    }
+    if args.coverage_out.is_some() || args.coverage_disasm.is_some() || args.html_report.is_some() {
+        chip8.enable_coverage();
+    }
+
+    if args.hash_frames == Some(HashFrames::Every) {
+        chip8.on_frame(|frame, pixels| println!("{} {:08x}", frame, database::crc32(pixels)));
+    }
+
+    let result = chip8.run();
+    // a crash log that cannot be written must not hide the error the emulator stopped with
+    let crash_log = args.crash_log.clone().or_else(paths::crash_log_file);
+    if let (Err(err), Some(path)) = (&result, crash_log) {
+        match chip8.dump_recent_steps(&path, err) {
+            Ok(()) => eprintln!("Last executed instructions written to {}", path.display()),
+            Err(dump_err) => eprintln!(
+                "Could not write the last executed instructions to {}: {}",
+                path.display(),
+                dump_err
+            ),
+        }
+    }
+    if let (Some(path), Some(report)) = (&args.profile_out, chip8.profile_report(PROFILE_ENTRIES)) {
+        std::fs::write(path, report)?;
+    }
+    if let (Some(path), Some(report)) = (&args.coverage_out, chip8.coverage_report()) {
+        std::fs::write(path, report)?;
+    }
+    if let (Some(path), Some(disassembly)) = (&args.coverage_disasm, chip8.coverage_disassembly()) {
+        std::fs::write(path, disassembly)?;
+    }
+    match args.dump_display.as_deref() {
+        Some("-") => println!("{}", chip8.display_text()),
+        Some(path) if path.to_ascii_lowercase().ends_with(".png") => save_screen(path, chip8.pixels())?,
+        Some(path) => std::fs::write(path, chip8.display_text() + "\n")?,
+        None => {}
+    }
+    // a failed check still writes the other reports and the end of the trace
+    let mut code = ExitCode::SUCCESS;
+    if let (Some(path), Ok(())) = (&args.expect_screen, &result) {
+        match compare_screen(path, chip8.pixels())? {
+            Some(report) => {
+                print!("{}", report);
+                code = ExitCode::FAILURE;
+            }
+            None => println!("The screen matches {}", path),
+        }
+    }
+    if args.max_frames.is_some_and(|max| chip8.frame_count() >= max) && result.is_ok() {
+        println!("Stopped after {} frames", chip8.frame_count());
+    }
+    if args.max_cycles.is_some_and(|max| chip8.cycle_count() >= max) && result.is_ok() {
+        println!("Stopped after {} instructions", chip8.cycle_count());
+    }
+    if let Some(addr) = chip8.exited_at() {
+        println!("Reached {:#05X}", addr);
+    } else if !args.exit_at.is_empty() && result.is_ok() {
+        println!("The program never reached --exit-at");
+        code = ExitCode::FAILURE;
+    }
+    if args.hash_frames == Some(HashFrames::Exit) {
+        println!("{} {:08x}", chip8.frame_count(), database::crc32(chip8.pixels()));
+    }
+    if let (Some(path), Some(report)) = (&args.html_report, chip8.html_report(rom)) {
+        std::fs::write(path, report)?;
+    }
+    // flushes the trace before the exit code is returned
+    drop(chip8);
+    result.map(|()| code)
+}
+
+fn exit_code(passed: bool) -> ExitCode {
+    if passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn print_loaded_rom(rom: &str, loaded: &emulator::LoadedRom) {
+    if let Some(patch) = &loaded.patch {
+        println!("Applied the patch {}", patch.display());
+    }
+    if let Some(info) = &loaded.recognized {
+        println!(
+            "Recognized {} ({}), running with quirks: {}",
+            info.title, info.platform, info.quirks
+        );
+    }
+    if let Some((file, count)) = &loaded.cheats {
+        println!("Loaded {} cheat(s) from {}", count, file.display());
+    }
+    if let Some(err) = &loaded.cheat_error {
+        eprintln!("Ignoring the cheats of {}: {}", rom, err);
+    }
+}
+
+#[cfg(feature = "png")]
+fn save_screen(path: &str, pixels: &[u8]) -> Result<(), Chip8Error> {
+    screen::save_png(path, pixels, emulator::DISPLAY_WIDTH)
+}
+
+// The differences from the expected screen, None when there are none.
+#[cfg(feature = "png")]
+fn compare_screen(path: &str, pixels: &[u8]) -> Result<Option<String>, Chip8Error> {
+    let expected = screen::load_png(path, emulator::DISPLAY_WIDTH, emulator::DISPLAY_HEIGHT)?;
+    Ok(screen::compare(&expected, pixels, emulator::DISPLAY_WIDTH))
+}
+
+#[cfg(not(feature = "png"))]
+fn save_screen(_path: &str, _pixels: &[u8]) -> Result<(), Chip8Error> {
+    Err(Chip8Error::InvalidImage(
+        "build with --features png to write PNG files".to_string(),
+    ))
+}
+
+#[cfg(not(feature = "png"))]
+fn compare_screen(_path: &str, _pixels: &[u8]) -> Result<Option<String>, Chip8Error> {
+    Err(Chip8Error::InvalidImage(
+        "build with --features png to read PNG files".to_string(),
+    ))
+}
+
+// Lets a user who started the emulator from a desktop icon choose the ROM, None when they cancel.
+#[cfg(feature = "file-dialog")]
+fn pick_rom() -> Option<String> {
+    rfd::FileDialog::new()
+        .set_title("Open CHIP-8 ROM")
+        .add_filter("CHIP-8 ROM", &roms::EXTENSIONS)
+        .add_filter("All files", &["*"])
+        .pick_file()
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+// clap already insists on --rom without the dialog
+#[cfg(not(feature = "file-dialog"))]
+fn pick_rom() -> Option<String> {
+    None
+}
+
+fn config_path(args: &Args) -> Option<PathBuf> {
+    args.config.as_ref().map(PathBuf::from).or_else(Config::default_path)
+}
+
+// The config file with the command line flags applied on top of it.
+fn load_config(args: &Args) -> Result<Config, Chip8Error> {
+    let mut config = match (&args.config, config_path(args)) {
+        (Some(path), _) => std::fs::read_to_string(path)?.parse()?,
+        (None, Some(path)) => Config::load(&path)?,
+        (None, None) => Config::default(),
+    };
+    apply_args(&mut config, args);
+    Ok(config)
+}
+
+fn apply_args(config: &mut Config, args: &Args) {
+    if let Some(ips) = args.ips {
+        config.ips = ips;
+    }
+    if let Some(scale) = args.scale {
+        config.scale = scale;
+    }
+    if let Some(theme) = args.theme.as_deref().and_then(theme::find) {
+        config.palette = theme.palette();
+    }
+    if let Some(color) = args.foreground {
+        config.palette.foreground = color;
+    }
+    if let Some(color) = args.background {
+        config.palette.background = color;
+    }
+    if let Some(color) = args.plane2 {
+        config.palette.plane2 = color;
+    }
+    if let Some(color) = args.both_planes {
+        config.palette.both_planes = color;
+    }
+    if let Some(quirks) = args.quirks {
+        config.quirks = quirks;
+    }
+    if let Some(volume) = args.volume {
+        config.audio.volume = volume.clamp(0.0, 1.0);
+    }
+    if args.mute {
+        config.audio.enabled = false;
+    }
+    if let Some(layout) = args.layout {
+        config.layout = layout.into();
+    }
+}
+
+fn configure_keys(
+    args: &Args,
+    config: &mut Config,
+    screen: &mut dyn Display,
+    keyboard: &mut dyn Input,
+) -> Result<(), Chip8Error> {
+    let Some(path) = config_path(args) else {
+        return Err(Chip8Error::IoError(std::io::Error::other(
+            "there is no config directory to save the keys in, pass --config",
+        )));
+    };
+    let Some(keys) = io::configure_keys(screen, keyboard, &config.key_map())? else {
+        println!("Key binding cancelled, {} was not changed", path.display());
+        return Ok(());
+    };
+    keys.save(&path)?;
+    println!("Keys saved to {}", path.display());
+    keyboard.set_key_map(&keys)?;
+    config.keys = keys.iter().map(|(key, name)| (key, name.to_string())).collect();
     Ok(())
 }
@@ -1,7 +1,22 @@
-#[derive(Debug)]
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use crate::error::Chip8Error;
+#[cfg(feature = "std")]
+use crate::symbols::Symbols;
+
+pub const PATTERNS: [&str; 35] = [
+    "0NNN", "00E0", "00EE", "1NNN", "2NNN", "3XNN", "4XNN", "5XY0", "6XNN", "7XNN", "8XY0", "8XY1", "8XY2", "8XY3",
+    "8XY4", "8XY5", "8XY6", "8XY7", "8XYE", "9XY0", "ANNN", "BNNN", "CXNN", "DXYN", "EX9E", "EXA1", "FX07", "FX0A",
+    "FX15", "FX18", "FX1E", "FX29", "FX33", "FX55", "FX65",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opcode {
     Clear,                   // 00E0
     Return,                  // 00EE
+    MachineCall(u16),        // 0NNN
     Jump(u16),               // 1NNN
     Call(u16),               // 2NNN
     SkipEqualVal(u8, u8),    // 3XNN
@@ -15,9 +30,9 @@ pub enum Opcode {
     Xor(u8, u8),             // 8XY3
     Add(u8, u8),             // 8XY4
     SubY(u8, u8),            // 8XY5
-    ShiftRight(u8),          // 8XY6
+    ShiftRight(u8, u8),      // 8XY6
     SubX(u8, u8),            // 8XY7
-    ShiftLeft(u8),           // 8XYE
+    ShiftLeft(u8, u8),       // 8XYE
     SkipNotEqual(u8, u8),    // 9XY0
     SetI(u16),               // ANNN
     JumpV0(u16),             // BNNN
@@ -35,3 +50,201 @@ pub enum Opcode {
     StoreRegs(u8),           // FX55
     LoadRegs(u8),            // FX65
 }
+
+impl Opcode {
+    pub fn decode(instruction: u16) -> Result<Self, Chip8Error> {
+        let first_nibble = (instruction & 0xF000) >> 12;
+        let x = ((instruction & 0x0F00) >> 8) as u8;
+        let y = ((instruction & 0x00F0) >> 4) as u8;
+        let n = (instruction & 0x000F) as u8;
+        let nn = (instruction & 0x00FF) as u8;
+        let nnn = instruction & 0x0FFF;
+
+        match (first_nibble, x, y, n) {
+            (0x0, 0x0, 0xE, 0x0) => Ok(Opcode::Clear),
+            (0x0, 0x0, 0xE, 0xE) => Ok(Opcode::Return),
+            (0x0, _, _, _) => Ok(Opcode::MachineCall(nnn)),
+            (0x1, _, _, _) => Ok(Opcode::Jump(nnn)),
+            (0x2, _, _, _) => Ok(Opcode::Call(nnn)),
+            (0x3, _, _, _) => Ok(Opcode::SkipEqualVal(x, nn)),
+            (0x4, _, _, _) => Ok(Opcode::SkipNotEqualVal(x, nn)),
+            (0x5, _, _, 0x0) => Ok(Opcode::SkipEqual(x, y)),
+            (0x6, _, _, _) => Ok(Opcode::SetVal(x, nn)),
+            (0x7, _, _, _) => Ok(Opcode::AddVal(x, nn)),
+            (0x8, _, _, 0x0) => Ok(Opcode::Set(x, y)),
+            (0x8, _, _, 0x1) => Ok(Opcode::Or(x, y)),
+            (0x8, _, _, 0x2) => Ok(Opcode::And(x, y)),
+            (0x8, _, _, 0x3) => Ok(Opcode::Xor(x, y)),
+            (0x8, _, _, 0x4) => Ok(Opcode::Add(x, y)),
+            (0x8, _, _, 0x5) => Ok(Opcode::SubY(x, y)),
+            (0x8, _, _, 0x6) => Ok(Opcode::ShiftRight(x, y)),
+            (0x8, _, _, 0x7) => Ok(Opcode::SubX(x, y)),
+            (0x8, _, _, 0xE) => Ok(Opcode::ShiftLeft(x, y)),
+            (0x9, _, _, 0x0) => Ok(Opcode::SkipNotEqual(x, y)),
+            (0xA, _, _, _) => Ok(Opcode::SetI(nnn)),
+            (0xB, _, _, _) => Ok(Opcode::JumpV0(nnn)),
+            (0xC, _, _, _) => Ok(Opcode::Random(x, nn)),
+            (0xD, _, _, _) => Ok(Opcode::Draw(x, y, n)),
+            (0xE, _, 0x9, 0xE) => Ok(Opcode::SkipKey(x)),
+            (0xE, _, 0xA, 0x1) => Ok(Opcode::SkipNotKey(x)),
+            (0xF, _, 0x0, 0x7) => Ok(Opcode::GetDelay(x)),
+            (0xF, _, 0x0, 0xA) => Ok(Opcode::WaitKey(x)),
+            (0xF, _, 0x1, 0x5) => Ok(Opcode::SetDelay(x)),
+            (0xF, _, 0x1, 0x8) => Ok(Opcode::SetSound(x)),
+            (0xF, _, 0x1, 0xE) => Ok(Opcode::AddI(x)),
+            (0xF, _, 0x2, 0x9) => Ok(Opcode::SetSprite(x)),
+            (0xF, _, 0x3, 0x3) => Ok(Opcode::StoreBCD(x)),
+            (0xF, _, 0x5, 0x5) => Ok(Opcode::StoreRegs(x)),
+            (0xF, _, 0x6, 0x5) => Ok(Opcode::LoadRegs(x)),
+            _ => Err(Chip8Error::InvalidOpcode(instruction)),
+        }
+    }
+}
+
+impl Opcode {
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Opcode::Clear => "00E0",
+            Opcode::Return => "00EE",
+            Opcode::MachineCall(_) => "0NNN",
+            Opcode::Jump(_) => "1NNN",
+            Opcode::Call(_) => "2NNN",
+            Opcode::SkipEqualVal(_, _) => "3XNN",
+            Opcode::SkipNotEqualVal(_, _) => "4XNN",
+            Opcode::SkipEqual(_, _) => "5XY0",
+            Opcode::SetVal(_, _) => "6XNN",
+            Opcode::AddVal(_, _) => "7XNN",
+            Opcode::Set(_, _) => "8XY0",
+            Opcode::Or(_, _) => "8XY1",
+            Opcode::And(_, _) => "8XY2",
+            Opcode::Xor(_, _) => "8XY3",
+            Opcode::Add(_, _) => "8XY4",
+            Opcode::SubY(_, _) => "8XY5",
+            Opcode::ShiftRight(_, _) => "8XY6",
+            Opcode::SubX(_, _) => "8XY7",
+            Opcode::ShiftLeft(_, _) => "8XYE",
+            Opcode::SkipNotEqual(_, _) => "9XY0",
+            Opcode::SetI(_) => "ANNN",
+            Opcode::JumpV0(_) => "BNNN",
+            Opcode::Random(_, _) => "CXNN",
+            Opcode::Draw(_, _, _) => "DXYN",
+            Opcode::SkipKey(_) => "EX9E",
+            Opcode::SkipNotKey(_) => "EXA1",
+            Opcode::GetDelay(_) => "FX07",
+            Opcode::WaitKey(_) => "FX0A",
+            Opcode::SetDelay(_) => "FX15",
+            Opcode::SetSound(_) => "FX18",
+            Opcode::AddI(_) => "FX1E",
+            Opcode::SetSprite(_) => "FX29",
+            Opcode::StoreBCD(_) => "FX33",
+            Opcode::StoreRegs(_) => "FX55",
+            Opcode::LoadRegs(_) => "FX65",
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Opcode {
+    pub fn disassemble(&self, symbols: &Symbols) -> String {
+        let target = match *self {
+            Opcode::Jump(addr) | Opcode::Call(addr) | Opcode::SetI(addr) | Opcode::JumpV0(addr) => symbols.label(addr),
+            _ => None,
+        };
+
+        match (self, target) {
+            (Opcode::Jump(_), Some(label)) => format!("JP {}", label),
+            (Opcode::Call(_), Some(label)) => format!("CALL {}", label),
+            (Opcode::SetI(_), Some(label)) => format!("LD I, {}", label),
+            (Opcode::JumpV0(_), Some(label)) => format!("JP V0, {}", label),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl Opcode {
+    // Describes what the instruction does in plain English, for the explain mode of the debugger.
+    pub fn explain(&self) -> String {
+        match *self {
+            Opcode::Clear => "clear the screen".to_string(),
+            Opcode::Return => "return from the subroutine: PC ← address on top of the stack".to_string(),
+            Opcode::MachineCall(addr) => format!("call the machine code routine at {:#05X} of the original CPU", addr),
+            Opcode::Jump(addr) => format!("PC ← {:#05X} (jump)", addr),
+            Opcode::Call(addr) => format!("push the return address onto the stack, PC ← {:#05X} (call)", addr),
+            Opcode::SkipEqualVal(x, nn) => format!("skip the next instruction if V{:X} = {:#04X}", x, nn),
+            Opcode::SkipNotEqualVal(x, nn) => format!("skip the next instruction if V{:X} ≠ {:#04X}", x, nn),
+            Opcode::SkipEqual(x, y) => format!("skip the next instruction if V{:X} = V{:X}", x, y),
+            Opcode::SetVal(x, nn) => format!("V{:X} ← {:#04X}", x, nn),
+            Opcode::AddVal(x, nn) => format!("V{:X} ← V{:X} + {:#04X} (no carry flag)", x, x, nn),
+            Opcode::Set(x, y) => format!("V{:X} ← V{:X}", x, y),
+            Opcode::Or(x, y) => format!("V{:X} ← V{:X} OR V{:X}", x, x, y),
+            Opcode::And(x, y) => format!("V{:X} ← V{:X} AND V{:X}", x, x, y),
+            Opcode::Xor(x, y) => format!("V{:X} ← V{:X} XOR V{:X}", x, x, y),
+            Opcode::Add(x, y) => format!("V{:X} ← V{:X} + V{:X}, VF ← 1 on carry, else 0", x, x, y),
+            Opcode::SubY(x, y) => format!("V{:X} ← V{:X} - V{:X}, VF ← 0 on borrow, else 1", x, x, y),
+            Opcode::ShiftRight(x, _) => format!("V{:X} ← V{:X} >> 1, VF ← the bit shifted out", x, x),
+            Opcode::SubX(x, y) => format!("V{:X} ← V{:X} - V{:X}, VF ← 0 on borrow, else 1", x, y, x),
+            Opcode::ShiftLeft(x, _) => format!("V{:X} ← V{:X} << 1, VF ← the bit shifted out", x, x),
+            Opcode::SkipNotEqual(x, y) => format!("skip the next instruction if V{:X} ≠ V{:X}", x, y),
+            Opcode::SetI(addr) => format!("I ← {:#05X}", addr),
+            Opcode::JumpV0(addr) => format!("PC ← V0 + {:#05X} (jump)", addr),
+            Opcode::Random(x, nn) => format!("V{:X} ← random byte AND {:#04X}", x, nn),
+            Opcode::Draw(x, y, n) => format!(
+                "draw the {} byte sprite at I at (V{:X}, V{:X}), VF ← 1 if a pixel was erased",
+                n, x, y
+            ),
+            Opcode::SkipKey(x) => format!("skip the next instruction if the key in V{:X} is pressed", x),
+            Opcode::SkipNotKey(x) => format!("skip the next instruction if the key in V{:X} is not pressed", x),
+            Opcode::GetDelay(x) => format!("V{:X} ← delay timer", x),
+            Opcode::WaitKey(x) => format!("wait for a key press, V{:X} ← the key", x),
+            Opcode::SetDelay(x) => format!("delay timer ← V{:X}", x),
+            Opcode::SetSound(x) => format!("sound timer ← V{:X}", x),
+            Opcode::AddI(x) => format!("I ← I + V{:X}", x),
+            Opcode::SetSprite(x) => format!("I ← address of the font sprite for the digit in V{:X}", x),
+            Opcode::StoreBCD(x) => format!("memory[I..I+2] ← hundreds, tens and ones of V{:X}", x),
+            Opcode::StoreRegs(x) => format!("memory[I..I+{:X}] ← V0..V{:X}", x, x),
+            Opcode::LoadRegs(x) => format!("V0..V{:X} ← memory[I..I+{:X}]", x, x),
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Opcode::Clear => write!(f, "CLS"),
+            Opcode::Return => write!(f, "RET"),
+            Opcode::MachineCall(addr) => write!(f, "SYS {:#05X}", addr),
+            Opcode::Jump(addr) => write!(f, "JP {:#05X}", addr),
+            Opcode::Call(addr) => write!(f, "CALL {:#05X}", addr),
+            Opcode::SkipEqualVal(x, nn) => write!(f, "SE V{:X}, {:#04X}", x, nn),
+            Opcode::SkipNotEqualVal(x, nn) => write!(f, "SNE V{:X}, {:#04X}", x, nn),
+            Opcode::SkipEqual(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Opcode::SetVal(x, nn) => write!(f, "LD V{:X}, {:#04X}", x, nn),
+            Opcode::AddVal(x, nn) => write!(f, "ADD V{:X}, {:#04X}", x, nn),
+            Opcode::Set(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Opcode::Or(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Opcode::And(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Opcode::Xor(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Opcode::Add(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Opcode::SubY(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Opcode::ShiftRight(x, _) => write!(f, "SHR V{:X}", x),
+            Opcode::SubX(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Opcode::ShiftLeft(x, _) => write!(f, "SHL V{:X}", x),
+            Opcode::SkipNotEqual(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Opcode::SetI(addr) => write!(f, "LD I, {:#05X}", addr),
+            Opcode::JumpV0(addr) => write!(f, "JP V0, {:#05X}", addr),
+            Opcode::Random(x, nn) => write!(f, "RND V{:X}, {:#04X}", x, nn),
+            Opcode::Draw(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Opcode::SkipKey(x) => write!(f, "SKP V{:X}", x),
+            Opcode::SkipNotKey(x) => write!(f, "SKNP V{:X}", x),
+            Opcode::GetDelay(x) => write!(f, "LD V{:X}, DT", x),
+            Opcode::WaitKey(x) => write!(f, "LD V{:X}, K", x),
+            Opcode::SetDelay(x) => write!(f, "LD DT, V{:X}", x),
+            Opcode::SetSound(x) => write!(f, "LD ST, V{:X}", x),
+            Opcode::AddI(x) => write!(f, "ADD I, V{:X}", x),
+            Opcode::SetSprite(x) => write!(f, "LD F, V{:X}", x),
+            Opcode::StoreBCD(x) => write!(f, "LD B, V{:X}", x),
+            Opcode::StoreRegs(x) => write!(f, "LD [I], V{:X}", x),
+            Opcode::LoadRegs(x) => write!(f, "LD V{:X}, [I]", x),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::ops::RangeInclusive;
+
+use crate::quirks::Quirks;
+
+pub fn parse_u16(s: &str) -> Result<u16, String> {
+    let s = s.trim();
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|_| format!("invalid number: {}", s))
+}
+
+pub fn parse_u8(s: &str) -> Result<u8, String> {
+    let value = parse_u16(s)?;
+    u8::try_from(value).map_err(|_| format!("value does not fit in a byte: {}", s.trim()))
+}
+
+pub fn parse_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    let (start, end) = s
+        .split_once('-')
+        .ok_or(format!("invalid range (expected START-END): {}", s))?;
+    let start = parse_u16(start)?;
+    let end = parse_u16(end)?;
+    if start > end {
+        return Err(format!("invalid range (start is after end): {}", s));
+    }
+    Ok(start..=end)
+}
+
+// A comma separated list of quirk names, "vip" for the COSMAC VIP set or "none" to turn them all off.
+pub fn parse_quirks(s: &str) -> Result<Quirks, String> {
+    let mut quirks = Quirks::default();
+    for name in s.split(',').map(str::trim) {
+        match name {
+            "none" => quirks = Quirks::default(),
+            "vip" => quirks = Quirks::cosmac_vip(),
+            "vf-reset" => quirks.vf_reset = true,
+            "memory-increment" => quirks.memory_increment = true,
+            "shift-vy" => quirks.shift_vy = true,
+            "jump-vx" => quirks.jump_vx = true,
+            "wrap-sprites" => quirks.wrap_sprites = true,
+            "key-wait-on-press" => quirks.key_wait_on_press = true,
+            _ => return Err(format!("unknown quirk: {}", name)),
+        }
+    }
+    Ok(quirks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_u16() {
+        assert_eq!(parse_u16("0x2A0"), Ok(0x2A0));
+        assert_eq!(parse_u16("512"), Ok(512));
+        assert!(parse_u16("0xZZ").is_err());
+    }
+
+    #[test]
+    fn test_parse_u8() {
+        assert_eq!(parse_u8("0xAA"), Ok(0xAA));
+        assert!(parse_u8("0x100").is_err());
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("0x200-0x2FF"), Ok(0x200..=0x2FF));
+        assert!(parse_range("0x300-0x200").is_err());
+        assert!(parse_range("0x300").is_err());
+    }
+
+    #[test]
+    fn test_parse_quirks() {
+        assert_eq!(parse_quirks("none"), Ok(Quirks::default()));
+        let quirks = parse_quirks("vip, wrap-sprites").unwrap();
+        assert!(quirks.vf_reset && quirks.wrap_sprites && !quirks.jump_vx);
+        assert!(parse_quirks("key-wait-on-press").unwrap().key_wait_on_press);
+        assert!(parse_quirks("shift-vx").is_err());
+        assert_eq!(quirks.to_string(), "vf-reset,memory-increment,shift-vy,wrap-sprites");
+        assert_eq!(parse_quirks(&quirks.to_string()), Ok(quirks));
+        assert_eq!(Quirks::default().to_string(), "none");
+    }
+}
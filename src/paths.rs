@@ -0,0 +1,91 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+const APP_DIR: &str = "chip8-emulator-rs";
+
+// Where the emulator keeps its files: the config and breakpoints in the config directory, everything it
+// produces while running in the data directory. None when the home directory cannot be determined.
+//
+//            config directory                          data directory
+// Linux      $XDG_CONFIG_HOME or ~/.config             $XDG_DATA_HOME or ~/.local/share
+// macOS      ~/Library/Application Support             ~/Library/Application Support
+// Windows    %APPDATA%                                 %APPDATA%
+//
+// with a chip8-emulator-rs directory below each of them.
+pub fn config_dir() -> Option<PathBuf> {
+    base_dir(Kind::Config, |name| std::env::var_os(name)).map(|dir| dir.join(APP_DIR))
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    base_dir(Kind::Data, |name| std::env::var_os(name)).map(|dir| dir.join(APP_DIR))
+}
+
+pub fn config_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+// ROMs to recognize besides the ones in the bundled database
+pub fn database_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("roms.txt"))
+}
+
+pub fn breakpoint_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("breakpoints"))
+}
+
+// One file per ROM with its cheats
+pub fn cheat_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("cheats"))
+}
+
+// The last executed instructions when the emulator stopped with an error
+pub fn crash_log_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("crash.log"))
+}
+
+pub fn recent_roms_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("recent.txt"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Config,
+    Data,
+}
+
+// Takes the environment as a function so the lookup can be tested without changing the real one.
+fn base_dir(kind: Kind, env: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let var = |name: &str| env(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    if cfg!(windows) {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        match kind {
+            Kind::Config => var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config"))),
+            Kind::Data => var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local").join("share"))),
+        }
+    }
+}
+
+#[cfg(all(test, not(any(windows, target_os = "macos"))))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xdg_directories_fall_back_to_home() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.into())
+        };
+        let home = env(&[("HOME", "/home/ann"), ("XDG_CONFIG_HOME", "")]);
+        assert_eq!(base_dir(Kind::Config, home), Some(PathBuf::from("/home/ann/.config")));
+        assert_eq!(
+            base_dir(Kind::Data, home),
+            Some(PathBuf::from("/home/ann/.local/share"))
+        );
+
+        let xdg = env(&[("HOME", "/home/ann"), ("XDG_DATA_HOME", "/data")]);
+        assert_eq!(base_dir(Kind::Data, xdg), Some(PathBuf::from("/data")));
+        assert_eq!(base_dir(Kind::Config, env(&[])), None);
+    }
+}
@@ -0,0 +1,99 @@
+use std::io::{Stdout, Write};
+use std::ops::RangeInclusive;
+
+use crate::emulator::Chip8;
+
+// Extra hardware registered with Chip8::add_peripheral. A peripheral can map itself over a range of
+// addresses, which can also lie past the end of the 4 KB memory since I is 16 bits wide, and handle 0NNN
+// machine code calls. Every method has a default that leaves the machine alone.
+pub trait Peripheral {
+    // The addresses whose reads and writes go to this peripheral instead of memory.
+    fn memory_range(&self) -> Option<RangeInclusive<u16>> {
+        None
+    }
+
+    fn read(&mut self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn write(&mut self, _addr: u16, _value: u8) {}
+
+    // Called for 0NNN instructions, returns true when the peripheral handled the call. The other
+    // peripherals are not reachable from the Chip8 passed in while this runs.
+    fn machine_call(&mut self, _nnn: u16, _chip8: &mut Chip8) -> bool {
+        false
+    }
+}
+
+// The address the serial console listens on by default, the last byte of memory.
+pub const SERIAL_CONSOLE_ADDR: u16 = 0xFFF;
+
+// Prints every byte written to one address, so test ROMs can report in text: LD I, 0xFFF then
+// LD [I], V0 for each character. Reading the address returns 0.
+pub struct SerialConsole<W: Write = Stdout> {
+    addr: u16,
+    output: W,
+}
+
+impl SerialConsole {
+    pub fn new() -> Self {
+        SerialConsole::with_output(SERIAL_CONSOLE_ADDR, std::io::stdout())
+    }
+}
+
+impl Default for SerialConsole {
+    fn default() -> Self {
+        SerialConsole::new()
+    }
+}
+
+impl<W: Write> SerialConsole<W> {
+    pub fn with_output(addr: u16, output: W) -> Self {
+        SerialConsole { addr, output }
+    }
+}
+
+impl<W: Write> Peripheral for SerialConsole<W> {
+    fn memory_range(&self) -> Option<RangeInclusive<u16>> {
+        Some(self.addr..=self.addr)
+    }
+
+    fn write(&mut self, _addr: u16, value: u8) {
+        // the ROM has no way to handle an error, a closed stdout just loses the output
+        let _ = self.output.write_all(&[value]);
+        let _ = self.output.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serial_console_prints_written_bytes() {
+        // LD I, 0xFFF; LD V0, 'O'; LD [I], V0; LD V0, 'K'; LD [I], V0
+        let rom = [0xAF, 0xFF, 0x60, b'O', 0xF0, 0x55, 0x60, b'K', 0xF0, 0x55];
+        let mut chip8 = Chip8::builder().rom_bytes(&rom).build().unwrap();
+        let output = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        chip8.add_peripheral(SerialConsole::with_output(
+            SERIAL_CONSOLE_ADDR,
+            SharedOutput(output.clone()),
+        ));
+        for _ in 0..5 {
+            chip8.step().unwrap();
+        }
+        assert_eq!(*output.borrow(), b"OK");
+    }
+
+    struct SharedOutput(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
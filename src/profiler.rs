@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use crate::debugger;
+use crate::opcode::Opcode;
+use crate::symbols::Symbols;
+
+pub struct Profiler {
+    pc_counts: Vec<u64>,
+    opcode_counts: HashMap<&'static str, u64>,
+    total: u64,
+}
+
+impl Profiler {
+    pub fn new(memory_size: usize) -> Self {
+        Profiler {
+            pc_counts: vec![0; memory_size],
+            opcode_counts: HashMap::new(),
+            total: 0,
+        }
+    }
+
+    pub fn record(&mut self, pc: u16, opcode: &Opcode) {
+        if let Some(count) = self.pc_counts.get_mut(pc as usize) {
+            *count += 1;
+        }
+        *self.opcode_counts.entry(opcode.pattern()).or_insert(0) += 1;
+        self.total += 1;
+    }
+
+    pub fn pc_counts(&self) -> &[u64] {
+        &self.pc_counts
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    // Returns the executed addresses with their counts, hottest first.
+    pub fn hottest(&self) -> Vec<(usize, u64)> {
+        let mut hottest: Vec<(usize, u64)> = self
+            .pc_counts
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        hottest.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hottest
+    }
+
+    pub fn report(&self, top: usize, memory: &[u8], symbols: &Symbols) -> String {
+        let hottest = self.hottest();
+
+        let mut mix: Vec<(&str, u64)> = self.opcode_counts.iter().map(|(&name, &count)| (name, count)).collect();
+        mix.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+        let mut result = format!("Executed instructions: {}\n\nHottest addresses:", self.total);
+        for (pc, count) in hottest.iter().take(top) {
+            result.push_str(&format!(
+                "\n  {:#06X}  {:>10}  {:>6.2}%  {:<18}{}",
+                pc,
+                count,
+                self.percentage(*count),
+                debugger::disassemble(memory, *pc as u16, symbols),
+                symbols.describe(*pc as u16).unwrap_or_default()
+            ));
+        }
+
+        result.push_str("\n\nInstruction mix:");
+        for (name, count) in mix {
+            result.push_str(&format!(
+                "\n  {}  {:>10}  {:>6.2}%",
+                name,
+                count,
+                self.percentage(count)
+            ));
+        }
+        result
+    }
+
+    pub fn percentage(&self, count: u64) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            count as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(steps: &[u16], memory: &[u8]) -> Profiler {
+        let mut profiler = Profiler::new(memory.len());
+        for &pc in steps {
+            let instruction = u16::from_be_bytes([memory[pc as usize], memory[pc as usize + 1]]);
+            profiler.record(pc, &Opcode::decode(instruction).unwrap());
+        }
+        profiler
+    }
+
+    #[test]
+    fn test_hottest_orders_by_count_then_address() {
+        // LD V0, 1; ADD V0, 1; JP 0x002
+        let memory = [0x60, 0x01, 0x70, 0x01, 0x10, 0x02];
+        let profiler = profile(&[0, 2, 4, 2, 4, 2], &memory);
+
+        assert_eq!(profiler.hottest(), vec![(2, 3), (4, 2), (0, 1)]);
+        assert_eq!(profiler.total(), 6);
+        assert_eq!(profiler.percentage(3), 50.0);
+        assert_eq!(Profiler::new(4).percentage(0), 0.0);
+    }
+
+    #[test]
+    fn test_report_lists_the_hottest_addresses_and_the_mix() {
+        let memory = [0x60, 0x01, 0x70, 0x01, 0x10, 0x02];
+        let profiler = profile(&[0, 2, 4, 2, 4, 2], &memory);
+        let report = profiler.report(2, &memory, &Symbols::default());
+        let lines: Vec<&str> = report.lines().map(str::trim_end).collect();
+
+        assert_eq!(
+            lines,
+            [
+                "Executed instructions: 6",
+                "",
+                "Hottest addresses:",
+                "  0x0002           3   50.00%  ADD V0, 0x01",
+                "  0x0004           2   33.33%  JP 0x002",
+                "",
+                "Instruction mix:",
+                "  7XNN           3   50.00%",
+                "  1NNN           2   33.33%",
+                "  6XNN           1   16.67%",
+            ]
+        );
+    }
+}
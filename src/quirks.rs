@@ -0,0 +1,54 @@
+// Behaviors that differ between CHIP-8 interpreters. The defaults match what this emulator always did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct Quirks {
+    // 8XY1, 8XY2 and 8XY3 reset VF to 0 (original COSMAC VIP).
+    pub vf_reset: bool,
+    // FX55 and FX65 leave I pointing behind the last register instead of keeping it.
+    pub memory_increment: bool,
+    // 8XY6 and 8XYE shift VY and store the result in VX instead of shifting VX in place.
+    pub shift_vy: bool,
+    // BNNN jumps to XNN + VX (CHIP-48, SUPER-CHIP) instead of NNN + V0.
+    pub jump_vx: bool,
+    // Sprites crossing the edge of the screen wrap around instead of being clipped.
+    pub wrap_sprites: bool,
+    // FX0A finishes as soon as a key goes down. Without it FX0A waits for the key to be released again like
+    // the COSMAC VIP did, which keeps a held key from counting twice.
+    pub key_wait_on_press: bool,
+}
+
+impl Quirks {
+    // The behavior of the original COSMAC VIP interpreter.
+    pub fn cosmac_vip() -> Self {
+        Quirks {
+            vf_reset: true,
+            memory_increment: true,
+            shift_vy: true,
+            jump_vx: false,
+            wrap_sprites: false,
+            key_wait_on_press: false,
+        }
+    }
+}
+
+// The names --quirks takes, "none" when all are off.
+impl core::fmt::Display for Quirks {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let names = [
+            (self.vf_reset, "vf-reset"),
+            (self.memory_increment, "memory-increment"),
+            (self.shift_vy, "shift-vy"),
+            (self.jump_vx, "jump-vx"),
+            (self.wrap_sprites, "wrap-sprites"),
+            (self.key_wait_on_press, "key-wait-on-press"),
+        ];
+        let mut enabled = names.iter().filter(|&&(on, _)| on).map(|&(_, name)| name);
+        match enabled.next() {
+            Some(first) => {
+                write!(f, "{}", first)?;
+                enabled.try_for_each(|name| write!(f, ",{}", name))
+            }
+            None => write!(f, "none"),
+        }
+    }
+}
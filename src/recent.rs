@@ -0,0 +1,99 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::Chip8Error;
+use crate::paths;
+
+const MAX_RECENT: usize = 10;
+
+// The ROMs played last, newest first, kept one path per line in the data directory.
+#[derive(Debug, Clone, Default)]
+pub struct RecentRoms {
+    file: Option<PathBuf>,
+    roms: Vec<String>,
+}
+
+impl RecentRoms {
+    // Empty when nothing was played yet or the data directory is unknown.
+    pub fn load() -> Self {
+        match paths::recent_roms_file() {
+            Some(file) => Self::load_from(file),
+            None => Self::default(),
+        }
+    }
+
+    pub fn load_from(file: PathBuf) -> Self {
+        let roms = fs::read_to_string(&file)
+            .map(|content| {
+                content
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        RecentRoms { file: Some(file), roms }
+    }
+
+    pub fn roms(&self) -> &[String] {
+        &self.roms
+    }
+
+    pub fn last(&self) -> Option<&str> {
+        self.roms.first().map(String::as_str)
+    }
+
+    // Moves rom to the top of the list, by its absolute path so it can be found from any directory.
+    pub fn add(&mut self, rom: &str) {
+        let rom = fs::canonicalize(rom).map_or_else(|_| rom.to_string(), |path| path.to_string_lossy().into_owned());
+        self.roms.retain(|known| *known != rom);
+        self.roms.insert(0, rom);
+        self.roms.truncate(MAX_RECENT);
+    }
+
+    pub fn save(&self) -> Result<(), Chip8Error> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(file, self.roms.join("\n"))?;
+        Ok(())
+    }
+
+    // Adds rom and saves the list, only warning when that fails since the ROM runs either way.
+    pub fn remember(rom: &str) {
+        let mut recent = Self::load();
+        recent.add(rom);
+        if let Err(err) = recent.save() {
+            eprintln!("Could not save the recent ROMs: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_roms_are_newest_first() {
+        let file = std::env::temp_dir().join("chip8-recent-test.txt");
+        let _ = fs::remove_file(&file);
+        let mut recent = RecentRoms::load_from(file.clone());
+        assert_eq!(recent.last(), None);
+
+        for rom in 0..MAX_RECENT + 2 {
+            recent.add(&format!("/roms/{}.ch8", rom));
+        }
+        recent.add("/roms/5.ch8");
+        recent.save().unwrap();
+
+        let recent = RecentRoms::load_from(file);
+        assert_eq!(recent.roms().len(), MAX_RECENT);
+        assert_eq!(recent.last(), Some("/roms/5.ch8"));
+        assert_eq!(recent.roms()[1], "/roms/11.ch8");
+        assert_eq!(recent.roms().iter().filter(|rom| *rom == "/roms/5.ch8").count(), 1);
+        assert!(!recent.roms().contains(&"/roms/0.ch8".to_string()));
+    }
+}
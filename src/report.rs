@@ -0,0 +1,187 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::coverage::Coverage;
+use crate::profiler::Profiler;
+use crate::symbols::Symbols;
+
+const HOT_SPOTS: usize = 32;
+const PIXEL_SIZE: usize = 10;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; background: #1e1e1e; color: #ddd; margin: 2em; }
+h1, h2 { font-weight: normal; }
+table { border-collapse: collapse; }
+td, th { padding: 0.1em 0.8em; text-align: left; font-family: monospace; }
+th { border-bottom: 1px solid #555; }
+a { color: #8cf; }
+pre { line-height: 1.3; }
+.hit { background: #1f3a24; }
+.miss { background: #4a1f1f; }
+.label { color: #fc6; }
+.count { color: #999; }
+svg { border: 1px solid #555; }";
+
+pub struct Report<'a> {
+    pub title: &'a str,
+    pub memory: &'a [u8],
+    pub rom: Range<usize>,
+    pub coverage: &'a Coverage,
+    pub profiler: &'a Profiler,
+    pub display: &'a [u8],
+    pub display_width: usize,
+    pub symbols: &'a Symbols,
+}
+
+impl Report<'_> {
+    // Renders a self-contained HTML page, everything including the screenshot is inlined.
+    pub fn html(&self) -> String {
+        let mut html = String::new();
+        let total = self.rom.len();
+        let covered = self.coverage.executed_bytes(self.rom.clone());
+
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n",
+            escape(self.title),
+            STYLE
+        );
+        let _ = writeln!(html, "<h1>{}</h1>", escape(self.title));
+        let _ = writeln!(
+            html,
+            "<p>Executed instructions: {}<br>ROM bytes executed: {} / {}</p>",
+            self.profiler.total(),
+            covered,
+            total
+        );
+
+        html.push_str("<h2>Final display</h2>\n");
+        html.push_str(&self.screenshot());
+        html.push_str("<h2>Hot spots</h2>\n");
+        html.push_str(&self.hot_spots());
+        html.push_str("<h2>Disassembly</h2>\n");
+        html.push_str(&self.disassembly());
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn screenshot(&self) -> String {
+        let width = self.display_width;
+        let height = self.display.len() / width;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">\n<rect width=\"{}\" height=\"{}\" fill=\"#000\"/>\n",
+            width * PIXEL_SIZE,
+            height * PIXEL_SIZE,
+            width,
+            height,
+            width,
+            height
+        );
+        for (index, &pixel) in self.display.iter().enumerate() {
+            if pixel != 0 {
+                let _ = writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"#fff\"/>",
+                    index % width,
+                    index / width
+                );
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn hot_spots(&self) -> String {
+        let mut table =
+            String::from("<table>\n<tr><th>Address</th><th>Count</th><th>Share</th><th>Location</th></tr>\n");
+        for (pc, count) in self.profiler.hottest().into_iter().take(HOT_SPOTS) {
+            let _ = writeln!(
+                table,
+                "<tr><td><a href=\"#a{:04X}\">{:#06X}</a></td><td>{}</td><td>{:.2}%</td><td>{}</td></tr>",
+                pc,
+                pc,
+                count,
+                self.profiler.percentage(count),
+                escape(&self.symbols.describe(pc as u16).unwrap_or_default())
+            );
+        }
+        table.push_str("</table>\n");
+        table
+    }
+
+    fn disassembly(&self) -> String {
+        let counts = self.profiler.pc_counts();
+        let mut pre = String::from("<pre>\n");
+        for line in self.coverage.lines(self.memory, self.rom.clone(), self.symbols) {
+            if let Some(label) = self.symbols.label(line.addr as u16) {
+                let _ = writeln!(pre, "<span class=\"label\">{}:</span>", escape(label));
+            }
+            let count = counts.get(line.addr).copied().unwrap_or(0);
+            let _ = writeln!(
+                pre,
+                "<span id=\"a{:04X}\" class=\"{}\">{:#06X}  {:<4}  {}</span><span class=\"count\">{}</span>",
+                line.addr,
+                if line.executed { "hit" } else { "miss" },
+                line.addr,
+                line.bytes,
+                escape(&format!("{:<24}", line.text)),
+                if count > 0 { count.to_string() } else { String::new() }
+            );
+        }
+        pre.push_str("</pre>\n");
+        pre
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcode::Opcode;
+
+    #[test]
+    fn test_html_escapes_and_marks_coverage() {
+        // LD V0, 1; JP 0x202; CLS, never reached
+        let rom = [0x60, 0x01, 0x12, 0x02, 0x00, 0xE0];
+        let mut memory = vec![0; 0x210];
+        memory[0x200..0x206].copy_from_slice(&rom);
+        let mut coverage = Coverage::new(memory.len());
+        let mut profiler = Profiler::new(memory.len());
+        for pc in [0x200, 0x202, 0x202] {
+            coverage.record(pc);
+            let instruction = u16::from_be_bytes([memory[pc as usize], memory[pc as usize + 1]]);
+            profiler.record(pc, &Opcode::decode(instruction).unwrap());
+        }
+        let symbols = Symbols::parse("0x202 wait<&loop").unwrap();
+        // a 4x2 display with three pixels lit
+        let display = [1, 0, 0, 1, 0, 1, 0, 0];
+        let report = Report {
+            title: "Tom & Jerry <demo>",
+            memory: &memory,
+            rom: 0x200..0x206,
+            coverage: &coverage,
+            profiler: &profiler,
+            display: &display,
+            display_width: 4,
+            symbols: &symbols,
+        };
+        let html = report.html();
+
+        assert!(html.contains("<title>Tom &amp; Jerry &lt;demo&gt;</title>"));
+        assert!(html.contains("<span class=\"label\">wait&lt;&amp;loop:</span>"));
+        assert!(!html.contains("wait<&loop"));
+        assert!(html.contains("ROM bytes executed: 4 / 6"));
+        assert!(html.contains("<span id=\"a0200\" class=\"hit\">"));
+        assert!(html.contains("<span id=\"a0202\" class=\"hit\">"));
+        assert!(html.contains("<span id=\"a0204\" class=\"miss\">"));
+        assert!(html.contains("viewBox=\"0 0 4 2\""));
+        assert_eq!(html.matches("width=\"1\" height=\"1\" fill=\"#fff\"").count(), 3);
+        assert!(html.contains("<td>66.67%</td><td>wait&lt;&amp;loop</td>"));
+    }
+}
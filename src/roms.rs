@@ -0,0 +1,241 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::builtin;
+use crate::error::Chip8Error;
+use crate::ips::Patch;
+#[cfg(feature = "archives")]
+use crate::machine::MEMORY_SIZE;
+
+// What CHIP-8 ROMs are usually named, for file dialogs and directory listings.
+pub const EXTENSIONS: [&str; 3] = ["ch8", "c8", "rom"];
+
+// A location starting with this names one of the ROMs that come with the emulator, like builtin:catch.
+pub const BUILTIN_PREFIX: &str = "builtin:";
+
+// The ROM at location, a built-in one, a file or with the http feature an http:// or https:// URL. With the archives
+// feature it can also be a .gz file or a .zip archive, of which the first ROM is taken unless a member is
+// named after a #, like games.zip#pong.ch8.
+pub fn read(location: &str) -> Result<Vec<u8>, Chip8Error> {
+    if let Some(name) = location.strip_prefix(BUILTIN_PREFIX) {
+        let builtin = builtin::find(name)
+            .ok_or_else(|| Chip8Error::IoError(std::io::Error::other(format!("no built-in ROM named {}", name))))?;
+        return Ok(builtin.data.to_vec());
+    }
+    let (source, member) = match location.rsplit_once('#') {
+        Some((archive, member)) if has_extension(archive, "zip") => (archive, Some(member)),
+        _ => (location, None),
+    };
+    let data = if is_url(source) {
+        download(source)?
+    } else {
+        fs::read(source)?
+    };
+    if has_extension(source, "zip") {
+        unzip(&data, member)
+    } else if has_extension(source, "gz") {
+        gunzip(&data)
+    } else {
+        Ok(data)
+    }
+}
+
+// The ROM data with the IPS patch given, or else the one next to the ROM file at location named like it with .ips
+// for its extension, like pong.ips for pong.ch8. Also returns the patch that was applied, if any.
+pub fn patch(
+    location: Option<&str>,
+    patch: Option<&str>,
+    data: &[u8],
+) -> Result<(Vec<u8>, Option<PathBuf>), Chip8Error> {
+    let path = match (patch, location) {
+        (Some(patch), _) => PathBuf::from(patch),
+        (None, Some(location)) if !location.starts_with(BUILTIN_PREFIX) && !is_url(location) => {
+            let path = Path::new(location).with_extension("ips");
+            if !path.is_file() {
+                return Ok((data.to_vec(), None));
+            }
+            path
+        }
+        _ => return Ok((data.to_vec(), None)),
+    };
+    let patched = Patch::load(&path.to_string_lossy())?.apply(data);
+    Ok((patched, Some(path)))
+}
+
+pub fn is_url(location: &str) -> bool {
+    ["http://", "https://"].iter().any(|scheme| {
+        location
+            .get(..scheme.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(scheme))
+    })
+}
+
+#[cfg(feature = "http")]
+fn download(url: &str) -> Result<Vec<u8>, Chip8Error> {
+    let mut response = ureq::get(url).call().map_err(std::io::Error::other)?;
+    let data = response.body_mut().read_to_vec().map_err(std::io::Error::other)?;
+    Ok(data)
+}
+
+#[cfg(not(feature = "http"))]
+fn download(url: &str) -> Result<Vec<u8>, Chip8Error> {
+    Err(Chip8Error::IoError(std::io::Error::other(format!(
+        "cannot download {}, build with --features http",
+        url
+    ))))
+}
+
+fn has_extension(location: &str, extension: &str) -> bool {
+    location
+        .rsplit_once('.')
+        .is_some_and(|(_, known)| known.eq_ignore_ascii_case(extension))
+}
+
+#[cfg(feature = "archives")]
+fn unzip(data: &[u8], member: Option<&str>) -> Result<Vec<u8>, Chip8Error> {
+    let invalid = |err: zip::result::ZipError| Chip8Error::InvalidArchive(err.to_string());
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data)).map_err(invalid)?;
+    let name = match member {
+        Some(member) => member.to_string(),
+        None => archive
+            .file_names()
+            .filter(|name| is_rom(Path::new(name)))
+            .min_by_key(|name| archive.index_for_name(name))
+            .ok_or_else(|| Chip8Error::InvalidArchive("no ROM in the archive".to_string()))?
+            .to_string(),
+    };
+    let file = archive.by_name(&name).map_err(invalid)?;
+    read_limited(file)
+}
+
+#[cfg(feature = "archives")]
+fn gunzip(data: &[u8]) -> Result<Vec<u8>, Chip8Error> {
+    read_limited(flate2::read::GzDecoder::new(data))
+}
+
+// A ROM never fills more than the memory, anything longer is cut off there and then rejected as too large
+// without unpacking all of it.
+#[cfg(feature = "archives")]
+fn read_limited(reader: impl std::io::Read) -> Result<Vec<u8>, Chip8Error> {
+    use std::io::Read;
+
+    let mut data = Vec::new();
+    reader.take(MEMORY_SIZE as u64).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(not(feature = "archives"))]
+fn unzip(_data: &[u8], _member: Option<&str>) -> Result<Vec<u8>, Chip8Error> {
+    Err(Chip8Error::InvalidArchive(
+        "build with --features archives to open .zip files".to_string(),
+    ))
+}
+
+#[cfg(not(feature = "archives"))]
+fn gunzip(_data: &[u8]) -> Result<Vec<u8>, Chip8Error> {
+    Err(Chip8Error::InvalidArchive(
+        "build with --features archives to open .gz files".to_string(),
+    ))
+}
+
+// The ROMs in dir, sorted by name without regard to case. Subdirectories are not searched.
+pub fn list(dir: &Path) -> Result<Vec<PathBuf>, Chip8Error> {
+    let mut roms: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && is_rom(path))
+        .collect();
+    roms.sort_by_key(|path| path.file_name().unwrap_or_default().to_ascii_lowercase());
+    Ok(roms)
+}
+
+fn is_rom(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.iter().any(|known| known.eq_ignore_ascii_case(extension)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls_are_told_apart_from_files() {
+        assert!(is_url("https://example.com/pong.ch8"));
+        assert!(is_url("HTTP://example.com/pong.ch8"));
+        assert!(!is_url("roms/http.ch8"));
+        assert!(!is_url("ftp://example.com/pong.ch8"));
+    }
+
+    #[cfg(feature = "archives")]
+    #[test]
+    fn test_read_unpacks_archives() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join("chip8-archive-test");
+        fs::create_dir_all(&dir).unwrap();
+        let zip_path = dir.join("games.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        for (name, data) in [("readme.txt", [0x00]), ("pong.ch8", [0x01]), ("tetris.c8", [0x02])] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(&data).unwrap();
+        }
+        zip.finish().unwrap();
+        let zip_path = zip_path.to_str().unwrap();
+        assert_eq!(read(zip_path).unwrap(), [0x01]);
+        assert_eq!(read(&format!("{}#tetris.c8", zip_path)).unwrap(), [0x02]);
+        assert!(matches!(
+            read(&format!("{}#missing.ch8", zip_path)),
+            Err(Chip8Error::InvalidArchive(_))
+        ));
+
+        let gz_path = dir.join("pong.ch8.gz");
+        let mut gz = flate2::write::GzEncoder::new(fs::File::create(&gz_path).unwrap(), Default::default());
+        gz.write_all(&[0x12, 0x00]).unwrap();
+        gz.finish().unwrap();
+        assert_eq!(read(gz_path.to_str().unwrap()).unwrap(), [0x12, 0x00]);
+    }
+
+    #[test]
+    fn test_read_finds_builtins() {
+        assert_eq!(read("builtin:splash").unwrap(), builtin::BUILTINS[0].data);
+        assert!(read("builtin:missing").is_err());
+    }
+
+    #[test]
+    fn test_patch_finds_the_patch_next_to_the_rom() {
+        let dir = std::env::temp_dir().join("chip8-patch-test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("pong.ips"), b"PATCH\x00\x00\x01\x00\x01\xE0EOF").unwrap();
+        fs::write(dir.join("other.ips"), b"PATCH\x00\x00\x00\x00\x01\x13EOF").unwrap();
+        let rom = dir.join("pong.ch8");
+        let rom = rom.to_str().unwrap();
+        let other = dir.join("other.ips");
+
+        assert_eq!(
+            patch(Some(rom), None, &[0x00, 0x00]).unwrap(),
+            (vec![0x00, 0xE0], Some(dir.join("pong.ips")))
+        );
+        assert_eq!(
+            patch(Some(rom), other.to_str(), &[0x12, 0x00]).unwrap(),
+            (vec![0x13, 0x00], Some(other.clone()))
+        );
+        assert_eq!(patch(Some("builtin:pong"), None, &[0x12]).unwrap(), (vec![0x12], None));
+        assert_eq!(patch(None, None, &[0x12]).unwrap(), (vec![0x12], None));
+    }
+
+    #[test]
+    fn test_list_finds_roms_by_extension() {
+        let dir = std::env::temp_dir().join("chip8-roms-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested.ch8")).unwrap();
+        for name in ["pong.ch8", "Breakout.C8", "readme.txt", "tetris.rom"] {
+            fs::write(dir.join(name), [0x12, 0x00]).unwrap();
+        }
+        let names: Vec<_> = list(&dir)
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["Breakout.C8", "pong.ch8", "tetris.rom"]);
+    }
+}
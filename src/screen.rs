@@ -0,0 +1,129 @@
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+use crate::error::Chip8Error;
+
+// Window pixels per CHIP-8 pixel in the images written by save_png
+const SCALE: usize = 8;
+
+fn invalid(err: impl std::fmt::Display) -> Chip8Error {
+    Chip8Error::InvalidImage(err.to_string())
+}
+
+// Writes the display as a white on black PNG.
+pub fn save_png(path: &str, pixels: &[u8], width: usize) -> Result<(), Chip8Error> {
+    let height = pixels.len() / width;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        (width * SCALE) as u32,
+        (height * SCALE) as u32,
+    );
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut data = Vec::with_capacity(pixels.len() * SCALE * SCALE);
+    for row in pixels.chunks(width) {
+        for _ in 0..SCALE {
+            for &pixel in row {
+                data.extend([if pixel != 0 { 0xFF } else { 0x00 }; SCALE]);
+            }
+        }
+    }
+    encoder
+        .write_header()
+        .map_err(invalid)?
+        .write_image_data(&data)
+        .map_err(invalid)?;
+    Ok(())
+}
+
+// Reads a picture of a display of width by height pixels, at any whole scale like the screenshots of a window.
+// A pixel counts as lit when its brightness is at least half, so the picture has to be light on dark.
+pub fn load_png(path: &str, width: usize, height: usize) -> Result<Vec<u8>, Chip8Error> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let mut data = vec![0; reader.output_buffer_size().ok_or_else(|| invalid("image too large"))?];
+    let info = reader.next_frame(&mut data).map_err(invalid)?;
+
+    let (image_width, image_height) = (info.width as usize, info.height as usize);
+    let scale = image_width / width;
+    if scale == 0 || image_width != width * scale || image_height != height * scale {
+        return Err(invalid(format!(
+            "{}x{} is not a {}x{} display at a whole scale",
+            image_width, image_height, width, height
+        )));
+    }
+    let samples = info.color_type.samples();
+    let brightness = |x: usize, y: usize| {
+        let start = y * info.line_size + x * samples;
+        let pixel = &data[start..start + samples];
+        match info.color_type {
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => pixel[0] as u32,
+            _ => (pixel[0] as u32 + pixel[1] as u32 + pixel[2] as u32) / 3,
+        }
+    };
+    // the middle of every scaled pixel, clear of blurred edges
+    Ok((0..width * height)
+        .map(|index| {
+            let (x, y) = (index % width * scale + scale / 2, index / width * scale + scale / 2);
+            (brightness(x, y) >= 0x80) as u8
+        })
+        .collect())
+}
+
+// None when both displays are the same, else how many pixels differ and the rows they are in with '+' for a
+// pixel that is lit but should be dark and '-' for one that is dark but should be lit.
+pub fn compare(expected: &[u8], actual: &[u8], width: usize) -> Option<String> {
+    let differing = expected
+        .iter()
+        .zip(actual)
+        .filter(|(expected, actual)| expected != actual)
+        .count();
+    if differing == 0 {
+        return None;
+    }
+    let mut report = format!("{} pixels differ from the expected screen:\n", differing);
+    for (y, (expected, actual)) in expected.chunks(width).zip(actual.chunks(width)).enumerate() {
+        if expected == actual {
+            continue;
+        }
+        let row: String = expected
+            .iter()
+            .zip(actual)
+            .map(|pair| match pair {
+                (0, 0) => '.',
+                (0, _) => '+',
+                (_, 0) => '-',
+                _ => '#',
+            })
+            .collect();
+        let _ = writeln!(report, "{:>2}  {}", y, row);
+    }
+    Some(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_screens_load_and_compare() {
+        let path = std::env::temp_dir().join("chip8-screen-test.png");
+        let path = path.to_str().unwrap();
+        let mut pixels = vec![0; 8 * 4];
+        pixels[9] = 1;
+        save_png(path, &pixels, 8).unwrap();
+        assert_eq!(load_png(path, 8, 4).unwrap(), pixels);
+        assert!(load_png(path, 16, 16).is_err());
+
+        let mut actual = pixels.clone();
+        assert_eq!(compare(&pixels, &actual, 8), None);
+        actual[9] = 0;
+        actual[30] = 1;
+        assert_eq!(
+            compare(&pixels, &actual, 8).unwrap(),
+            "2 pixels differ from the expected screen:\n 1  .-......\n 3  ......+.\n"
+        );
+    }
+}
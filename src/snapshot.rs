@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+use crate::quirks::Quirks;
+
+// The complete machine state at one point in time, for save states, rewinding and tests. Memory and display
+// are vectors because serde does not handle arrays of that size.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chip8Snapshot {
+    pub memory: Vec<u8>,
+    pub registers: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub display: Vec<u8>,
+    pub quirks: Quirks,
+}
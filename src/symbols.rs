@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::error::Chip8Error;
+use crate::parse::parse_u16;
+
+#[derive(Debug, Default)]
+pub struct Symbols {
+    labels: BTreeMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn load(path: &str) -> Result<Self, Chip8Error> {
+        Self::parse(&fs::read_to_string(path)?).map_err(Chip8Error::InvalidSymbols)
+    }
+
+    // Every line holds a label and its address in either order, e.g. "main 0x200" or "0x200 main".
+    // Everything after a '#' is a comment.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut labels = BTreeMap::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line
+                .split(|c: char| c.is_whitespace() || c == '=')
+                .filter(|f| !f.is_empty())
+                .collect();
+            let (label, addr) = match fields[..] {
+                [first, second] => match (parse_u16(first), parse_u16(second)) {
+                    (Ok(addr), Err(_)) => (second, addr),
+                    (Err(_), Ok(addr)) => (first, addr),
+                    _ => return Err(format!("line {}: expected a label and an address", number + 1)),
+                },
+                _ => return Err(format!("line {}: expected a label and an address", number + 1)),
+            };
+            labels.insert(addr, label.to_string());
+        }
+        Ok(Symbols { labels })
+    }
+
+    // Keeps a label already given to addr.
+    pub fn insert(&mut self, addr: u16, label: String) {
+        self.labels.entry(addr).or_insert(label);
+    }
+
+    pub fn label(&self, addr: u16) -> Option<&str> {
+        self.labels.get(&addr).map(String::as_str)
+    }
+
+    // Describes an address relative to the closest label at or before it, e.g. "draw_player+0x4".
+    pub fn describe(&self, addr: u16) -> Option<String> {
+        let (&start, label) = self.labels.range(..=addr).next_back()?;
+        if start == addr {
+            Some(label.clone())
+        } else {
+            Some(format!("{}+{:#X}", label, addr - start))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_describe() {
+        let symbols = Symbols::parse("# labels\nmain 0x200\n0x230 draw_player\nscore = 0x3F0\n").unwrap();
+
+        assert_eq!(symbols.label(0x200), Some("main"));
+        assert_eq!(symbols.label(0x3F0), Some("score"));
+        assert_eq!(symbols.describe(0x234), Some("draw_player+0x4".to_string()));
+        assert_eq!(symbols.describe(0x100), None);
+        assert!(Symbols::parse("main").is_err());
+    }
+}
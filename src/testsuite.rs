@@ -0,0 +1,157 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::builtin;
+use crate::detect::{self, Outcome};
+use crate::emulator::display_text;
+use crate::error::Chip8Error;
+use crate::parse::parse_quirks;
+use crate::roms;
+
+// The quirks every test ROM runs with, as named for --quirks
+pub const CONFIGURATIONS: [&str; 2] = ["none", "vip"];
+const GOLDEN_EXTENSION: &str = "golden";
+// The screen the built-in self-test shows when every instruction works
+const SELF_TEST_GOLDEN: &str = include_str!("../roms/test.golden");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail,
+    Crash,
+    // there is no pass screen to compare with
+    Unknown,
+}
+
+impl Verdict {
+    fn name(self) -> &'static str {
+        match self {
+            Verdict::Pass => "pass",
+            Verdict::Fail => "FAIL",
+            Verdict::Crash => "CRASH",
+            Verdict::Unknown => "-",
+        }
+    }
+}
+
+struct Case {
+    name: String,
+    rom: Vec<u8>,
+    // where the pass screens are kept, None for the built-in ones
+    path: Option<PathBuf>,
+}
+
+impl Case {
+    // The pass screen for one configuration, like 3-corax+.vip.golden, or else the one for all of them, like
+    // 3-corax+.golden.
+    fn golden(&self, configuration: &str) -> Option<String> {
+        let Some(path) = &self.path else {
+            return Some(SELF_TEST_GOLDEN.to_string());
+        };
+        fs::read_to_string(golden_file(path, Some(configuration)))
+            .or_else(|_| fs::read_to_string(golden_file(path, None)))
+            .ok()
+    }
+}
+
+fn golden_file(rom: &Path, configuration: Option<&str>) -> PathBuf {
+    let extension = match configuration {
+        Some(configuration) => format!("{}.{}", configuration, GOLDEN_EXTENSION),
+        None => GOLDEN_EXTENSION.to_string(),
+    };
+    rom.with_extension(extension)
+}
+
+// Runs the built-in self-test, or every ROM in dir, under each configuration and compares the final screen with
+// its pass screen. With record the screens are saved as the pass screens instead. Tells whether nothing failed.
+pub fn run(dir: Option<&str>, frames: u64, record: bool) -> Result<bool, Chip8Error> {
+    let cases = match dir {
+        None => vec![Case {
+            name: "builtin:test".to_string(),
+            rom: builtin::find("test").map(|test| test.data.to_vec()).unwrap_or_default(),
+            path: None,
+        }],
+        Some(dir) => roms::list(Path::new(dir))?
+            .into_iter()
+            .map(|path| {
+                Ok(Case {
+                    name: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                    rom: fs::read(&path)?,
+                    path: Some(path),
+                })
+            })
+            .collect::<Result<_, Chip8Error>>()?,
+    };
+    if cases.is_empty() {
+        println!("No ROMs in {}", dir.unwrap_or_default());
+        return Ok(true);
+    }
+
+    let width = cases.iter().map(|case| case.name.len()).max().unwrap_or(0);
+    // wide enough for every verdict
+    let cell = |text: &str, configuration: &str| format!("{:<w$}", text, w = configuration.len().max(5));
+    let header: Vec<String> = CONFIGURATIONS.iter().map(|name| cell(name, name)).collect();
+    println!("{:<width$}  {}", "", header.join("  ").trim_end());
+    let mut failed = 0;
+    let mut passed = 0;
+    for case in &cases {
+        let mut cells = Vec::new();
+        for configuration in CONFIGURATIONS {
+            let quirks = parse_quirks(configuration).map_err(Chip8Error::InvalidConfig)?;
+            let trial = detect::trial(&case.rom, quirks, frames)?;
+            let screen = display_text(&trial.pixels);
+            let verdict = match (&trial.outcome, &case.path) {
+                (Outcome::Crashed { .. }, _) => Verdict::Crash,
+                (_, Some(path)) if record => {
+                    fs::write(golden_file(path, Some(configuration)), screen + "\n")?;
+                    Verdict::Unknown
+                }
+                _ => match case.golden(configuration) {
+                    Some(golden) if golden.trim_end() == screen => Verdict::Pass,
+                    Some(_) => Verdict::Fail,
+                    None => Verdict::Unknown,
+                },
+            };
+            match verdict {
+                Verdict::Pass => passed += 1,
+                Verdict::Fail | Verdict::Crash => failed += 1,
+                Verdict::Unknown => {}
+            }
+            cells.push(cell(verdict.name(), configuration));
+        }
+        println!("{:<width$}  {}", case.name, cells.join("  ").trim_end());
+    }
+
+    if record {
+        println!("Recorded the screens as the pass screens, check them before trusting them");
+    } else {
+        println!("{} passed, {} failed", passed, failed);
+    }
+    Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes_in_every_configuration() {
+        assert!(run(None, detect::DEFAULT_FRAMES, false).unwrap());
+    }
+
+    #[test]
+    fn test_recorded_screens_pass() {
+        let dir = std::env::temp_dir().join("chip8-test-suite-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        // draws the font 0 and halts
+        fs::write(dir.join("zero.ch8"), [0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04]).unwrap();
+        let dir = dir.to_str().unwrap();
+
+        assert!(run(Some(dir), 10, true).unwrap());
+        assert!(run(Some(dir), 10, false).unwrap());
+        let golden = Path::new(dir).join("zero.vip.golden");
+        fs::write(&golden, fs::read_to_string(&golden).unwrap().replace('#', ".")).unwrap();
+        assert!(!run(Some(dir), 10, false).unwrap());
+    }
+}
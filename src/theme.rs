@@ -0,0 +1,167 @@
+use crate::config::{Palette, Rgb};
+
+// A named set of screen colors for --theme and the theme hotkey. CHIP-8 draws with the first two, the other two
+// are the colors XO-CHIP games get for their second plane and for both planes, which this emulator does not
+// draw yet.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub description: &'static str,
+    // unlit, plane 1, plane 2, both planes
+    pub colors: [Rgb; 4],
+}
+
+// The hotkey goes through them in this order. The first one is the default palette.
+pub const THEMES: [Theme; 8] = [
+    Theme {
+        name: "vip",
+        description: "White on black, like the COSMAC VIP on a TV",
+        colors: [
+            Rgb(0x00, 0x00, 0x00),
+            Rgb(0xFF, 0xFF, 0xFF),
+            Rgb(0xAA, 0xAA, 0xAA),
+            Rgb(0x55, 0x55, 0x55),
+        ],
+    },
+    Theme {
+        name: "gameboy",
+        description: "The four greens of the original Game Boy screen",
+        colors: [
+            Rgb(0x0F, 0x38, 0x0F),
+            Rgb(0x9B, 0xBC, 0x0F),
+            Rgb(0x30, 0x62, 0x30),
+            Rgb(0x8B, 0xAC, 0x0F),
+        ],
+    },
+    Theme {
+        name: "amber",
+        description: "An amber monochrome monitor",
+        colors: [
+            Rgb(0x14, 0x0C, 0x00),
+            Rgb(0xFF, 0xB0, 0x00),
+            Rgb(0x8C, 0x5A, 0x00),
+            Rgb(0xFF, 0xD9, 0x8A),
+        ],
+    },
+    Theme {
+        name: "c64",
+        description: "Light blue on blue as the Commodore 64 starts up",
+        colors: [
+            Rgb(0x35, 0x28, 0x79),
+            Rgb(0x6C, 0x5E, 0xB5),
+            Rgb(0x9A, 0xD2, 0x84),
+            Rgb(0xB8, 0xC7, 0x6F),
+        ],
+    },
+    Theme {
+        name: "high-contrast",
+        description: "Yellow on black, the plane colors as far apart as they go",
+        colors: [
+            Rgb(0x00, 0x00, 0x00),
+            Rgb(0xFF, 0xFF, 0x00),
+            Rgb(0x00, 0xFF, 0xFF),
+            Rgb(0xFF, 0xFF, 0xFF),
+        ],
+    },
+    // The colors for color blindness keep apart in both hue and brightness, after the Okabe-Ito palette, so
+    // the planes still differ for the colors that are confused.
+    Theme {
+        name: "deuteranopia",
+        description: "Sky blue, orange and white on black, without the greens and reds that look alike",
+        colors: [
+            Rgb(0x00, 0x00, 0x00),
+            Rgb(0x56, 0xB4, 0xE9),
+            Rgb(0xE6, 0x9F, 0x00),
+            Rgb(0xFF, 0xFF, 0xFF),
+        ],
+    },
+    Theme {
+        name: "protanopia",
+        description: "Yellow, blue and white on black, without the reds that look dark",
+        colors: [
+            Rgb(0x00, 0x00, 0x00),
+            Rgb(0xF0, 0xE4, 0x42),
+            Rgb(0x00, 0x72, 0xB2),
+            Rgb(0xFF, 0xFF, 0xFF),
+        ],
+    },
+    Theme {
+        name: "tritanopia",
+        description: "Pink, teal and white on black, without the blues and yellows that look alike",
+        colors: [
+            Rgb(0x00, 0x00, 0x00),
+            Rgb(0xFF, 0x6E, 0x8A),
+            Rgb(0x00, 0x9E, 0x9E),
+            Rgb(0xFF, 0xFF, 0xFF),
+        ],
+    },
+];
+
+impl Theme {
+    pub fn palette(&self) -> Palette {
+        Palette {
+            foreground: self.colors[1],
+            background: self.colors[0],
+            plane2: self.colors[2],
+            both_planes: self.colors[3],
+        }
+    }
+}
+
+pub fn find(name: &str) -> Option<&'static Theme> {
+    THEMES.iter().find(|theme| theme.name.eq_ignore_ascii_case(name.trim()))
+}
+
+// The theme after the one with this palette, the first one for a palette of other colors.
+pub fn next(palette: Palette) -> &'static Theme {
+    let current = THEMES.iter().position(|theme| theme.palette() == palette);
+    &THEMES[current.map_or(0, |index| (index + 1) % THEMES.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_theme_is_the_default_palette() {
+        assert_eq!(THEMES[0].palette(), Palette::default());
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("gameboy").unwrap().name, "gameboy");
+        assert_eq!(find(" High-Contrast").unwrap().name, "high-contrast");
+        assert!(find("sepia").is_none());
+    }
+
+    #[test]
+    fn test_next_cycles_through_the_themes() {
+        let mut palette = Palette::default();
+        let mut names = Vec::new();
+        for _ in 0..THEMES.len() {
+            let theme = next(palette);
+            names.push(theme.name);
+            palette = theme.palette();
+        }
+        assert_eq!(
+            names,
+            [
+                "gameboy",
+                "amber",
+                "c64",
+                "high-contrast",
+                "deuteranopia",
+                "protanopia",
+                "tritanopia",
+                "vip"
+            ]
+        );
+
+        let custom = Palette {
+            foreground: Rgb(0x33, 0xFF, 0x66),
+            background: Rgb(0x10, 0x10, 0x10),
+            ..Palette::default()
+        };
+        assert_eq!(next(custom).name, "vip");
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Chip8Error;
+use crate::opcode::Opcode;
+use crate::symbols::Symbols;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Registers {
+    pub v: [u8; 16],
+    pub i: u16,
+    #[serde(rename = "dt")]
+    pub delay_timer: u8,
+    #[serde(rename = "st")]
+    pub sound_timer: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsonStep {
+    pub pc: u16,
+    pub opcode: u16,
+    pub mnemonic: String,
+    pub before: Registers,
+    pub after: Registers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraceFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+pub struct Tracer {
+    writer: BufWriter<File>,
+    range: Option<RangeInclusive<u16>>,
+    format: TraceFormat,
+}
+
+impl Tracer {
+    pub fn new(path: &str, range: Option<RangeInclusive<u16>>, format: TraceFormat) -> Result<Self, Chip8Error> {
+        Ok(Tracer {
+            writer: BufWriter::new(File::create(path)?),
+            range,
+            format,
+        })
+    }
+
+    pub fn record(
+        &mut self,
+        pc: u16,
+        instruction: u16,
+        mnemonic: &str,
+        before: &Registers,
+        after: &Registers,
+    ) -> Result<(), Chip8Error> {
+        if let Some(range) = &self.range {
+            if !range.contains(&pc) {
+                return Ok(());
+            }
+        }
+
+        match self.format {
+            TraceFormat::Text => writeln!(
+                self.writer,
+                "{:04X}  {:04X}  {:<18}{}",
+                pc,
+                instruction,
+                mnemonic,
+                changes(before, after)
+            )?,
+            TraceFormat::Json => {
+                let step = JsonStep {
+                    pc,
+                    opcode: instruction,
+                    mnemonic: mnemonic.to_string(),
+                    before: *before,
+                    after: *after,
+                };
+                serde_json::to_writer(&mut self.writer, &step).map_err(std::io::Error::from)?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Step {
+    pc: u16,
+    instruction: u16,
+    before: Registers,
+    after: Registers,
+}
+
+// Keeps the last executed instructions so a crash can be dumped with the steps leading up to it.
+pub struct RecentSteps {
+    capacity: usize,
+    steps: VecDeque<Step>,
+}
+
+impl RecentSteps {
+    pub fn new(capacity: usize) -> Self {
+        RecentSteps {
+            capacity,
+            steps: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn record(&mut self, pc: u16, instruction: u16, before: Registers, after: Registers) {
+        if self.steps.len() == self.capacity {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(Step {
+            pc,
+            instruction,
+            before,
+            after,
+        });
+    }
+
+    // Writes the recorded steps in the text trace format, followed by the error that stopped the emulator.
+    pub fn dump(&self, path: &Path, symbols: &Symbols, err: &Chip8Error) -> Result<(), Chip8Error> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        for step in &self.steps {
+            let mnemonic = Opcode::decode(step.instruction)
+                .map(|opcode| opcode.disassemble(symbols))
+                .unwrap_or_default();
+            writeln!(
+                writer,
+                "{:04X}  {:04X}  {:<18}{}",
+                step.pc,
+                step.instruction,
+                mnemonic,
+                changes(&step.before, &step.after)
+            )?;
+        }
+        writeln!(writer, "Error: {}", err)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+pub fn changes(before: &Registers, after: &Registers) -> String {
+    let mut result = String::new();
+    for (reg, (old, new)) in before.v.iter().zip(after.v.iter()).enumerate() {
+        if old != new {
+            result.push_str(&format!(" V{:X}={:#04X}", reg, new));
+        }
+    }
+    if before.i != after.i {
+        result.push_str(&format!(" I={:#05X}", after.i));
+    }
+    if before.delay_timer != after.delay_timer {
+        result.push_str(&format!(" DT={:#04X}", after.delay_timer));
+    }
+    if before.sound_timer != after.sound_timer {
+        result.push_str(&format!(" ST={:#04X}", after.sound_timer));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+
+    fn registers(v0: u8, i: u16) -> Registers {
+        let mut v = [0; 16];
+        v[0] = v0;
+        Registers {
+            v,
+            i,
+            delay_timer: 0,
+            sound_timer: 0,
+        }
+    }
+
+    // Records the steps and reads the lines back.
+    fn trace(name: &str, range: Option<RangeInclusive<u16>>, format: TraceFormat) -> Vec<String> {
+        let path = std::env::temp_dir().join(format!("chip8-trace-{}-{}", name, std::process::id()));
+        let mut tracer = Tracer::new(path.to_str().unwrap(), range, format).unwrap();
+        tracer
+            .record(0x200, 0x7005, "ADD V0, 0x05", &registers(1, 0), &registers(6, 0))
+            .unwrap();
+        tracer
+            .record(0x202, 0xA300, "LD I, 0x300", &registers(6, 0), &registers(6, 0x300))
+            .unwrap();
+        drop(tracer);
+
+        let reader = std::io::BufReader::new(File::open(&path).unwrap());
+        let lines = reader.lines().collect::<Result<Vec<String>, _>>().unwrap();
+        let _ = std::fs::remove_file(&path);
+        lines
+    }
+
+    #[test]
+    fn test_json_trace_reads_back() {
+        let lines = trace("json", None, TraceFormat::Json);
+        assert_eq!(lines.len(), 2);
+
+        let step: JsonStep = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!((step.pc, step.opcode), (0x200, 0x7005));
+        assert_eq!(step.mnemonic, "ADD V0, 0x05");
+        assert_eq!(step.before, registers(1, 0));
+        assert_eq!(step.after, registers(6, 0));
+    }
+
+    #[test]
+    fn test_text_trace_lists_changed_registers() {
+        let lines = trace("text", None, TraceFormat::Text);
+        assert_eq!(
+            lines,
+            [
+                "0200  7005  ADD V0, 0x05       V0=0x06",
+                "0202  A300  LD I, 0x300        I=0x300",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trace_range_skips_other_addresses() {
+        let lines = trace("range", Some(0x202..=0x2FF), TraceFormat::Text);
+        assert_eq!(lines, ["0202  A300  LD I, 0x300        I=0x300"]);
+    }
+}
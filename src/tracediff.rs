@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+
+use crate::error::Chip8Error;
+use crate::parse::parse_u16;
+use crate::trace::{self, JsonStep, Registers};
+
+const CONTEXT_STEPS: usize = 3;
+
+struct Step {
+    line: String,
+    pc: u16,
+    opcode: u16,
+    changes: String,
+    after: Option<Registers>,
+}
+
+impl Step {
+    fn parse(line: &str) -> Result<Self, String> {
+        if line.starts_with('{') {
+            let step: JsonStep = serde_json::from_str(line).map_err(|err| err.to_string())?;
+            return Ok(Step {
+                line: line.to_string(),
+                pc: step.pc,
+                opcode: step.opcode,
+                changes: trace::changes(&step.before, &step.after),
+                after: Some(step.after),
+            });
+        }
+
+        let mut fields = line.split_whitespace();
+        let pc = fields.next().ok_or("empty line")?;
+        let opcode = fields.next().ok_or("missing opcode")?;
+        let changes: Vec<&str> = fields.filter(|field| field.contains('=')).collect();
+
+        Ok(Step {
+            line: line.to_string(),
+            pc: parse_u16(&format!("0x{}", pc))?,
+            opcode: parse_u16(&format!("0x{}", opcode))?,
+            changes: changes.iter().map(|change| format!(" {}", change)).collect(),
+            after: None,
+        })
+    }
+
+    fn differences(&self, other: &Step) -> Vec<String> {
+        let mut differences = Vec::new();
+        if self.pc != other.pc {
+            differences.push(format!("PC {:#06X} vs {:#06X}", self.pc, other.pc));
+        }
+        if self.opcode != other.opcode {
+            differences.push(format!("opcode {:04X} vs {:04X}", self.opcode, other.opcode));
+        }
+
+        match (&self.after, &other.after) {
+            (Some(a), Some(b)) => {
+                for (reg, (x, y)) in a.v.iter().zip(b.v.iter()).enumerate() {
+                    if x != y {
+                        differences.push(format!("V{:X} {:#04X} vs {:#04X}", reg, x, y));
+                    }
+                }
+                if a.i != b.i {
+                    differences.push(format!("I {:#05X} vs {:#05X}", a.i, b.i));
+                }
+                if a.delay_timer != b.delay_timer {
+                    differences.push(format!("DT {:#04X} vs {:#04X}", a.delay_timer, b.delay_timer));
+                }
+                if a.sound_timer != b.sound_timer {
+                    differences.push(format!("ST {:#04X} vs {:#04X}", a.sound_timer, b.sound_timer));
+                }
+            }
+            _ if self.changes != other.changes => {
+                differences.push(format!(
+                    "changed registers '{}' vs '{}'",
+                    self.changes.trim(),
+                    other.changes.trim()
+                ));
+            }
+            _ => {}
+        }
+        differences
+    }
+}
+
+struct Trace {
+    name: String,
+    lines: Lines<BufReader<File>>,
+    line_number: usize,
+}
+
+impl Trace {
+    fn open(path: &str) -> Result<Self, Chip8Error> {
+        Ok(Trace {
+            name: path.to_string(),
+            lines: BufReader::new(File::open(path)?).lines(),
+            line_number: 0,
+        })
+    }
+
+    fn next_step(&mut self) -> Result<Option<Step>, Chip8Error> {
+        for line in self.lines.by_ref() {
+            let line = line?;
+            self.line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Step::parse(&line)
+                .map(Some)
+                .map_err(|err| Chip8Error::InvalidTrace(format!("{}:{}: {}", self.name, self.line_number, err)));
+        }
+        Ok(None)
+    }
+}
+
+// Compares two traces step by step and prints the first divergence. Returns whether they match.
+pub fn run(first: &str, second: &str) -> Result<bool, Chip8Error> {
+    let mut a = Trace::open(first)?;
+    let mut b = Trace::open(second)?;
+    let mut context: VecDeque<String> = VecDeque::with_capacity(CONTEXT_STEPS);
+    let mut step = 0usize;
+
+    loop {
+        let (step_a, step_b) = match (a.next_step()?, b.next_step()?) {
+            (None, None) => {
+                println!("Traces are identical ({} steps)", step);
+                return Ok(true);
+            }
+            (Some(_), None) => {
+                println!("{} ends after {} steps, {} continues", b.name, step, a.name);
+                return Ok(false);
+            }
+            (None, Some(_)) => {
+                println!("{} ends after {} steps, {} continues", a.name, step, b.name);
+                return Ok(false);
+            }
+            (Some(step_a), Some(step_b)) => (step_a, step_b),
+        };
+        step += 1;
+
+        let differences = step_a.differences(&step_b);
+        if !differences.is_empty() {
+            println!("Traces diverge at step {}:", step);
+            if !context.is_empty() {
+                println!("Previous steps:");
+                for line in &context {
+                    println!("    {}", line);
+                }
+            }
+            println!("  {}:{}\n    {}", a.name, a.line_number, step_a.line);
+            println!("  {}:{}\n    {}", b.name, b.line_number, step_b.line);
+            println!("Differences: {}", differences.join(", "));
+            return Ok(false);
+        }
+
+        if context.len() == CONTEXT_STEPS {
+            context.pop_front();
+        }
+        context.push_back(step_a.line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_and_json_steps_compare_changes() {
+        let text = Step::parse("0200  6A02  LD VA, 0x02         VA=0x02").unwrap();
+        let json = Step::parse(
+            r#"{"pc":512,"opcode":27138,"mnemonic":"LD VA, 0x02","before":{"v":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"i":0,"dt":0,"st":0},"after":{"v":[0,0,0,0,0,0,0,0,0,0,2,0,0,0,0,0],"i":0,"dt":0,"st":0}}"#,
+        )
+        .unwrap();
+        assert!(text.differences(&json).is_empty());
+
+        let other = Step::parse("0200  6A02  LD VA, 0x02         VA=0x03").unwrap();
+        assert_eq!(text.differences(&other).len(), 1);
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::HashSet;
+use std::fs;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::emulator::{Chip8, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::error::Chip8Error;
+use crate::machine::{FONT, FONT_OFFSET, MEMORY_SIZE, PROGRAM_START};
+use crate::opcode::Opcode;
+use crate::quirks::Quirks;
+use crate::snapshot::Chip8Snapshot;
+
+pub const DEFAULT_COUNT: usize = 10;
+// Left out of exported vectors: 0NNN depends on the machine call policy and CXNN on the random number generator
+const SKIPPED: [&str; 2] = ["0NNN", "CXNN"];
+// Bytes of random data after I, for the instructions that read memory
+const DATA_BYTES: u16 = 16;
+const LIT_PIXELS: usize = 40;
+const MAX_STACK: usize = 4;
+// How many differing cells or pixels a failure lists
+const MAX_DIFFERENCES: usize = 4;
+
+// One instruction executed on a known state and the state it has to leave behind. The runner writes the opcode
+// at PC before executing it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vector {
+    pub name: String,
+    #[serde(default)]
+    pub quirks: Quirks,
+    pub opcode: u16,
+    #[serde(default)]
+    pub mnemonic: String,
+    pub before: State,
+    pub after: State,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct State {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    #[serde(default)]
+    pub stack: Vec<u16>,
+    #[serde(default, rename = "dt")]
+    pub delay_timer: u8,
+    #[serde(default, rename = "st")]
+    pub sound_timer: u8,
+    // the bytes that differ from a fresh machine, which only has the font at 0x050
+    #[serde(default)]
+    pub memory: Vec<Cells>,
+    // the lit pixels as [x, y]
+    #[serde(default)]
+    pub lit: Vec<[u8; 2]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cells {
+    pub addr: u16,
+    pub bytes: Vec<u8>,
+}
+
+fn fresh_memory() -> Vec<u8> {
+    let mut memory = vec![0; MEMORY_SIZE];
+    memory[FONT_OFFSET..FONT_OFFSET + FONT.len()].copy_from_slice(&FONT);
+    memory
+}
+
+// The runs of bytes that differ from a fresh machine.
+fn cells(memory: &[u8]) -> Vec<Cells> {
+    let fresh = fresh_memory();
+    let mut cells: Vec<Cells> = Vec::new();
+    for (addr, &byte) in memory.iter().enumerate() {
+        if fresh.get(addr) == Some(&byte) {
+            continue;
+        }
+        match cells.last_mut() {
+            Some(run) if run.addr as usize + run.bytes.len() == addr => run.bytes.push(byte),
+            _ => cells.push(Cells {
+                addr: addr as u16,
+                bytes: vec![byte],
+            }),
+        }
+    }
+    cells
+}
+
+impl State {
+    fn from_snapshot(snapshot: &Chip8Snapshot) -> Self {
+        State {
+            v: snapshot.registers,
+            i: snapshot.i,
+            pc: snapshot.pc,
+            stack: snapshot.stack.clone(),
+            delay_timer: snapshot.delay_timer,
+            sound_timer: snapshot.sound_timer,
+            memory: cells(&snapshot.memory),
+            lit: (0..snapshot.display.len())
+                .filter(|&offset| snapshot.display[offset] != 0)
+                .map(|offset| [(offset % DISPLAY_WIDTH) as u8, (offset / DISPLAY_WIDTH) as u8])
+                .collect(),
+        }
+    }
+
+    fn memory(&self) -> Vec<u8> {
+        let mut memory = fresh_memory();
+        for cells in &self.memory {
+            for (offset, &byte) in cells.bytes.iter().enumerate() {
+                memory[(cells.addr as usize + offset) % MEMORY_SIZE] = byte;
+            }
+        }
+        memory
+    }
+
+    fn display(&self) -> Vec<u8> {
+        let mut display = vec![0; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+        for &[x, y] in &self.lit {
+            display[(y as usize % DISPLAY_HEIGHT) * DISPLAY_WIDTH + x as usize % DISPLAY_WIDTH] = 1;
+        }
+        display
+    }
+
+    fn snapshot(&self, quirks: Quirks) -> Chip8Snapshot {
+        Chip8Snapshot {
+            memory: self.memory(),
+            registers: self.v,
+            i: self.i,
+            pc: self.pc,
+            stack: self.stack.clone(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            display: self.display(),
+            quirks,
+        }
+    }
+}
+
+// Runs the vector's instruction on its before state and returns the state it leaves.
+pub fn execute(vector: &Vector) -> Result<State, Chip8Error> {
+    let mut snapshot = vector.before.snapshot(vector.quirks);
+    let pc = vector.before.pc as usize % MEMORY_SIZE;
+    snapshot.memory[pc] = (vector.opcode >> 8) as u8;
+    snapshot.memory[(pc + 1) % MEMORY_SIZE] = vector.opcode as u8;
+
+    let mut chip8 = Chip8::builder().quirks(vector.quirks).build()?;
+    chip8.restore(&snapshot)?;
+    chip8.step()?;
+    Ok(State::from_snapshot(&chip8.snapshot()))
+}
+
+// What differs between the expected and the actual state, one line each.
+pub fn differences(expected: &State, actual: &State) -> Vec<String> {
+    let mut lines = Vec::new();
+    for x in 0..16 {
+        if expected.v[x] != actual.v[x] {
+            lines.push(format!(
+                "V{:X} is {:#04X} instead of {:#04X}",
+                x, actual.v[x], expected.v[x]
+            ));
+        }
+    }
+    let words = [
+        ("I", expected.i, actual.i),
+        ("PC", expected.pc, actual.pc),
+        ("DT", expected.delay_timer as u16, actual.delay_timer as u16),
+        ("ST", expected.sound_timer as u16, actual.sound_timer as u16),
+    ];
+    for (name, expected, actual) in words {
+        if expected != actual {
+            lines.push(format!("{} is {:#05X} instead of {:#05X}", name, actual, expected));
+        }
+    }
+    if expected.stack != actual.stack {
+        lines.push(format!(
+            "the stack is {:04X?} instead of {:04X?}",
+            actual.stack, expected.stack
+        ));
+    }
+    let (expected_memory, actual_memory) = (expected.memory(), actual.memory());
+    let cells: Vec<usize> = (0..MEMORY_SIZE)
+        .filter(|&addr| expected_memory[addr] != actual_memory[addr])
+        .collect();
+    for &addr in cells.iter().take(MAX_DIFFERENCES) {
+        lines.push(format!(
+            "[{:#05X}] is {:#04X} instead of {:#04X}",
+            addr, actual_memory[addr], expected_memory[addr]
+        ));
+    }
+    if cells.len() > MAX_DIFFERENCES {
+        lines.push(format!("and {} more memory cells", cells.len() - MAX_DIFFERENCES));
+    }
+    let (expected_display, actual_display) = (expected.display(), actual.display());
+    let pixels: Vec<usize> = (0..expected_display.len())
+        .filter(|&offset| expected_display[offset] != actual_display[offset])
+        .collect();
+    for &offset in pixels.iter().take(MAX_DIFFERENCES) {
+        let state = if actual_display[offset] != 0 { "lit" } else { "dark" };
+        lines.push(format!(
+            "the pixel at {},{} is {}",
+            offset % DISPLAY_WIDTH,
+            offset / DISPLAY_WIDTH,
+            state
+        ));
+    }
+    if pixels.len() > MAX_DIFFERENCES {
+        lines.push(format!("and {} more pixels", pixels.len() - MAX_DIFFERENCES));
+    }
+    lines
+}
+
+pub fn load(path: &str) -> Result<Vec<Vector>, Chip8Error> {
+    serde_json::from_str(&fs::read_to_string(path)?).map_err(|err| Chip8Error::InvalidVectors(err.to_string()))
+}
+
+// Runs every vector in the file and reports the ones that fail. Tells whether all of them passed.
+pub fn run(path: &str) -> Result<bool, Chip8Error> {
+    let vectors = load(path)?;
+    let mut failed = 0;
+    for vector in &vectors {
+        let problems = match execute(vector) {
+            Ok(actual) => differences(&vector.after, &actual),
+            Err(err) => vec![err.to_string()],
+        };
+        if problems.is_empty() {
+            continue;
+        }
+        failed += 1;
+        println!("FAIL {}  {:04X}  {}", vector.name, vector.opcode, vector.mnemonic);
+        for problem in problems {
+            println!("     {}", problem);
+        }
+    }
+    println!("{} passed, {} failed", vectors.len() - failed, failed);
+    Ok(failed == 0)
+}
+
+// Every instruction pattern in the order of the opcodes, like 00E0, 00EE, 1NNN.
+fn patterns() -> Vec<&'static str> {
+    let mut seen = HashSet::new();
+    (0..=u16::MAX)
+        .filter_map(|instruction| Opcode::decode(instruction).ok())
+        .map(|opcode| opcode.pattern())
+        .filter(|pattern| seen.insert(*pattern))
+        .collect()
+}
+
+// An instruction of the pattern with random digits in place of X, Y and N.
+fn instruction(pattern: &str, rng: &mut StdRng) -> u16 {
+    pattern.chars().fold(0, |instruction, digit| {
+        let nibble = digit.to_digit(16).unwrap_or_else(|| rng.random_range(0..16));
+        instruction << 4 | nibble as u16
+    })
+}
+
+fn random_state(rng: &mut StdRng) -> State {
+    let i = rng.random_range(0..MEMORY_SIZE as u16);
+    let mut state = State {
+        v: rng.random(),
+        i,
+        pc: rng.random_range(PROGRAM_START as u16 / 2..0x700) * 2,
+        stack: (0..rng.random_range(0..=MAX_STACK))
+            .map(|_| rng.random_range(PROGRAM_START as u16 / 2..0x800) * 2)
+            .collect(),
+        delay_timer: rng.random(),
+        sound_timer: rng.random(),
+        memory: Vec::new(),
+        lit: (0..LIT_PIXELS)
+            .map(|_| {
+                [
+                    rng.random_range(0..DISPLAY_WIDTH as u8),
+                    rng.random_range(0..DISPLAY_HEIGHT as u8),
+                ]
+            })
+            .collect(),
+    };
+    let mut memory = fresh_memory();
+    for offset in 0..DATA_BYTES {
+        memory[i.wrapping_add(offset) as usize % MEMORY_SIZE] = rng.random();
+    }
+    state.memory = cells(&memory);
+    state.lit.sort_by_key(|&[x, y]| (y, x));
+    state.lit.dedup();
+    state
+}
+
+// Makes count vectors for every instruction but 0NNN and CXNN from what this emulator does with them.
+// Instructions that fail, like 00EE on an empty stack, are tried again with another state.
+pub fn generate(quirks: Quirks, count: usize, seed: u64) -> Result<Vec<Vector>, Chip8Error> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut vectors = Vec::new();
+    for pattern in patterns().into_iter().filter(|pattern| !SKIPPED.contains(pattern)) {
+        for number in 1..=count {
+            loop {
+                let opcode = instruction(pattern, &mut rng);
+                let mut vector = Vector {
+                    name: format!("{} #{}", pattern, number),
+                    quirks,
+                    opcode,
+                    mnemonic: Opcode::decode(opcode)?.to_string(),
+                    before: random_state(&mut rng),
+                    after: State::default(),
+                };
+                if let Ok(after) = execute(&vector) {
+                    vector.after = after;
+                    vectors.push(vector);
+                    break;
+                }
+            }
+        }
+    }
+    Ok(vectors)
+}
+
+pub fn export(path: &str, quirks: Quirks, count: usize, seed: u64) -> Result<(), Chip8Error> {
+    let vectors = generate(quirks, count, seed)?;
+    let json = serde_json::to_string_pretty(&vectors).map_err(|err| Chip8Error::InvalidVectors(err.to_string()))?;
+    fs::write(path, json + "\n")?;
+    println!("Wrote {} vectors to {}", vectors.len(), path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_vectors_pass() {
+        for quirks in [Quirks::default(), Quirks::cosmac_vip()] {
+            let vectors = generate(quirks, 2, 7).unwrap();
+            assert_eq!(vectors.len(), (patterns().len() - SKIPPED.len()) * 2);
+            for vector in &vectors {
+                assert_eq!(
+                    differences(&vector.after, &execute(vector).unwrap()),
+                    Vec::<String>::new()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_vector_from_json() {
+        let json = r#"{
+            "name": "carry",
+            "opcode": 32788,
+            "before": {"v": [255, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], "i": 0, "pc": 512},
+            "after": {"v": [1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1], "i": 0, "pc": 514,
+                      "memory": [{"addr": 512, "bytes": [128, 20]}]}
+        }"#;
+        let mut vector: Vector = serde_json::from_str(json).unwrap();
+        assert!(differences(&vector.after, &execute(&vector).unwrap()).is_empty());
+
+        vector.after.v[0xF] = 0;
+        assert_eq!(
+            differences(&vector.after, &execute(&vector).unwrap()),
+            vec!["VF is 0x01 instead of 0x00"]
+        );
+    }
+}
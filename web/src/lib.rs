@@ -0,0 +1,87 @@
+use chip8_emulator_rs::machine::{DEFAULT_IPS, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use chip8_emulator_rs::{Machine, Quirks};
+use wasm_bindgen::prelude::*;
+
+// The bare interpreter for a web page: key events go in, the page calls frame 60 times per second
+// and copies the framebuffer to a canvas.
+#[wasm_bindgen]
+pub struct Emulator {
+    machine: Machine,
+    cycles_per_frame: u32,
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(rom: &[u8], seed: u32, cycles_per_frame: u32) -> Result<Emulator, JsError> {
+        let machine =
+            Machine::new(rom, Quirks::default(), seed as u64).map_err(|err| JsError::new(&err.to_string()))?;
+        Ok(Emulator {
+            machine,
+            cycles_per_frame: cycles_per_frame.max(1),
+        })
+    }
+
+    // Runs the instructions of one 60 Hz frame and ticks the timers, returns whether the display changed.
+    pub fn frame(&mut self) -> Result<bool, JsError> {
+        let frame = self
+            .machine
+            .run_frame(self.cycles_per_frame)
+            .map_err(|err| JsError::new(&err.to_string()))?;
+        Ok(frame.display_changed)
+    }
+
+    // One byte per pixel, row by row, 0 is off and 1 is on.
+    pub fn framebuffer(&self) -> Vec<u8> {
+        self.machine.pixels().to_vec()
+    }
+
+    pub fn key_down(&mut self, key: u8) {
+        self.machine.keypad_mut().press(key);
+    }
+
+    pub fn key_up(&mut self, key: u8) {
+        self.machine.keypad_mut().release(key);
+    }
+
+    pub fn beeping(&self) -> bool {
+        self.machine.beeping()
+    }
+
+    pub fn width() -> usize {
+        DISPLAY_WIDTH
+    }
+
+    pub fn height() -> usize {
+        DISPLAY_HEIGHT
+    }
+
+    // The desktop emulator's speed, for the constructor.
+    pub fn default_cycles_per_frame() -> u32 {
+        DEFAULT_IPS / 60
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_draw_to_the_framebuffer() {
+        // LD V0, K; LD F, V0; DRW V1, V1, 5; JP 0x206
+        let rom = [0xF0, 0x0A, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x06];
+        let mut emulator = Emulator::new(&rom, 1, Emulator::default_cycles_per_frame()).unwrap();
+        assert!(!emulator.frame().unwrap());
+
+        emulator.key_down(0x8);
+        assert!(!emulator.frame().unwrap());
+        emulator.key_up(0x8);
+        assert!(emulator.frame().unwrap());
+
+        let framebuffer = emulator.framebuffer();
+        assert_eq!(framebuffer.len(), Emulator::width() * Emulator::height());
+        // the font's 8 has 16 pixels set
+        assert_eq!(framebuffer.iter().filter(|&&pixel| pixel == 1).count(), 16);
+        assert!(!emulator.beeping());
+    }
+}